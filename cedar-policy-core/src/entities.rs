@@ -130,9 +130,11 @@ impl Entities {
         extensions: Extensions<'_>,
     ) -> Result<Self> {
         let checker = schema.map(|schema| EntitySchemaConformanceChecker::new(schema, extensions));
+        let mut added = Vec::new();
         for entity in collection.into_iter() {
             if let Some(checker) = checker.as_ref() {
                 checker.validate_entity(&entity)?;
+                added.push(entity.uid());
             }
             match self.entities.entry(entity.uid()) {
                 hash_map::Entry::Occupied(_) => return Err(EntitiesError::Duplicate(entity.uid())),
@@ -148,6 +150,14 @@ impl Entities {
             }
             TCComputation::ComputeNow => compute_tc(&mut self.entities, true).map_err(Box::new)?,
         };
+        // Required parents may be reached through other entities, so they are
+        // checked once the transitive closure is known.
+        if let Some(checker) = checker.as_ref() {
+            for entity in added.iter().filter_map(|uid| self.entities.get(uid)) {
+                checker
+                    .validate_required_parents(entity, |parent| entity.is_descendant_of(parent))?;
+            }
+        }
         self.evaluated_entities = None;
         Ok(self)
     }
@@ -166,20 +176,34 @@ impl Entities {
         schema: Option<&impl Schema>,
         tc_computation: TCComputation,
         extensions: Extensions<'_>,
+    ) -> Result<Self> {
+        Self::from_entities_with_checker(
+            entities,
+            schema.map(|schema| EntitySchemaConformanceChecker::new(schema, extensions)),
+            tc_computation,
+        )
+    }
+
+    /// Implementation of `from_entities()` which validates the entities with
+    /// the given `checker`, and adds action entities from its schema.
+    pub(crate) fn from_entities_with_checker<S: Schema>(
+        entities: impl IntoIterator<Item = Entity>,
+        checker: Option<EntitySchemaConformanceChecker<'_, S>>,
+        tc_computation: TCComputation,
     ) -> Result<Self> {
         let mut entity_map = create_entity_map(entities.into_iter())?;
-        if let Some(schema) = schema {
+        if let Some(checker) = checker.as_ref() {
             // validate entities against schema.
             // we do this before adding the actions, because we trust the
             // actions were already validated as part of constructing the
             // `Schema`
-            let checker = EntitySchemaConformanceChecker::new(schema, extensions);
             for entity in entity_map.values() {
                 checker.validate_entity(entity)?;
             }
             // now add the action entities from the schema
             entity_map.extend(
-                schema
+                checker
+                    .schema()
                     .action_entities()
                     .into_iter()
                     .map(|e| (e.uid(), unwrap_or_clone(e))),
@@ -194,6 +218,14 @@ impl Entities {
                 compute_tc(&mut entity_map, true).map_err(Box::new)?;
             }
         }
+        // Required parents may be reached through other entities, so they are
+        // checked once the transitive closure is known.
+        if let Some(checker) = checker {
+            for entity in entity_map.values() {
+                checker
+                    .validate_required_parents(entity, |parent| entity.is_descendant_of(parent))?;
+            }
+        }
         Ok(Self {
             entities: entity_map,
             mode: Mode::default(),
@@ -227,6 +259,34 @@ impl Entities {
         Ok(())
     }
 
+    /// Convert an `Entities` object into a JSON value as
+    /// [`Entities::to_json_value()`] does, but replace the value of each
+    /// attribute for which `redact` returns `true`, given the entity's type and
+    /// the path to the attribute, with the string [`REDACTED_ATTRIBUTE_VALUE`].
+    /// The path is as described for [`EntityJson::from_entity_redacted()`], so
+    /// attributes of records nested inside entity attributes can be redacted.
+    ///
+    /// The result is meant for logging. A redacted value generally does not
+    /// have the attribute's declared type, so the result may not conform to a
+    /// schema.
+    pub fn to_json_value_redacted(
+        &self,
+        redact: impl Fn(&EntityType, &[SmolStr]) -> bool,
+    ) -> Result<serde_json::Value> {
+        let ejsons = self
+            .entities
+            .values()
+            .map(|entity| {
+                EntityJson::from_entity_redacted(entity, |path| {
+                    redact(entity.uid().entity_type(), path)
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, JsonSerializationError>>()?;
+        serde_json::to_value(ejsons)
+            .map_err(JsonSerializationError::from)
+            .map_err(Into::into)
+    }
+
     /// Internal helper function to convert this `Entities` into a `Vec<EntityJson>`
     fn to_ejsons(&self) -> Result<Vec<EntityJson>> {
         self.entities
@@ -1608,8 +1668,44 @@ mod json_parsing_tests {
         .expect("Failed to construct entities");
         assert!(matches!(
             roundtrip(&entities),
-            Err(EntitiesError::Serialization(JsonSerializationError::ReservedKey { key })) if key.as_str() == "__entity"
+            Err(EntitiesError::Serialization(JsonSerializationError::ReservedKey { key, .. })) if key.as_str() == "__entity"
         ));
+
+        // the error reports where the record was found
+        let nested_oops_entity = Entity::new(
+            EntityUID::with_eid("oops"),
+            [(
+                "outer".into(),
+                RestrictedExpr::record([(
+                    "inner".into(),
+                    RestrictedExpr::record([("__extn".into(), RestrictedExpr::val("hi"))]).unwrap(),
+                )])
+                .unwrap(),
+            )]
+            .into_iter()
+            .collect(),
+            std::collections::HashSet::new(),
+        );
+        let entities = Entities::from_entities(
+            [nested_oops_entity],
+            None::<&NoEntitiesSchema>,
+            TCComputation::ComputeNow,
+            Extensions::all_available(),
+        )
+        .expect("Failed to construct entities");
+        match roundtrip(&entities) {
+            Err(EntitiesError::Serialization(err)) => {
+                assert_eq!(
+                    err.code(),
+                    "cedar_policy_core::entities::json_serialization::reserved_key"
+                );
+                assert_eq!(
+                    err.to_string(),
+                    r#"in attribute `outer.inner` on `test_entity_type::"oops"`, record uses reserved key `__extn`"#
+                );
+            }
+            r => panic!("expected ReservedKey error, got {r:?}"),
+        }
     }
 
     /// test that an Action having a non-Action parent is an error
@@ -1783,6 +1879,24 @@ mod entities_tests {
         )
         .expect("Should have succeeded");
     }
+
+    #[test]
+    fn heterogeneous_set_error_details() {
+        let rexpr = RestrictedExpr::set([
+            RestrictedExpr::val(1),
+            RestrictedExpr::val(2),
+            RestrictedExpr::val("three"),
+            RestrictedExpr::val(false),
+        ]);
+        match type_of_restricted_expr(rexpr.as_borrowed(), Extensions::all_available()) {
+            Err(TypeOfRestrictedExprError::HeterogeneousSet(err)) => {
+                assert_eq!(err.first_type(), &SchemaType::Long);
+                assert_eq!(err.conflicting_type(), &SchemaType::String);
+                assert_eq!(err.conflicting_index(), 2);
+            }
+            res => panic!("expected a heterogeneous set error, got {res:?}"),
+        }
+    }
 }
 
 // PANIC SAFETY: Unit Test Code
@@ -1793,6 +1907,7 @@ mod entities_tests {
 mod schema_based_parsing_tests {
     use super::*;
     use crate::extensions::Extensions;
+    use cool_asserts::assert_matches;
     use serde_json::json;
     use smol_str::SmolStr;
     use std::collections::HashSet;
@@ -1856,9 +1971,9 @@ mod schema_based_parsing_tests {
             let employee_ty = || SchemaType::Entity {
                 ty: self.entity_type(),
             };
-            let hr_ty = || SchemaType::Entity {
-                ty: EntityType::Concrete(Name::parse_unqualified_name("HR").expect("valid")),
-            };
+            let hr_ty_name =
+                || EntityType::Concrete(Name::parse_unqualified_name("HR").expect("valid"));
+            let hr_ty = || SchemaType::Entity { ty: hr_ty_name() };
             match attr {
                 "isFullTime" => Some(SchemaType::Bool),
                 "numDirectReports" => Some(SchemaType::Long),
@@ -1895,6 +2010,9 @@ mod schema_based_parsing_tests {
                 "trust_score" => Some(SchemaType::Extension {
                     name: Name::parse_unqualified_name("decimal").expect("valid"),
                 }),
+                "approver" => Some(SchemaType::EntityUnion {
+                    tys: [self.entity_type(), hr_ty_name()].into_iter().collect(),
+                }),
                 "tricky" => Some(SchemaType::Record {
                     attrs: [
                         ("type".into(), AttributeType::required(SchemaType::String)),
@@ -2182,6 +2300,92 @@ mod schema_based_parsing_tests {
         );
     }
 
+    /// JSON numbers parsed as `Long` must be integers in the range of `Long`
+    #[test]
+    fn long_overflow_and_floats() {
+        let vparser = ValueParser::new(Extensions::all_available());
+        let parse = |val: serde_json::Value| {
+            vparser.val_into_restricted_expr(val, Some(&SchemaType::Long), || {
+                JsonDeserializationErrorContext::Context
+            })
+        };
+        assert_eq!(
+            parse(json!(i64::MAX)).expect("i64::MAX should parse"),
+            RestrictedExpr::val(i64::MAX)
+        );
+        assert_eq!(
+            parse(json!(i64::MIN)).expect("i64::MIN should parse"),
+            RestrictedExpr::val(i64::MIN)
+        );
+
+        let too_big: serde_json::Value =
+            serde_json::from_str("9223372036854775808").expect("valid JSON");
+        assert_matches!(parse(too_big), Err(JsonDeserializationError::IntegerOverflow { value, .. }) => {
+            assert_eq!(value.to_string(), "9223372036854775808");
+        });
+        let too_small: serde_json::Value =
+            serde_json::from_str("-9223372036854775809").expect("valid JSON");
+        let err = parse(too_small).expect_err("i64::MIN - 1 should overflow");
+        assert_matches!(&err, JsonDeserializationError::IntegerOverflow { .. });
+        assert!(err.is_schema_conformance());
+        assert!(
+            err.to_string().contains("is out of range for type long"),
+            "actual error message was {err}"
+        );
+
+        assert_matches!(parse(json!(3.5)), Err(JsonDeserializationError::ExpectedInteger { value, .. }) => {
+            assert_eq!(value.to_string(), "3.5");
+        });
+        assert_matches!(
+            parse(json!(3.0)),
+            Err(JsonDeserializationError::ExpectedInteger { .. })
+        );
+
+        // Set elements and record attributes are checked too.
+        let set_ty = SchemaType::Set {
+            element_ty: Box::new(SchemaType::Long),
+        };
+        assert_matches!(
+            vparser.val_into_restricted_expr(json!([1, 2.5]), Some(&set_ty), || {
+                JsonDeserializationErrorContext::Context
+            }),
+            Err(JsonDeserializationError::ExpectedInteger { .. })
+        );
+    }
+
+    /// test classification of malformed JSON and bad escapes
+    #[test]
+    fn malformed_json_classification() {
+        let eparser = EntityJsonParser::new(
+            Some(&MockSchema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let err = eparser
+            .from_json_str(r#"[ { "uid": "#)
+            .expect_err("should fail due to malformed JSON");
+        assert_matches!(err, EntitiesError::Deserialization(err) => {
+            assert!(err.is_malformed_json());
+            assert!(!err.is_schema_conformance());
+        });
+
+        let err = eparser
+            .from_json_value(json!([
+                {
+                    "uid": { "__entity": { "type": "Employee", "id": "12UA45" } },
+                    "attrs": {
+                        "manager": { "__entity": { "type": "Employee ::", "id": "34FB87" } }
+                    },
+                    "parents": []
+                }
+            ]))
+            .expect_err("should fail due to an unparseable escape");
+        assert_matches!(err, EntitiesError::Deserialization(err) => {
+            assert!(err.is_malformed_json(), "unexpected error: {err}");
+            assert!(!err.is_schema_conformance());
+        });
+    }
+
     #[cfg(all(feature = "decimal", feature = "ipaddr"))]
     /// another simple type mismatch with expected type
     #[test]
@@ -2194,7 +2398,7 @@ mod schema_based_parsing_tests {
                         "isFullTime": true,
                         "numDirectReports": 3,
                         "department": "Sales",
-                        "manager": "34FB87",
+                        "manager": 34,
                         "hr_contacts": [
                             { "type": "HR", "id": "aaaaa" },
                             { "type": "HR", "id": "bbbbb" }
@@ -2223,7 +2427,135 @@ mod schema_based_parsing_tests {
             .expect_err("should fail due to type mismatch on manager");
         assert!(
             err.to_string()
-                .contains(r#"in attribute `manager` on `Employee::"12UA45"`, expected a literal entity reference, but got `"34FB87"`"#),
+                .contains(r#"in attribute `manager` on `Employee::"12UA45"`, expected a literal entity reference, but got `34`"#),
+            "actual error message was {err}"
+        );
+    }
+
+    #[cfg(all(feature = "decimal", feature = "ipaddr"))]
+    /// a bare string is accepted as the eid of an entity reference when the
+    /// expected entity type is unambiguous
+    #[test]
+    fn bare_eid_entity_refs() {
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": {
+                        "isFullTime": true,
+                        "numDirectReports": 3,
+                        "department": "Sales",
+                        "manager": "34FB87",
+                        "hr_contacts": ["aaaaa", { "type": "HR", "id": "bbbbb" }],
+                        "json_blob": {
+                            "inner1": false,
+                            "inner2": "-*/",
+                            "inner3": { "innerinner": "09AE76" },
+                        },
+                        "home_ip": "222.222.222.101",
+                        "work_ip": { "fn": "ip", "arg": "2.2.2.0/24" },
+                        "trust_score": "5.7",
+                    },
+                    "parents": []
+                }
+            ]
+        );
+        let eparser = EntityJsonParser::new(
+            Some(&MockSchema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let parsed = eparser
+            .from_json_value(entitiesjson)
+            .expect("bare eids should be accepted");
+        let parsed = parsed
+            .entity(&r#"Employee::"12UA45""#.parse().expect("valid"))
+            .expect("that should be the employee id");
+        let manager = parsed.get("manager").expect("manager attr should exist");
+        assert_eq!(
+            RestrictedExprShapeOnly::new(manager.as_borrowed()),
+            RestrictedExprShapeOnly::new(
+                RestrictedExpr::val("Employee::\"34FB87\"".parse::<EntityUID>().expect("valid"))
+                    .as_borrowed()
+            )
+        );
+        let hr_contacts = parsed
+            .get("hr_contacts")
+            .expect("hr_contacts attr should exist");
+        let ExprKind::Set(set) = hr_contacts.expr_kind() else {
+            panic!("hr_contacts should be a Set")
+        };
+        let mut contacts = set
+            .iter()
+            .map(|contact| match contact.expr_kind() {
+                ExprKind::Lit(Literal::EntityUID(uid)) => uid.to_string(),
+                _ => panic!("contacts should be entity references"),
+            })
+            .collect::<Vec<_>>();
+        contacts.sort();
+        assert_eq!(contacts, [r#"HR::"aaaaa""#, r#"HR::"bbbbb""#]);
+        let json_blob = parsed
+            .get("json_blob")
+            .expect("json_blob attr should exist");
+        let ExprKind::Record(map) = json_blob.expr_kind() else {
+            panic!("json_blob should be a Record")
+        };
+        let (_, inner3) = map
+            .iter()
+            .find(|(k, _)| *k == "inner3")
+            .expect("inner3 attr should exist");
+        let ExprKind::Record(innermap) = inner3.expr_kind() else {
+            panic!("inner3 should be a Record")
+        };
+        let (_, innerinner) = innermap
+            .iter()
+            .find(|(k, _)| *k == "innerinner")
+            .expect("innerinner attr should exist");
+        assert_matches!(
+            innerinner.expr_kind(),
+            ExprKind::Lit(Literal::EntityUID(uid)) => {
+                assert_eq!(uid.to_string(), r#"Employee::"09AE76""#);
+            }
+        );
+
+        // `approver` may be an `Employee` or an `HR`, so a bare eid is
+        // ambiguous and must be rejected
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": {
+                        "isFullTime": true,
+                        "numDirectReports": 3,
+                        "department": "Sales",
+                        "manager": "34FB87",
+                        "hr_contacts": [],
+                        "json_blob": {
+                            "inner1": false,
+                            "inner2": "-*/",
+                            "inner3": { "innerinner": "09AE76" },
+                        },
+                        "home_ip": "222.222.222.101",
+                        "work_ip": { "fn": "ip", "arg": "2.2.2.0/24" },
+                        "trust_score": "5.7",
+                        "approver": "34FB87",
+                    },
+                    "parents": []
+                }
+            ]
+        );
+        let err = eparser
+            .from_json_value(entitiesjson)
+            .expect_err("should fail because the type of `approver` is ambiguous");
+        assert_matches!(
+            err,
+            EntitiesError::Deserialization(
+                JsonDeserializationError::ExpectedLiteralEntityRef { .. }
+            )
+        );
+        assert!(
+            err.to_string()
+                .contains(r#"in attribute `approver` on `Employee::"12UA45"`, expected a literal entity reference, but got `"34FB87"`"#),
             "actual error message was {err}"
         );
     }
@@ -2361,6 +2693,261 @@ mod schema_based_parsing_tests {
         );
     }
 
+    /// an implied extension constructor with the wrong argument type reports
+    /// the constructors which are available
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn missing_implied_constructor_lists_available() {
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": {
+                        "isFullTime": true,
+                        "numDirectReports": 3,
+                        "department": "Sales",
+                        "manager": { "type": "Employee", "id": "34FB87" },
+                        "hr_contacts": [],
+                        "json_blob": {
+                            "inner1": false,
+                            "inner2": "-*/",
+                            "inner3": { "innerinner": { "type": "Employee", "id": "09AE76" }},
+                        },
+                        "home_ip": "222.222.222.101",
+                        "work_ip": "2.2.2.0/24",
+                        "trust_score": 5,
+                        "tricky": { "type": "Employee", "id": "34FB87" }
+                    },
+                    "parents": []
+                }
+            ]
+        );
+        let eparser = EntityJsonParser::new(
+            Some(&MockSchema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let err = eparser
+            .from_json_value(entitiesjson)
+            .expect_err("should fail due to type mismatch on trust_score");
+        match &err {
+            EntitiesError::Deserialization(
+                JsonDeserializationError::MissingImpliedConstructor {
+                    available_constructors,
+                    ..
+                },
+            ) => {
+                assert_eq!(available_constructors, &["decimal(string) -> decimal"]);
+            }
+            e => panic!("expected MissingImpliedConstructor, got {e:?}"),
+        }
+        assert!(
+            err.to_string().ends_with(
+                "missing extension constructor for long -> decimal; found `decimal(string) -> decimal`"
+            ),
+            "actual error message was {err}"
+        );
+    }
+
+    /// Extension values which can't be interpreted are kept as opaque values
+    /// when parsing leniently
+    #[test]
+    fn opaque_unknown_extension_values() {
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": {
+                        "isFullTime": true,
+                        "numDirectReports": 3,
+                        "department": "Sales",
+                        "manager": { "type": "Employee", "id": "34FB87" },
+                        "hr_contacts": [
+                            { "type": "HR", "id": "aaaaa" },
+                            { "type": "HR", "id": "bbbbb" }
+                        ],
+                        "json_blob": {
+                            "inner1": false,
+                            "inner2": "-*/",
+                            "inner3": { "innerinner": { "type": "Employee", "id": "09AE76" }},
+                        },
+                        "home_ip": "222.222.222.101",
+                        "work_ip": { "fn": "ip", "arg": "2.2.2.0/24" },
+                        "trust_score": { "__extn": { "fn": "decimal", "arg": "5.7" } },
+                    },
+                    "parents": []
+                }
+            ]
+        );
+        let eparser = EntityJsonParser::new(
+            Some(&MockSchema),
+            Extensions::none(),
+            TCComputation::ComputeNow,
+        );
+        assert_matches!(
+            eparser.from_json_value(entitiesjson.clone()),
+            Err(EntitiesError::Deserialization(
+                JsonDeserializationError::MissingImpliedConstructor { .. }
+            ))
+        );
+        let (parsed, warnings) = eparser
+            .from_json_value_lenient(entitiesjson)
+            .expect("should parse leniently");
+        let uid: EntityUID = r#"Employee::"12UA45""#.parse().expect("valid uid");
+        let mut warnings = warnings
+            .iter()
+            .map(|w| {
+                assert_eq!(w.uid(), &uid);
+                (
+                    w.attr().as_str(),
+                    w.fn_name().to_string(),
+                    w.args().to_vec(),
+                )
+            })
+            .collect::<Vec<_>>();
+        warnings.sort_by(|a, b| a.0.cmp(b.0));
+        assert_eq!(
+            warnings,
+            vec![
+                (
+                    "home_ip",
+                    "ipaddr".to_string(),
+                    vec![RestrictedExpr::val("222.222.222.101")]
+                ),
+                (
+                    "trust_score",
+                    "decimal".to_string(),
+                    vec![RestrictedExpr::val("5.7")]
+                ),
+                (
+                    "work_ip",
+                    "ip".to_string(),
+                    vec![RestrictedExpr::val("2.2.2.0/24")]
+                ),
+            ]
+        );
+        let parsed = parsed
+            .entity(&uid)
+            .expect("Employee::\"12UA45\" should exist");
+        assert_eq!(
+            parsed.get("trust_score"),
+            Some(&RestrictedExpr::call_extension_fn(
+                "decimal".parse().expect("valid name"),
+                vec![RestrictedExpr::val("5.7")]
+            ))
+        );
+        assert_eq!(
+            parsed.get("numDirectReports"),
+            Some(&RestrictedExpr::val(3))
+        );
+    }
+
+    /// Values of an extension type which is enabled are still checked against
+    /// its constructors when parsing leniently
+    #[cfg(feature = "ipaddr")]
+    #[test]
+    fn lenient_parsing_checks_enabled_extensions() {
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": {
+                        "isFullTime": true,
+                        "numDirectReports": 3,
+                        "department": "Sales",
+                        "manager": { "type": "Employee", "id": "34FB87" },
+                        "hr_contacts": [
+                            { "type": "HR", "id": "aaaaa" },
+                            { "type": "HR", "id": "bbbbb" }
+                        ],
+                        "json_blob": {
+                            "inner1": false,
+                            "inner2": "-*/",
+                            "inner3": { "innerinner": { "type": "Employee", "id": "09AE76" }},
+                        },
+                        "home_ip": 5,
+                        "work_ip": { "fn": "ip", "arg": "2.2.2.0/24" },
+                        "trust_score": "5.7",
+                    },
+                    "parents": []
+                }
+            ]
+        );
+        let eparser = EntityJsonParser::new(
+            Some(&MockSchema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        assert_matches!(
+            eparser.from_json_value_lenient(entitiesjson),
+            Err(EntitiesError::Deserialization(
+                JsonDeserializationError::MissingImpliedConstructor { .. }
+            ))
+        );
+    }
+
+    /// An entity which does not conform to the schema is reported on its own
+    /// and does not prevent the other entities from loading
+    #[cfg(all(feature = "decimal", feature = "ipaddr"))]
+    #[test]
+    fn per_entity_results() {
+        let attrs = json!({
+            "isFullTime": true,
+            "numDirectReports": 3,
+            "department": "Sales",
+            "manager": { "type": "Employee", "id": "34FB87" },
+            "hr_contacts": [
+                { "type": "HR", "id": "aaaaa" },
+                { "type": "HR", "id": "bbbbb" }
+            ],
+            "json_blob": {
+                "inner1": false,
+                "inner2": "-*/",
+                "inner3": { "innerinner": { "type": "Employee", "id": "09AE76" }},
+            },
+            "home_ip": "222.222.222.101",
+            "work_ip": { "fn": "ip", "arg": "2.2.2.0/24" },
+            "trust_score": "5.7",
+        });
+        let mut bad_attrs = attrs.clone();
+        bad_attrs["numDirectReports"] = json!("three");
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": attrs,
+                    "parents": []
+                },
+                {
+                    "uid": { "type": "Employee", "id": "34FB87" },
+                    "attrs": bad_attrs,
+                    "parents": []
+                }
+            ]
+        );
+        let eparser = EntityJsonParser::new(
+            Some(&MockSchema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        assert_matches!(eparser.from_json_value(entitiesjson.clone()), Err(_));
+        let (parsed, results) = eparser
+            .from_json_value_per_entity_results(entitiesjson)
+            .expect("should parse the list of entities");
+        let good: EntityUID = r#"Employee::"12UA45""#.parse().expect("valid uid");
+        let bad: EntityUID = r#"Employee::"34FB87""#.parse().expect("valid uid");
+        assert_eq!(results.len(), 2);
+        assert_matches!(results.get(&good), Some(Ok(())));
+        assert_matches!(
+            results.get(&bad),
+            Some(Err(JsonDeserializationError::EntitySchemaConformance(
+                EntitySchemaConformanceError::TypeMismatch { .. }
+            )))
+        );
+        assert_matches!(parsed.entity(&good), Dereference::Data(_));
+        assert_matches!(parsed.entity(&bad), Dereference::NoSuchEntity);
+    }
+
     #[cfg(all(feature = "decimal", feature = "ipaddr"))]
     #[test]
     fn missing_record_attr() {
@@ -2527,6 +3114,110 @@ mod schema_based_parsing_tests {
         );
     }
 
+    #[cfg(all(feature = "decimal", feature = "ipaddr"))]
+    /// missing a record attribute in a record nested inside another record
+    #[test]
+    fn missing_nested_record_attr() {
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": {
+                        "isFullTime": true,
+                        "numDirectReports": 3,
+                        "department": "Sales",
+                        "manager": { "type": "Employee", "id": "34FB87" },
+                        "hr_contacts": [
+                            { "type": "HR", "id": "aaaaa" },
+                            { "type": "HR", "id": "bbbbb" }
+                        ],
+                        "json_blob": {
+                            "inner1": false,
+                            "inner2": "-*/",
+                            "inner3": {},
+                        },
+                        "home_ip": "222.222.222.101",
+                        "work_ip": { "fn": "ip", "arg": "2.2.2.0/24" },
+                        "trust_score": "5.7",
+                        "tricky": { "type": "Employee", "id": "34FB87" }
+                    },
+                    "parents": []
+                }
+            ]
+        );
+        let eparser = EntityJsonParser::new(
+            Some(&MockSchema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let err = eparser
+            .from_json_value(entitiesjson)
+            .expect_err("should fail due to missing attribute \"innerinner\"");
+        assert!(
+            err.to_string().contains(r#"in attribute `json_blob.inner3` on `Employee::"12UA45"`, expected the record to have an attribute `innerinner`, but it does not"#),
+            "actual error message was {err}"
+        );
+        assert_matches!(&err, EntitiesError::Deserialization(err) => {
+            assert!(err.is_schema_conformance());
+            assert!(!err.is_malformed_json());
+        });
+        assert_matches!(
+            err,
+            EntitiesError::Deserialization(JsonDeserializationError::MissingRequiredRecordAttr { path, record_attr, .. })
+                if path == vec![SmolStr::from("inner3")] && record_attr == "innerinner"
+        );
+    }
+
+    #[cfg(all(feature = "decimal", feature = "ipaddr"))]
+    /// unexpected record attribute in a record nested inside another record
+    #[test]
+    fn unexpected_nested_record_attr() {
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": {
+                        "isFullTime": true,
+                        "numDirectReports": 3,
+                        "department": "Sales",
+                        "manager": { "type": "Employee", "id": "34FB87" },
+                        "hr_contacts": [
+                            { "type": "HR", "id": "aaaaa" },
+                            { "type": "HR", "id": "bbbbb" }
+                        ],
+                        "json_blob": {
+                            "inner1": false,
+                            "inner2": "-*/",
+                            "inner3": { "innerinner": { "type": "Employee", "id": "09AE76" }, "extra": 1 },
+                        },
+                        "home_ip": "222.222.222.101",
+                        "work_ip": { "fn": "ip", "arg": "2.2.2.0/24" },
+                        "trust_score": "5.7",
+                        "tricky": { "type": "Employee", "id": "34FB87" }
+                    },
+                    "parents": []
+                }
+            ]
+        );
+        let eparser = EntityJsonParser::new(
+            Some(&MockSchema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let err = eparser
+            .from_json_value(entitiesjson)
+            .expect_err("should fail due to unexpected attribute \"extra\"");
+        assert!(
+            err.to_string().contains(r#"in attribute `json_blob.inner3` on `Employee::"12UA45"`, record attribute `extra` should not exist"#),
+            "actual error message was {err}"
+        );
+        assert_matches!(
+            err,
+            EntitiesError::Deserialization(JsonDeserializationError::UnexpectedRecordAttr { path, record_attr, .. })
+                if path == vec![SmolStr::from("inner3")] && record_attr == "extra"
+        );
+    }
+
     #[cfg(all(feature = "decimal", feature = "ipaddr"))]
     /// entity is missing a required attribute
     #[test]
@@ -2571,6 +3262,30 @@ mod schema_based_parsing_tests {
         );
     }
 
+    /// conformance checking without constructing an `Entities`
+    #[test]
+    fn conformance_errors_only() {
+        let missing_attrs = Entity::with_uid(
+            EntityUID::with_eid_and_type("Employee", "12UA45").expect("valid uid"),
+        );
+        let undeclared = Entity::with_uid(
+            EntityUID::with_eid_and_type("Contractor", "34FB87").expect("valid uid"),
+        );
+        let checker = EntitySchemaConformanceChecker::new(&MockSchema, Extensions::all_available());
+        let errs = checker.validate_entities([&missing_attrs, &undeclared]);
+        assert_matches!(
+            errs.as_slice(),
+            [
+                EntitySchemaConformanceError::MissingRequiredEntityAttr { uid: uid1, .. },
+                EntitySchemaConformanceError::UnexpectedEntityType { uid: uid2, .. },
+            ] if uid1 == &missing_attrs.uid() && uid2 == &undeclared.uid()
+        );
+
+        assert!(checker
+            .validate_entities(std::iter::empty::<&Entity>())
+            .is_empty());
+    }
+
     #[cfg(all(feature = "decimal", feature = "ipaddr"))]
     /// unexpected entity attribute
     #[test]
@@ -1,8 +1,9 @@
 use super::{AttributeType, EntityTypeDescription, Schema, SchemaType};
 use crate::ast::{BorrowedRestrictedExpr, Entity, EntityType, EntityUID, ExprKind, Literal};
 use crate::extensions::{ExtensionFunctionLookupError, Extensions};
+use itertools::Itertools;
 use smol_str::SmolStr;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 
 /// Errors raised when entities do not conform to the schema
@@ -37,6 +38,17 @@ pub enum EntitySchemaConformanceError {
         /// Type which was encountered instead
         actual: Box<SchemaType>,
     },
+    /// The given attribute on the given entity contained a string which is not
+    /// one of the values allowed by the schema
+    #[error("in attribute `{attr}` on `{uid}`, {err}")]
+    DisallowedValue {
+        /// Entity where the disallowed value occurred
+        uid: EntityUID,
+        /// Name of the attribute where the disallowed value occurred
+        attr: SmolStr,
+        /// Underlying error
+        err: Box<DisallowedValueError>,
+    },
     /// Found a set whose elements don't all have the same type. This doesn't match
     /// any possible schema.
     #[error("in attribute `{attr}` on `{uid}`, {err}")]
@@ -99,17 +111,79 @@ pub enum EntitySchemaConformanceError {
         /// Underlying error
         err: ExtensionFunctionLookupError,
     },
+    /// The id of the given entity does not match the format the schema
+    /// declares for its entity type
+    #[error("id of entity `{uid}` does not match the format `{format}` required by the schema")]
+    InvalidIdFormat {
+        /// Entity whose id has the wrong format
+        uid: EntityUID,
+        /// Format the id was expected to match
+        format: SmolStr,
+    },
+    /// The given entity is not a member of an entity which the schema
+    /// requires every entity of its type to be a member of
+    #[error("entity `{uid}` must be a member of `{parent}` according to the schema")]
+    MissingRequiredParent {
+        /// Entity which is missing the parent
+        uid: EntityUID,
+        /// The required parent
+        parent: Box<EntityUID>, // boxed to avoid this variant being very large
+    },
+}
+
+/// Found a string which is not one of the values allowed by the schema
+#[derive(Debug, Error)]
+#[error("value {value:?} is not allowed, expected one of {}", .allowed_values.iter().map(|v| format!("{v:?}")).join(", "))]
+pub struct DisallowedValueError {
+    /// Value which is not allowed
+    pub(crate) value: SmolStr,
+    /// Values which are allowed, in sorted order
+    pub(crate) allowed_values: Vec<SmolStr>,
+}
+
+impl DisallowedValueError {
+    /// Get the string which is not allowed
+    pub fn value(&self) -> &SmolStr {
+        &self.value
+    }
+
+    /// Get the values which are allowed, in sorted order
+    pub fn allowed_values(&self) -> &[SmolStr] {
+        &self.allowed_values
+    }
 }
 
 /// Found a set whose elements don't all have the same type.  This doesn't match
 /// any possible schema.
 #[derive(Debug, Error)]
-#[error("set elements have different types: {ty1} and {ty2}")]
+#[error("set elements have different types: {ty1} and {ty2} (first mismatch at index {index})")]
 pub struct HeterogeneousSetError {
     /// First element type which was found
     ty1: Box<SchemaType>,
     /// Second element type which was found
     ty2: Box<SchemaType>,
+    /// Index of the first element whose type conflicts with `ty1`
+    index: usize,
+}
+
+impl HeterogeneousSetError {
+    /// Get the type of the first element of the set. All other elements were
+    /// expected to be consistent with this type.
+    pub fn first_type(&self) -> &SchemaType {
+        &self.ty1
+    }
+
+    /// Get the type of the first element which was not consistent with
+    /// `first_type()`
+    pub fn conflicting_type(&self) -> &SchemaType {
+        &self.ty2
+    }
+
+    /// Get the index (in the order the set elements were written) of the
+    /// element with type `conflicting_type()`
+    pub fn conflicting_index(&self) -> usize {
+        self.index
+    }
 }
 
 /// Struct used to check whether entities conform to a schema
@@ -119,16 +193,73 @@ pub struct EntitySchemaConformanceChecker<'a, S: Schema> {
     schema: &'a S,
     /// Extensions which are active for the conformance checks
     extensions: Extensions<'a>,
+    /// Whether attributes containing calls to unknown extension functions are
+    /// accepted without checking their types
+    opaque_unknown_extensions: bool,
 }
 
 impl<'a, S: Schema> EntitySchemaConformanceChecker<'a, S> {
     /// Create a new checker
     pub fn new(schema: &'a S, extensions: Extensions<'a>) -> Self {
-        Self { schema, extensions }
+        Self {
+            schema,
+            extensions,
+            opaque_unknown_extensions: false,
+        }
+    }
+
+    /// Accept attributes containing calls to extension functions which are
+    /// not available, instead of returning an error. The types of those
+    /// attributes cannot be checked.
+    pub(crate) fn with_opaque_unknown_extensions(self) -> Self {
+        Self {
+            opaque_unknown_extensions: true,
+            ..self
+        }
+    }
+
+    /// The schema this checker checks conformance with
+    pub(crate) fn schema(&self) -> &'a S {
+        self.schema
+    }
+
+    /// Validate each of the given entities against the schema, returning all
+    /// of the [`EntitySchemaConformanceError`]s encountered (at most one per
+    /// entity).
+    ///
+    /// Unlike `Entities::from_entities()`, this does not build an `Entities`
+    /// or compute transitive closure, so it is suitable for checking entity
+    /// data which will not otherwise be used.
+    pub fn validate_entities<'e>(
+        &self,
+        entities: impl IntoIterator<Item = &'e Entity>,
+    ) -> Vec<EntitySchemaConformanceError> {
+        let entities = entities.into_iter().collect::<Vec<_>>();
+        let by_uid = entities
+            .iter()
+            .map(|entity| (entity.uid(), *entity))
+            .collect::<HashMap<_, _>>();
+        entities
+            .into_iter()
+            .filter_map(|entity| {
+                self.validate_entity(entity)
+                    .and_then(|()| {
+                        self.validate_required_parents(entity, |parent| {
+                            is_ancestor_in(&by_uid, entity, parent)
+                        })
+                    })
+                    .err()
+            })
+            .collect()
     }
 
     /// Validate an entity against the schema, returning an
     /// [`EntitySchemaConformanceError`] if it does not comply.
+    ///
+    /// This does not check that the entity is a member of the entities which
+    /// the schema requires, since it may be a member of them through other
+    /// entities. That is checked by `validate_entities`, and when constructing
+    /// `Entities` once the transitive closure is computed.
     pub fn validate_entity(&self, entity: &Entity) -> Result<(), EntitySchemaConformanceError> {
         let uid = entity.uid();
         let etype = uid.entity_type();
@@ -157,6 +288,12 @@ impl<'a, S: Schema> EntitySchemaConformanceChecker<'a, S> {
                     suggested_types,
                 }
             })?;
+            schema_etype.check_id_format(uid.eid()).map_err(|format| {
+                EntitySchemaConformanceError::InvalidIdFormat {
+                    uid: uid.clone(),
+                    format,
+                }
+            })?;
             // Ensure that all required attributes for `etype` are actually
             // included in `entity`
             for required_attr in schema_etype.required_attrs() {
@@ -182,10 +319,20 @@ impl<'a, S: Schema> EntitySchemaConformanceChecker<'a, S> {
                     Some(expected_ty) => {
                         // typecheck: ensure that the entity attribute value matches
                         // the expected type
-                        match type_of_restricted_expr(val, self.extensions) {
+                        match type_of_restricted_expr(val.clone(), self.extensions) {
                             Ok(actual_ty) => {
                                 if actual_ty.is_consistent_with(&expected_ty) {
-                                    // typecheck passes
+                                    // typecheck passes, but strings must also
+                                    // be among the values allowed by the schema
+                                    if let Some(err) = expected_ty.find_disallowed_value(val) {
+                                        return Err(
+                                            EntitySchemaConformanceError::DisallowedValue {
+                                                uid: uid.clone(),
+                                                attr: attr.into(),
+                                                err: Box::new(err),
+                                            },
+                                        );
+                                    }
                                 } else {
                                     return Err(EntitySchemaConformanceError::TypeMismatch {
                                         uid: uid.clone(),
@@ -202,6 +349,12 @@ impl<'a, S: Schema> EntitySchemaConformanceChecker<'a, S> {
                                     err,
                                 });
                             }
+                            Err(TypeOfRestrictedExprError::ExtensionFunctionLookup(_))
+                                if self.opaque_unknown_extensions =>
+                            {
+                                // the attribute holds an opaque extension
+                                // value, so there is nothing more to check
+                            }
                             Err(TypeOfRestrictedExprError::ExtensionFunctionLookup(err)) => {
                                 return Err(
                                     EntitySchemaConformanceError::ExtensionFunctionLookup {
@@ -234,6 +387,53 @@ impl<'a, S: Schema> EntitySchemaConformanceChecker<'a, S> {
         }
         Ok(())
     }
+
+    /// Validate that `entity` is a member of every entity which the schema
+    /// requires all entities of its type to be members of, returning
+    /// [`EntitySchemaConformanceError::MissingRequiredParent`] otherwise.
+    /// `is_ancestor` decides whether an entity is an ancestor of `entity`, so
+    /// this should be checked once the transitive closure of the entity
+    /// hierarchy is known.
+    pub(crate) fn validate_required_parents(
+        &self,
+        entity: &Entity,
+        is_ancestor: impl Fn(&EntityUID) -> bool,
+    ) -> Result<(), EntitySchemaConformanceError> {
+        let uid = entity.uid();
+        let Some(schema_etype) = self.schema.entity_type(uid.entity_type()) else {
+            return Ok(());
+        };
+        for parent in schema_etype.required_parents() {
+            if !is_ancestor(&parent) {
+                return Err(EntitySchemaConformanceError::MissingRequiredParent {
+                    uid,
+                    parent: Box::new(parent),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Is `ancestor` an ancestor of `entity`, following the parents of the
+/// entities in `entities`? The ancestors of each entity need not be
+/// transitively closed.
+pub(crate) fn is_ancestor_in(
+    entities: &HashMap<EntityUID, &Entity>,
+    entity: &Entity,
+    ancestor: &EntityUID,
+) -> bool {
+    let mut visited = HashSet::new();
+    let mut worklist = entity.ancestors().collect::<Vec<_>>();
+    while let Some(uid) = worklist.pop() {
+        if uid == ancestor {
+            return true;
+        }
+        if visited.insert(uid) {
+            worklist.extend(entities.get(uid).into_iter().flat_map(|e| e.ancestors()));
+        }
+    }
+    false
 }
 
 /// Errors thrown by [`type_of_restricted_expr()`]
@@ -274,20 +474,21 @@ pub fn type_of_restricted_expr(
         ExprKind::Set(elements) => {
             let mut element_types = elements.iter().map(|el| {
                 type_of_restricted_expr(BorrowedRestrictedExpr::new_unchecked(el), extensions) // assuming the invariant holds for the set as a whole, it will also hold for each element
-            });
+            }).enumerate();
             match element_types.next() {
                 None => Ok(SchemaType::EmptySet),
-                Some(Err(e)) => Err(e),
-                Some(Ok(element_ty)) => {
+                Some((_, Err(e))) => Err(e),
+                Some((_, Ok(element_ty))) => {
                     let matches_element_ty = |ty: &Result<SchemaType, TypeOfRestrictedExprError>| matches!(ty, Ok(ty) if ty.is_consistent_with(&element_ty));
-                    let conflicting_ty = element_types.find(|ty| !matches_element_ty(ty));
+                    let conflicting_ty = element_types.find(|(_, ty)| !matches_element_ty(ty));
                     match conflicting_ty {
                         None => Ok(SchemaType::Set { element_ty: Box::new(element_ty) }),
-                        Some(Ok(conflicting_ty)) => Err(HeterogeneousSetError {
+                        Some((index, Ok(conflicting_ty))) => Err(HeterogeneousSetError {
                                 ty1: Box::new(element_ty),
                                 ty2: Box::new(conflicting_ty),
+                                index,
                         }.into()),
-                        Some(Err(e)) => Err(e),
+                        Some((_, Err(e))) => Err(e),
                     }
                 }
             }
@@ -19,10 +19,12 @@ use super::{
     JsonDeserializationErrorContext, JsonSerializationError, NoEntitiesSchema, Schema, TypeAndId,
     ValueParser,
 };
-use crate::ast::{Entity, EntityType, EntityUID, RestrictedExpr};
+use crate::ast::{Entity, EntityType, EntityUID, ExprKind, Name, RestrictedExpr};
+use crate::entities::conformance::is_ancestor_in;
 use crate::entities::{
     type_of_restricted_expr, unwrap_or_clone, Entities, EntitiesError,
-    EntitySchemaConformanceError, TCComputation, TypeOfRestrictedExprError,
+    EntitySchemaConformanceChecker, EntitySchemaConformanceError, TCComputation,
+    TypeOfRestrictedExprError,
 };
 use crate::extensions::Extensions;
 use crate::jsonvalue::JsonValueWithNoDuplicateKeys;
@@ -31,6 +33,11 @@ use serde_with::serde_as;
 use smol_str::SmolStr;
 use std::collections::HashMap;
 use std::sync::Arc;
+use thiserror::Error;
+
+/// The value written in place of the value of each redacted attribute by
+/// [`EntityJson::from_entity_redacted()`].
+pub const REDACTED_ATTRIBUTE_VALUE: &str = "<redacted>";
 
 /// Serde JSON format for a single entity
 #[serde_as]
@@ -51,6 +58,10 @@ pub struct EntityJson {
     parents: Vec<EntityUidJson>,
 }
 
+/// The result of parsing and checking each entity, as returned by
+/// [`EntityJsonParser::from_json_value_per_entity_results()`].
+pub type PerEntityResults = HashMap<EntityUID, Result<(), JsonDeserializationError>>;
+
 /// Struct used to parse entities from JSON.
 #[derive(Debug, Clone)]
 pub struct EntityJsonParser<'e, 's, S: Schema = NoEntitiesSchema> {
@@ -69,6 +80,47 @@ pub struct EntityJsonParser<'e, 's, S: Schema = NoEntitiesSchema> {
     tc_computation: TCComputation,
 }
 
+/// Warning returned by the lenient parsing methods of [`EntityJsonParser`]:
+/// an entity attribute contains an extension value which could not be
+/// interpreted, because its extension function is not available. The value is
+/// kept as an opaque call to that function.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("attribute `{attr}` on `{uid}` contains an uninterpreted value of unknown extension function `{fn_name}`")]
+pub struct OpaqueExtensionValue {
+    /// Entity with the opaque value
+    uid: EntityUID,
+    /// Name of the attribute containing the opaque value
+    attr: SmolStr,
+    /// Name of the unknown extension function
+    fn_name: Name,
+    /// Arguments to the unknown extension function
+    args: Vec<RestrictedExpr>,
+}
+
+impl OpaqueExtensionValue {
+    /// Entity with the opaque value
+    pub fn uid(&self) -> &EntityUID {
+        &self.uid
+    }
+
+    /// Name of the attribute containing the opaque value
+    pub fn attr(&self) -> &SmolStr {
+        &self.attr
+    }
+
+    /// Name of the unknown extension function. For values which omitted the
+    /// `__extn` escape, this is the name of the extension type the schema
+    /// expected.
+    pub fn fn_name(&self) -> &Name {
+        &self.fn_name
+    }
+
+    /// Arguments to the unknown extension function
+    pub fn args(&self) -> &[RestrictedExpr] {
+        &self.args
+    }
+}
+
 /// Schema information about a single entity can take one of these forms:
 enum EntitySchemaInfo<E: EntityTypeDescription> {
     /// There is no schema, i.e. we're not doing schema-based parsing
@@ -143,6 +195,146 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
         self.parse_ejsons(ejsons)
     }
 
+    /// Parse an entities JSON file (in [`&str`] form) into an [`Entities`]
+    /// object, keeping extension values which cannot be interpreted.
+    ///
+    /// This is like [`EntityJsonParser::from_json_str()`], except that
+    /// extension values whose extension function (or, when the `__extn` escape
+    /// is omitted, whose constructor) is not among the active extensions do not
+    /// cause an error. They are kept as opaque extension function calls,
+    /// retaining the function name and arguments, their types are not checked
+    /// against the `schema`, and each is reported in the returned warnings.
+    /// Evaluating an attribute containing an opaque value will fail.
+    pub fn from_json_str_lenient(
+        &self,
+        json: &str,
+    ) -> Result<(Entities, Vec<OpaqueExtensionValue>), EntitiesError> {
+        let ejsons: Vec<EntityJson> =
+            serde_json::from_str(json).map_err(JsonDeserializationError::from)?;
+        self.parse_ejsons_lenient(ejsons)
+    }
+
+    /// Parse an entities JSON file (in [`serde_json::Value`] form) into an
+    /// [`Entities`] object, keeping extension values which cannot be
+    /// interpreted.
+    ///
+    /// See [`EntityJsonParser::from_json_str_lenient()`].
+    pub fn from_json_value_lenient(
+        &self,
+        json: serde_json::Value,
+    ) -> Result<(Entities, Vec<OpaqueExtensionValue>), EntitiesError> {
+        let ejsons: Vec<EntityJson> =
+            serde_json::from_value(json).map_err(JsonDeserializationError::from)?;
+        self.parse_ejsons_lenient(ejsons)
+    }
+
+    /// Parse an entities JSON file (in [`std::io::Read`] form) into an
+    /// [`Entities`] object, keeping extension values which cannot be
+    /// interpreted.
+    ///
+    /// See [`EntityJsonParser::from_json_str_lenient()`].
+    pub fn from_json_file_lenient(
+        &self,
+        json: impl std::io::Read,
+    ) -> Result<(Entities, Vec<OpaqueExtensionValue>), EntitiesError> {
+        let ejsons: Vec<EntityJson> =
+            serde_json::from_reader(json).map_err(JsonDeserializationError::from)?;
+        self.parse_ejsons_lenient(ejsons)
+    }
+
+    /// Parse an entities JSON file (in [`serde_json::Value`] form) into an
+    /// [`Entities`] object, parsing and checking each entity independently of
+    /// the others and reporting the result for every entity.
+    ///
+    /// Each entity which fails to parse or, if the `EntityJsonParser` has a
+    /// `schema`, to conform to the `schema` is mapped to its error and left
+    /// out of the returned [`Entities`]. This includes an entity which is not
+    /// a member, through the entities which are included, of an entity the
+    /// `schema` requires. Every other entity is mapped to `Ok(())` and
+    /// included, and the transitive closure is computed over those entities
+    /// only (plus the `Action` entities declared in the `schema`). An error is returned instead if the JSON is not a list of
+    /// entities, if an entity's `uid` is malformed, if two entities have the
+    /// same `uid`, or if computing the transitive closure fails.
+    pub fn from_json_value_per_entity_results(
+        &self,
+        json: serde_json::Value,
+    ) -> Result<(Entities, PerEntityResults), EntitiesError> {
+        let ejsons: Vec<EntityJson> =
+            serde_json::from_value(json).map_err(JsonDeserializationError::from)?;
+        let checker = self
+            .schema
+            .map(|schema| EntitySchemaConformanceChecker::new(schema, self.extensions));
+        let mut results = HashMap::new();
+        let mut entities = Vec::new();
+        for ejson in ejsons {
+            let uid = ejson
+                .uid
+                .clone()
+                .into_euid(|| JsonDeserializationErrorContext::EntityUid)?;
+            if results.contains_key(&uid) {
+                return Err(EntitiesError::Duplicate(uid));
+            }
+            let result = self.parse_ejson(ejson, false).and_then(|entity| {
+                if let Some(checker) = &checker {
+                    checker
+                        .validate_entity(&entity)
+                        .map_err(JsonDeserializationError::EntitySchemaConformance)?;
+                }
+                Ok(entity)
+            });
+            match result {
+                Ok(entity) => {
+                    entities.push(entity);
+                    results.insert(uid, Ok(()));
+                }
+                Err(err) => {
+                    results.insert(uid, Err(err));
+                }
+            }
+        }
+        // Required parents may be reached through other entities, so they are
+        // checked over the entities which conform. Leaving out an entity may
+        // leave another without a path to its required parent, so this is
+        // repeated until every remaining entity has its required parents.
+        if let Some(checker) = &checker {
+            loop {
+                let missing = {
+                    let by_uid = entities
+                        .iter()
+                        .map(|entity| (entity.uid(), entity))
+                        .collect::<HashMap<_, _>>();
+                    entities
+                        .iter()
+                        .filter_map(|entity| {
+                            checker
+                                .validate_required_parents(entity, |parent| {
+                                    is_ancestor_in(&by_uid, entity, parent)
+                                })
+                                .err()
+                                .map(|err| (entity.uid(), err))
+                        })
+                        .collect::<Vec<_>>()
+                };
+                if missing.is_empty() {
+                    break;
+                }
+                for (uid, err) in missing {
+                    entities.retain(|entity| entity.uid() != uid);
+                    results.insert(
+                        uid,
+                        Err(JsonDeserializationError::EntitySchemaConformance(err)),
+                    );
+                }
+            }
+        }
+        // Passing the checker again adds the `Action` entities declared in the
+        // schema. The remaining entities conform and have their required
+        // parents, so checking them again does not fail.
+        let entities =
+            Entities::from_entities_with_checker(entities, checker, self.tc_computation)?;
+        Ok((entities, results))
+    }
+
     /// Parse an entities JSON file (in [`&str`] form) into an iterator over [`Entity`]s.
     ///
     /// If the `EntityJsonParser` has a `schema`, this also adds `Action`
@@ -191,7 +383,7 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
     ) -> Result<impl Iterator<Item = Entity> + '_, EntitiesError> {
         let mut entities: Vec<Entity> = ejsons
             .into_iter()
-            .map(|ejson| self.parse_ejson(ejson).map_err(EntitiesError::from))
+            .map(|ejson| self.parse_ejson(ejson, false).map_err(EntitiesError::from))
             .collect::<Result<_, _>>()?;
         if let Some(schema) = &self.schema {
             entities.extend(schema.action_entities().into_iter().map(unwrap_or_clone));
@@ -210,16 +402,64 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
     ) -> Result<Entities, EntitiesError> {
         let entities: Vec<Entity> = ejsons
             .into_iter()
-            .map(|ejson| self.parse_ejson(ejson))
+            .map(|ejson| self.parse_ejson(ejson, false))
             .collect::<Result<_, _>>()?;
         Entities::from_entities(entities, self.schema, self.tc_computation, self.extensions)
     }
 
+    /// Internal function that creates an [`Entities`] from a stream of
+    /// [`EntityJson`], keeping extension values which cannot be interpreted
+    /// and returning a warning for each attribute containing one.
+    fn parse_ejsons_lenient(
+        &self,
+        ejsons: impl IntoIterator<Item = EntityJson>,
+    ) -> Result<(Entities, Vec<OpaqueExtensionValue>), EntitiesError> {
+        let entities: Vec<Entity> = ejsons
+            .into_iter()
+            .map(|ejson| self.parse_ejson(ejson, true))
+            .collect::<Result<_, _>>()?;
+        let mut warnings = Vec::new();
+        for entity in &entities {
+            for (attr, val) in entity.attrs() {
+                for e in val.as_ref().subexpressions() {
+                    if let ExprKind::ExtensionFunctionApp { fn_name, args } = e.expr_kind() {
+                        if self.extensions.func(fn_name).is_err() {
+                            warnings.push(OpaqueExtensionValue {
+                                uid: entity.uid(),
+                                attr: attr.into(),
+                                fn_name: fn_name.clone(),
+                                args: args
+                                    .iter()
+                                    .cloned()
+                                    .map(RestrictedExpr::new_unchecked)
+                                    .collect(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        let checker = self.schema.map(|schema| {
+            EntitySchemaConformanceChecker::new(schema, self.extensions)
+                .with_opaque_unknown_extensions()
+        });
+        let entities =
+            Entities::from_entities_with_checker(entities, checker, self.tc_computation)?;
+        Ok((entities, warnings))
+    }
+
     /// Internal function that parses an `EntityJson` into an `Entity`.
     ///
     /// This function is not responsible for fully validating the `Entity`
     /// against the `schema`; that happens on construction of an `Entities`
-    fn parse_ejson(&self, ejson: EntityJson) -> Result<Entity, JsonDeserializationError> {
+    ///
+    /// If `opaque_unknown_extensions` is set, extension values which cannot be
+    /// interpreted are kept as opaque extension function calls.
+    fn parse_ejson(
+        &self,
+        ejson: EntityJson,
+        opaque_unknown_extensions: bool,
+    ) -> Result<Entity, JsonDeserializationError> {
         let uid = ejson
             .uid
             .into_euid(|| JsonDeserializationErrorContext::EntityUid)?;
@@ -251,8 +491,12 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
                 }
             }
         };
-        let vparser = ValueParser::new(self.extensions);
-        let attrs: HashMap<SmolStr, RestrictedExpr> = ejson
+        let vparser = if opaque_unknown_extensions {
+            ValueParser::new(self.extensions).with_opaque_unknown_extensions()
+        } else {
+            ValueParser::new(self.extensions)
+        };
+        let mut attrs: HashMap<SmolStr, RestrictedExpr> = ejson
             .attrs
             .into_iter()
             .map(|(k, v)| match &entity_schema_info {
@@ -348,6 +592,13 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
                 }
             })
             .collect::<Result<_, JsonDeserializationError>>()?;
+        if let EntitySchemaInfo::NonAction(desc) = &entity_schema_info {
+            // optional attributes which are absent take their default value
+            // from the schema, if the schema declares one
+            for (attr, default) in desc.attr_defaults() {
+                attrs.entry(attr).or_insert(default);
+            }
+        }
         let is_parent_allowed = |parent_euid: &EntityUID| {
             // full validation isn't done in this function (see doc comments on
             // this function), but we do need to do the following check which
@@ -389,16 +640,37 @@ impl EntityJson {
     ///
     /// (for the reverse transformation, use `EntityJsonParser`)
     pub fn from_entity(entity: &Entity) -> Result<Self, JsonSerializationError> {
+        Self::from_entity_redacted(entity, |_| false)
+    }
+
+    /// Convert an `Entity` into an `EntityJson`, replacing the value of each
+    /// attribute for which `redact` returns `true` with the string
+    /// [`REDACTED_ATTRIBUTE_VALUE`]. `redact` is given the path to the
+    /// attribute: an attribute of the entity, followed by the attributes of
+    /// the records nested inside it, if any. Records in sets are reached
+    /// through the path of the set.
+    pub fn from_entity_redacted(
+        entity: &Entity,
+        redact: impl Fn(&[SmolStr]) -> bool,
+    ) -> Result<Self, JsonSerializationError> {
         Ok(Self {
             // for now, we encode `uid` and `parents` using an implied `__entity` escape
             uid: EntityUidJson::ImplicitEntityEscape(TypeAndId::from(entity.uid())),
             attrs: entity
                 .attrs()
                 .map(|(k, expr)| {
-                    Ok((
-                        k.into(),
-                        serde_json::to_value(CedarValueJson::from_expr(expr)?)?.into(),
-                    ))
+                    let mut path = vec![SmolStr::from(k)];
+                    let value = if redact(&path) {
+                        serde_json::Value::from(REDACTED_ATTRIBUTE_VALUE)
+                    } else {
+                        serde_json::to_value(redact_nested(
+                            CedarValueJson::from_expr(expr)
+                                .map_err(|e| e.in_attribute(k).in_entity(entity.uid()))?,
+                            &mut path,
+                            &redact,
+                        ))?
+                    };
+                    Ok((k.into(), value.into()))
                 })
                 .collect::<Result<_, JsonSerializationError>>()?,
             parents: entity
@@ -409,6 +681,40 @@ impl EntityJson {
     }
 }
 
+/// Replace the attributes of the records in `value`, which is at `path`, for
+/// which `redact` returns `true` with [`REDACTED_ATTRIBUTE_VALUE`], as
+/// described for [`EntityJson::from_entity_redacted()`].
+fn redact_nested(
+    value: CedarValueJson,
+    path: &mut Vec<SmolStr>,
+    redact: &impl Fn(&[SmolStr]) -> bool,
+) -> CedarValueJson {
+    match value {
+        CedarValueJson::Record(record) => CedarValueJson::Record(
+            record
+                .into_iter()
+                .map(|(attr, value)| {
+                    path.push(attr.clone());
+                    let value = if redact(path) {
+                        CedarValueJson::String(REDACTED_ATTRIBUTE_VALUE.into())
+                    } else {
+                        redact_nested(value, path, redact)
+                    };
+                    path.pop();
+                    (attr, value)
+                })
+                .collect(),
+        ),
+        CedarValueJson::Set(elements) => CedarValueJson::Set(
+            elements
+                .into_iter()
+                .map(|element| redact_nested(element, path, redact))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
 // PANIC SAFETY unit test code
 #[allow(clippy::panic)]
 #[cfg(test)]
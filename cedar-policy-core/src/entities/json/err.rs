@@ -17,8 +17,12 @@
 use std::fmt::Display;
 
 use super::SchemaType;
-use crate::ast::{EntityUID, Expr, ExprKind, Name, PolicyID, RestrictedExpr, RestrictedExprError};
-use crate::entities::conformance::{EntitySchemaConformanceError, HeterogeneousSetError};
+use crate::ast::{
+    EntityType, EntityUID, Expr, ExprKind, Name, PolicyID, RestrictedExpr, RestrictedExprError,
+};
+use crate::entities::conformance::{
+    DisallowedValueError, EntitySchemaConformanceError, HeterogeneousSetError,
+};
 use crate::extensions::ExtensionFunctionLookupError;
 use crate::parser::err::ParseErrors;
 use either::Either;
@@ -85,6 +89,13 @@ pub enum JsonDeserializationError {
         /// Expression we got instead
         got: Box<RestrictedExpr>,
     },
+    /// Context was parsed against the context type of an action, but that
+    /// action is not declared in the schema
+    #[error("action `{action}` is not declared in the schema")]
+    UndeclaredAction {
+        /// Action which is not declared
+        action: Box<EntityUID>,
+    },
     /// Parents of actions should be actions, but this action has a non-action parent
     #[error("action `{uid}` has a non-action parent `{parent}`")]
     ActionParentIsNotAction {
@@ -95,7 +106,12 @@ pub enum JsonDeserializationError {
     },
     /// Schema-based parsing needed an implicit extension constructor, but no suitable
     /// constructor was found
-    #[error("{ctx}, missing extension constructor for {arg_type} -> {return_type}")]
+    #[error("{ctx}, missing extension constructor for {arg_type} -> {return_type}{}",
+        match .available_constructors.as_slice() {
+            [] => String::new(),
+            constructors => format!("; found {}", constructors.iter().map(|c| format!("`{c}`")).join(", ")),
+        }
+    )]
     MissingImpliedConstructor {
         /// Context of this error
         ctx: Box<JsonDeserializationErrorContext>,
@@ -103,6 +119,10 @@ pub enum JsonDeserializationError {
         return_type: Box<SchemaType>,
         /// argument type of the constructor we were looking for
         arg_type: Box<SchemaType>,
+        /// Signatures of the available single-argument constructors with the
+        /// right return type but a different argument type, e.g.,
+        /// `ip(string) -> ipaddr`, in sorted order
+        available_constructors: Vec<String>,
     },
     /// The same key appears two or more times in a single record literal
     #[error("{ctx}, duplicate key `{key}` in record literal")]
@@ -119,21 +139,43 @@ pub enum JsonDeserializationError {
     /// listed below, among other things.
     #[error(transparent)]
     EntitySchemaConformance(EntitySchemaConformanceError),
+    /// During schema-based parsing, encountered a string which is not one of
+    /// the values allowed by the schema
+    #[error("{}, {err}", display_ctx_with_path(.ctx, .path))]
+    DisallowedValue {
+        /// Context of this error, which will be something other than `EntityAttribute`.
+        /// (Disallowed values in entity attributes are reported as
+        /// `Self::EntitySchemaConformance`.)
+        ctx: Box<JsonDeserializationErrorContext>,
+        /// Keys of the nested records leading from the value described by
+        /// `ctx` to the disallowed value, if any
+        path: Vec<SmolStr>,
+        /// Underlying error
+        err: Box<DisallowedValueError>,
+    },
     /// During schema-based parsing, encountered this attribute on a record, but
     /// that attribute shouldn't exist on that record
-    #[error("{ctx}, record attribute `{record_attr}` should not exist according to the schema")]
+    #[error("{}, record attribute `{record_attr}` should not exist according to the schema", display_ctx_with_path(.ctx, .path))]
     UnexpectedRecordAttr {
         /// Context of this error
         ctx: Box<JsonDeserializationErrorContext>,
+        /// Keys of the nested records leading from the value described by
+        /// `ctx` to the record with the unexpected attribute. This is empty
+        /// when that record is the value described by `ctx` itself.
+        path: Vec<SmolStr>,
         /// Name of the (Record) attribute which was unexpected
         record_attr: SmolStr,
     },
     /// During schema-based parsing, didn't encounter this attribute of a
     /// record, but that attribute should have existed
-    #[error("{ctx}, expected the record to have an attribute `{record_attr}`, but it does not")]
+    #[error("{}, expected the record to have an attribute `{record_attr}`, but it does not", display_ctx_with_path(.ctx, .path))]
     MissingRequiredRecordAttr {
         /// Context of this error
         ctx: Box<JsonDeserializationErrorContext>,
+        /// Keys of the nested records leading from the value described by
+        /// `ctx` to the record missing the attribute. This is empty when that
+        /// record is the value described by `ctx` itself.
+        path: Vec<SmolStr>,
         /// Name of the (Record) attribute which was expected
         record_attr: SmolStr,
     },
@@ -190,11 +232,58 @@ pub enum JsonDeserializationError {
         /// Underlying error
         err: ExtensionFunctionLookupError,
     },
+    /// During schema-based parsing, encountered a JSON integer which is
+    /// outside the range of `Long`, i.e., of a signed 64-bit integer, where
+    /// the schema expected a `Long`
+    #[error("{ctx}, integer `{value}` is out of range for type long")]
+    IntegerOverflow {
+        /// Context of this error
+        ctx: Box<JsonDeserializationErrorContext>,
+        /// The integer which is out of range
+        value: serde_json::Number,
+    },
+    /// During schema-based parsing, encountered a JSON floating-point number
+    /// where the schema expected a `Long`
+    #[error("{ctx}, expected an integer of type long, but got floating-point number `{value}`")]
+    ExpectedInteger {
+        /// Context of this error
+        ctx: Box<JsonDeserializationErrorContext>,
+        /// The floating-point number we got instead
+        value: serde_json::Number,
+    },
     /// Raised when a JsonValue contains the no longer supported `__expr` escape
     #[error("{0}, invalid escape. The `__expr` escape is no longer supported")]
     ExprTag(Box<JsonDeserializationErrorContext>),
 }
 
+impl JsonDeserializationError {
+    /// Returns `true` if this error indicates that well-formed input did not
+    /// conform to the schema, e.g. a type mismatch or an unexpected or missing
+    /// record attribute.
+    pub fn is_schema_conformance(&self) -> bool {
+        matches!(
+            self,
+            Self::EntitySchemaConformance(_)
+                | Self::TypeMismatch { .. }
+                | Self::UnexpectedRecordAttr { .. }
+                | Self::MissingRequiredRecordAttr { .. }
+                | Self::HeterogeneousSet { .. }
+                | Self::DisallowedValue { .. }
+                | Self::IntegerOverflow { .. }
+                | Self::ExpectedInteger { .. }
+        )
+    }
+
+    /// Returns `true` if this error indicates that the input was not valid
+    /// JSON, or that it contained an escape which could not be parsed.
+    ///
+    /// Errors that are neither malformed JSON nor schema conformance errors
+    /// return `false` from both this method and `is_schema_conformance()`.
+    pub fn is_malformed_json(&self) -> bool {
+        matches!(self, Self::Serde(_) | Self::ParseEscape { .. })
+    }
+}
+
 /// Errors thrown during serialization to JSON
 #[derive(Debug, Error)]
 pub enum JsonSerializationError {
@@ -217,10 +306,17 @@ pub enum JsonSerializationError {
     },
     /// Encountered a `Record` which can't be serialized to JSON because it
     /// contains a key which is reserved as a JSON escape.
-    #[error("record uses reserved key `{key}`")]
+    #[error("{}record uses reserved key `{key}`", display_reserved_key_location(.uid.as_ref(), .path))]
     ReservedKey {
         /// Reserved key which was used by the `Record`
         key: SmolStr,
+        /// The entity whose attribute contains the `Record`, if it occurred
+        /// while serializing an entity
+        uid: Option<EntityUID>,
+        /// The attribute containing the `Record`, followed by the keys of the
+        /// nested records leading to it. Empty if the `Record` is the value
+        /// being serialized.
+        path: Vec<SmolStr>,
     },
     /// Encountered an `ExprKind` which we didn't expect. Either a case is
     /// missing in `CedarValueJson::from_expr()`, or an internal invariant was
@@ -232,6 +328,55 @@ pub enum JsonSerializationError {
     },
 }
 
+impl JsonSerializationError {
+    /// A stable identifier for the kind of this error, which does not change
+    /// with the wording of its message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Serde(_) => "cedar_policy_core::entities::json_serialization::serde",
+            Self::ExtnCall0Arguments { .. } => {
+                "cedar_policy_core::entities::json_serialization::extn_call_0_arguments"
+            }
+            Self::ExtnCall2OrMoreArguments { .. } => {
+                "cedar_policy_core::entities::json_serialization::extn_call_2_or_more_arguments"
+            }
+            Self::ReservedKey { .. } => {
+                "cedar_policy_core::entities::json_serialization::reserved_key"
+            }
+            Self::UnexpectedRestrictedExprKind { .. } => {
+                "cedar_policy_core::entities::json_serialization::unexpected_restricted_expr_kind"
+            }
+        }
+    }
+
+    /// Record that this error occurred in the value of the attribute `attr`
+    /// of a record or entity.
+    pub(crate) fn in_attribute(mut self, attr: &str) -> Self {
+        if let Self::ReservedKey { path, .. } = &mut self {
+            path.insert(0, attr.into());
+        }
+        self
+    }
+
+    /// Record that this error occurred in an attribute of the entity `uid`.
+    pub(crate) fn in_entity(mut self, entity: EntityUID) -> Self {
+        if let Self::ReservedKey { uid, .. } = &mut self {
+            uid.get_or_insert(entity);
+        }
+        self
+    }
+}
+
+/// Display where a record using a reserved key was found, for inclusion at the
+/// start of the message of `JsonSerializationError::ReservedKey`.
+fn display_reserved_key_location(uid: Option<&EntityUID>, path: &[SmolStr]) -> String {
+    match uid {
+        _ if path.is_empty() => String::new(),
+        Some(uid) => format!("in attribute `{}` on `{uid}`, ", path.iter().join(".")),
+        None => format!("in record attribute `{}`, ", path.iter().join(".")),
+    }
+}
+
 /// Gives information about the context of a JSON deserialization error (e.g.,
 /// where we were in the JSON document).
 #[derive(Debug, Clone)]
@@ -257,6 +402,14 @@ pub enum JsonDeserializationErrorContext {
         /// ID of the policy we were deserializing
         id: PolicyID,
     },
+    /// The error occurred while deserializing the default value which a
+    /// schema declares for the attribute `attr` of an entity type.
+    AttributeDefault {
+        /// Entity type whose attribute has the default value
+        entity_type: EntityType,
+        /// Attribute with the default value
+        attr: SmolStr,
+    },
 }
 
 impl std::fmt::Display for JsonDeserializationErrorContext {
@@ -267,7 +420,25 @@ impl std::fmt::Display for JsonDeserializationErrorContext {
             Self::EntityUid => write!(f, "in uid field of <unknown entity>"),
             Self::Context => write!(f, "while parsing context"),
             Self::Policy { id } => write!(f, "while parsing JSON policy `{id}`"),
+            Self::AttributeDefault { entity_type, attr } => write!(
+                f,
+                "in default value for attribute `{attr}` of entity type `{entity_type}`"
+            ),
+        }
+    }
+}
+
+/// Display the context of an error which occurred inside nested records,
+/// including the `path` of record keys leading to the error. For entity
+/// attributes, the path is appended to the attribute name, e.g.,
+/// "in attribute `address.zip` on `User::"alice"`".
+fn display_ctx_with_path(ctx: &JsonDeserializationErrorContext, path: &[SmolStr]) -> String {
+    match ctx {
+        _ if path.is_empty() => ctx.to_string(),
+        JsonDeserializationErrorContext::EntityAttribute { uid, attr } => {
+            format!("in attribute `{attr}.{}` on `{uid}`", path.iter().join("."))
         }
+        ctx => format!("{ctx}, in record attribute `{}`", path.iter().join(".")),
     }
 }
 
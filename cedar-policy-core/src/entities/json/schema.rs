@@ -1,5 +1,5 @@
 use super::SchemaType;
-use crate::ast::{Entity, EntityType, EntityUID, Id, Name};
+use crate::ast::{Eid, Entity, EntityType, EntityUID, Id, Name, RestrictedExpr};
 use smol_str::SmolStr;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
@@ -110,6 +110,31 @@ pub trait EntityTypeDescription {
 
     /// Get the entity types which are allowed to be parents of this entity type.
     fn allowed_parent_types(&self) -> Arc<HashSet<EntityType>>;
+
+    /// Get the default values for optional attributes of this entity type.
+    /// When parsing entity data, an optional attribute that is absent will be
+    /// given its default value, if it has one.
+    ///
+    /// By default, no attributes have default values.
+    fn attr_defaults<'s>(&'s self) -> Box<dyn Iterator<Item = (SmolStr, RestrictedExpr)> + 's> {
+        Box::new(std::iter::empty())
+    }
+
+    /// Check that `eid` is a valid id for entities of this type, returning
+    /// the expected format if it is not.
+    ///
+    /// By default, every id is valid.
+    fn check_id_format(&self, _eid: &Eid) -> Result<(), SmolStr> {
+        Ok(())
+    }
+
+    /// Get the specific entities which every entity of this type must be a
+    /// member of.
+    ///
+    /// By default, there are none.
+    fn required_parents<'s>(&'s self) -> Box<dyn Iterator<Item = EntityUID> + 's> {
+        Box::new(std::iter::empty())
+    }
 }
 
 /// Simple type that implements `EntityTypeDescription` by expecting no
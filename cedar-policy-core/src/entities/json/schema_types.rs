@@ -14,9 +14,11 @@
  * limitations under the License.
  */
 
-use crate::ast::{EntityType, Name, Type};
+use crate::ast::{BorrowedRestrictedExpr, EntityType, ExprKind, Literal, Name, Type};
+use crate::entities::DisallowedValueError;
+use itertools::Itertools;
 use smol_str::SmolStr;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 /// Possible types that schema-based parsing can expect for Cedar values.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -27,6 +29,11 @@ pub enum SchemaType {
     Long,
     /// String
     String,
+    /// String which must be one of the given values
+    StringEnum {
+        /// Values the string is allowed to take
+        allowed_values: BTreeSet<SmolStr>,
+    },
     /// Set, with homogeneous elements of the specified type
     Set {
         /// Element type
@@ -44,6 +51,11 @@ pub enum SchemaType {
         /// Entity type
         ty: EntityType,
     },
+    /// Entity of any one of several types
+    EntityUnion {
+        /// The entity types
+        tys: BTreeSet<EntityType>,
+    },
     /// Extension types
     Extension {
         /// Name of the extension type.
@@ -72,10 +84,12 @@ impl SchemaType {
             (SchemaType::Bool, Type::Bool) => true,
             (SchemaType::Long, Type::Long) => true,
             (SchemaType::String, Type::String) => true,
+            (SchemaType::StringEnum { .. }, Type::String) => true,
             (SchemaType::Set { .. }, Type::Set) => true,
             (SchemaType::EmptySet, Type::Set) => true,
             (SchemaType::Record { .. }, Type::Record) => true,
             (SchemaType::Entity { ty: ty1 }, Type::Entity { ty: ty2 }) => ty1 == ty2,
+            (SchemaType::EntityUnion { tys }, Type::Entity { ty }) => tys.contains(ty),
             (SchemaType::Extension { name: name1 }, Type::Extension { name: name2 }) => {
                 name1 == name2
             }
@@ -92,6 +106,20 @@ impl SchemaType {
         } else {
             use SchemaType::*;
             match (self, other) {
+                (String, StringEnum { .. }) => true,
+                (StringEnum { .. }, String) => true,
+                (
+                    StringEnum {
+                        allowed_values: values1,
+                    },
+                    StringEnum {
+                        allowed_values: values2,
+                    },
+                ) => !values1.is_disjoint(values2),
+                (Entity { ty }, EntityUnion { tys }) | (EntityUnion { tys }, Entity { ty }) => {
+                    tys.contains(ty)
+                }
+                (EntityUnion { tys: tys1 }, EntityUnion { tys: tys2 }) => !tys1.is_disjoint(tys2),
                 (Set { .. }, EmptySet) => true,
                 (EmptySet, Set { .. }) => true,
                 (Set { element_ty: elty1 }, Set { element_ty: elty2 }) => {
@@ -132,6 +160,39 @@ impl SchemaType {
             }
         }
     }
+
+    /// Find a string in `val` which is not one of the allowed values of the
+    /// `StringEnum` type it is expected to have according to this type.
+    ///
+    /// This does not check that `val` otherwise has this type. Parts of `val`
+    /// which do not have the shape expected by this type are ignored.
+    pub fn find_disallowed_value(
+        &self,
+        val: BorrowedRestrictedExpr<'_>,
+    ) -> Option<DisallowedValueError> {
+        match (self, val.expr_kind()) {
+            (Self::StringEnum { allowed_values }, ExprKind::Lit(Literal::String(s))) => {
+                (!allowed_values.contains(s)).then(|| DisallowedValueError {
+                    value: s.clone(),
+                    allowed_values: allowed_values.iter().cloned().collect(),
+                })
+            }
+            (Self::Set { element_ty }, ExprKind::Set(elements)) => {
+                elements.iter().find_map(|element| {
+                    // assuming the invariant holds for the set as a whole, it will also hold for each element
+                    element_ty.find_disallowed_value(BorrowedRestrictedExpr::new_unchecked(element))
+                })
+            }
+            (Self::Record { attrs }, ExprKind::Record(map)) => map.iter().find_map(|(k, v)| {
+                // assuming the invariant holds for the record as a whole, it will also hold for each attribute value
+                attrs
+                    .get(k)?
+                    .attr_type
+                    .find_disallowed_value(BorrowedRestrictedExpr::new_unchecked(v))
+            }),
+            _ => None,
+        }
+    }
 }
 
 impl AttributeType {
@@ -168,6 +229,11 @@ impl std::fmt::Display for SchemaType {
             Self::Bool => write!(f, "bool"),
             Self::Long => write!(f, "long"),
             Self::String => write!(f, "string"),
+            Self::StringEnum { allowed_values } => write!(
+                f,
+                "string (one of {})",
+                allowed_values.iter().map(|v| format!("{v:?}")).join(", ")
+            ),
             Self::Set { element_ty } => write!(f, "(set of {})", &element_ty),
             Self::EmptySet => write!(f, "empty-set"),
             Self::Record { attrs } => {
@@ -186,6 +252,11 @@ impl std::fmt::Display for SchemaType {
                 EntityType::Unspecified => write!(f, "(entity of unspecified type)"),
                 EntityType::Concrete(name) => write!(f, "(entity of type `{}`)", name),
             },
+            Self::EntityUnion { tys } => write!(
+                f,
+                "(entity of one of the types {})",
+                tys.iter().map(|ty| format!("`{ty}`")).join(", ")
+            ),
             Self::Extension { name } => write!(f, "{}", name),
         }
     }
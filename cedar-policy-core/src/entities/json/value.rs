@@ -18,8 +18,8 @@ use super::{
     JsonDeserializationError, JsonDeserializationErrorContext, JsonSerializationError, SchemaType,
 };
 use crate::ast::{
-    BorrowedRestrictedExpr, Eid, EntityUID, ExprConstructionError, ExprKind, Literal, Name,
-    RestrictedExpr,
+    BorrowedRestrictedExpr, Eid, EntityType, EntityUID, ExprConstructionError, ExprKind, Literal,
+    Name, RestrictedExpr,
 };
 use crate::entities::{
     type_of_restricted_expr, EntitySchemaConformanceError, EscapeKind, TypeOfRestrictedExprError,
@@ -27,6 +27,7 @@ use crate::entities::{
 use crate::extensions::Extensions;
 use crate::FromNormalizedStr;
 use either::Either;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use smol_str::SmolStr;
@@ -40,7 +41,7 @@ use std::collections::{BTreeMap, HashSet};
 ///
 /// For example, this is the JSON format for attribute values expected by
 /// `EntityJsonParser`, when schema-based parsing is not used.
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum CedarValueJson {
     /// The `__expr` escape has been removed, but is still reserved in order to throw meaningful errors.
@@ -88,7 +89,7 @@ pub enum CedarValueJson {
 
 /// Structure representing a Cedar record in JSON
 #[serde_as]
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct JsonRecord {
     /// Cedar records must have string keys, but values can be any
     /// `CedarValueJson`s, even heterogeneously
@@ -140,7 +141,7 @@ impl JsonRecord {
 }
 
 /// Structure expected by the `__entity` escape
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct TypeAndId {
     /// Entity typename
     #[serde(rename = "type")]
@@ -180,7 +181,7 @@ impl TryFrom<TypeAndId> for EntityUID {
 }
 
 /// Structure expected by the `__extn` escape
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct FnAndArg {
     /// Extension constructor function
     #[serde(rename = "fn")]
@@ -287,6 +288,8 @@ impl CedarValueJson {
                 if let Some(collision) = collision {
                     Err(JsonSerializationError::ReservedKey {
                         key: collision.clone(),
+                        uid: None,
+                        path: Vec::new(),
                     })
                 } else {
                     // the common case: the record doesn't use any reserved keys
@@ -298,7 +301,8 @@ impl CedarValueJson {
                                     CedarValueJson::from_expr(
                                         // assuming the invariant holds for `expr`, it must also hold here
                                         BorrowedRestrictedExpr::new_unchecked(v),
-                                    )?,
+                                    )
+                                    .map_err(|e| e.in_attribute(k))?,
                                 ))
                             })
                             .collect::<Result<_, JsonSerializationError>>()?,
@@ -348,12 +352,30 @@ impl FnAndArg {
 pub struct ValueParser<'e> {
     /// Extensions which are active for the JSON parsing.
     extensions: Extensions<'e>,
+    /// Whether values of an expected extension type which no enabled
+    /// extension provides are kept as opaque extension function calls rather
+    /// than rejected
+    opaque_unknown_extensions: bool,
 }
 
 impl<'e> ValueParser<'e> {
     /// Create a new `ValueParser`.
     pub fn new(extensions: Extensions<'e>) -> Self {
-        Self { extensions }
+        Self {
+            extensions,
+            opaque_unknown_extensions: false,
+        }
+    }
+
+    /// Keep values of an expected extension type which no enabled extension
+    /// provides as opaque calls to a function named after the extension type,
+    /// instead of returning an error. Values of extension types which are
+    /// provided must still match one of their constructors.
+    pub(crate) fn with_opaque_unknown_extensions(self) -> Self {
+        Self {
+            opaque_unknown_extensions: true,
+            ..self
+        }
     }
 
     /// internal function that converts a Cedar value (in JSON) into a
@@ -365,15 +387,37 @@ impl<'e> ValueParser<'e> {
         val: serde_json::Value,
         expected_ty: Option<&SchemaType>,
         ctx: impl Fn() -> JsonDeserializationErrorContext + Clone,
+    ) -> Result<RestrictedExpr, JsonDeserializationError> {
+        self.val_into_restricted_expr_at_path(val, expected_ty, ctx, &[])
+    }
+
+    /// internal function implementing `val_into_restricted_expr()`. `path` is
+    /// the keys of the nested records leading from the value described by
+    /// `ctx` to `val`, and is used to report errors in nested records.
+    fn val_into_restricted_expr_at_path(
+        &self,
+        val: serde_json::Value,
+        expected_ty: Option<&SchemaType>,
+        ctx: impl Fn() -> JsonDeserializationErrorContext + Clone,
+        path: &[SmolStr],
     ) -> Result<RestrictedExpr, JsonDeserializationError> {
         match expected_ty {
             // The expected type is an entity reference. Special parsing rules
             // apply: for instance, the `__entity` escape can optionally be omitted.
             // What this means is that we parse the contents as `EntityUidJson`, and
             // then convert that into an entity reference `RestrictedExpr`
-            Some(SchemaType::Entity { .. }) => {
-                let uidjson: EntityUidJson = serde_json::from_value(val)?;
-                Ok(RestrictedExpr::val(uidjson.into_euid(ctx)?))
+            // If only one entity type is expected, a bare string is also
+            // accepted, as the eid of an entity of that type.
+            Some(expected_ty @ (SchemaType::Entity { .. } | SchemaType::EntityUnion { .. })) => {
+                match (val, unambiguous_entity_type(expected_ty)) {
+                    (serde_json::Value::String(eid), Some(name)) => Ok(RestrictedExpr::val(
+                        EntityUID::from_components(name.clone(), Eid::new(eid)),
+                    )),
+                    (val, _) => {
+                        let uidjson: EntityUidJson = serde_json::from_value(val)?;
+                        Ok(RestrictedExpr::val(uidjson.into_euid(ctx)?))
+                    }
+                }
             }
             // The expected type is an extension type. Special parsing rules apply:
             // for instance, the `__extn` escape can optionally be omitted. What
@@ -390,7 +434,12 @@ impl<'e> ValueParser<'e> {
                     elements
                         .into_iter()
                         .map(|element| {
-                            self.val_into_restricted_expr(element, Some(element_ty), ctx.clone())
+                            self.val_into_restricted_expr_at_path(
+                                element,
+                                Some(element_ty),
+                                ctx.clone(),
+                                path,
+                            )
                         })
                         .collect::<Result<Vec<RestrictedExpr>, JsonDeserializationError>>()?,
                 )),
@@ -442,13 +491,15 @@ impl<'e> ValueParser<'e> {
                         .filter_map(move |(k, expected_attr_ty)| {
                             match mut_actual_attrs.remove(k.as_str()) {
                                 Some(actual_attr) => {
-                                    match self.val_into_restricted_expr(actual_attr, Some(expected_attr_ty.schema_type()), ctx.clone()) {
+                                    let nested_path = path.iter().chain(std::iter::once(k)).cloned().collect::<Vec<_>>();
+                                    match self.val_into_restricted_expr_at_path(actual_attr, Some(expected_attr_ty.schema_type()), ctx.clone(), &nested_path) {
                                         Ok(actual_attr) => Some(Ok((k.clone(), actual_attr))),
                                         Err(e) => Some(Err(e)),
                                     }
                                 }
                                 None if expected_attr_ty.is_required() => Some(Err(JsonDeserializationError::MissingRequiredRecordAttr {
                                     ctx: Box::new(ctx()),
+                                    path: path.to_vec(),
                                     record_attr: k.clone(),
                                 })),
                                 None => None,
@@ -460,6 +511,7 @@ impl<'e> ValueParser<'e> {
                     if let Some((record_attr, _)) = actual_attrs.into_iter().next() {
                         return Err(JsonDeserializationError::UnexpectedRecordAttr {
                             ctx: Box::new(ctx2()),
+                            path: path.to_vec(),
                             record_attr: record_attr.into(),
                         });
                     }
@@ -508,6 +560,55 @@ impl<'e> ValueParser<'e> {
                     }
                 }
             },
+            // The expected type is a string enumeration. No special parsing
+            // rules apply, but a string must be one of the allowed values.
+            Some(expected_ty @ SchemaType::StringEnum { .. }) => {
+                let jvalue: CedarValueJson = serde_json::from_value(val)?;
+                let rexpr = jvalue.into_expr(ctx.clone())?;
+                match expected_ty.find_disallowed_value(rexpr.as_borrowed()) {
+                    Some(err) => match ctx() {
+                        JsonDeserializationErrorContext::EntityAttribute { uid, attr } => {
+                            Err(JsonDeserializationError::EntitySchemaConformance(
+                                EntitySchemaConformanceError::DisallowedValue {
+                                    uid,
+                                    attr,
+                                    err: Box::new(err),
+                                },
+                            ))
+                        }
+                        ctx => Err(JsonDeserializationError::DisallowedValue {
+                            ctx: Box::new(ctx),
+                            path: path.to_vec(),
+                            err: Box::new(err),
+                        }),
+                    },
+                    None => Ok(rexpr),
+                }
+            }
+            // The expected type is `Long`. A JSON number must be an integer in
+            // the range of `Long`; other values are parsed as usual and
+            // checked against the expected type by the caller.
+            Some(SchemaType::Long) => match val {
+                serde_json::Value::Number(n) => match n.as_i64() {
+                    Some(i) => Ok(RestrictedExpr::val(i)),
+                    None if n.is_u64()
+                        || matches!(n.as_f64(), Some(f) if is_integral_beyond_long(f)) =>
+                    {
+                        Err(JsonDeserializationError::IntegerOverflow {
+                            ctx: Box::new(ctx()),
+                            value: n,
+                        })
+                    }
+                    None => Err(JsonDeserializationError::ExpectedInteger {
+                        ctx: Box::new(ctx()),
+                        value: n,
+                    }),
+                },
+                val => {
+                    let jvalue: CedarValueJson = serde_json::from_value(val)?;
+                    Ok(jvalue.into_expr(ctx)?)
+                }
+            },
             // The expected type is any other type, or we don't have an expected type.
             // No special parsing rules apply; we do ordinary, non-schema-based parsing.
             Some(_) | None => {
@@ -552,6 +653,17 @@ impl<'e> ValueParser<'e> {
                     type_of_restricted_expr(arg.as_borrowed(), self.extensions).map_err(|e| {
                         type_of_restricted_expr_error_to_json_deserialization_error(e, ctx())
                     })?;
+                if self.opaque_unknown_extensions
+                    && !self.extensions.provides_type(&expected_typename)
+                {
+                    // the value can't be interpreted, so we keep the argument
+                    // as is, recording the extension type it was expected to
+                    // have in place of the constructor name
+                    return Ok(RestrictedExpr::call_extension_fn(
+                        expected_typename,
+                        vec![arg],
+                    ));
+                }
                 let func = self
                     .extensions
                     .lookup_single_arg_constructor(
@@ -564,12 +676,18 @@ impl<'e> ValueParser<'e> {
                         ctx: Box::new(ctx()),
                         err,
                     })?
-                    .ok_or_else(|| JsonDeserializationError::MissingImpliedConstructor {
-                        ctx: Box::new(ctx()),
-                        return_type: Box::new(SchemaType::Extension {
+                    .ok_or_else(|| {
+                        let return_type = SchemaType::Extension {
                             name: expected_typename,
-                        }),
-                        arg_type: Box::new(argty.clone()),
+                        };
+                        JsonDeserializationError::MissingImpliedConstructor {
+                            ctx: Box::new(ctx()),
+                            available_constructors: self
+                                .extensions
+                                .single_arg_constructor_signatures(&return_type),
+                            return_type: Box::new(return_type),
+                            arg_type: Box::new(argty.clone()),
+                        }
                     })?;
                 Ok(RestrictedExpr::call_extension_fn(
                     func.name().clone(),
@@ -610,6 +728,28 @@ fn type_of_restricted_expr_error_to_json_deserialization_error(
     }
 }
 
+/// If `ty` is an entity type which allows exactly one concrete entity type,
+/// get the name of that entity type
+fn unambiguous_entity_type(ty: &SchemaType) -> Option<&Name> {
+    match ty {
+        SchemaType::Entity {
+            ty: EntityType::Concrete(name),
+        } => Some(name),
+        SchemaType::EntityUnion { tys } => match tys.iter().exactly_one() {
+            Ok(EntityType::Concrete(name)) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Is `f`, a JSON number which did not parse as an `i64`, an integer outside
+/// the range of `Long`? JSON integers which do not fit in an `i64` or `u64` are
+/// parsed as floating-point numbers, and may be rounded to `i64::MIN`.
+fn is_integral_beyond_long(f: f64) -> bool {
+    f.fract() == 0.0 && (f <= i64::MIN as f64 || f >= i64::MAX as f64)
+}
+
 /// Serde JSON format for Cedar values where we know we're expecting an entity
 /// reference
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
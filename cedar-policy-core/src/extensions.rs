@@ -101,6 +101,33 @@ impl<'a> Extensions<'a> {
         self.extensions.iter().flat_map(|ext| ext.funcs())
     }
 
+    /// Whether any of these extensions has a function returning the extension
+    /// type `name`, i.e., whether that extension type is provided at all.
+    pub(crate) fn provides_type(&self, name: &Name) -> bool {
+        self.all_funcs().any(|f| match f.return_type() {
+            Some(SchemaType::Extension { name: ty }) => ty == name,
+            _ => false,
+        })
+    }
+
+    /// Get the signatures of all single-argument constructors with the given
+    /// return type, e.g., `ip(string) -> ipaddr`, in sorted order.
+    pub(crate) fn single_arg_constructor_signatures(
+        &self,
+        return_type: &SchemaType,
+    ) -> Vec<String> {
+        let mut signatures = self
+            .all_funcs()
+            .filter(|f| f.is_constructor() && f.return_type() == Some(return_type))
+            .filter_map(|f| match f.arg_types() {
+                [Some(arg_type)] => Some(format!("{}({arg_type}) -> {return_type}", f.name())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        signatures.sort();
+        signatures
+    }
+
     /// Lookup a single-argument constructor by its return type and argument type.
     /// This will ignore polymorphic functions (that accept multiple argument types).
     ///
@@ -14,14 +14,18 @@
  * limitations under the License.
  */
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 use cedar_policy_core::{
     ast::{EntityUID, Name},
-    parser::err::{ParseError, ParseErrors},
+    parser::{
+        err::{ParseError, ParseErrors},
+        SourceInfo,
+    },
     transitive_closure,
 };
 use itertools::Itertools;
+use smol_str::SmolStr;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -29,6 +33,16 @@ pub enum SchemaError {
     /// Error thrown by the `serde_json` crate during deserialization
     #[error("failed to parse schema: {0}")]
     Serde(#[from] serde_json::Error),
+    /// Error reading a schema file, as opposed to parsing its contents.
+    /// `bytes_read` is the number of bytes successfully read from the file
+    /// before the error.
+    #[error("failed to read schema after {bytes_read} bytes: {source}")]
+    Io {
+        /// The underlying IO error
+        source: std::io::Error,
+        /// Bytes read from the file before the error
+        bytes_read: usize,
+    },
     /// Errors occurring while computing or enforcing transitive closure on
     /// action hierarchy.
     #[error("transitive closure computation/enforcement error on action hierarchy: {0}")]
@@ -49,36 +63,51 @@ pub enum SchemaError {
     /// Undeclared action(s) used in the `memberOf` field of an action.
     #[error("undeclared action(s): {0:?}")]
     UndeclaredActions(HashSet<String>),
-    /// Undeclared common type(s) used in entity or context attributes.
-    #[error("undeclared common type(s): {0:?}")]
-    UndeclaredCommonTypes(HashSet<String>),
-    /// Duplicate specifications for an entity type. Argument is the name of
-    /// the duplicate entity type.
-    #[error("duplicate entity type `{0}`")]
-    DuplicateEntityType(String),
-    /// Duplicate specifications for an action. Argument is the name of the
-    /// duplicate action.
-    #[error("duplicate action `{0}`")]
-    DuplicateAction(String),
-    /// Duplicate specification for a reusable type declaration.
-    #[error("duplicate common type `{0}`")]
-    DuplicateCommonType(String),
+    /// Undeclared common type(s) used in entity or context attributes, or in
+    /// the definitions of other common types.
+    #[error("undeclared common type(s): {0}")]
+    UndeclaredCommonTypes(UndeclaredCommonTypeNames),
+    /// Duplicate specifications for an entity type. Arguments are the name of
+    /// the duplicate entity type and, if the declarations came from different
+    /// fragments, the indices of those fragments.
+    #[error("duplicate entity type `{0}`{}", Self::format_fragments(.1))]
+    DuplicateEntityType(String, Option<FragmentIndices>),
+    /// Duplicate specifications for an action. Arguments are the name of the
+    /// duplicate action and, if the declarations came from different
+    /// fragments, the indices of those fragments.
+    #[error("duplicate action `{0}`{}", Self::format_fragments(.1))]
+    DuplicateAction(String, Option<FragmentIndices>),
+    /// Duplicate specification for a reusable type declaration. Arguments are
+    /// as for `DuplicateEntityType`.
+    #[error("duplicate common type `{0}`{}", Self::format_fragments(.1))]
+    DuplicateCommonType(String, Option<FragmentIndices>),
     /// Cycle in the schema's action hierarchy.
     #[error("cycle in action hierarchy")]
     CycleInActionHierarchy,
-    /// Parse errors occurring while parsing an entity type.
-    #[error("parse error in entity type: {}", Self::format_parse_errs(.0))]
-    ParseEntityType(ParseErrors),
+    /// Cycle in the schema's entity type hierarchy. These are permitted when
+    /// constructing a schema, but prevent sorting its entity types.
+    #[error("cycle in entity type hierarchy")]
+    CycleInEntityHierarchy,
+    /// Cycle in the references between common type definitions. The argument
+    /// lists, sorted, the common types which are in or depend on the cycle.
+    #[error("cycle in common type definitions: {}", .0.join(", "))]
+    CycleInCommonTypes(Vec<String>),
+    /// Parse errors occurring while parsing an entity type. The second
+    /// argument records where the entity type name appeared in the schema.
+    #[error("parse error in entity type{}: {}", Self::format_location(.1), Self::format_parse_errs(.0))]
+    ParseEntityType(ParseErrors, SchemaLocation),
     /// Parse errors occurring while parsing a namespace identifier.
     #[error("parse error in namespace identifier: {}", Self::format_parse_errs(.0))]
     ParseNamespace(ParseErrors),
-    /// Parse errors occurring while parsing an extension type.
-    #[error("parse error in extension type: {}", Self::format_parse_errs(.0))]
-    ParseExtensionType(ParseErrors),
+    /// Parse errors occurring while parsing an extension type. The second
+    /// argument records where the extension type name appeared in the schema.
+    #[error("parse error in extension type{}: {}", Self::format_location(.1), Self::format_parse_errs(.0))]
+    ParseExtensionType(ParseErrors, SchemaLocation),
     /// Parse errors occurring while parsing the name of one of reusable
-    /// declared types.
-    #[error("parse error in common type identifier: {}", Self::format_parse_errs(.0))]
-    ParseCommonType(ParseErrors),
+    /// declared types. The second argument records where the name appeared in
+    /// the schema.
+    #[error("parse error in common type identifier{}: {}", Self::format_location(.1), Self::format_parse_errs(.0))]
+    ParseCommonType(ParseErrors, SchemaLocation),
     /// The schema file included an entity type `Action` in the entity type
     /// list. The `Action` entity type is always implicitly declared, and it
     /// cannot currently have attributes or be in any groups, so there is no
@@ -99,6 +128,153 @@ pub enum SchemaError {
     UnsupportedActionAttribute(EntityUID, String),
     #[error("uses the `__expr` escape, which is no longer supported")]
     ExprEscapeUsed,
+    /// The default value declared for an entity attribute is invalid, either
+    /// because it does not have the attribute's type or because the attribute
+    /// is required.
+    #[error("invalid default value for attribute `{1}` of entity type `{0}`: {2}")]
+    InvalidAttributeDefault(Name, SmolStr, String),
+    /// The value of an action attribute does not conform to the type recorded
+    /// for it. This error variant should only be used when `PermitAttributes`
+    /// is enabled.
+    #[error("attribute `{1}` of action `{0}` does not match its type: {2}")]
+    InvalidActionAttribute(EntityUID, SmolStr, String),
+    /// The context type of an action cannot be used to parse context data.
+    /// This should not happen for contexts declared in a schema file.
+    #[error("context for action `{0}` cannot be used to parse context data: {1}")]
+    UnrepresentableContext(EntityUID, String),
+    /// The schema file is not valid UTF-8. The argument is the byte offset of
+    /// the first invalid byte, ignoring any byte order mark.
+    #[error("schema is not valid UTF-8: invalid byte at offset {0}")]
+    InvalidUtf8(usize),
+    /// Error parsing one of several schema inputs. The first argument is the
+    /// index of the input which failed to parse.
+    #[error("in schema input {0}: {1}")]
+    InInput(usize, Box<SchemaError>),
+    /// Entity types or actions declared in the same namespace have names which
+    /// differ only by case. This is only reported by
+    /// `ValidatorSchema::check_case_collisions`.
+    #[error("names differ only by case: {}", .names.iter().map(|name| format!("`{name}`")).join(", "))]
+    CaseCollision {
+        /// The colliding names, fully qualified and sorted
+        names: Vec<String>,
+    },
+    /// A `String` type declares an empty list of `allowedValues`, so no
+    /// string would have the type.
+    #[error("`allowedValues` for a `String` type must not be empty")]
+    EmptyAllowedValues,
+    /// An `Entity` type declares an empty list of `names`, so no entity would
+    /// have the type.
+    #[error("`names` for an `Entity` type must not be empty")]
+    EmptyEntityTypeNames,
+    /// Flattening a namespace into the root namespace would merge entity types
+    /// or actions with distinct ones already declared in the root namespace.
+    /// This is only reported by `ValidatorSchema::flatten_namespace`.
+    #[error("flattening namespace would merge distinct declarations: {}", .collisions.iter().map(|(name, flattened)| format!("`{name}` with `{flattened}`")).join(", "))]
+    FlattenCollision {
+        /// Each name in the flattened namespace, fully qualified, paired with
+        /// the root namespace name it would be merged with. Sorted.
+        collisions: Vec<(String, String)>,
+    },
+    /// An `idFormat` declared for an entity type is not a valid regular
+    /// expression.
+    #[error("invalid `idFormat` `{pattern}` for entity type `{entity_type}`: {message}")]
+    InvalidIdFormat {
+        /// The fully qualified name of the entity type
+        entity_type: String,
+        /// The pattern as written in the schema
+        pattern: String,
+        /// Why the pattern could not be compiled
+        message: String,
+    },
+    /// One or more top-level keys of a schema are not well-formed namespace
+    /// identifiers. Each malformed key is listed, in sorted order, with the
+    /// errors from parsing it. This is only reported by
+    /// `ValidatorSchema::from_json_value_checked`.
+    #[error("malformed namespace identifiers: {}", .0.iter().map(|(key, errs)| format!("`{key}` ({})", Self::format_parse_errs(errs))).join("; "))]
+    MalformedNamespaces(Vec<(String, ParseErrors)>),
+    /// An unqualified common type reference inside a namespace matches both a
+    /// common type declared in that namespace and one declared in the root
+    /// namespace.
+    #[error("common type `{reference}` is ambiguous: it may refer to `{local}` or to `{reference}` in the root namespace")]
+    AmbiguousCommonType {
+        /// The reference as written in the schema
+        reference: String,
+        /// The fully qualified name of the common type in the namespace of
+        /// the reference
+        local: String,
+    },
+    /// An attribute of an entity type shape or action context, or of a
+    /// record nested inside one, has an empty name or a name consisting only
+    /// of whitespace.
+    #[error("{location} declares an attribute with invalid name `{name}`: attribute names must not be empty or only whitespace")]
+    InvalidAttributeName {
+        /// The invalid attribute name
+        name: SmolStr,
+        /// The shape or context declaring the attribute
+        location: ContextOrShape,
+    },
+    /// The `__expr` escape in the value of an action attribute of a schema
+    /// written in version 1 of the schema format could not be converted when
+    /// migrating the schema to the current version.
+    #[error(
+        "cannot migrate `__expr` escape in attribute `{attr}` of action `{action}`: {message}"
+    )]
+    InvalidExprEscape {
+        /// The action declaring the attribute, as written in the schema
+        action: String,
+        /// The attribute
+        attr: String,
+        /// Why the escape could not be converted
+        message: String,
+    },
+    /// The namespace into which another namespace is cloned already declares
+    /// entity types, actions or common types. This is only reported by
+    /// `ValidatorSchema::clone_namespace`.
+    #[error("cannot clone into namespace `{0}` because it already has declarations")]
+    NamespaceAlreadyDeclared(String),
+    /// An entity type shape or action context has an attribute whose record
+    /// types are nested more deeply than allowed. This is only reported by
+    /// `ValidatorSchema::from_schema_fragments_with_limits`.
+    #[error("records nested {depth} deep exceed the limit of {limit}{}", Self::format_location(.location))]
+    RecordTooDeep {
+        /// The entity type shape or action context containing the attribute
+        location: SchemaLocation,
+        /// How deeply records are nested in the attribute
+        depth: usize,
+        /// The deepest nesting allowed
+        limit: usize,
+    },
+    /// Entity types or actions required to be declared are not. This is only
+    /// reported by `ValidatorSchema::require`.
+    #[error("schema does not declare required {}", Self::format_missing_required(.entity_types, .actions))]
+    MissingRequired {
+        /// The required entity types which are not declared, as given
+        entity_types: Vec<String>,
+        /// The required actions which are not declared, as given
+        actions: Vec<String>,
+    },
+    /// Entities conforming to the schema could not be generated for the
+    /// action. This is only reported by `ValidatorSchema::minimal_entities_for`.
+    #[error("cannot generate entities for action `{0}`: {1}")]
+    EntityGeneration(EntityUID, String),
+    /// A mutation would alter the declarations of a frozen namespace. This is
+    /// only reported by mutating operations such as
+    /// `ValidatorSchema::clone_namespace`.
+    #[error("namespace `{ns}` is frozen")]
+    NamespaceFrozen {
+        /// The frozen namespace
+        ns: String,
+    },
+    /// An entity in the `memberOf` list of an entity type has a type which
+    /// the `memberOfTypes` of the entity type does not allow as an ancestor,
+    /// so no entity of the entity type could be a member of it.
+    #[error("entity type `{entity_type}` cannot be a member of `{parent}` because `memberOfTypes` does not allow its type")]
+    DisallowedRequiredParent {
+        /// The fully qualified name of the entity type
+        entity_type: String,
+        /// The entity from the `memberOf` list
+        parent: String,
+    },
 }
 
 impl From<transitive_closure::TcError<EntityUID>> for SchemaError {
@@ -121,9 +297,338 @@ impl SchemaError {
     fn format_parse_errs(errs: &[ParseError]) -> String {
         errs.iter().map(|e| e.to_string()).join(", ")
     }
+
+    fn format_missing_required(entity_types: &[String], actions: &[String]) -> String {
+        let names = |kind: &str, names: &[String]| {
+            (!names.is_empty()).then(|| {
+                format!(
+                    "{kind} {}",
+                    names.iter().map(|name| format!("`{name}`")).join(", ")
+                )
+            })
+        };
+        names("entity types", entity_types)
+            .into_iter()
+            .chain(names("actions", actions))
+            .join(" and ")
+    }
+
+    fn format_location(location: &SchemaLocation) -> String {
+        if location.is_unknown() {
+            String::new()
+        } else {
+            format!(" ({location})")
+        }
+    }
+
+    fn format_fragments(fragments: &Option<FragmentIndices>) -> String {
+        match fragments {
+            Some(fragments) => format!(": {fragments}"),
+            None => String::new(),
+        }
+    }
+
+    /// An error for a single undeclared common type, whose use has not yet
+    /// been located.
+    pub(crate) fn undeclared_common_type(name: String) -> Self {
+        Self::UndeclaredCommonTypes(UndeclaredCommonTypeNames {
+            names: HashSet::from([name.clone()]),
+            references: BTreeMap::from([(name, vec![CommonTypeReference::default()])]),
+        })
+    }
+
+    pub(crate) fn parse_entity_type(errs: ParseErrors) -> Self {
+        Self::ParseEntityType(errs, SchemaLocation::default())
+    }
+
+    pub(crate) fn parse_extension_type(errs: ParseErrors) -> Self {
+        Self::ParseExtensionType(errs, SchemaLocation::default())
+    }
+
+    pub(crate) fn parse_common_type(errs: ParseErrors) -> Self {
+        Self::ParseCommonType(errs, SchemaLocation::default())
+    }
+
+    /// Where in the schema the name which failed to parse appeared, for the
+    /// errors which record this.
+    pub fn location(&self) -> Option<&SchemaLocation> {
+        match self {
+            Self::ParseEntityType(_, location)
+            | Self::ParseExtensionType(_, location)
+            | Self::ParseCommonType(_, location) => Some(location),
+            _ => None,
+        }
+    }
+
+    /// For a duplicate declaration found while combining several fragments,
+    /// the index of the fragment containing the second declaration. The index
+    /// counts the fragments in the order they were passed to
+    /// `ValidatorSchema::from_schema_fragments`.
+    pub fn source_fragment_index(&self) -> Option<usize> {
+        match self {
+            Self::DuplicateEntityType(_, fragments)
+            | Self::DuplicateAction(_, fragments)
+            | Self::DuplicateCommonType(_, fragments) => {
+                fragments.as_ref().map(|fragments| fragments.duplicate)
+            }
+            _ => None,
+        }
+    }
+
+    /// The locations recorded by this error, including the locations of the
+    /// uses of undeclared common types
+    fn locations_mut(&mut self) -> Vec<&mut SchemaLocation> {
+        match self {
+            Self::ParseEntityType(_, location)
+            | Self::ParseExtensionType(_, location)
+            | Self::ParseCommonType(_, location)
+            | Self::RecordTooDeep { location, .. } => vec![location],
+            Self::UndeclaredCommonTypes(names) => names
+                .references
+                .values_mut()
+                .flatten()
+                .map(|reference| &mut reference.location)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Record the section of the schema in which this error occurred, unless
+    /// a more specific section was already recorded.
+    pub(crate) fn in_section(mut self, section: SchemaSection) -> Self {
+        for location in self.locations_mut() {
+            location.section.get_or_insert_with(|| section.clone());
+        }
+        self
+    }
+
+    /// Record the namespace in which this error occurred.
+    pub(crate) fn in_namespace(mut self, namespace: Option<&SmolStr>) -> Self {
+        for location in self.locations_mut() {
+            location.namespace = namespace.filter(|ns| !ns.is_empty()).cloned();
+        }
+        self
+    }
+
+    /// Record that the undeclared common types in this error were used in the
+    /// type of the attribute `attr`, or in the type of an attribute nested
+    /// inside it.
+    pub(crate) fn in_attribute(mut self, attr: &SmolStr) -> Self {
+        if let Self::UndeclaredCommonTypes(names) = &mut self {
+            for reference in names.references.values_mut().flatten() {
+                reference.attr_path.insert(0, attr.clone());
+            }
+        }
+        self
+    }
 }
 
-#[derive(Debug)]
+/// The fragments containing two declarations of the same name, as indices
+/// into the fragments passed to `ValidatorSchema::from_schema_fragments`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FragmentIndices {
+    /// The fragment containing the first declaration
+    pub first: usize,
+    /// The fragment containing the duplicate declaration
+    pub duplicate: usize,
+}
+
+impl std::fmt::Display for FragmentIndices {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "declared in fragment {} and fragment {}",
+            self.first, self.duplicate
+        )
+    }
+}
+
+/// Where in a schema file an error occurred.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaLocation {
+    /// The namespace containing the error, or `None` for the empty namespace
+    /// or if it is not known.
+    pub namespace: Option<SmolStr>,
+    /// The part of the namespace definition containing the error, if known.
+    pub section: Option<SchemaSection>,
+}
+
+impl SchemaLocation {
+    /// Returns `true` if neither the namespace nor the section is known.
+    pub fn is_unknown(&self) -> bool {
+        self.namespace.is_none() && self.section.is_none()
+    }
+}
+
+impl std::fmt::Display for SchemaLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.namespace, &self.section) {
+            (Some(namespace), Some(section)) => {
+                write!(f, "in namespace `{namespace}`, in {section}")
+            }
+            (Some(namespace), None) => write!(f, "in namespace `{namespace}`"),
+            (None, Some(section)) => write!(f, "in {section}"),
+            (None, None) => write!(f, "at unknown location"),
+        }
+    }
+}
+
+/// The undeclared common types reported by
+/// `SchemaError::UndeclaredCommonTypes`, as written in the schema, along with
+/// where each was used. This dereferences to the set of names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UndeclaredCommonTypeNames {
+    names: HashSet<String>,
+    references: BTreeMap<String, Vec<CommonTypeReference>>,
+}
+
+impl UndeclaredCommonTypeNames {
+    /// For each undeclared common type, where it was used
+    pub fn references(&self) -> &BTreeMap<String, Vec<CommonTypeReference>> {
+        &self.references
+    }
+}
+
+impl std::ops::Deref for UndeclaredCommonTypeNames {
+    type Target = HashSet<String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.names
+    }
+}
+
+impl From<HashSet<String>> for UndeclaredCommonTypeNames {
+    fn from(names: HashSet<String>) -> Self {
+        Self {
+            names,
+            references: BTreeMap::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for UndeclaredCommonTypeNames {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.names)?;
+        for (name, refs) in self.references.iter() {
+            write!(
+                f,
+                "; `{name}` is used {}",
+                refs.iter().map(ToString::to_string).join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A use of a common type in a schema file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommonTypeReference {
+    /// The declaration in which the common type was used
+    pub location: SchemaLocation,
+    /// For a use in the type of an attribute, the names of the attributes
+    /// leading from the declared record type to that attribute. Empty if the
+    /// common type was used directly, e.g., as the definition of another
+    /// common type.
+    pub attr_path: Vec<SmolStr>,
+}
+
+impl std::fmt::Display for CommonTypeReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.attr_path.is_empty() {
+            write!(f, "by attribute `{}` ", self.attr_path.iter().join("."))?;
+        }
+        write!(f, "{}", self.location)
+    }
+}
+
+/// A part of a namespace definition in a schema file. Each variant holds the
+/// key under which the enclosing declaration appears.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaSection {
+    /// A `commonTypes` declaration
+    CommonType(SmolStr),
+    /// The name of an `entityTypes` declaration
+    EntityType(SmolStr),
+    /// The `memberOfTypes` list of an entity type
+    EntityTypeMemberOf(SmolStr),
+    /// The `shape` of an entity type
+    EntityTypeShape(SmolStr),
+    /// The `appliesTo` specification of an action
+    ActionAppliesTo(SmolStr),
+    /// The `memberOf` list of an action
+    ActionMemberOf(SmolStr),
+}
+
+impl std::fmt::Display for SchemaSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CommonType(name) => write!(f, "common type `{name}`"),
+            Self::EntityType(name) => write!(f, "entity type `{name}`"),
+            Self::EntityTypeMemberOf(name) => write!(f, "`memberOfTypes` of entity type `{name}`"),
+            Self::EntityTypeShape(name) => write!(f, "shape of entity type `{name}`"),
+            Self::ActionAppliesTo(name) => write!(f, "`appliesTo` of action `{name}`"),
+            Self::ActionMemberOf(name) => write!(f, "`memberOf` of action `{name}`"),
+        }
+    }
+}
+
+/// A `SchemaError` together with an owned copy of the schema source it was
+/// produced from, and the span of that source where the error occurred if it
+/// is known. Returned by `ValidatorSchema::from_json_str_with_source`.
+#[derive(Debug, Error)]
+#[error("{error}")]
+pub struct SchemaErrorWithSource {
+    error: Box<SchemaError>,
+    src: String,
+    span: Option<SourceInfo>,
+}
+
+impl SchemaErrorWithSource {
+    /// Attach `src` to `error`. The span is known for errors reported by
+    /// `serde_json`, which include the line and column of the last character
+    /// read before the error was detected.
+    pub(crate) fn new(error: SchemaError, src: &str) -> Self {
+        let span = match &error {
+            SchemaError::Serde(err) if err.line() > 0 => {
+                let line_start = src
+                    .split_inclusive('\n')
+                    .take(err.line() - 1)
+                    .map(str::len)
+                    .sum::<usize>();
+                let offset = (line_start + err.column().saturating_sub(1)).min(src.len());
+                Some(SourceInfo::from_offset(offset))
+            }
+            _ => None,
+        };
+        Self {
+            error: Box::new(error),
+            src: src.to_string(),
+            span,
+        }
+    }
+
+    /// The underlying error
+    pub fn error(&self) -> &SchemaError {
+        &self.error
+    }
+
+    /// Discard the source, returning the underlying error
+    pub fn into_error(self) -> SchemaError {
+        *self.error
+    }
+
+    /// The schema source in which the error occurred
+    pub fn source_text(&self) -> &str {
+        &self.src
+    }
+
+    /// The span of the source at which the error occurred, if it is known
+    pub fn span(&self) -> Option<&SourceInfo> {
+        self.span.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ContextOrShape {
     ActionContext(EntityUID),
     EntityTypeShape(Name),
@@ -145,6 +650,8 @@ pub enum UnsupportedFeature {
     OpenRecordsAndEntities,
     // Action attributes are allowed if `ActionBehavior` is `PermitAttributes`
     ActionAttributes(Vec<String>),
+    // Default values are only allowed for top-level entity attributes
+    NonEntityAttributeDefaults(Vec<String>),
 }
 
 impl std::fmt::Display for UnsupportedFeature {
@@ -159,6 +666,41 @@ impl std::fmt::Display for UnsupportedFeature {
                 "action declared with attributes: [{}]",
                 attrs.iter().join(", ")
             ),
+            Self::NonEntityAttributeDefaults(attrs) => write!(
+                f,
+                "default values declared for attributes which are not top-level entity attributes: [{}]",
+                attrs.iter().join(", ")
+            ),
         }
     }
 }
+
+/// Error returned by `ValidatorSchema::type_of_path` when an attribute path
+/// cannot be resolved. Path segments are numbered from 0.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PathError {
+    /// The entity type at the root of the path is not declared in the schema
+    #[error("undeclared entity type `{0}`")]
+    UndeclaredEntityType(Name),
+    /// The path has no segments, so there is no attribute to find the type of
+    #[error("attribute path is empty")]
+    EmptyPath,
+    /// An attribute in the path is not declared for the entity type or record
+    /// it is accessed on
+    #[error("attribute `{attr}` (path segment {index}) does not exist")]
+    MissingAttribute {
+        /// Position of the attribute in the path
+        index: usize,
+        /// The missing attribute
+        attr: SmolStr,
+    },
+    /// An attribute in the path is accessed on an attribute whose type is not
+    /// a record
+    #[error("cannot access attribute `{attr}` (path segment {index}) because the preceding attribute is not a record")]
+    NotARecord {
+        /// Position of the attribute in the path
+        index: usize,
+        /// The attribute which could not be accessed
+        attr: SmolStr,
+    },
+}
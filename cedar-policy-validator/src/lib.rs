@@ -158,7 +158,7 @@ impl Validator {
 
 #[cfg(test)]
 mod test {
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
 
     use super::*;
     use cedar_policy_core::{ast, parser};
@@ -175,14 +175,24 @@ mod test {
                     foo_type.into(),
                     EntityType {
                         member_of_types: vec![],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
                 (
                     bar_type.into(),
                     EntityType {
                         member_of_types: vec![],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
             ],
@@ -191,11 +201,17 @@ mod test {
                 ActionType {
                     applies_to: Some(ApplySpec {
                         resource_types: None,
+                        resource_types_with_descendants: None,
                         principal_types: None,
+                        principals_in: Vec::new(),
+                        resources_in: Vec::new(),
                         context: AttributesOrContext::default(),
                     }),
                     member_of: None,
                     attributes: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
                 },
             )],
         );
@@ -392,4 +408,40 @@ mod test {
 
         Ok(())
     }
+
+    /// Case-insensitive action lookup does not apply to policies, since an
+    /// action literal with different case never matches the declared action.
+    #[test]
+    fn case_insensitive_lookup_does_not_affect_validation() {
+        let schema = ValidatorSchema::from_json_value(serde_json::json!({
+            "": {
+                "entityTypes": { "User": {}, "Photo": {} },
+                "actions": {
+                    "view_photo": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] }
+                    }
+                }
+            }
+        }))
+        .expect("Schema should construct without error.")
+        .with_lookup_config(LookupConfig {
+            case_insensitive_actions: true,
+        });
+        let validator = Validator::new(schema);
+        let mut set = PolicySet::new();
+        let policy = parser::parse_policy(
+            Some("policy".to_string()),
+            r#"permit(principal, action == Action::"View_Photo", resource);"#,
+        )
+        .expect("Test Policy Should Parse");
+        set.add_static(policy)
+            .expect("Policy already present in PolicySet");
+
+        let result = validator.validate(&set, ValidationMode::default());
+        assert!(!result.validation_passed());
+        assert!(result.validation_errors().any(|err| matches!(
+            err.error_kind(),
+            ValidationErrorKind::UnrecognizedActionId(_)
+        )));
+    }
 }
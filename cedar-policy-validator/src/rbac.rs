@@ -344,7 +344,7 @@ impl Validator {
         self.get_actions_satisfying_constraint(action_constraint)
             // Get the action type if the id string exists, and then the
             // applies_to list.
-            .filter_map(|action_id| self.schema.get_action_id(&action_id))
+            .filter_map(|action_id| self.schema.get_action_id_exact(&action_id))
             .map(|action| &action.applies_to)
     }
 
@@ -504,28 +504,48 @@ mod test {
                     user_type.into(),
                     EntityType {
                         member_of_types: vec![group_type.into()],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
                 (
                     group_type.into(),
                     EntityType {
                         member_of_types: vec![],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
                 (
                     widget_type.into(),
                     EntityType {
                         member_of_types: vec![bin_type.into()],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
                 (
                     bin_type.into(),
                     EntityType {
                         member_of_types: vec![],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
             ],
@@ -534,11 +554,17 @@ mod test {
                 ActionType {
                     applies_to: Some(ApplySpec {
                         resource_types: Some(vec![widget_type.into()]),
+                        resource_types_with_descendants: None,
                         principal_types: Some(vec![user_type.into()]),
+                        principals_in: Vec::new(),
+                        resources_in: Vec::new(),
                         context: AttributesOrContext::default(),
                     }),
                     member_of: None,
                     attributes: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
                 },
             )],
         );
@@ -580,7 +606,12 @@ mod test {
                 foo_type.into(),
                 EntityType {
                     member_of_types: vec![],
-                    shape: AttributesOrContext::default(),
+                    member_of: vec![],
+                    shape: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
+                    id_format: None,
                 },
             )],
             [],
@@ -615,7 +646,12 @@ mod test {
                 "foo_type".into(),
                 EntityType {
                     member_of_types: vec![],
-                    shape: AttributesOrContext::default(),
+                    member_of: vec![],
+                    shape: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
+                    id_format: None,
                 },
             )],
             [],
@@ -703,6 +739,9 @@ mod test {
                     applies_to: None,
                     member_of: None,
                     attributes: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
                 },
             )],
         );
@@ -736,7 +775,12 @@ mod test {
                 p_name.into(),
                 EntityType {
                     member_of_types: vec![],
-                    shape: AttributesOrContext::default(),
+                    member_of: vec![],
+                    shape: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
+                    id_format: None,
                 },
             )],
             [],
@@ -760,7 +804,12 @@ mod test {
                 p_name.into(),
                 EntityType {
                     member_of_types: vec![],
-                    shape: AttributesOrContext::default(),
+                    member_of: vec![],
+                    shape: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
+                    id_format: None,
                 },
             )],
             [],
@@ -784,7 +833,12 @@ mod test {
                 p_name.into(),
                 EntityType {
                     member_of_types: vec![],
-                    shape: AttributesOrContext::default(),
+                    member_of: vec![],
+                    shape: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
+                    id_format: None,
                 },
             )],
             [],
@@ -830,6 +884,9 @@ mod test {
                     applies_to: None,
                     member_of: None,
                     attributes: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
                 },
             )],
         );
@@ -1020,6 +1077,9 @@ mod test {
                     applies_to: None,
                     member_of: None,
                     attributes: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
                 },
             )],
         );
@@ -1049,6 +1109,9 @@ mod test {
                     applies_to: None,
                     member_of: None,
                     attributes: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
                 },
             )],
         );
@@ -1078,6 +1141,9 @@ mod test {
                     applies_to: None,
                     member_of: None,
                     attributes: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
                 },
             )],
         );
@@ -1104,7 +1170,12 @@ mod test {
                 foo_type.into(),
                 EntityType {
                     member_of_types: vec![],
-                    shape: AttributesOrContext::default(),
+                    member_of: vec![],
+                    shape: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
+                    id_format: None,
                 },
             )],
             [],
@@ -1139,14 +1210,24 @@ mod test {
                     principal_type.into(),
                     EntityType {
                         member_of_types: vec![],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
                 (
                     resource_type.into(),
                     EntityType {
                         member_of_types: vec![],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
             ],
@@ -1155,11 +1236,17 @@ mod test {
                 ActionType {
                     applies_to: Some(ApplySpec {
                         resource_types: Some(vec![resource_type.into()]),
+                        resource_types_with_descendants: None,
                         principal_types: Some(vec![principal_type.into()]),
+                        principals_in: Vec::new(),
+                        resources_in: Vec::new(),
                         context: AttributesOrContext::default(),
                     }),
                     member_of: Some(vec![]),
                     attributes: None,
+                    annotations: BTreeMap::new(),
+                    deprecated: false,
+                    deprecation_message: None,
                 },
             )],
         )
@@ -1344,28 +1431,48 @@ mod test {
                     principal_type.into(),
                     EntityType {
                         member_of_types: vec![],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
                 (
                     resource_type.into(),
                     EntityType {
                         member_of_types: vec![resource_parent_type.into()],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
                 (
                     resource_parent_type.into(),
                     EntityType {
                         member_of_types: vec![resource_grandparent_type.into()],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
                 (
                     resource_grandparent_type.into(),
                     EntityType {
                         member_of_types: vec![],
-                        shape: AttributesOrContext::default(),
+                        member_of: vec![],
+                        shape: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
+                        id_format: None,
                     },
                 ),
             ],
@@ -1375,7 +1482,10 @@ mod test {
                     ActionType {
                         applies_to: Some(ApplySpec {
                             resource_types: Some(vec![resource_type.into()]),
+                            resource_types_with_descendants: None,
                             principal_types: Some(vec![principal_type.into()]),
+                            principals_in: Vec::new(),
+                            resources_in: Vec::new(),
                             context: AttributesOrContext::default(),
                         }),
                         member_of: Some(vec![ActionEntityUID {
@@ -1383,6 +1493,9 @@ mod test {
                             id: action_parent_name.into(),
                         }]),
                         attributes: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
                     },
                 ),
                 (
@@ -1394,6 +1507,9 @@ mod test {
                             id: action_grandparent_name.into(),
                         }]),
                         attributes: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
                     },
                 ),
                 (
@@ -1402,6 +1518,9 @@ mod test {
                         applies_to: None,
                         member_of: Some(vec![]),
                         attributes: None,
+                        annotations: BTreeMap::new(),
+                        deprecated: false,
+                        deprecation_message: None,
                     },
                 ),
             ],
@@ -20,37 +20,80 @@
 //! `member_of` relation from the schema is reversed and the transitive closure is
 //! computed to obtain a `descendants` relation.
 
-use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::cmp::Ordering;
+use std::collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 
 use cedar_policy_core::{
-    ast::{Entity, EntityType, EntityUID, Id, Name},
-    entities::{Entities, TCComputation},
+    ast::{Entity, EntityType, EntityUID, Id, Name, Pattern, PatternElem, RestrictedExpr},
+    entities::{
+        type_of_restricted_expr, CedarValueJson, ContextJsonParser, Entities,
+        EntitySchemaConformanceChecker, EntitySchemaConformanceError, JsonDeserializationError,
+        JsonDeserializationErrorContext, TCComputation, TypeOfRestrictedExprError, ValueParser,
+    },
     extensions::Extensions,
-    transitive_closure::compute_tc,
+    transitive_closure::{compute_tc, TCNode, TcError},
+    FromNormalizedStr,
+};
+use regex::Regex;
+use serde::{
+    de::{Error as _, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
 };
-use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use smol_str::SmolStr;
 
 use super::NamespaceDefinition;
+use crate::schema_file_format::{skip_utf8_bom, CountingReader, VERSION_KEY};
 use crate::types::OpenTag;
 use crate::{
     err::*,
-    types::{Attributes, EntityRecordKind, Type},
-    SchemaFragment,
+    types::{AttributeType, Attributes, EntityRecordKind, Type},
+    SchemaFragment, SchemaVersion,
 };
 
 mod action;
-pub use action::ValidatorActionId;
+pub use action::{ContextDiff, ValidatorActionId};
+mod canonical;
+mod compat;
+mod composer;
+mod deprecation;
+mod describe;
+mod dot;
 pub(crate) use action::ValidatorApplySpec;
+pub use compat::Incompatibility;
+pub use composer::SchemaComposer;
+pub use deprecation::{DeprecatedItem, DeprecatedUsage};
+pub use describe::{ActionDescription, ContextAttributeDescription};
 mod entity_type;
+pub(crate) use entity_type::IdFormat;
 pub use entity_type::ValidatorEntityType;
+mod fixtures;
+mod flatten;
 mod namespace_def;
 pub(crate) use namespace_def::is_action_entity_type;
 pub use namespace_def::ValidatorNamespaceDef;
+use namespace_def::WithUnresolvedTypeDefs;
+mod query;
+mod report;
 #[cfg(test)]
 pub(crate) use namespace_def::ACTION_ENTITY_TYPE;
+pub use query::SchemaQuery;
+pub use report::{
+    SchemaDiagnostic, SchemaDiagnosticKind, SchemaLint, Severity, ValidationReport,
+    MAX_RECOMMENDED_HIERARCHY_DEPTH,
+};
+
+/// Hash the elements of an unordered collection in sorted order, so that equal
+/// collections hash equally regardless of iteration order.
+fn hash_unordered<'a, T: 'a + Ord + std::hash::Hash>(
+    items: impl IntoIterator<Item = &'a T>,
+    state: &mut impl std::hash::Hasher,
+) {
+    let mut items = items.into_iter().collect::<Vec<_>>();
+    items.sort();
+    std::hash::Hash::hash(&items, state);
+}
 
 // We do not have a dafny model for action attributes, so we disable them by defualt.
 #[derive(Eq, PartialEq, Copy, Clone, Default)]
@@ -63,6 +106,153 @@ pub enum ActionBehavior {
     PermitAttributes,
 }
 
+/// Controls how a qualified action type in an action's `memberOf` list is
+/// resolved when the action is declared inside a namespace.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum ActionReferenceResolution {
+    /// Qualified action types always start from the root namespace, so
+    /// `Foo::Action` refers to `Foo::Action` even when written inside the
+    /// namespace `ExampleCo::Personnel`.
+    #[default]
+    Root,
+    /// Qualified action types are first resolved relative to the enclosing
+    /// namespace, so `Foo::Action` inside `ExampleCo::Personnel` refers to
+    /// `ExampleCo::Personnel::Foo::Action` if that action is declared in any
+    /// fragment. Otherwise, it is resolved from the root as in `Root`.
+    RelativeThenRoot,
+}
+
+/// Limits on the structure of a schema, enforced by
+/// `ValidatorSchema::from_schema_fragments_with_limits`, e.g., when accepting
+/// schemas from untrusted sources. The default imposes no limits.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct SchemaLimits {
+    /// The deepest nesting of record types allowed in the type of an entity
+    /// attribute or action context attribute. An attribute with a record type
+    /// has depth 1, a record nested inside it has depth 2, and so on. Sets do
+    /// not add to the depth.
+    pub max_record_nesting: usize,
+}
+
+impl Default for SchemaLimits {
+    fn default() -> Self {
+        Self {
+            max_record_nesting: usize::MAX,
+        }
+    }
+}
+
+/// Options for how a `ValidatorSchema` looks up the names it is given, set
+/// with `ValidatorSchema::with_lookup_config`. The default matches names
+/// exactly.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct LookupConfig {
+    /// When no action has exactly the given id, match an action whose id
+    /// differs from it only by case, e.g., `Action::"View_Photo"` matches the
+    /// declared `Action::"view_photo"`. Only the eid is compared ignoring
+    /// case; the action entity type must match exactly. An id matching
+    /// several actions ignoring case matches none of them. This applies to
+    /// lookups such as `get_action_id`, `action_entity`, `require` and
+    /// `describe_action`, but not to validating policies, in which an action
+    /// must be written exactly as declared.
+    pub case_insensitive_actions: bool,
+}
+
+/// A name which is referenced but not declared by the fragments passed to
+/// `ValidatorSchema::from_schema_fragments_with_resolver`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UndeclaredName {
+    /// An undeclared entity type
+    EntityType(Name),
+    /// An undeclared action
+    Action(EntityUID),
+    /// An undeclared common type, as written in the schema
+    CommonType(SmolStr),
+}
+
+impl UndeclaredName {
+    /// The undeclared names reported by `err`, if it is an error for
+    /// undeclared entity types, actions or common types.
+    fn from_error(err: &SchemaError) -> Vec<Self> {
+        match err {
+            SchemaError::UndeclaredEntityTypes(names) => names
+                .iter()
+                .filter_map(|name| name.parse().ok().map(Self::EntityType))
+                .collect(),
+            SchemaError::UndeclaredActions(names) => names
+                .iter()
+                .filter_map(|name| name.parse().ok().map(Self::Action))
+                .collect(),
+            SchemaError::UndeclaredCommonTypes(names) => names
+                .iter()
+                .map(|name| Self::CommonType(name.into()))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A place in a schema where an entity type is referenced, as returned by
+/// `ValidatorSchema::references_to`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchemaReference {
+    /// The type of an attribute of an entity type refers to the entity type,
+    /// possibly inside a record or set.
+    EntityAttribute {
+        /// The entity type declaring the attribute
+        entity_type: Name,
+        /// The top-level attribute whose type contains the reference
+        attr: SmolStr,
+    },
+    /// Entities of `entity_type` may be members of the referenced entity type,
+    /// either directly or transitively.
+    MemberOf {
+        /// The member entity type
+        entity_type: Name,
+    },
+    /// The type of a context attribute of an action refers to the entity
+    /// type, possibly inside a record or set.
+    ContextAttribute {
+        /// The action declaring the context attribute
+        action: EntityUID,
+        /// The top-level context attribute whose type contains the reference
+        attr: SmolStr,
+    },
+    /// The type of an attribute of an action entity refers to the entity type,
+    /// possibly inside a record or set.
+    ActionAttribute {
+        /// The action declaring the attribute
+        action: EntityUID,
+        /// The top-level attribute whose type contains the reference
+        attr: SmolStr,
+    },
+    /// The entity type is one of the principal types the action applies to.
+    AppliesToPrincipal {
+        /// The action
+        action: EntityUID,
+    },
+    /// The entity type is one of the resource types the action applies to.
+    AppliesToResource {
+        /// The action
+        action: EntityUID,
+    },
+}
+
+/// Size metrics for the attributes declared in a schema, as returned by
+/// `ValidatorSchema::attribute_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AttributeStats {
+    /// The total number of top-level attributes across all entity types
+    pub total: usize,
+    /// The largest number of top-level attributes declared by one entity type
+    pub max_per_type: usize,
+    /// The deepest nesting of record types found in any entity attribute,
+    /// action context attribute or action attribute. An attribute with a
+    /// record type has depth 1, a record nested inside it has depth 2, and so
+    /// on. Sets do not add to the depth.
+    pub max_nesting_depth: usize,
+}
+
 #[derive(Debug)]
 pub struct ValidatorSchemaFragment(Vec<ValidatorNamespaceDef>);
 
@@ -85,6 +275,7 @@ impl ValidatorSchemaFragment {
     ) -> Result<Self> {
         Ok(Self(
             fragment
+                .migrate()?
                 .0
                 .into_iter()
                 .map(|(fragment_ns, ns_def)| {
@@ -98,24 +289,155 @@ impl ValidatorSchemaFragment {
         ))
     }
 
+    /// Construct a `ValidatorSchemaFragment` from a file containing a JSON
+    /// schema fragment without first reading the whole file into memory. Each
+    /// namespace definition is deserialized and converted before the next is
+    /// read, so only one namespace's JSON representation is held in memory at
+    /// a time. A leading UTF-8 byte order mark is ignored. Because namespaces
+    /// are converted as they are read, a `"$version"` key must come before
+    /// every namespace definition.
+    pub fn from_file_streaming(
+        file: impl std::io::Read,
+        action_behavior: ActionBehavior,
+    ) -> Result<Self> {
+        let mut file = CountingReader::new(file);
+        let reader = std::io::BufReader::new(skip_utf8_bom(&mut file)?);
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        let mut schema_error = None;
+        let result = deserializer
+            .deserialize_map(NamespaceDefVisitor {
+                action_behavior,
+                schema_error: &mut schema_error,
+            })
+            .and_then(|namespaces| {
+                deserializer.end()?;
+                Ok(namespaces)
+            });
+        drop(deserializer);
+        match (result, schema_error) {
+            (_, Some(err)) => Err(err),
+            (Err(err), None) => Err(file.schema_error(err)),
+            (Ok(namespaces), None) => Ok(Self(namespaces)),
+        }
+    }
+
     /// Access the `Name`s for the namespaces in this fragment.
     pub fn namespaces(&self) -> impl Iterator<Item = &Option<Name>> {
         self.0.iter().map(|d| d.namespace())
     }
 }
 
+/// Visitor for the top-level map of a schema fragment which converts each
+/// namespace definition into a `ValidatorNamespaceDef` as soon as it has been
+/// deserialized, migrating it from the version of the schema format declared
+/// by a preceding `"$version"` key. `SchemaError`s are not `serde` errors, so
+/// the first one encountered is stored in `schema_error` and deserialization
+/// is aborted.
+struct NamespaceDefVisitor<'a> {
+    action_behavior: ActionBehavior,
+    schema_error: &'a mut Option<SchemaError>,
+}
+
+impl<'de, 'a> Visitor<'de> for NamespaceDefVisitor<'a> {
+    type Value = Vec<ValidatorNamespaceDef>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a map from namespace names to namespace definitions")
+    }
+
+    fn visit_map<M>(self, mut map: M) -> std::result::Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut seen = HashSet::new();
+        let mut namespaces = Vec::new();
+        let mut version = None;
+        while let Some(namespace) = map.next_key::<SmolStr>()? {
+            if namespace == VERSION_KEY {
+                if version.is_some() {
+                    return Err(M::Error::duplicate_field(VERSION_KEY));
+                }
+                if !namespaces.is_empty() {
+                    return Err(M::Error::custom(
+                        "`$version` must come before the namespace definitions",
+                    ));
+                }
+                version = Some(map.next_value::<SchemaVersion>()?);
+                continue;
+            }
+            if !seen.insert(namespace.clone()) {
+                return Err(M::Error::custom("invalid entry: found duplicate key"));
+            }
+            let ns_def = map.next_value::<NamespaceDefinition>()?;
+            match ns_def
+                .migrate(version.unwrap_or(SchemaVersion::CURRENT))
+                .and_then(|ns_def| {
+                    ValidatorNamespaceDef::from_namespace_definition(
+                        Some(namespace),
+                        ns_def,
+                        self.action_behavior,
+                    )
+                }) {
+                Ok(ns_def) => namespaces.push(ns_def),
+                Err(err) => {
+                    *self.schema_error = Some(err);
+                    return Err(M::Error::custom("invalid namespace definition"));
+                }
+            }
+        }
+        Ok(namespaces)
+    }
+}
+
+/// The map from names to entity types or actions used by `ValidatorSchema`.
+/// With the `preserve-order` feature this is an `IndexMap`, so iterating over
+/// the entity types and actions of a schema visits them in the order they were
+/// declared in. Otherwise it is a `HashMap` and the order is arbitrary.
+#[cfg(feature = "preserve-order")]
+pub(crate) type ValidatorMap<K, V> = indexmap::IndexMap<K, V>;
+/// The map from names to entity types or actions used by `ValidatorSchema`.
+/// See the `preserve-order` variant.
+#[cfg(not(feature = "preserve-order"))]
+pub(crate) type ValidatorMap<K, V> = HashMap<K, V>;
+
+/// The `Entry` type of `ValidatorMap`.
+#[cfg(feature = "preserve-order")]
+pub(crate) use indexmap::map::Entry as ValidatorMapEntry;
+/// The `Entry` type of `ValidatorMap`.
+#[cfg(not(feature = "preserve-order"))]
+pub(crate) use std::collections::hash_map::Entry as ValidatorMapEntry;
+
+/// Internal representation of a schema used during validation.
+///
+/// The schema is immutable once constructed, so its maps are shared behind an
+/// `Arc` and cloning a `ValidatorSchema` is cheap.
 #[serde_as]
 #[derive(Clone, Debug, Serialize)]
 pub struct ValidatorSchema {
     /// Map from entity type names to the ValidatorEntityType object.
     #[serde(rename = "entityTypes")]
-    #[serde_as(as = "Vec<(_, _)>")]
-    entity_types: HashMap<Name, ValidatorEntityType>,
+    #[serde_as(as = "Arc<Vec<(_, _)>>")]
+    entity_types: Arc<ValidatorMap<Name, ValidatorEntityType>>,
 
     /// Map from action id names to the ValidatorActionId object.
     #[serde(rename = "actionIds")]
-    #[serde_as(as = "Vec<(_, _)>")]
-    action_ids: HashMap<EntityUID, ValidatorActionId>,
+    #[serde_as(as = "Arc<Vec<(_, _)>>")]
+    action_ids: Arc<ValidatorMap<EntityUID, ValidatorActionId>>,
+
+    /// Map from common type names to the common types referenced by their
+    /// definitions. Common types are inlined during construction, so this is
+    /// the only record of them.
+    #[serde(skip)]
+    common_type_dependencies: Arc<HashMap<Name, HashSet<Name>>>,
+
+    /// Namespaces whose declarations mutating operations such as
+    /// `clone_namespace` may not alter. See `freeze_namespace`.
+    #[serde(skip)]
+    frozen_namespaces: Arc<HashSet<Name>>,
+
+    /// How names given to lookups such as `get_action_id` are matched.
+    #[serde(skip)]
+    lookup_config: LookupConfig,
 }
 
 impl std::str::FromStr for ValidatorSchema {
@@ -148,11 +470,24 @@ impl ValidatorSchema {
     // Create a ValidatorSchema without any entity types or actions ids.
     pub fn empty() -> ValidatorSchema {
         Self {
-            entity_types: HashMap::new(),
-            action_ids: HashMap::new(),
+            entity_types: Arc::new(ValidatorMap::new()),
+            action_ids: Arc::new(ValidatorMap::new()),
+            common_type_dependencies: Arc::new(HashMap::new()),
+            frozen_namespaces: Arc::new(HashSet::new()),
+            lookup_config: LookupConfig::default(),
         }
     }
 
+    /// Construct a `ValidatorSchema` from a JSON string, as with `FromStr`,
+    /// but on failure keep an owned copy of the source alongside the error so
+    /// that diagnostics can quote it.
+    pub fn from_json_str_with_source(
+        src: &str,
+    ) -> std::result::Result<Self, SchemaErrorWithSource> {
+        src.parse()
+            .map_err(|error| SchemaErrorWithSource::new(error, src))
+    }
+
     /// Construct a `ValidatorSchema` from a JSON value (which should be an
     /// object matching the `SchemaFileFormat` shape).
     pub fn from_json_value(json: serde_json::Value) -> Result<Self> {
@@ -162,11 +497,67 @@ impl ValidatorSchema {
         )
     }
 
+    /// Construct a `ValidatorSchema` from a JSON value, as `from_json_value`,
+    /// but first check that every top-level key of the JSON object is a
+    /// well-formed namespace (or the empty string). If any are not, returns
+    /// `SchemaError::MalformedNamespaces` listing every malformed key, before
+    /// any other part of the schema is parsed.
+    pub fn from_json_value_checked(json: serde_json::Value) -> Result<Self> {
+        if let serde_json::Value::Object(namespaces) = &json {
+            let mut malformed = namespaces
+                .keys()
+                .filter(|key| !key.is_empty() && key.as_str() != VERSION_KEY)
+                .filter_map(|key| {
+                    Name::from_normalized_str(key)
+                        .err()
+                        .map(|errs| (key.clone(), errs))
+                })
+                .collect::<Vec<_>>();
+            if !malformed.is_empty() {
+                malformed.sort_by(|(a, _), (b, _)| a.cmp(b));
+                return Err(SchemaError::MalformedNamespaces(malformed));
+            }
+        }
+        Self::from_json_value(json)
+    }
+
     /// Construct a `ValidatorSchema` directly from a file.
     pub fn from_file(file: impl std::io::Read) -> Result<Self> {
         Self::from_schema_file(SchemaFragment::from_file(file)?, ActionBehavior::default())
     }
 
+    /// Construct a `ValidatorSchema` from a file without first reading the
+    /// whole file into memory. See `ValidatorSchemaFragment::from_file_streaming`.
+    pub fn from_file_streaming(file: impl std::io::Read) -> Result<Self> {
+        Self::from_schema_fragments([ValidatorSchemaFragment::from_file_streaming(
+            file,
+            ActionBehavior::default(),
+        )?])
+    }
+
+    /// Construct a `ValidatorSchema` from several files, each containing a
+    /// schema fragment. The fragments are composed as by
+    /// `from_schema_fragments`, so entity types and common types may be
+    /// referenced across files. An error parsing one of the files is wrapped
+    /// in `SchemaError::InInput` with the index of that file.
+    pub fn from_files(files: impl IntoIterator<Item = impl std::io::Read>) -> Result<Self> {
+        let fragments = files
+            .into_iter()
+            .enumerate()
+            .map(|(index, file)| {
+                SchemaFragment::from_file(file)
+                    .and_then(|fragment| {
+                        ValidatorSchemaFragment::from_schema_fragment(
+                            fragment,
+                            ActionBehavior::default(),
+                        )
+                    })
+                    .map_err(|err| SchemaError::InInput(index, Box::new(err)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::from_schema_fragments(fragments)
+    }
+
     pub fn from_schema_file(
         schema_file: SchemaFragment,
         action_behavior: ActionBehavior,
@@ -181,41 +572,311 @@ impl ValidatorSchema {
     pub fn from_schema_fragments(
         fragments: impl IntoIterator<Item = ValidatorSchemaFragment>,
     ) -> Result<ValidatorSchema> {
-        let mut type_defs = HashMap::new();
-        let mut entity_type_fragments = HashMap::new();
-        let mut action_fragments = HashMap::new();
-
-        for ns_def in fragments.into_iter().flat_map(|f| f.0.into_iter()) {
-            // Build aggregate maps for the declared typedefs, entity types, and
-            // actions, checking that nothing is defined twice.  Namespaces were
-            // already added by the `ValidatorNamespaceDef`, so the same base
-            // type name may appear multiple times so long as the namespaces are
-            // different.
-            for (name, ty) in ns_def.type_defs.type_defs {
-                match type_defs.entry(name) {
-                    Entry::Vacant(v) => v.insert(ty),
-                    Entry::Occupied(o) => {
-                        return Err(SchemaError::DuplicateCommonType(o.key().to_string()));
-                    }
-                };
+        Self::from_schema_fragments_with_action_resolution(
+            fragments,
+            ActionReferenceResolution::default(),
+        )
+    }
+
+    /// Construct a new `ValidatorSchema` from some number of schema fragments,
+    /// resolving qualified action types in `memberOf` lists as specified by
+    /// `action_resolution`.
+    pub fn from_schema_fragments_with_action_resolution(
+        fragments: impl IntoIterator<Item = ValidatorSchemaFragment>,
+        action_resolution: ActionReferenceResolution,
+    ) -> Result<ValidatorSchema> {
+        let mut errors = Vec::new();
+        let schema = Self::build_collecting(
+            fragments,
+            action_resolution,
+            SchemaLimits::default(),
+            &mut errors,
+            None,
+        );
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(schema),
+        }
+    }
+
+    /// Construct a new `ValidatorSchema` from some number of schema fragments,
+    /// as `from_schema_fragments`, but also check that the schema is within
+    /// `limits`. Returns `RecordTooDeep` for an entity type shape or action
+    /// context with an attribute whose records are nested more deeply than
+    /// `limits.max_record_nesting`. The limits are checked as each shape and
+    /// context is converted, before the schema is fully constructed.
+    pub fn from_schema_fragments_with_limits(
+        fragments: impl IntoIterator<Item = ValidatorSchemaFragment>,
+        limits: SchemaLimits,
+    ) -> Result<ValidatorSchema> {
+        let mut errors = Vec::new();
+        let schema = Self::build_collecting(
+            fragments,
+            ActionReferenceResolution::default(),
+            limits,
+            &mut errors,
+            None,
+        );
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(schema),
+        }
+    }
+
+    /// Construct a new `ValidatorSchema` from some number of schema fragments,
+    /// as `from_schema_fragments`, but declare any entity type which is
+    /// referenced (in a `memberOfTypes` list, an `appliesTo` list, or the type
+    /// of an attribute) without being declared, instead of returning an
+    /// `UndeclaredEntityTypes` error. Each such entity type is declared with
+    /// no attributes, and its descendants are the declared entity types
+    /// listing it in `memberOfTypes`.
+    ///
+    /// Returns the schema along with the names of the entity types which were
+    /// declared this way, sorted, so they can be reported as warnings.
+    /// Undeclared actions are still an error.
+    pub fn from_schema_fragments_autodeclare(
+        fragments: impl IntoIterator<Item = ValidatorSchemaFragment>,
+    ) -> Result<(ValidatorSchema, Vec<Name>)> {
+        let mut errors = Vec::new();
+        let mut autodeclared = Vec::new();
+        let schema = Self::build_collecting(
+            fragments,
+            ActionReferenceResolution::default(),
+            SchemaLimits::default(),
+            &mut errors,
+            Some(&mut autodeclared),
+        );
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => {
+                autodeclared.sort();
+                Ok((schema, autodeclared))
             }
+        }
+    }
 
-            for (name, entity_type) in ns_def.entity_types.entity_types {
-                match entity_type_fragments.entry(name) {
-                    Entry::Vacant(v) => v.insert(entity_type),
-                    Entry::Occupied(o) => {
-                        return Err(SchemaError::DuplicateEntityType(o.key().to_string()))
-                    }
-                };
+    /// Construct a new `ValidatorSchema` from some number of schema fragments,
+    /// as `from_schema_fragments`, but consult `resolver` for the declarations
+    /// of entity types, actions and common types which are referenced but not
+    /// declared, e.g., to load them on demand from a sharded schema.
+    ///
+    /// Whenever construction finds undeclared names, `resolver` is called once
+    /// for each of them in sorted order, and the fragments it returns are
+    /// added before the schema is constructed again. Names declared by those
+    /// fragments may in turn reference further undeclared names. A name is
+    /// never passed to `resolver` twice, and construction fails with the
+    /// usual error for undeclared names once `resolver` supplies no new
+    /// fragments.
+    pub fn from_schema_fragments_with_resolver(
+        fragments: impl IntoIterator<Item = SchemaFragment>,
+        mut resolver: impl FnMut(&UndeclaredName) -> Option<SchemaFragment>,
+    ) -> Result<ValidatorSchema> {
+        let mut fragments = fragments.into_iter().collect::<Vec<_>>();
+        let mut requested = HashSet::new();
+        loop {
+            let validator_fragments = fragments
+                .iter()
+                .cloned()
+                .map(TryInto::try_into)
+                .collect::<Result<Vec<ValidatorSchemaFragment>>>()?;
+            let mut errors = Vec::new();
+            let schema = Self::build_collecting(
+                validator_fragments,
+                ActionReferenceResolution::default(),
+                SchemaLimits::default(),
+                &mut errors,
+                None,
+            );
+            let mut undeclared = errors
+                .iter()
+                .flat_map(UndeclaredName::from_error)
+                .filter(|name| requested.insert(name.clone()))
+                .collect::<Vec<_>>();
+            undeclared.sort();
+            let resolved = undeclared
+                .iter()
+                .filter_map(&mut resolver)
+                .collect::<Vec<_>>();
+            match errors.into_iter().next() {
+                None => return Ok(schema),
+                Some(err) if resolved.is_empty() => return Err(err),
+                Some(_) => fragments.extend(resolved),
             }
+        }
+    }
 
-            for (action_euid, action) in ns_def.actions.actions {
-                match action_fragments.entry(action_euid) {
-                    Entry::Vacant(v) => v.insert(action),
-                    Entry::Occupied(o) => {
-                        return Err(SchemaError::DuplicateAction(o.key().to_string()))
-                    }
-                };
+    /// Construct a new `ValidatorSchema` from some number of schema fragments,
+    /// as `from_schema_fragments`, but report every problem found instead of
+    /// only the first. The checks run in stages:
+    ///
+    /// 1. Each namespace of each fragment is parsed independently. If any
+    ///    fail, only those errors are returned, because references into a
+    ///    namespace which failed to parse would otherwise be reported as
+    ///    undeclared.
+    /// 2. Duplicate common types, entity types and actions are reported; the
+    ///    first declaration of each is kept for the remaining checks.
+    /// 3. Common type definitions are resolved, each after the common types
+    ///    it refers to, and cycles between them are reported. Each entity
+    ///    type shape and action context is then resolved and checked
+    ///    independently (undeclared common types, shapes or contexts which
+    ///    are not records, invalid attribute defaults). An entity type or
+    ///    action with such an error is treated as having no attributes for
+    ///    the remaining checks, so it is still considered declared.
+    /// 4. Cycles in the action hierarchy are reported. The transitive closure
+    ///    of a cyclic hierarchy is incomplete, but the check for undeclared
+    ///    names does not depend on it.
+    /// 5. Undeclared entity types and undeclared actions are each reported.
+    ///
+    /// When there is at least one error, the first error in the result is the
+    /// one which `from_schema_fragments` would return.
+    pub fn validate_collecting(
+        fragments: impl IntoIterator<Item = SchemaFragment>,
+    ) -> std::result::Result<ValidatorSchema, Vec<SchemaError>> {
+        let mut errors = Vec::new();
+        let mut namespaces = Vec::new();
+        for fragment in fragments {
+            for (fragment_ns, ns_def) in fragment.0 {
+                match ValidatorNamespaceDef::from_namespace_definition(
+                    Some(fragment_ns),
+                    ns_def,
+                    ActionBehavior::default(),
+                ) {
+                    Ok(ns_def) => namespaces.push(ns_def),
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let schema = Self::build_collecting(
+            [ValidatorSchemaFragment(namespaces)],
+            ActionReferenceResolution::default(),
+            SchemaLimits::default(),
+            &mut errors,
+            None,
+        );
+        if errors.is_empty() {
+            Ok(schema)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Build a schema from the given fragments, pushing every error
+    /// encountered onto `errors`. The result is only meaningful if no errors
+    /// were pushed. See `validate_collecting` for which checks depend on
+    /// which. Entity type shapes and action contexts which exceed `limits`
+    /// are reported as they are converted. If `autodeclared` is provided, undeclared entity types are
+    /// declared with no attributes and their names pushed onto it, as
+    /// described for `from_schema_fragments_autodeclare`.
+    fn build_collecting(
+        fragments: impl IntoIterator<Item = ValidatorSchemaFragment>,
+        action_resolution: ActionReferenceResolution,
+        limits: SchemaLimits,
+        errors: &mut Vec<SchemaError>,
+        mut autodeclared: Option<&mut Vec<Name>>,
+    ) -> ValidatorSchema {
+        let mut unresolved_type_defs = HashMap::new();
+        let mut type_def_references = HashMap::new();
+        let mut entity_type_fragments = ValidatorMap::new();
+        let mut action_fragments = ValidatorMap::new();
+
+        // The fragment in which each common type, entity type and action was
+        // first declared, so that duplicate errors can report both fragments.
+        let mut type_def_sources = HashMap::new();
+        let mut entity_type_sources = HashMap::new();
+        let mut action_sources = HashMap::new();
+        let fragment_indices = |first: Option<&usize>, duplicate: usize| {
+            first
+                .filter(|first| **first != duplicate)
+                .map(|&first| FragmentIndices { first, duplicate })
+        };
+
+        for (index, fragment) in fragments.into_iter().enumerate() {
+            for ns_def in fragment.0 {
+                for (name, references) in ns_def.type_defs.dependencies {
+                    type_def_references.entry(name).or_insert(references);
+                }
+                // Build aggregate maps for the declared typedefs, entity types,
+                // and actions, checking that nothing is defined twice.
+                // Namespaces were already added by the `ValidatorNamespaceDef`,
+                // so the same base type name may appear multiple times so long
+                // as the namespaces are different.
+                for (name, ty) in ns_def.type_defs.type_defs {
+                    match unresolved_type_defs.entry(name) {
+                        Entry::Vacant(v) => {
+                            type_def_sources.insert(v.key().clone(), index);
+                            v.insert(ty);
+                        }
+                        Entry::Occupied(o) => {
+                            errors.push(SchemaError::DuplicateCommonType(
+                                o.key().to_string(),
+                                fragment_indices(type_def_sources.get(o.key()), index),
+                            ));
+                        }
+                    };
+                }
+
+                for (name, entity_type) in ns_def.entity_types.entity_types {
+                    match entity_type_fragments.entry(name) {
+                        ValidatorMapEntry::Vacant(v) => {
+                            entity_type_sources.insert(v.key().clone(), index);
+                            v.insert(entity_type);
+                        }
+                        ValidatorMapEntry::Occupied(o) => {
+                            errors.push(SchemaError::DuplicateEntityType(
+                                o.key().to_string(),
+                                fragment_indices(entity_type_sources.get(o.key()), index),
+                            ));
+                        }
+                    };
+                }
+
+                for (action_euid, action) in ns_def.actions.actions {
+                    match action_fragments.entry(action_euid) {
+                        ValidatorMapEntry::Vacant(v) => {
+                            action_sources.insert(v.key().clone(), index);
+                            v.insert(action);
+                        }
+                        ValidatorMapEntry::Occupied(o) => {
+                            errors.push(SchemaError::DuplicateAction(
+                                o.key().to_string(),
+                                fragment_indices(action_sources.get(o.key()), index),
+                            ));
+                        }
+                    };
+                }
+            }
+        }
+
+        // A common type definition may refer to other common types, so each
+        // is resolved once the common types it depends on have been.
+        let common_type_dependencies = type_def_references
+            .into_iter()
+            .map(|(name, references)| {
+                let dependencies = references
+                    .into_iter()
+                    .filter(|reference| unresolved_type_defs.contains_key(reference))
+                    .collect::<HashSet<_>>();
+                (name, dependencies)
+            })
+            .collect::<HashMap<_, _>>();
+        let type_defs =
+            Self::resolve_common_types(unresolved_type_defs, &common_type_dependencies, errors);
+
+        // Now that every action is known, prefer the relatively resolved
+        // parent actions where they are declared.
+        if action_resolution == ActionReferenceResolution::RelativeThenRoot {
+            let declared_actions = action_fragments.keys().cloned().collect::<HashSet<_>>();
+            for action in action_fragments.values_mut() {
+                action.parents = std::mem::take(&mut action.parents)
+                    .into_iter()
+                    .map(|parent| match action.relative_parents.get(&parent) {
+                        Some(relative) if declared_actions.contains(relative) => relative.clone(),
+                        _ => parent,
+                    })
+                    .collect();
             }
         }
 
@@ -233,7 +894,7 @@ impl ValidatorSchema {
 
         let mut entity_types = entity_type_fragments
             .into_iter()
-            .map(|(name, entity_type)| -> Result<_> {
+            .map(|(name, entity_type)| {
                 // Keys of the `entity_children` map were values of an
                 // `memberOfTypes` list, so they might not have been declared in
                 // their fragment.  By removing entries from `entity_children`
@@ -244,21 +905,106 @@ impl ValidatorSchema {
                 // error for any other undeclared entity types by
                 // `check_for_undeclared`.
                 let descendants = entity_children.remove(&name).unwrap_or_default();
-                Ok((
+                let in_shape = |e: SchemaError| {
+                    e.in_section(SchemaSection::EntityTypeShape(
+                        name.basename().clone().to_smolstr(),
+                    ))
+                    .in_namespace(Some(&name.namespace().into()))
+                };
+                let attributes_and_defaults = entity_type
+                    .attributes
+                    .resolve_type_defs(&type_defs)
+                    .map_err(in_shape)
+                    .and_then(|ty| {
+                        Self::record_attributes_or_none(ty).ok_or(
+                            SchemaError::ContextOrShapeNotRecord(ContextOrShape::EntityTypeShape(
+                                name.clone(),
+                            )),
+                        )
+                    })
+                    .and_then(|attributes| {
+                        Self::check_attribute_names(&attributes, || {
+                            ContextOrShape::EntityTypeShape(name.clone())
+                        })?;
+                        Self::check_record_nesting(&attributes, limits.max_record_nesting)
+                            .map_err(in_shape)?;
+                        let attribute_defaults = Self::parse_attribute_defaults(
+                            &name,
+                            &attributes,
+                            entity_type.attribute_defaults,
+                        )?;
+                        Ok((attributes, attribute_defaults))
+                    });
+                let (attributes, attribute_defaults) =
+                    attributes_and_defaults.unwrap_or_else(|err| {
+                        errors.push(err);
+                        (Attributes::with_attributes(None), HashMap::new())
+                    });
+                (
                     name.clone(),
                     ValidatorEntityType {
-                        name: name.clone(),
+                        name,
                         descendants,
-                        attributes: Self::record_attributes_or_none(
-                            entity_type.attributes.resolve_type_defs(&type_defs)?,
-                        )
-                        .ok_or(SchemaError::ContextOrShapeNotRecord(
-                            ContextOrShape::EntityTypeShape(name),
-                        ))?,
+                        required_parents: entity_type.required_parents,
+                        attributes,
+                        attribute_defaults,
+                        shape_was_explicit: entity_type.shape_was_explicit,
+                        annotations: entity_type.annotations,
+                        id_format: entity_type.id_format,
+                        deprecated: entity_type.deprecated,
+                        deprecation_message: entity_type.deprecation_message,
                     },
-                ))
+                )
             })
-            .collect::<Result<HashMap<_, _>>>()?;
+            .collect::<ValidatorMap<_, _>>();
+
+        // Declare the entity types which are parents in a `memberOfTypes` list
+        // or referenced by an attribute type without being declared. This
+        // happens before computing the transitive closure so that their
+        // descendants are closed as well.
+        if let Some(autodeclared) = autodeclared.as_deref_mut() {
+            let undeclared = entity_children
+                .keys()
+                .cloned()
+                .chain(Self::undeclared_entity_type_names(
+                    &entity_types,
+                    &ValidatorMap::new(),
+                ))
+                .collect::<HashSet<_>>();
+            for name in undeclared {
+                let descendants = entity_children.remove(&name).unwrap_or_default();
+                entity_types.insert(
+                    name.clone(),
+                    Self::empty_entity_type(name.clone(), descendants),
+                );
+                autodeclared.push(name);
+            }
+        }
+
+        // We constructed entity types with child maps, but we need
+        // transitively closed descendants. These are needed before building
+        // the actions so that `resourceTypesWithDescendants` can be expanded.
+        if let Err(err) = Self::compute_schema_tc(&mut entity_types, false) {
+            errors.push(err.into());
+        }
+
+        // An entity in a `memberOf` list must have a type which `memberOfTypes`
+        // allows as an ancestor, or no entity could conform. Undeclared types
+        // are reported by `check_for_undeclared`.
+        for (name, entity_type) in entity_types.iter() {
+            for parent in entity_type.required_parents() {
+                let EntityType::Concrete(parent_ty) = parent.entity_type() else {
+                    continue;
+                };
+                if matches!(entity_types.get(parent_ty), Some(parent_ty) if !parent_ty.descendants.contains(name))
+                {
+                    errors.push(SchemaError::DisallowedRequiredParent {
+                        entity_type: name.to_string(),
+                        parent: parent.to_string(),
+                    });
+                }
+            }
+        }
 
         let mut action_children = HashMap::new();
         for (euid, action) in action_fragments.iter() {
@@ -271,34 +1017,90 @@ impl ValidatorSchema {
         }
         let mut action_ids = action_fragments
             .into_iter()
-            .map(|(name, action)| -> Result<_> {
+            .map(|(name, action)| {
                 let descendants = action_children.remove(&name).unwrap_or_default();
+                let in_context = |e: SchemaError| {
+                    let namespace = match name.entity_type() {
+                        EntityType::Concrete(ty) => ty.namespace(),
+                        EntityType::Unspecified => String::new(),
+                    };
+                    let eid: &SmolStr = name.eid().as_ref();
+                    e.in_section(SchemaSection::ActionAppliesTo(eid.clone()))
+                        .in_namespace(Some(&namespace.into()))
+                };
+                let context = action
+                    .context
+                    .resolve_type_defs(&type_defs)
+                    .map_err(in_context)
+                    .and_then(|ty| {
+                        Self::record_attributes_or_none(ty).ok_or(
+                            SchemaError::ContextOrShapeNotRecord(ContextOrShape::ActionContext(
+                                name.clone(),
+                            )),
+                        )
+                    })
+                    // Check now that the context can be used to parse context
+                    // data, so that `get_context_schema` cannot fail later.
+                    .and_then(|context| {
+                        Self::check_attribute_names(&context, || {
+                            ContextOrShape::ActionContext(name.clone())
+                        })?;
+                        Self::check_record_nesting(&context, limits.max_record_nesting)
+                            .map_err(in_context)?;
+                        ContextSchema::new(&name, &context)?;
+                        Ok(context)
+                    })
+                    .unwrap_or_else(|err| {
+                        errors.push(err);
+                        Attributes::with_attributes(None)
+                    });
+                // Undeclared roots are reported by `check_for_undeclared`
+                // because they are already in `applies_to`.
+                let mut applies_to = action.applies_to;
+                applies_to.add_resource_types(
+                    action
+                        .resource_descendant_roots
+                        .iter()
+                        .filter_map(|root| entity_types.get(root))
+                        .flat_map(|root| root.descendants.iter())
+                        .map(|ty| EntityType::Concrete(ty.clone())),
+                );
 
-                Ok((
+                (
                     name.clone(),
                     ValidatorActionId {
-                        name: name.clone(),
-                        applies_to: action.applies_to,
+                        name,
+                        applies_to,
                         descendants,
-                        context: Self::record_attributes_or_none(
-                            action.context.resolve_type_defs(&type_defs)?,
-                        )
-                        .ok_or(SchemaError::ContextOrShapeNotRecord(
-                            ContextOrShape::ActionContext(name),
-                        ))?,
+                        context,
                         attribute_types: action.attribute_types,
                         attributes: action.attributes,
+                        annotations: action.annotations,
+                        deprecated: action.deprecated,
+                        deprecation_message: action.deprecation_message,
                     },
-                ))
+                )
             })
-            .collect::<Result<HashMap<_, _>>>()?;
+            .collect::<ValidatorMap<_, _>>();
+
+        // We also need transitively closed descendants for actions. Pass `true`
+        // here so that we also check that the action hierarchy does not
+        // contain cycles.
+        if let Err(err) = Self::compute_schema_tc(&mut action_ids, true) {
+            errors.push(err.into());
+        }
 
-        // We constructed entity types and actions with child maps, but we need
-        // transitively closed descendants.
-        compute_tc(&mut entity_types, false)?;
-        // Pass `true` here so that we also check that the action hierarchy does
-        // not contain cycles.
-        compute_tc(&mut action_ids, true)?;
+        // The remaining undeclared entity types are only referenced by actions,
+        // so they have no descendants and the closure is unaffected.
+        if let Some(autodeclared) = autodeclared {
+            for name in Self::undeclared_entity_type_names(&entity_types, &action_ids) {
+                entity_types.insert(
+                    name.clone(),
+                    Self::empty_entity_type(name.clone(), HashSet::new()),
+                );
+                autodeclared.push(name);
+            }
+        }
 
         // Return with an error if there is an undeclared entity or action
         // referenced in any fragment. `{entity,action}_children` are provided
@@ -311,12 +1113,106 @@ impl ValidatorSchema {
             entity_children.into_keys(),
             &action_ids,
             action_children.into_keys(),
-        )?;
+            errors,
+        );
 
-        Ok(ValidatorSchema {
-            entity_types,
-            action_ids,
-        })
+        ValidatorSchema {
+            entity_types: Arc::new(entity_types),
+            action_ids: Arc::new(action_ids),
+            common_type_dependencies: Arc::new(common_type_dependencies),
+            frozen_namespaces: Arc::new(HashSet::new()),
+            lookup_config: LookupConfig::default(),
+        }
+    }
+
+    /// Resolve the common type definitions in `pending`, each after the
+    /// common types it depends on according to `dependencies`, so that
+    /// definitions may refer to one another. A definition which fails to
+    /// resolve is reported and left out of the result. The common types which
+    /// are in, or depend on, a cycle of references are reported together as
+    /// `CycleInCommonTypes`.
+    fn resolve_common_types(
+        mut pending: HashMap<Name, WithUnresolvedTypeDefs<Type>>,
+        dependencies: &HashMap<Name, HashSet<Name>>,
+        errors: &mut Vec<SchemaError>,
+    ) -> HashMap<Name, Type> {
+        let mut type_defs = HashMap::new();
+        loop {
+            let ready = pending
+                .keys()
+                .filter(|name| {
+                    dependencies
+                        .get(*name)
+                        .into_iter()
+                        .flatten()
+                        .all(|dependency| !pending.contains_key(dependency))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if ready.is_empty() {
+                break;
+            }
+            for name in ready {
+                if let Some(ty) = pending.remove(&name) {
+                    match ty.resolve_type_defs(&type_defs) {
+                        Ok(ty) => {
+                            type_defs.insert(name, ty);
+                        }
+                        Err(err) => errors.push(
+                            err.in_section(SchemaSection::CommonType(
+                                name.basename().clone().to_smolstr(),
+                            ))
+                            .in_namespace(Some(&name.namespace().into())),
+                        ),
+                    }
+                }
+            }
+        }
+        if !pending.is_empty() {
+            let mut names = pending
+                .into_keys()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>();
+            names.sort();
+            errors.push(SchemaError::CycleInCommonTypes(names));
+        }
+        type_defs
+    }
+
+    /// Compute the transitive closure of the entity types or actions in `nodes`,
+    /// as `compute_tc` does for a `HashMap`.
+    #[cfg(not(feature = "preserve-order"))]
+    fn compute_schema_tc<K, V>(
+        nodes: &mut ValidatorMap<K, V>,
+        enforce_dag: bool,
+    ) -> std::result::Result<(), TcError<K>>
+    where
+        K: Clone + Eq + std::hash::Hash + std::fmt::Debug + std::fmt::Display,
+        V: TCNode<K>,
+    {
+        compute_tc(nodes, enforce_dag)
+    }
+
+    /// Compute the transitive closure of the entity types or actions in `nodes`.
+    /// `compute_tc` needs a `HashMap`, so the entries are moved into one and
+    /// then back in their original order.
+    #[cfg(feature = "preserve-order")]
+    fn compute_schema_tc<K, V>(
+        nodes: &mut ValidatorMap<K, V>,
+        enforce_dag: bool,
+    ) -> std::result::Result<(), TcError<K>>
+    where
+        K: Clone + Eq + std::hash::Hash + std::fmt::Debug + std::fmt::Display,
+        V: TCNode<K>,
+    {
+        let order = nodes.keys().cloned().collect::<Vec<_>>();
+        let mut unordered = std::mem::take(nodes).into_iter().collect::<HashMap<_, _>>();
+        let result = compute_tc(&mut unordered, enforce_dag);
+        *nodes = order
+            .into_iter()
+            .filter_map(|key| unordered.remove(&key).map(|node| (key, node)))
+            .collect();
+        result
     }
 
     /// Check that all entity types and actions referenced in the schema are in
@@ -324,11 +1220,12 @@ impl ValidatorSchema {
     /// function assumes that all entity types are fully qualified. This is
     /// handled by the `SchemaFragment` constructor.
     fn check_for_undeclared(
-        entity_types: &HashMap<Name, ValidatorEntityType>,
+        entity_types: &ValidatorMap<Name, ValidatorEntityType>,
         undeclared_parent_entities: impl IntoIterator<Item = Name>,
-        action_ids: &HashMap<EntityUID, ValidatorActionId>,
+        action_ids: &ValidatorMap<EntityUID, ValidatorActionId>,
         undeclared_parent_actions: impl IntoIterator<Item = EntityUID>,
-    ) -> Result<()> {
+        errors: &mut Vec<SchemaError>,
+    ) {
         // When we constructed `entity_types`, we removed entity types from  the
         // `entity_children` map as we encountered a declaration for that type.
         // Any entity types left in the map are therefore undeclared. These are
@@ -350,6 +1247,14 @@ impl ValidatorSchema {
                     &mut undeclared_e,
                 );
             }
+            // The types of the entities in a `memberOf` list
+            for parent in entity_type.required_parents() {
+                if let EntityType::Concrete(ty) = parent.entity_type() {
+                    if !entity_types.contains_key(ty) {
+                        undeclared_e.insert(ty.to_string());
+                    }
+                }
+            }
         }
 
         // Undeclared actions in a `memberOf` list.
@@ -390,17 +1295,195 @@ impl ValidatorSchema {
                     EntityType::Unspecified => (),
                 }
             }
+
+            // The types of the entities in `principalsIn` and `resourcesIn`
+            for group in action
+                .applies_to
+                .principal_groups()
+                .chain(action.applies_to.resource_groups())
+            {
+                if let EntityType::Concrete(ty) = group.entity_type() {
+                    if !entity_types.contains_key(ty) {
+                        undeclared_e.insert(ty.to_string());
+                    }
+                }
+            }
         }
         if !undeclared_e.is_empty() {
-            return Err(SchemaError::UndeclaredEntityTypes(undeclared_e));
+            errors.push(SchemaError::UndeclaredEntityTypes(undeclared_e));
         }
         if !undeclared_a.is_empty() {
-            return Err(SchemaError::UndeclaredActions(undeclared_a));
+            errors.push(SchemaError::UndeclaredActions(undeclared_a));
+        }
+    }
+
+    /// Get the entity types referenced by the attribute types of
+    /// `entity_types` or by the context types and `appliesTo` lists of
+    /// `action_ids` which are not declared in `entity_types`.
+    fn undeclared_entity_type_names(
+        entity_types: &ValidatorMap<Name, ValidatorEntityType>,
+        action_ids: &ValidatorMap<EntityUID, ValidatorActionId>,
+    ) -> HashSet<Name> {
+        let mut names = Vec::new();
+        for entity_type in entity_types.values() {
+            for (_, attr_typ) in entity_type.attributes() {
+                Self::collect_entity_types_in_type(&attr_typ.attr_type, &mut names);
+            }
+            names.extend(entity_type.required_parents().filter_map(|parent| {
+                match parent.entity_type() {
+                    EntityType::Concrete(name) => Some(name),
+                    EntityType::Unspecified => None,
+                }
+            }));
+        }
+        for action in action_ids.values() {
+            for (_, attr_typ) in action.context.iter() {
+                Self::collect_entity_types_in_type(&attr_typ.attr_type, &mut names);
+            }
+            names.extend(
+                action
+                    .applies_to
+                    .applicable_principal_types()
+                    .chain(action.applies_to.applicable_resource_types())
+                    .chain(
+                        action
+                            .applies_to
+                            .principal_groups()
+                            .chain(action.applies_to.resource_groups())
+                            .map(EntityUID::entity_type),
+                    )
+                    .filter_map(|ty| match ty {
+                        EntityType::Concrete(name) => Some(name),
+                        EntityType::Unspecified => None,
+                    }),
+            );
+        }
+        names
+            .into_iter()
+            .filter(|name| !entity_types.contains_key(*name))
+            .cloned()
+            .collect()
+    }
+
+    /// An entity type with no attributes, as declared by
+    /// `from_schema_fragments_autodeclare`.
+    fn empty_entity_type(name: Name, descendants: HashSet<Name>) -> ValidatorEntityType {
+        ValidatorEntityType {
+            name,
+            descendants,
+            required_parents: BTreeSet::new(),
+            attributes: Attributes::with_attributes(None),
+            attribute_defaults: HashMap::new(),
+            shape_was_explicit: false,
+            annotations: BTreeMap::new(),
+            id_format: None,
+            deprecated: false,
+            deprecation_message: None,
+        }
+    }
+
+    /// Check the default values declared for the attributes of an entity type
+    /// against the types of those attributes, converting them into the
+    /// `RestrictedExpr`s used when parsing entity data.
+    fn parse_attribute_defaults(
+        entity_type: &Name,
+        attributes: &Attributes,
+        mut defaults: HashMap<SmolStr, CedarValueJson>,
+    ) -> Result<HashMap<SmolStr, RestrictedExpr>> {
+        let extensions = Extensions::all_available();
+        let vparser = ValueParser::new(extensions);
+        attributes
+            .iter()
+            .filter_map(|(attr, attr_ty)| Some((attr, attr_ty, defaults.remove(attr)?)))
+            .map(|(attr, attr_ty, default)| -> Result<_> {
+                let invalid = |reason: String| {
+                    SchemaError::InvalidAttributeDefault(entity_type.clone(), attr.clone(), reason)
+                };
+                if attr_ty.is_required {
+                    return Err(invalid(
+                        "defaults can only be declared for optional attributes".to_string(),
+                    ));
+                }
+                let expected_ty: cedar_policy_core::entities::SchemaType =
+                    attr_ty.attr_type.clone().try_into().map_err(invalid)?;
+                let rexpr = vparser
+                    .val_into_restricted_expr(
+                        serde_json::to_value(default)?,
+                        Some(&expected_ty),
+                        || JsonDeserializationErrorContext::AttributeDefault {
+                            entity_type: EntityType::Concrete(entity_type.clone()),
+                            attr: attr.clone(),
+                        },
+                    )
+                    .map_err(|e| invalid(e.to_string()))?;
+                Self::check_attribute_default(entity_type, attr, Some(attr_ty), &rexpr)?;
+                Ok((attr.clone(), rexpr))
+            })
+            .collect()
+    }
+
+    /// Check the default value `default` of the attribute `attr` of an entity
+    /// type against `attr_ty`, the type of the attribute, or `None` if the
+    /// entity type has no such attribute.
+    fn check_attribute_default(
+        entity_type: &Name,
+        attr: &SmolStr,
+        attr_ty: Option<&AttributeType>,
+        default: &RestrictedExpr,
+    ) -> Result<()> {
+        let invalid = |reason: String| {
+            SchemaError::InvalidAttributeDefault(entity_type.clone(), attr.clone(), reason)
+        };
+        let attr_ty = attr_ty.ok_or_else(|| invalid("no such attribute".to_string()))?;
+        if attr_ty.is_required {
+            return Err(invalid(
+                "defaults can only be declared for optional attributes".to_string(),
+            ));
+        }
+        let expected_ty: cedar_policy_core::entities::SchemaType =
+            attr_ty.attr_type.clone().try_into().map_err(invalid)?;
+        let actual_ty = type_of_restricted_expr(default.as_borrowed(), Extensions::all_available())
+            .map_err(|e| invalid(e.to_string()))?;
+        if actual_ty.is_consistent_with(&expected_ty) {
+            Ok(())
+        } else {
+            Err(invalid(format!(
+                "expected a value of type {expected_ty}, but found a value of type {actual_ty}"
+            )))
         }
+    }
 
+    /// Check that no attribute of a shape or context, including attributes
+    /// of records nested inside it, has an empty name or a name consisting
+    /// only of whitespace.
+    fn check_attribute_names(
+        attributes: &Attributes,
+        location: impl Fn() -> ContextOrShape,
+    ) -> Result<()> {
+        for attrs in Self::records_in(attributes) {
+            if let Some((name, _)) = attrs.iter().find(|(name, _)| name.trim().is_empty()) {
+                return Err(SchemaError::InvalidAttributeName {
+                    name: name.clone(),
+                    location: location(),
+                });
+            }
+        }
         Ok(())
     }
 
+    /// `attributes` followed by the attributes of every record nested inside
+    /// them.
+    fn records_in(attributes: &Attributes) -> impl Iterator<Item = &Attributes> {
+        let nested = attributes
+            .iter()
+            .flat_map(|(_, attr_ty)| Self::type_nodes(&attr_ty.attr_type))
+            .filter_map(|ty| match ty {
+                Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => Some(attrs),
+                _ => None,
+            });
+        std::iter::once(attributes).chain(nested)
+    }
+
     fn record_attributes_or_none(ty: Type) -> Option<Attributes> {
         match ty {
             Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => Some(attrs),
@@ -411,49 +1494,534 @@ impl ValidatorSchema {
     // Check that all entity types appearing inside a type are in the set of
     // declared entity types, adding any undeclared entity types to the
     // `undeclared_types` set.
+    // Entity types are referenced by name and never expanded, so a type
+    // referring to the entity type whose attribute it is, directly or through
+    // other entity types, is checked like any other reference and cannot
+    // cause unbounded recursion.
     fn check_undeclared_in_type(
         ty: &Type,
-        entity_types: &HashMap<Name, ValidatorEntityType>,
+        entity_types: &ValidatorMap<Name, ValidatorEntityType>,
         undeclared_types: &mut HashSet<String>,
     ) {
-        match ty {
-            Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => {
-                for name in lub.iter() {
-                    if !entity_types.contains_key(name) {
-                        undeclared_types.insert(name.to_string());
-                    }
-                }
+        let mut names = Vec::new();
+        Self::collect_entity_types_in_type(ty, &mut names);
+        for name in names {
+            if !entity_types.contains_key(name) {
+                undeclared_types.insert(name.to_string());
             }
+        }
+    }
 
-            Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
-                for (_, attr_ty) in attrs.iter() {
-                    Self::check_undeclared_in_type(
-                        &attr_ty.attr_type,
-                        entity_types,
-                        undeclared_types,
-                    );
+    /// Iterate over `ty` and every type nested inside it, i.e., the types of
+    /// record attributes and set elements, recursively. Each type is yielded
+    /// before the types nested inside it.
+    fn type_nodes(ty: &Type) -> impl Iterator<Item = &Type> {
+        let mut stack = vec![ty];
+        std::iter::from_fn(move || {
+            let ty = stack.pop()?;
+            match ty {
+                Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+                    stack.extend(attrs.iter().map(|(_, attr_ty)| &attr_ty.attr_type))
                 }
+                Type::Set {
+                    element_type: Some(element_type),
+                } => stack.push(element_type),
+                _ => (),
             }
-
-            Type::Set {
-                element_type: Some(element_type),
-            } => Self::check_undeclared_in_type(element_type, entity_types, undeclared_types),
-
-            _ => (),
-        }
+            Some(ty)
+        })
     }
 
-    /// Lookup the ValidatorActionId object in the schema with the given name.
-    pub fn get_action_id(&self, action_id: &EntityUID) -> Option<&ValidatorActionId> {
+    /// Iterate over every type appearing in this schema: the types of entity
+    /// attributes, action context attributes and action attributes, and every
+    /// type nested inside them (record attribute and set element types,
+    /// recursively). Types are yielded once per occurrence, so the same type
+    /// may be yielded many times. The order is unspecified, except that each
+    /// type is yielded before the types nested inside it.
+    pub fn iter_all_types(&self) -> impl Iterator<Item = &Type> {
+        let entity_attrs = self
+            .entity_types
+            .values()
+            .flat_map(|entity_type| entity_type.attributes.iter());
+        let action_attrs = self
+            .action_ids
+            .values()
+            .flat_map(|action| action.context.iter().chain(action.attribute_types.iter()));
+        entity_attrs
+            .chain(action_attrs)
+            .flat_map(|(_, attr_ty)| Self::type_nodes(&attr_ty.attr_type))
+    }
+
+    /// Count the attributes declared by entity types and find how deeply
+    /// records are nested in attribute types, e.g., to enforce size limits on
+    /// schemas written by users.
+    pub fn attribute_stats(&self) -> AttributeStats {
+        let counts = self
+            .entity_types
+            .values()
+            .map(|entity_type| entity_type.attributes.iter().count());
+        let (total, max_per_type) = counts.fold((0, 0), |(total, max), count| {
+            (total + count, max.max(count))
+        });
+        let max_nesting_depth = self
+            .iter_all_types()
+            .map(Self::record_depth)
+            .max()
+            .unwrap_or(0);
+        AttributeStats {
+            total,
+            max_per_type,
+            max_nesting_depth,
+        }
+    }
+
+    /// Check that no attribute in `attributes` has records nested more than
+    /// `limit` deep. The location of the error is left for the caller to fill
+    /// in.
+    fn check_record_nesting(attributes: &Attributes, limit: usize) -> Result<()> {
+        match attributes
+            .iter()
+            .map(|(_, attr_ty)| Self::record_depth(&attr_ty.attr_type))
+            .max()
+        {
+            Some(depth) if depth > limit => Err(SchemaError::RecordTooDeep {
+                location: SchemaLocation::default(),
+                depth,
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The number of record types nested inside one another in `ty`,
+    /// counting `ty` itself.
+    fn record_depth(ty: &Type) -> usize {
+        match ty {
+            Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+                1 + attrs
+                    .iter()
+                    .map(|(_, attr_ty)| Self::record_depth(&attr_ty.attr_type))
+                    .max()
+                    .unwrap_or(0)
+            }
+            Type::Set {
+                element_type: Some(element_type),
+            } => Self::record_depth(element_type),
+            _ => 0,
+        }
+    }
+
+    /// Lookup the ValidatorActionId object in the schema with the given name.
+    /// The name is matched according to the `LookupConfig` of the schema.
+    pub fn get_action_id(&self, action_id: &EntityUID) -> Option<&ValidatorActionId> {
+        self.action_ids.get(self.resolve_action_id(action_id)?)
+    }
+
+    /// Lookup the ValidatorActionId object in the schema with exactly the
+    /// given name, regardless of the `LookupConfig` of the schema. Validation
+    /// uses this, since a policy mentioning an action with different case
+    /// never matches the declared action when it is authorized.
+    pub(crate) fn get_action_id_exact(&self, action_id: &EntityUID) -> Option<&ValidatorActionId> {
         self.action_ids.get(action_id)
     }
 
+    /// Get the id of the action declared in the schema which `action_id`
+    /// refers to according to the `LookupConfig` of the schema, i.e.,
+    /// `action_id` itself if it is declared, or the declared id differing
+    /// from it only by case if the lookup is case-insensitive. Returns `None`
+    /// if no action matches, or if several actions match ignoring case.
+    pub fn resolve_action_id<'a>(&'a self, action_id: &'a EntityUID) -> Option<&'a EntityUID> {
+        if self.action_ids.contains_key(action_id) {
+            return Some(action_id);
+        }
+        if !self.lookup_config.case_insensitive_actions {
+            return None;
+        }
+        let fold = |euid: &EntityUID| {
+            let eid: &str = euid.eid().as_ref();
+            eid.to_lowercase()
+        };
+        let folded = fold(action_id);
+        let mut matches = self
+            .action_ids
+            .keys()
+            .filter(|euid| euid.entity_type() == action_id.entity_type() && fold(euid) == folded);
+        match (matches.next(), matches.next()) {
+            (Some(euid), None) => Some(euid),
+            _ => None,
+        }
+    }
+
+    /// Get the `LookupConfig` used to match the names given to lookups such as
+    /// `get_action_id`.
+    pub fn lookup_config(&self) -> LookupConfig {
+        self.lookup_config
+    }
+
+    /// Use `lookup_config` to match the names given to lookups such as
+    /// `get_action_id`, e.g., to accept action ids with inconsistent casing
+    /// from legacy integrations. The declarations in the schema are
+    /// unchanged, so resolved names keep the case they were declared with.
+    pub fn with_lookup_config(self, lookup_config: LookupConfig) -> Self {
+        Self {
+            lookup_config,
+            ..self
+        }
+    }
+
+    /// Compare the context attributes of the actions `a` and `b`, e.g., to
+    /// find attributes which could be moved into a shared common type.
+    /// Returns `None` if either action is not in the schema.
+    pub fn action_context_diff(&self, a: &EntityUID, b: &EntityUID) -> Option<ContextDiff> {
+        let a = &self.get_action_id(a)?.context;
+        let b = &self.get_action_id(b)?.context;
+        let mut diff = ContextDiff {
+            only_in_a: BTreeMap::new(),
+            only_in_b: BTreeMap::new(),
+            differing: BTreeMap::new(),
+        };
+        for (attr, a_ty) in a.iter() {
+            match b.get_attr(attr) {
+                None => {
+                    diff.only_in_a.insert(attr.clone(), a_ty.clone());
+                }
+                Some(b_ty) if b_ty != a_ty => {
+                    diff.differing
+                        .insert(attr.clone(), (a_ty.clone(), b_ty.clone()));
+                }
+                Some(_) => (),
+            }
+        }
+        diff.only_in_b.extend(
+            b.iter()
+                .filter(|(attr, _)| a.get_attr(attr).is_none())
+                .map(|(attr, b_ty)| (attr.clone(), b_ty.clone())),
+        );
+        Some(diff)
+    }
+
+    /// Get the entity type expected by the context attribute `attr` of the
+    /// action `action`, e.g., `User` for an attribute of type `Entity<User>`.
+    /// Returns `None` if the action is not in the schema, its context has no
+    /// such attribute, or the attribute does not have a single entity type.
+    pub fn context_attr_entity_type(&self, action: &EntityUID, attr: &str) -> Option<&Name> {
+        let attr_ty = self.get_action_id(action)?.context.get_attr(attr)?;
+        match &attr_ty.attr_type {
+            Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => lub.get_single_entity(),
+            _ => None,
+        }
+    }
+
+    /// Check the name of every entity type attribute and action context
+    /// attribute, including attributes of records nested inside them, against
+    /// `pattern`, e.g., to enforce a naming convention. Returns every attribute
+    /// whose name does not match, with the shape or context declaring it,
+    /// ordered by entity type and then by action. A name matches if
+    /// `pattern.is_match` accepts it, so `pattern` should be anchored with `^`
+    /// and `$` to constrain the whole name.
+    pub fn check_attribute_name_conventions(
+        &self,
+        pattern: &Regex,
+    ) -> std::result::Result<(), Vec<(ContextOrShape, SmolStr)>> {
+        let mut violations = Vec::new();
+        let shapes = self
+            .entity_types_sorted()
+            .into_iter()
+            .map(|(name, entity_type)| {
+                (
+                    ContextOrShape::EntityTypeShape(name.clone()),
+                    &entity_type.attributes,
+                )
+            });
+        let contexts = self
+            .actions_sorted()
+            .into_iter()
+            .map(|(euid, action)| (ContextOrShape::ActionContext(euid.clone()), &action.context));
+        for (location, attributes) in shapes.chain(contexts) {
+            violations.extend(
+                Self::records_in(attributes)
+                    .flat_map(|attrs| attrs.iter())
+                    .filter(|(name, _)| !pattern.is_match(name))
+                    .map(|(name, _)| (location.clone(), name.clone())),
+            );
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
     /// Lookup the ValidatorEntityType object in the schema with the given name.
     pub fn get_entity_type(&self, entity_type_id: &Name) -> Option<&ValidatorEntityType> {
         self.entity_types.get(entity_type_id)
     }
 
-    /// Return true when the entity_type_id corresponds to a valid entity type.
+    /// Edit the entity type `name` in place by applying `f` to it, e.g., to
+    /// add an attribute through `ValidatorEntityType::attributes_mut`. The
+    /// edited entity type is then checked as when constructing a schema: its
+    /// attribute names must be valid, its attribute types and `descendants`
+    /// may only refer to declared entity types, and the default values of its
+    /// attributes must conform to their types. Defaults of attributes which
+    /// `f` removes are dropped. The transitive closure of the entity type
+    /// hierarchy is recomputed, so adding an entity type to `descendants` also
+    /// adds it to every entity type this one is a member of. Removing a
+    /// descendant does not remove it from those entity types.
+    ///
+    /// Returns `UndeclaredEntityTypes` if `name` is not declared and
+    /// `NamespaceFrozen` if it is declared in a frozen namespace, or if the
+    /// edit changes the descendants of an entity type declared in a frozen
+    /// namespace or adds an entity type declared in a frozen namespace as a
+    /// new descendant. The schema is left unchanged if an error is returned.
+    pub fn modify_entity_type(
+        &mut self,
+        name: &Name,
+        f: impl FnOnce(&mut ValidatorEntityType),
+    ) -> Result<()> {
+        let mut entity_type = self
+            .get_entity_type(name)
+            .ok_or_else(|| SchemaError::UndeclaredEntityTypes(HashSet::from([name.to_string()])))?
+            .clone();
+        self.check_not_frozen(name)?;
+        f(&mut entity_type);
+        entity_type.name = name.clone();
+
+        Self::check_attribute_names(&entity_type.attributes, || {
+            ContextOrShape::EntityTypeShape(name.clone())
+        })?;
+        let attributes = &entity_type.attributes;
+        entity_type
+            .attribute_defaults
+            .retain(|attr, _| attributes.get_attr(attr).is_some());
+        for (attr, default) in entity_type.attribute_defaults.iter() {
+            Self::check_attribute_default(name, attr, attributes.get_attr(attr), default)?;
+        }
+        let undeclared_descendants = entity_type
+            .descendants
+            .iter()
+            .filter(|descendant| *descendant != name && self.get_entity_type(descendant).is_none())
+            .map(|descendant| descendant.to_string())
+            .collect::<HashSet<_>>();
+        if !undeclared_descendants.is_empty() {
+            return Err(SchemaError::UndeclaredEntityTypes(undeclared_descendants));
+        }
+
+        let mut entity_types = ValidatorMap::clone(&self.entity_types);
+        entity_types.insert(name.clone(), entity_type);
+        Self::compute_schema_tc(&mut entity_types, false)?;
+        for (ty, modified) in entity_types.iter() {
+            if let Some(original) = self.get_entity_type(ty) {
+                if original.descendants != modified.descendants {
+                    self.check_not_frozen(ty)?;
+                    for descendant in modified.descendants.difference(&original.descendants) {
+                        self.check_not_frozen(descendant)?;
+                    }
+                }
+            }
+        }
+        let mut errors = Vec::new();
+        Self::check_for_undeclared(&entity_types, None, &self.action_ids, None, &mut errors);
+        if let Some(err) = errors.into_iter().next() {
+            return Err(err);
+        }
+        self.entity_types = Arc::new(entity_types);
+        Ok(())
+    }
+
+    /// Get a label for the entity type `ty` suitable for display: its `title`
+    /// annotation if it has one, and otherwise the basename of `ty`.
+    pub fn entity_type_display_name(&self, ty: &Name) -> String {
+        self.entity_types
+            .get(ty)
+            .and_then(|entity_type| entity_type.annotation("title"))
+            .map(ToString::to_string)
+            .unwrap_or_else(|| ty.basename().to_string())
+    }
+
+    /// Get a label for the action `action` suitable for display: its `title`
+    /// annotation if it has one, and otherwise its eid.
+    pub fn action_display_name(&self, action: &EntityUID) -> String {
+        self.action_ids
+            .get(action)
+            .and_then(|action_id| action_id.annotation("title"))
+            .map(ToString::to_string)
+            .unwrap_or_else(|| action.eid().to_string())
+    }
+
+    /// Get the type of the attribute reached by following `path` from the
+    /// entity type `root`, e.g., `["address", "zip"]` for `address.zip`.
+    /// Every attribute in the path except the last must have a record type;
+    /// paths do not continue through entity references. Optional attributes
+    /// are followed as if they were present.
+    pub fn type_of_path(
+        &self,
+        root: &Name,
+        path: &[&str],
+    ) -> std::result::Result<&Type, PathError> {
+        let entity_type = self
+            .get_entity_type(root)
+            .ok_or_else(|| PathError::UndeclaredEntityType(root.clone()))?;
+        let mut attrs = &entity_type.attributes;
+        let mut ty: Option<&Type> = None;
+        for (index, attr) in path.iter().enumerate() {
+            if let Some(ty) = ty {
+                attrs = match ty {
+                    Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => attrs,
+                    _ => {
+                        return Err(PathError::NotARecord {
+                            index,
+                            attr: (*attr).into(),
+                        })
+                    }
+                };
+            }
+            ty = Some(
+                &attrs
+                    .get_attr(attr)
+                    .ok_or_else(|| PathError::MissingAttribute {
+                        index,
+                        attr: (*attr).into(),
+                    })?
+                    .attr_type,
+            );
+        }
+        ty.ok_or(PathError::EmptyPath)
+    }
+
+    /// Return true when the action `child` is a (transitive) descendant of the
+    /// action `ancestor`. Returns false if `ancestor` is not in the schema.
+    /// Both actions are matched according to the `LookupConfig` of the schema.
+    pub fn is_action_descendant_of(&self, child: &EntityUID, ancestor: &EntityUID) -> bool {
+        let child = self.resolve_action_id(child).unwrap_or(child);
+        matches!(self.get_action_id(ancestor), Some(ancestor) if ancestor.descendants.contains(child))
+    }
+
+    /// Return true when the entity type `child` is a (transitive) descendant
+    /// of the entity type `ancestor`, i.e., entities of type `child` may be
+    /// members of entities of type `ancestor`. Returns false if `ancestor` is
+    /// not in the schema.
+    pub fn is_entity_type_descendant_of(&self, child: &Name, ancestor: &Name) -> bool {
+        matches!(self.get_entity_type(ancestor), Some(ancestor) if ancestor.descendants.contains(child))
+    }
+
+    /// Order the entity types `a` and `b` by the membership hierarchy, from
+    /// most general to most specific. Returns `Less` if `a` is an ancestor of
+    /// `b`, `Greater` if `a` is a descendant of `b`, and `Equal` if they are
+    /// the same entity type. Returns `None` if the types are unrelated, if
+    /// either is not in the schema, or if each is a descendant of the other
+    /// because the `memberOf` relation is cyclic.
+    pub fn compare_in_hierarchy(&self, a: &Name, b: &Name) -> Option<Ordering> {
+        if self.get_entity_type(a).is_none() || self.get_entity_type(b).is_none() {
+            return None;
+        }
+        if a == b {
+            return Some(Ordering::Equal);
+        }
+        match (
+            self.is_entity_type_descendant_of(b, a),
+            self.is_entity_type_descendant_of(a, b),
+        ) {
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            _ => None,
+        }
+    }
+
+    /// An iterator over the entity types which are not a descendant of any
+    /// other entity type, i.e., the roots of the `memberOf` hierarchy. Entity
+    /// types in a cycle of the hierarchy are never roots.
+    pub fn roots(&self) -> impl Iterator<Item = &Name> {
+        self.entity_types.keys().filter(|name| {
+            !self
+                .entity_types
+                .iter()
+                .any(|(other, ety)| other != *name && ety.descendants.contains(*name))
+        })
+    }
+
+    /// Traverse the `memberOf` hierarchy depth first starting from the entity
+    /// type `root`, which is visited first, and descending to direct children
+    /// in sorted order. An entity type which is a member of several visited
+    /// entity types is only visited the first time it is reached. Returns an
+    /// empty list if `root` is not in the schema.
+    pub fn dfs_from(&self, root: &Name) -> Vec<&Name> {
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        let mut stack = self
+            .entity_types
+            .get_key_value(root)
+            .map(|(name, _)| name)
+            .into_iter()
+            .collect::<Vec<_>>();
+        while let Some(name) = stack.pop() {
+            if !visited.insert(name) {
+                continue;
+            }
+            order.push(name);
+            let mut children = self.direct_children(name);
+            // Push in reverse so that the smallest child is visited first.
+            children.sort_by(|a, b| b.cmp(a));
+            stack.extend(children);
+        }
+        order
+    }
+
+    /// The entity types which are direct members of the entity type `parent`:
+    /// its descendants which are not also descendants of another of its
+    /// descendants.
+    fn direct_children(&self, parent: &Name) -> Vec<&Name> {
+        let Some(ety) = self.entity_types.get(parent) else {
+            return Vec::new();
+        };
+        ety.descendants
+            .iter()
+            .filter(|child| {
+                *child != parent
+                    && !ety.descendants.iter().any(|other| {
+                        other != *child
+                            && other != parent
+                            && self
+                                .entity_types
+                                .get(other)
+                                .map(|other| other.descendants.contains(*child))
+                                .unwrap_or(false)
+                    })
+            })
+            .collect()
+    }
+
+    /// Check that the entity types and action referenced by a policy scope are
+    /// declared in the schema. This does not check that the action applies to
+    /// the principal and resource types. Undeclared entity types are reported
+    /// before an undeclared action.
+    pub fn validate_policy_scope(
+        &self,
+        principal_ty: Option<&Name>,
+        action: Option<&EntityUID>,
+        resource_ty: Option<&Name>,
+    ) -> Result<()> {
+        let undeclared_e = principal_ty
+            .into_iter()
+            .chain(resource_ty)
+            .filter(|ty| !self.is_known_entity_type(ty))
+            .map(|ty| ty.to_string())
+            .collect::<HashSet<_>>();
+        if !undeclared_e.is_empty() {
+            return Err(SchemaError::UndeclaredEntityTypes(undeclared_e));
+        }
+        match action {
+            Some(action) if !self.is_known_action_id(action) => {
+                Err(SchemaError::UndeclaredActions(HashSet::from([
+                    action.to_string()
+                ])))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Return true when the action_id is exactly the id of a declared action,
+    /// regardless of the `LookupConfig` of the schema. See
+    /// `get_action_id_exact`.
     pub(crate) fn is_known_action_id(&self, action_id: &EntityUID) -> bool {
         self.action_ids.contains_key(action_id)
     }
@@ -473,409 +2041,1490 @@ impl ValidatorSchema {
         self.entity_types.keys()
     }
 
-    /// An iterator matching the entity Types to their Validator Types
+    /// An iterator matching the entity Types to their Validator Types. With
+    /// the `preserve-order` feature, they are in the order they were declared.
     pub fn entity_types(&self) -> impl Iterator<Item = (&Name, &ValidatorEntityType)> {
         self.entity_types.iter()
     }
 
-    /// Get the validator entity equal to an EUID using the component for a head
-    /// var kind.
-    pub(crate) fn get_entity_eq<'a, H, K>(&self, var: H, euid: EntityUID) -> Option<K>
-    where
-        H: 'a + HeadVar<K>,
-        K: 'a,
-    {
-        var.get_euid_component(euid)
+    /// An iterator over the actions whose uid matches the glob `pattern`, in
+    /// which `*` matches any sequence of characters, including none, and
+    /// every other character matches itself. Matching is over the full uid as
+    /// displayed, including the namespaced action entity type and the quoted
+    /// eid, e.g. `App::Action::"photo_*"` matches `App::Action::"photo_view"`
+    /// but `photo_*` matches nothing. The matching is that of the `like`
+    /// operator, with no way to match a literal `*`.
+    pub fn actions_matching<'a>(
+        &'a self,
+        pattern: &str,
+    ) -> impl Iterator<Item = &'a EntityUID> + 'a {
+        let pattern = Pattern::new(pattern.chars().map(|c| match c {
+            '*' => PatternElem::Wildcard,
+            c => PatternElem::Char(c),
+        }));
+        self.action_ids
+            .keys()
+            .filter(move |euid| pattern.wildcard_match(&euid.to_string()))
     }
 
-    /// Get the validator entities that are in the descendants of an EUID using
-    /// the component for a head var kind.
-    pub(crate) fn get_entities_in<'a, H, K>(
+    /// An iterator over the entity types which declare the attribute `attr`
+    /// (required or optional) in their shape, with the type of that attribute.
+    /// Attributes of records nested inside a shape are not considered.
+    pub fn entity_types_with_attribute<'a>(
         &'a self,
-        var: H,
-        euid: EntityUID,
-    ) -> impl Iterator<Item = K> + 'a
-    where
-        H: 'a + HeadVar<K>,
-        K: 'a + Clone,
-    {
-        var.get_descendants_if_present(self, euid.clone())
-            .into_iter()
-            .flatten()
-            .map(Clone::clone)
-            .chain(var.get_euid_component_if_present(self, euid))
+        attr: &'a str,
+    ) -> impl Iterator<Item = (&'a Name, &'a Type)> + 'a {
+        self.entity_types
+            .iter()
+            .filter_map(move |(name, entity_type)| Some((name, entity_type.attr_type(attr)?)))
     }
 
-    /// Get the validator entities that are in the descendants of any of the
-    /// entities in a set of EUID using the component for a head var kind.
-    pub(crate) fn get_entities_in_set<'a, H, K>(
-        &'a self,
-        var: H,
-        euids: impl IntoIterator<Item = EntityUID> + 'a,
-    ) -> impl Iterator<Item = K> + 'a
-    where
-        H: 'a + HeadVar<K>,
-        K: 'a + Clone,
-    {
-        euids
-            .into_iter()
-            .flat_map(move |e| self.get_entities_in(var, e))
+    /// Map each basename of an entity type in the schema to the fully
+    /// qualified names of the entity types with that basename, sorted. A
+    /// basename mapped to more than one name is declared in several
+    /// namespaces, so it cannot be resolved to an entity type on its own.
+    pub fn basename_map(&self) -> HashMap<Id, Vec<Name>> {
+        let mut map: HashMap<Id, Vec<Name>> = HashMap::new();
+        for name in self.entity_types.keys() {
+            map.entry(name.basename().clone())
+                .or_default()
+                .push(name.clone());
+        }
+        for names in map.values_mut() {
+            names.sort();
+        }
+        map
     }
 
-    /// Since different Actions have different schemas for `Context`, you must
-    /// specify the `Action` in order to get a `ContextSchema`.
-    ///
-    /// Returns `None` if the action is not in the schema.
-    pub fn get_context_schema(
-        &self,
-        action: &EntityUID,
-    ) -> Option<impl cedar_policy_core::entities::ContextSchema> {
-        self.get_action_id(action).map(|action_id| {
-            // The invariant on `ContextSchema` requires that the inner type is
-            // representable as a schema type. Here we build a closed record
-            // type, which are representable as long as their values are
-            // representable. The values are representable because they are
-            // taken from the context of a `ValidatorActionId` which was
-            // constructed directly from a schema.
-            ContextSchema(crate::types::Type::record_with_attributes(
-                action_id
-                    .context
-                    .iter()
-                    .map(|(k, v)| (k.clone(), v.clone())),
-                OpenTag::ClosedAttributes,
-            ))
-        })
+    /// The entity types in the schema with their Validator Types, sorted by
+    /// the string form of the entity type name.
+    pub fn entity_types_sorted(&self) -> Vec<(&Name, &ValidatorEntityType)> {
+        let mut entity_types = self.entity_types().collect::<Vec<_>>();
+        entity_types.sort_by_cached_key(|(name, _)| name.to_string());
+        entity_types
     }
 
-    /// Invert the action hierarchy to get the ancestor relation expected for
-    /// the `Entity` datatype instead of descendants as stored by the schema.
-    fn action_entities_iter(&self) -> impl Iterator<Item = cedar_policy_core::ast::Entity> + '_ {
-        // We could store the un-inverted `memberOf` relation for each action,
-        // but I [john-h-kastner-aws] judge that the current implementation is
-        // actually less error prone, as it minimizes the threading of data
-        // structures through some complicated bits of schema construction code,
-        // and avoids computing the TC twice.
-        let mut action_ancestors: HashMap<&EntityUID, HashSet<EntityUID>> = HashMap::new();
-        for (action_euid, action_def) in &self.action_ids {
-            for descendant in &action_def.descendants {
-                action_ancestors
-                    .entry(descendant)
-                    .or_default()
-                    .insert(action_euid.clone());
-            }
-        }
-        self.action_ids.iter().map(move |(action_id, action)| {
-            Entity::new(
-                action_id.clone(),
-                action.attributes.clone(),
-                action_ancestors.remove(action_id).unwrap_or_default(),
-            )
-        })
+    /// The actions in the schema with their definitions, sorted by the string
+    /// form of the action's entity UID.
+    pub fn actions_sorted(&self) -> Vec<(&EntityUID, &ValidatorActionId)> {
+        let mut actions = self.action_ids.iter().collect::<Vec<_>>();
+        actions.sort_by_cached_key(|(euid, _)| euid.to_string());
+        actions
     }
 
-    /// Construct an `Entity` object for each action in the schema
-    pub fn action_entities(&self) -> cedar_policy_core::entities::Result<Entities> {
-        Entities::from_entities(
-            self.action_entities_iter(),
-            None::<&cedar_policy_core::entities::NoEntitiesSchema>, // we don't want to tell `Entities::from_entities()` to add the schema's action entities, that would infinitely recurse
-            TCComputation::AssumeAlreadyComputed,
-            Extensions::all_available(),
+    /// Get every entity type which some action can be applied to as its
+    /// principal. The unspecified entity type is not included.
+    pub fn all_principal_types(&self) -> HashSet<&Name> {
+        Self::concrete_types(
+            self.action_ids
+                .values()
+                .flat_map(|action| action.applies_to.applicable_principal_types()),
         )
     }
-}
 
-/// Struct which carries enough information that it can (efficiently) impl Core's `Schema`
-pub struct CoreSchema<'a> {
-    /// Contains all the information
-    schema: &'a ValidatorSchema,
-    /// For easy lookup, this is a map from action name to `Entity` object
-    /// for each action in the schema. This information is contained in the
-    /// `ValidatorSchema`, but not efficient to extract -- getting the `Entity`
-    /// from the `ValidatorSchema` is O(N) as of this writing, but with this
-    /// cache it's O(1).
-    actions: HashMap<EntityUID, Arc<Entity>>,
-}
+    /// Get every entity type which some action can be applied to as its
+    /// resource, including entity types added by `resourceTypesWithDescendants`.
+    /// The unspecified entity type is not included.
+    pub fn all_resource_types(&self) -> HashSet<&Name> {
+        Self::concrete_types(
+            self.action_ids
+                .values()
+                .flat_map(|action| action.applies_to.applicable_resource_types()),
+        )
+    }
 
-impl<'a> CoreSchema<'a> {
-    pub fn new(schema: &'a ValidatorSchema) -> Self {
-        Self {
-            actions: schema
-                .action_entities_iter()
-                .map(|e| (e.uid(), Arc::new(e)))
-                .collect(),
-            schema,
-        }
+    /// Get the actions which may be requested with an unspecified principal,
+    /// i.e., the actions whose `appliesTo` omits `principalTypes` (or which
+    /// have no `appliesTo` at all). The actions are sorted, so the result can
+    /// be compared against an allowlist.
+    pub fn actions_allowing_unspecified_principal(&self) -> Vec<&EntityUID> {
+        self.actions_allowing_unspecified(|action| action.applies_to.applicable_principal_types())
     }
-}
 
-impl<'a> cedar_policy_core::entities::Schema for CoreSchema<'a> {
-    type EntityTypeDescription = EntityTypeDescription;
-    type ActionEntityIterator = Vec<Arc<Entity>>;
+    /// Get the actions which may be requested with an unspecified resource,
+    /// i.e., the actions whose `appliesTo` omits `resourceTypes` (or which
+    /// have no `appliesTo` at all). The actions are sorted, so the result can
+    /// be compared against an allowlist.
+    pub fn actions_allowing_unspecified_resource(&self) -> Vec<&EntityUID> {
+        self.actions_allowing_unspecified(|action| action.applies_to.applicable_resource_types())
+    }
 
-    fn entity_type(
-        &self,
-        entity_type: &cedar_policy_core::ast::EntityType,
-    ) -> Option<EntityTypeDescription> {
-        match entity_type {
-            cedar_policy_core::ast::EntityType::Unspecified => None, // Unspecified entities cannot be declared in the schema and should not appear in JSON data
-            cedar_policy_core::ast::EntityType::Concrete(name) => {
-                EntityTypeDescription::new(self.schema, name)
-            }
-        }
+    /// Get the actions which are neither a member of any action group nor an
+    /// action group themselves, e.g., to find actions which could be organized
+    /// into groups. The order is unspecified.
+    pub fn orphan_actions(&self) -> impl Iterator<Item = &EntityUID> {
+        let members = self
+            .action_ids
+            .values()
+            .flat_map(|action| action.descendants.iter())
+            .collect::<HashSet<_>>();
+        self.action_ids
+            .iter()
+            .filter(move |(action_id, action)| {
+                action.descendants.is_empty() && !members.contains(action_id)
+            })
+            .map(|(action_id, _)| action_id)
     }
 
-    fn action(&self, action: &EntityUID) -> Option<Arc<cedar_policy_core::ast::Entity>> {
-        self.actions.get(action).map(Arc::clone)
+    /// Get the actions whose context is the empty record, i.e., actions which
+    /// can be requested without any context attributes. The order is
+    /// unspecified.
+    pub fn actions_with_empty_context(&self) -> impl Iterator<Item = &EntityUID> {
+        self.action_ids
+            .iter()
+            .filter(|(_, action)| action.context.attrs.is_empty())
+            .map(|(action_id, _)| action_id)
     }
 
-    fn entity_types_with_basename<'b>(
-        &'b self,
-        basename: &'b Id,
-    ) -> Box<dyn Iterator<Item = EntityType> + 'b> {
-        Box::new(self.schema.entity_types().filter_map(move |(name, _)| {
-            if name.basename() == basename {
-                Some(EntityType::Concrete(name.clone()))
-            } else {
-                None
-            }
-        }))
+    /// Get the actions whose context declares at least one attribute. This is
+    /// the complement of `actions_with_empty_context`. The order is
+    /// unspecified.
+    pub fn actions_with_context(&self) -> impl Iterator<Item = &EntityUID> {
+        self.action_ids
+            .iter()
+            .filter(|(_, action)| !action.context.attrs.is_empty())
+            .map(|(action_id, _)| action_id)
     }
 
-    fn action_entities(&self) -> Self::ActionEntityIterator {
-        self.actions.values().map(Arc::clone).collect()
+    fn actions_allowing_unspecified<'a, I: Iterator<Item = &'a EntityType>>(
+        &'a self,
+        applicable_types: impl Fn(&'a ValidatorActionId) -> I,
+    ) -> Vec<&'a EntityUID> {
+        let mut actions = self
+            .action_ids
+            .iter()
+            .filter(|(_, action)| {
+                applicable_types(action).any(|ty| matches!(ty, EntityType::Unspecified))
+            })
+            .map(|(action_id, _)| action_id)
+            .collect::<Vec<_>>();
+        actions.sort();
+        actions
     }
-}
-
-/// Struct which carries enough information that it can impl Core's `EntityTypeDescription`
-pub struct EntityTypeDescription {
-    /// Core `EntityType` this is describing
-    core_type: cedar_policy_core::ast::EntityType,
-    /// Contains most of the schema information for this entity type
-    validator_type: ValidatorEntityType,
-    /// Allowed parent types for this entity type. (As of this writing, this
-    /// information is not contained in the `validator_type` by itself.)
-    allowed_parent_types: Arc<HashSet<cedar_policy_core::ast::EntityType>>,
-}
 
-impl EntityTypeDescription {
-    /// Create a description of the given type in the given schema.
-    /// Returns `None` if the given type is not in the given schema.
-    pub fn new(schema: &ValidatorSchema, type_name: &Name) -> Option<Self> {
-        Some(Self {
-            core_type: cedar_policy_core::ast::EntityType::Concrete(type_name.clone()),
-            validator_type: schema.get_entity_type(type_name).cloned()?,
-            allowed_parent_types: {
-                let mut set = HashSet::new();
-                for (possible_parent_typename, possible_parent_et) in &schema.entity_types {
-                    if possible_parent_et.descendants.contains(type_name) {
-                        set.insert(cedar_policy_core::ast::EntityType::Concrete(
-                            possible_parent_typename.clone(),
-                        ));
-                    }
-                }
-                Arc::new(set)
-            },
-        })
+    fn concrete_types<'a>(types: impl Iterator<Item = &'a EntityType>) -> HashSet<&'a Name> {
+        types
+            .filter_map(|ty| match ty {
+                EntityType::Concrete(name) => Some(name),
+                EntityType::Unspecified => None,
+            })
+            .collect()
     }
-}
 
-impl cedar_policy_core::entities::EntityTypeDescription for EntityTypeDescription {
-    fn entity_type(&self) -> cedar_policy_core::ast::EntityType {
-        self.core_type.clone()
+    /// Get the attributes shared by every concrete principal type the action
+    /// applies to. An attribute is kept only if it is declared by all of these
+    /// entity types with compatible types, in which case its type is the least
+    /// upper bound of the declared types, and it is required only if it is
+    /// required for every principal type. Returns an empty set of attributes if
+    /// the action is not declared or applies to no concrete principal types.
+    pub fn common_principal_attributes(&self, action: &EntityUID) -> Attributes {
+        let mut principal_attrs = self
+            .get_action_id(action)
+            .into_iter()
+            .flat_map(|action| action.applies_to.applicable_principal_types())
+            .filter_map(|ty| match ty {
+                EntityType::Concrete(name) => self.get_entity_type(name),
+                EntityType::Unspecified => None,
+            })
+            .map(|entity_type| &entity_type.attributes);
+        match principal_attrs.next() {
+            Some(first) => principal_attrs.fold(first.clone(), |acc, attrs| {
+                Attributes::strict_intersection(self, &acc, attrs)
+            }),
+            None => Attributes::with_attributes(None),
+        }
     }
 
-    fn attr_type(&self, attr: &str) -> Option<cedar_policy_core::entities::SchemaType> {
-        let attr_type: &crate::types::Type = &self.validator_type.attr(attr)?.attr_type;
-        // This converts a type from a schema into the representation of schema
-        // types used by core. `attr_type` is taken from a `ValidatorEntityType`
-        // which was constructed from a schema.
-        // PANIC SAFETY: see above
-        #[allow(clippy::expect_used)]
-        let core_schema_type: cedar_policy_core::entities::SchemaType = attr_type
-            .clone()
-            .try_into()
-            .expect("failed to convert validator type into Core SchemaType");
-        debug_assert!(attr_type.is_consistent_with(&core_schema_type));
-        Some(core_schema_type)
+    /// Get every entity attribute whose type is an extension type, as
+    /// `(entity type, attribute name, extension type name)` triples sorted by
+    /// entity type and attribute name. Only top-level attributes are included;
+    /// extension values nested inside records or sets are not.
+    pub fn extension_typed_attributes(&self) -> Vec<(Name, SmolStr, Name)> {
+        let mut attrs = self
+            .entity_types
+            .iter()
+            .flat_map(|(name, entity_type)| {
+                Self::extension_typed(entity_type.attributes())
+                    .map(move |(attr, ext)| (name.clone(), attr, ext))
+            })
+            .collect::<Vec<_>>();
+        attrs.sort();
+        attrs
     }
 
-    fn required_attrs<'s>(&'s self) -> Box<dyn Iterator<Item = SmolStr> + 's> {
-        Box::new(
-            self.validator_type
-                .attributes
-                .iter()
-                .filter(|(_, ty)| ty.is_required)
-                .map(|(attr, _)| attr.clone()),
-        )
+    /// Get every entity attribute declared `sensitive`, including attributes
+    /// of records nested inside entity attributes, as `(entity type, path)`
+    /// pairs sorted by entity type and path. A path lists the entity
+    /// attribute followed by the nested record attributes, e.g.,
+    /// `["profile", "phone"]`. Records in sets are reached through the path
+    /// of the set. Attributes nested inside a sensitive attribute are not
+    /// listed, since its whole value is sensitive.
+    pub fn sensitive_attributes(&self) -> Vec<(Name, Vec<SmolStr>)> {
+        let mut attrs = self
+            .entity_types
+            .iter()
+            .flat_map(|(name, entity_type)| {
+                let mut paths = Vec::new();
+                Self::collect_sensitive_paths(&entity_type.attributes, &mut Vec::new(), &mut paths);
+                paths.into_iter().map(move |path| (name.clone(), path))
+            })
+            .collect::<Vec<_>>();
+        attrs.sort();
+        attrs
     }
 
-    fn allowed_parent_types(&self) -> Arc<HashSet<cedar_policy_core::ast::EntityType>> {
-        Arc::clone(&self.allowed_parent_types)
+    // Push the paths of the sensitive attributes in `attrs`, which is at
+    // `path`, and of those nested inside them onto `paths`.
+    fn collect_sensitive_paths(
+        attrs: &Attributes,
+        path: &mut Vec<SmolStr>,
+        paths: &mut Vec<Vec<SmolStr>>,
+    ) {
+        for (attr, attr_ty) in attrs.iter() {
+            path.push(attr.clone());
+            if attr_ty.is_sensitive {
+                paths.push(path.clone());
+            } else if let Some(attrs) = Self::nested_record_attributes(&attr_ty.attr_type) {
+                Self::collect_sensitive_paths(attrs, path, paths);
+            }
+            path.pop();
+        }
     }
-}
-
-/// Struct which carries enough information that it can impl Core's
-/// `ContextSchema` INVARIANT: The `Type` stored in this struct must be
-/// representable as a `SchemaType` to avoid panicking in `context_type`.
-struct ContextSchema(crate::types::Type);
 
-/// A `Type` contains all the information we need for a Core `ContextSchema`.
-impl cedar_policy_core::entities::ContextSchema for ContextSchema {
-    fn context_type(&self) -> cedar_policy_core::entities::SchemaType {
-        // PANIC SAFETY: By `ContextSchema` invariant, `self.0` is representable as a schema type.
-        #[allow(clippy::expect_used)]
-        self.0
-            .clone()
-            .try_into()
-            .expect("failed to convert validator type into Core SchemaType")
+    // The attributes of the record type `ty`, or of the record type of the
+    // elements of `ty` if it is a set.
+    fn nested_record_attributes(ty: &Type) -> Option<&Attributes> {
+        match ty {
+            Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => Some(attrs),
+            Type::Set {
+                element_type: Some(element_type),
+            } => Self::nested_record_attributes(element_type),
+            _ => None,
+        }
     }
-}
 
-/// This trait configures what sort of entity (principals, actions, or resources)
-/// are returned by the function `get_entities_satisfying_constraint`.
-pub(crate) trait HeadVar<K>: Copy {
-    /// For a validator, get the known entities for this sort of head variable.
-    /// This is all entity types (for principals and resources), or actions ids
-    /// (for actions) that appear in the service description.
-    fn get_known_vars<'a>(
+    /// Serialize `entities` to JSON as `Entities::to_json_value` does, but
+    /// replace the value of every attribute listed by `sensitive_attributes`
+    /// with `REDACTED_ATTRIBUTE_VALUE`. This is meant for logging; the result
+    /// does not conform to this schema if any value was redacted.
+    pub fn entities_to_json_redacted(
         &self,
-        schema: &'a ValidatorSchema,
-    ) -> Box<dyn Iterator<Item = &'a K> + 'a>;
-
-    /// Extract the relevant component of an entity uid. This is the entity type
-    /// for principals and resources, and the entity id for actions.
-    fn get_euid_component(&self, euid: EntityUID) -> Option<K>;
-
-    /// Extract the relevant component of an entity uid if the entity uid is in
-    /// the schema. Otherwise return None.
-    fn get_euid_component_if_present(&self, schema: &ValidatorSchema, euid: EntityUID)
-        -> Option<K>;
+        entities: &Entities,
+    ) -> cedar_policy_core::entities::Result<serde_json::Value> {
+        entities.to_json_value_redacted(|entity_type, path| {
+            let EntityType::Concrete(name) = entity_type else {
+                return false;
+            };
+            let Some(entity_type) = self.get_entity_type(name) else {
+                return false;
+            };
+            let mut attrs = Some(&entity_type.attributes);
+            let mut sensitive = false;
+            for attr in path {
+                let Some(attr_ty) = attrs.and_then(|attrs| attrs.get_attr(attr)) else {
+                    return false;
+                };
+                sensitive = attr_ty.is_sensitive;
+                attrs = Self::nested_record_attributes(&attr_ty.attr_type);
+            }
+            sensitive
+        })
+    }
 
-    /// Get and iterator containing the valid descendants of an entity, if that
-    /// entity exists in the schema. Otherwise None.
-    fn get_descendants_if_present<'a>(
+    /// Check that every entity in `entities` conforms to this schema: its type
+    /// is declared, it has every required attribute and no undeclared ones,
+    /// its attribute values have the declared types, and its ancestors have
+    /// types it may be a member of. Action entities must match the actions
+    /// declared in the schema. This performs the same checks as parsing
+    /// entities from JSON with this schema, so it can be used for entities
+    /// built some other way. Every nonconforming entity is reported, with the
+    /// first error found for it.
+    pub fn validate_entities(
         &self,
-        schema: &'a ValidatorSchema,
-        euid: EntityUID,
-    ) -> Option<Box<dyn Iterator<Item = &'a K> + 'a>>;
-}
-
-/// Used to have `get_entities_satisfying_constraint` return the
-/// `EntityTypeNames` for either principals or resources satisfying the head
-/// constraints.
-#[derive(Debug, Clone, Copy)]
-pub(crate) enum PrincipalOrResourceHeadVar {
-    PrincipalOrResource,
-}
+        entities: &Entities,
+    ) -> std::result::Result<(), Vec<EntitySchemaConformanceError>> {
+        let core_schema = CoreSchema::new(self);
+        let errs = EntitySchemaConformanceChecker::new(&core_schema, Extensions::all_available())
+            .validate_entities(entities.iter());
+        if errs.is_empty() {
+            Ok(())
+        } else {
+            Err(errs)
+        }
+    }
 
-impl HeadVar<Name> for PrincipalOrResourceHeadVar {
-    fn get_known_vars<'a>(
-        &self,
-        schema: &'a ValidatorSchema,
-    ) -> Box<dyn Iterator<Item = &'a Name> + 'a> {
-        Box::new(schema.known_entity_types())
+    /// Get every context attribute whose type is an extension type, as
+    /// `(action, attribute name, extension type name)` triples sorted by action
+    /// and attribute name. As with `extension_typed_attributes`, only
+    /// top-level attributes are included.
+    pub fn context_extension_typed_attributes(&self) -> Vec<(EntityUID, SmolStr, Name)> {
+        let mut attrs = self
+            .action_ids
+            .iter()
+            .flat_map(|(action_id, action)| {
+                Self::extension_typed(action.context())
+                    .map(move |(attr, ext)| (action_id.clone(), attr, ext))
+            })
+            .collect::<Vec<_>>();
+        attrs.sort();
+        attrs
     }
 
-    fn get_euid_component(&self, euid: EntityUID) -> Option<Name> {
-        let (ty, _) = euid.components();
-        match ty {
-            EntityType::Unspecified => None,
-            EntityType::Concrete(name) => Some(name),
-        }
+    fn extension_typed<'a>(
+        attrs: impl Iterator<Item = (&'a SmolStr, &'a AttributeType)> + 'a,
+    ) -> impl Iterator<Item = (SmolStr, Name)> + 'a {
+        attrs.filter_map(|(attr, attr_ty)| match &attr_ty.attr_type {
+            Type::ExtensionType { name } => Some((attr.clone(), name.clone())),
+            _ => None,
+        })
     }
 
-    fn get_euid_component_if_present(
-        &self,
-        schema: &ValidatorSchema,
-        euid: EntityUID,
-    ) -> Option<Name> {
-        let euid_component = self.get_euid_component(euid)?;
-        if schema.is_known_entity_type(&euid_component) {
-            Some(euid_component)
-        } else {
-            None
+    /// Check that no two entity types, and no two actions, declared in the same
+    /// namespace have names which differ only by case, e.g. `view_photo` and
+    /// `View_Photo`. This check is not performed when constructing a schema,
+    /// since such names are otherwise legal. Returns `CaseCollision` listing
+    /// the colliding names if it fails. If there are several collisions, the
+    /// one reported is chosen deterministically, with entity types checked
+    /// before actions.
+    pub fn check_case_collisions(&self) -> Result<()> {
+        match self.case_collision_groups().into_iter().next() {
+            Some(names) => Err(SchemaError::CaseCollision {
+                names: names.into_iter().collect(),
+            }),
+            None => Ok(()),
         }
     }
 
-    fn get_descendants_if_present<'a>(
-        &self,
-        schema: &'a ValidatorSchema,
-        euid: EntityUID,
-    ) -> Option<Box<dyn Iterator<Item = &'a Name> + 'a>> {
-        let euid_component = self.get_euid_component(euid)?;
-        match schema.get_entity_type(&euid_component) {
-            Some(entity_type) => Some(Box::new(entity_type.descendants.iter())),
-            None => None,
+    /// Check that every entity type in `entity_types` and every action in
+    /// `actions` is declared in this schema, e.g., to check that a schema
+    /// provides the names an application depends on. Entity types are written
+    /// as fully qualified names (`App::User`) and actions as entity uids
+    /// (`App::Action::"login"`); a name which does not parse is not declared.
+    /// Actions are matched according to the `LookupConfig` of the schema.
+    /// Returns `MissingRequired` listing every name which is not declared.
+    pub fn require(&self, entity_types: &[&str], actions: &[&str]) -> Result<()> {
+        let missing_entity_types = entity_types
+            .iter()
+            .filter(|name| {
+                !Name::from_normalized_str(name)
+                    .map(|name| self.entity_types.contains_key(&name))
+                    .unwrap_or(false)
+            })
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let missing_actions = actions
+            .iter()
+            .filter(|action| {
+                !EntityUID::from_normalized_str(action)
+                    .map(|action| self.resolve_action_id(&action).is_some())
+                    .unwrap_or(false)
+            })
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        if missing_entity_types.is_empty() && missing_actions.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaError::MissingRequired {
+                entity_types: missing_entity_types,
+                actions: missing_actions,
+            })
         }
     }
-}
 
-/// Used to have `get_entities_satisfying_constraint` return the
-/// `ActionIdNames` for actions satisfying the head constraints
-#[derive(Debug, Clone, Copy)]
-pub(crate) enum ActionHeadVar {
-    Action,
-}
-
-impl HeadVar<EntityUID> for ActionHeadVar {
-    fn get_known_vars<'a>(
-        &self,
-        schema: &'a ValidatorSchema,
-    ) -> Box<dyn Iterator<Item = &'a EntityUID> + 'a> {
-        Box::new(schema.known_action_ids())
+    /// Get each group of entity types, and each group of actions, declared in
+    /// the same namespace with names which differ only by case. Entity type
+    /// groups come first, then action groups, each in a deterministic order.
+    fn case_collision_groups(&self) -> Vec<BTreeSet<String>> {
+        let entity_type_groups = Self::group_by_folded_case(
+            self.entity_types
+                .keys()
+                .map(|name| ((name.namespace(), name.basename().as_ref()), name)),
+        );
+        let action_groups = Self::group_by_folded_case(
+            self.action_ids
+                .keys()
+                .map(|euid| ((euid.entity_type().to_string(), euid.eid().as_ref()), euid)),
+        );
+        entity_type_groups
+            .into_iter()
+            .chain(action_groups)
+            .filter(|names| names.len() > 1)
+            .collect()
     }
 
-    fn get_euid_component(&self, euid: EntityUID) -> Option<EntityUID> {
-        Some(euid)
+    /// Group names by their scope and the lowercased form of their unqualified
+    /// part, returning the groups in a deterministic order.
+    fn group_by_folded_case<'a>(
+        names: impl Iterator<Item = ((String, &'a str), impl ToString)>,
+    ) -> Vec<BTreeSet<String>> {
+        let mut groups: BTreeMap<(String, String), BTreeSet<String>> = BTreeMap::new();
+        for ((scope, unqualified), name) in names {
+            groups
+                .entry((scope, unqualified.to_lowercase()))
+                .or_default()
+                .insert(name.to_string());
+        }
+        groups.into_values().collect()
     }
 
-    fn get_euid_component_if_present(
-        &self,
-        schema: &ValidatorSchema,
-        euid: EntityUID,
-    ) -> Option<EntityUID> {
-        let euid_component = self.get_euid_component(euid)?;
-        if schema.is_known_action_id(&euid_component) {
-            Some(euid_component)
-        } else {
-            None
+    /// Get every place in this schema where the entity type `ty` is
+    /// referenced, sorted by kind of reference and then by the referencing
+    /// entity type or action. Returns an empty vector if `ty` is not
+    /// referenced, including when it is not declared.
+    pub fn references_to(&self, ty: &Name) -> Vec<SchemaReference> {
+        let refers_to_ty = |attr_ty: &AttributeType| {
+            let mut names = Vec::new();
+            Self::collect_entity_types_in_type(&attr_ty.attr_type, &mut names);
+            names.contains(&ty)
+        };
+        let mut refs = Vec::new();
+        for (name, entity_type) in self.entity_types.iter() {
+            refs.extend(
+                entity_type
+                    .attributes()
+                    .filter(|(_, attr_ty)| refers_to_ty(attr_ty))
+                    .map(|(attr, _)| SchemaReference::EntityAttribute {
+                        entity_type: name.clone(),
+                        attr: attr.clone(),
+                    }),
+            );
+        }
+        if let Some(entity_type) = self.get_entity_type(ty) {
+            refs.extend(
+                entity_type
+                    .descendants
+                    .iter()
+                    .map(|member| SchemaReference::MemberOf {
+                        entity_type: member.clone(),
+                    }),
+            );
         }
+        let is_ty =
+            |candidate: &EntityType| matches!(candidate, EntityType::Concrete(name) if name == ty);
+        for (action_id, action) in self.action_ids.iter() {
+            refs.extend(
+                action
+                    .context
+                    .iter()
+                    .filter(|(_, attr_ty)| refers_to_ty(attr_ty))
+                    .map(|(attr, _)| SchemaReference::ContextAttribute {
+                        action: action_id.clone(),
+                        attr: attr.clone(),
+                    }),
+            );
+            refs.extend(
+                action
+                    .attribute_types
+                    .iter()
+                    .filter(|(_, attr_ty)| refers_to_ty(attr_ty))
+                    .map(|(attr, _)| SchemaReference::ActionAttribute {
+                        action: action_id.clone(),
+                        attr: attr.clone(),
+                    }),
+            );
+            if action.applies_to.applicable_principal_types().any(is_ty) {
+                refs.push(SchemaReference::AppliesToPrincipal {
+                    action: action_id.clone(),
+                });
+            }
+            if action.applies_to.applicable_resource_types().any(is_ty) {
+                refs.push(SchemaReference::AppliesToResource {
+                    action: action_id.clone(),
+                });
+            }
+        }
+        refs.sort();
+        refs
     }
 
-    fn get_descendants_if_present<'a>(
-        &self,
-        schema: &'a ValidatorSchema,
-        euid: EntityUID,
-    ) -> Option<Box<dyn Iterator<Item = &'a EntityUID> + 'a>> {
-        let euid_component = self.get_euid_component(euid)?;
-        match schema.get_action_id(&euid_component) {
-            Some(action_id) => Some(Box::new(action_id.descendants.iter())),
-            None => None,
-        }
+    /// Get every attribute of an entity type whose type refers to the entity
+    /// type `ty`, possibly inside a record or set, as `(entity type, attribute
+    /// name)` pairs sorted by entity type and then attribute name. These are
+    /// the attributes which would no longer be valid if `ty` were removed.
+    /// Unlike `references_to`, this does not report context attributes,
+    /// action attributes, or any reference other than an attribute type.
+    pub fn attributes_referencing(&self, ty: &Name) -> Vec<(Name, SmolStr)> {
+        let mut attrs = self
+            .entity_types
+            .iter()
+            .flat_map(|(name, entity_type)| {
+                entity_type
+                    .attributes()
+                    .map(move |(attr, attr_ty)| (name, attr, attr_ty))
+            })
+            .filter(|(_, _, attr_ty)| {
+                Self::type_nodes(&attr_ty.attr_type).any(|node| match node {
+                    Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => {
+                        lub.iter().any(|name| name == ty)
+                    }
+                    _ => false,
+                })
+            })
+            .map(|(name, attr, _)| (name.clone(), attr.clone()))
+            .collect::<Vec<_>>();
+        attrs.sort();
+        attrs
     }
-}
 
-/// Used to write a schema implicitly overriding the default handling of action
-/// groups.
-#[derive(Debug, Clone, Deserialize)]
-#[serde(transparent)]
-pub(crate) struct NamespaceDefinitionWithActionAttributes(pub(crate) NamespaceDefinition);
+    /// Construct a schema containing only the given action, its ancestor and
+    /// descendant actions, and the entity types reachable from these actions.
+    /// An entity type is reachable if it appears in the `appliesTo` lists of a
+    /// retained action, or in the type of a context attribute, action
+    /// attribute, or attribute of another reachable entity type. Descendant
+    /// sets are restricted to the retained entity types and actions, so the
+    /// result is itself a valid schema. Returns `None` if the action is not in
+    /// this schema.
+    pub fn subschema_for_action(&self, action: &EntityUID) -> Option<ValidatorSchema> {
+        let action_id = self.get_action_id(action)?;
+        let action = &action_id.name;
+        let retained_actions = self
+            .action_ids
+            .iter()
+            .filter(|(euid, other)| {
+                *euid == action
+                    || action_id.descendants.contains(euid)
+                    || other.descendants.contains(action)
+            })
+            .map(|(euid, _)| euid.clone())
+            .collect::<HashSet<_>>();
+        let action_ids = retained_actions
+            .iter()
+            .filter_map(|euid| {
+                let mut action = self.get_action_id(euid)?.clone();
+                action
+                    .descendants
+                    .retain(|descendant| retained_actions.contains(descendant));
+                Some((euid.clone(), action))
+            })
+            .collect::<HashMap<_, _>>();
 
-impl TryInto<ValidatorSchema> for NamespaceDefinitionWithActionAttributes {
-    type Error = SchemaError;
+        let mut worklist = Vec::new();
+        for action in action_ids.values() {
+            for ety in action
+                .applies_to
+                .applicable_principal_types()
+                .chain(action.applies_to.applicable_resource_types())
+                .chain(
+                    action
+                        .applies_to
+                        .principal_groups()
+                        .chain(action.applies_to.resource_groups())
+                        .map(EntityUID::entity_type),
+                )
+            {
+                if let EntityType::Concrete(name) = ety {
+                    worklist.push(name);
+                }
+            }
+            for (_, attr_ty) in action.context.iter().chain(action.attribute_types.iter()) {
+                Self::collect_entity_types_in_type(&attr_ty.attr_type, &mut worklist);
+            }
+        }
+        let mut entity_types = HashMap::new();
+        while let Some(name) = worklist.pop() {
+            if entity_types.contains_key(name) {
+                continue;
+            }
+            if let Some(entity_type) = self.get_entity_type(name) {
+                for (_, attr_ty) in entity_type.attributes() {
+                    Self::collect_entity_types_in_type(&attr_ty.attr_type, &mut worklist);
+                }
+                entity_types.insert(name.clone(), entity_type.clone());
+            }
+        }
+        let retained_types = entity_types.keys().cloned().collect::<HashSet<_>>();
+        for entity_type in entity_types.values_mut() {
+            entity_type
+                .descendants
+                .retain(|descendant| retained_types.contains(descendant));
+        }
 
-    fn try_into(self) -> Result<ValidatorSchema> {
-        ValidatorSchema::from_schema_fragments([ValidatorSchemaFragment::from_namespaces([
-            ValidatorNamespaceDef::from_namespace_definition(
-                None,
-                self.0,
-                crate::ActionBehavior::PermitAttributes,
-            )?,
-        ])])
+        Some(ValidatorSchema {
+            entity_types: Arc::new(Self::ordered_like(&self.entity_types, entity_types)),
+            action_ids: Arc::new(Self::ordered_like(&self.action_ids, action_ids)),
+            common_type_dependencies: Arc::clone(&self.common_type_dependencies),
+            frozen_namespaces: Arc::clone(&self.frozen_namespaces),
+            lookup_config: self.lookup_config,
+        })
     }
-}
 
-// PANIC SAFETY unit tests
+    /// Get the schema containing every entity type and action declared in the
+    /// namespaces `keep`, along with the declarations from other namespaces
+    /// they depend on: the entity types and actions they can be members of,
+    /// the entity types they apply to, and the entity types referenced by
+    /// their attributes and contexts, transitively. Namespaces in `keep` which
+    /// declare nothing in this schema are ignored.
+    ///
+    /// The result is checked for references to undeclared entity types and
+    /// actions, as when constructing a schema.
+    pub fn project_namespaces(&self, keep: &[Name]) -> Result<ValidatorSchema> {
+        let keep = keep.iter().map(ToString::to_string).collect::<HashSet<_>>();
+        let mut action_worklist = self
+            .action_ids
+            .keys()
+            .filter(|euid| match euid.entity_type() {
+                EntityType::Concrete(ty) => keep.contains(&ty.namespace()),
+                EntityType::Unspecified => false,
+            })
+            .collect::<Vec<_>>();
+        let mut worklist = self
+            .entity_types
+            .keys()
+            .filter(|name| keep.contains(&name.namespace()))
+            .collect::<Vec<_>>();
+
+        let mut action_ids = HashMap::new();
+        while let Some(euid) = action_worklist.pop() {
+            if action_ids.contains_key(euid) {
+                continue;
+            }
+            if let Some(action) = self.get_action_id(euid) {
+                action_worklist.extend(
+                    self.action_ids
+                        .iter()
+                        .filter(|(_, other)| other.descendants.contains(euid))
+                        .map(|(parent, _)| parent),
+                );
+                for ety in action
+                    .applies_to
+                    .applicable_principal_types()
+                    .chain(action.applies_to.applicable_resource_types())
+                    .chain(
+                        action
+                            .applies_to
+                            .principal_groups()
+                            .chain(action.applies_to.resource_groups())
+                            .map(EntityUID::entity_type),
+                    )
+                {
+                    if let EntityType::Concrete(name) = ety {
+                        worklist.push(name);
+                    }
+                }
+                for (_, attr_ty) in action.context.iter().chain(action.attribute_types.iter()) {
+                    Self::collect_entity_types_in_type(&attr_ty.attr_type, &mut worklist);
+                }
+                action_ids.insert(euid.clone(), action.clone());
+            }
+        }
+        let mut entity_types = HashMap::new();
+        while let Some(name) = worklist.pop() {
+            if entity_types.contains_key(name) {
+                continue;
+            }
+            if let Some(entity_type) = self.get_entity_type(name) {
+                worklist.extend(
+                    self.entity_types
+                        .iter()
+                        .filter(|(_, other)| other.descendants.contains(name))
+                        .map(|(parent, _)| parent),
+                );
+                for (_, attr_ty) in entity_type.attributes() {
+                    Self::collect_entity_types_in_type(&attr_ty.attr_type, &mut worklist);
+                }
+                entity_types.insert(name.clone(), entity_type.clone());
+            }
+        }
+
+        let retained_actions = action_ids.keys().cloned().collect::<HashSet<_>>();
+        for action in action_ids.values_mut() {
+            action
+                .descendants
+                .retain(|descendant| retained_actions.contains(descendant));
+        }
+        let retained_types = entity_types.keys().cloned().collect::<HashSet<_>>();
+        for entity_type in entity_types.values_mut() {
+            entity_type
+                .descendants
+                .retain(|descendant| retained_types.contains(descendant));
+        }
+
+        let entity_types = Self::ordered_like(&self.entity_types, entity_types);
+        let action_ids = Self::ordered_like(&self.action_ids, action_ids);
+        let mut errors = Vec::new();
+        Self::check_for_undeclared(&entity_types, None, &action_ids, None, &mut errors);
+        if let Some(err) = errors.into_iter().next() {
+            return Err(err);
+        }
+        Ok(ValidatorSchema {
+            entity_types: Arc::new(entity_types),
+            action_ids: Arc::new(action_ids),
+            common_type_dependencies: Arc::clone(&self.common_type_dependencies),
+            frozen_namespaces: Arc::clone(&self.frozen_namespaces),
+            lookup_config: self.lookup_config,
+        })
+    }
+
+    /// Move the entries of `map` into a `ValidatorMap`, in the order their keys
+    /// have in `order`, so that a subschema keeps the order of this schema.
+    fn ordered_like<K: Clone + Eq + std::hash::Hash, V, W>(
+        order: &ValidatorMap<K, W>,
+        mut map: HashMap<K, V>,
+    ) -> ValidatorMap<K, V> {
+        order
+            .keys()
+            .filter_map(|key| map.remove(key).map(|value| (key.clone(), value)))
+            .collect()
+    }
+
+    /// Sort the entity types in this schema so that each entity type comes
+    /// after the entity types it can be a member of and, where possible, after
+    /// the entity types referenced by its attributes. Returns
+    /// `CycleInEntityHierarchy` if the `memberOf` relation is cyclic.
+    ///
+    /// Attribute references may legitimately be recursive (e.g., a `User`
+    /// with a `manager` attribute of type `User`). When the remaining
+    /// attribute references form a cycle, they are ignored and the entity type
+    /// in the cycle with the least name (whose parents have all been emitted)
+    /// is placed next. Entity types which are otherwise unordered are sorted
+    /// by name, so the result is deterministic.
+    pub fn entity_types_topo_sorted(&self) -> Result<Vec<&Name>> {
+        if self
+            .entity_types
+            .iter()
+            .any(|(name, ety)| ety.descendants.contains(name))
+        {
+            return Err(SchemaError::CycleInEntityHierarchy);
+        }
+        // For each entity type, the entity types which must precede it
+        // (`memberOf` ancestors) and should precede it (attribute references).
+        let deps = self
+            .entity_types
+            .iter()
+            .map(|(name, ety)| {
+                let ancestors = self
+                    .entity_types
+                    .iter()
+                    .filter(|(_, other)| other.descendants.contains(name))
+                    .map(|(other, _)| other)
+                    .collect::<HashSet<_>>();
+                let mut referenced = Vec::new();
+                for (_, attr_ty) in ety.attributes() {
+                    Self::collect_entity_types_in_type(&attr_ty.attr_type, &mut referenced);
+                }
+                let referenced = referenced
+                    .into_iter()
+                    .filter(|other| *other != name && self.entity_types.contains_key(*other))
+                    .collect::<HashSet<_>>();
+                (name, (ancestors, referenced))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut remaining = self.entity_types.keys().collect::<Vec<_>>();
+        remaining.sort();
+        let mut emitted = HashSet::new();
+        let mut sorted = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            let is_ready = |name: &&Name, include_refs: bool| {
+                deps.get(name).into_iter().all(|(ancestors, referenced)| {
+                    ancestors.is_subset(&emitted)
+                        && (!include_refs || referenced.is_subset(&emitted))
+                })
+            };
+            let mut ready = remaining
+                .iter()
+                .filter(|name| is_ready(name, true))
+                .copied()
+                .collect::<Vec<_>>();
+            if ready.is_empty() {
+                // Only cycles of attribute references remain, so break one.
+                // Some entity type has all its ancestors emitted because the
+                // `memberOf` relation is acyclic.
+                ready.extend(remaining.iter().find(|name| is_ready(name, false)).copied());
+            }
+            if ready.is_empty() {
+                return Err(SchemaError::CycleInEntityHierarchy);
+            }
+            remaining.retain(|name| !ready.contains(name));
+            emitted.extend(ready.iter().copied());
+            sorted.extend(ready);
+        }
+        Ok(sorted)
+    }
+
+    /// Get the dependency graph of the common types declared in the schema,
+    /// mapping each common type to the common types referenced by its
+    /// definition before inlining, including inside set element and record
+    /// attribute types, e.g., `AddressList` to `Address` for a common type
+    /// `AddressList` which is a set of `Address`. All names are fully
+    /// qualified.
+    pub fn common_type_dependencies(&self) -> HashMap<Name, HashSet<Name>> {
+        self.common_type_dependencies.as_ref().clone()
+    }
+
+    /// Get the entity types which are reachable from themselves through their
+    /// attributes, e.g., a `Comment` with a `parent` attribute of type
+    /// `Comment`, or a `User` with a `team` attribute whose type has a `lead`
+    /// attribute of type `User`. Entity types nested in record and set
+    /// attribute types count as references. Recursive attributes are allowed,
+    /// just like cycles in the `memberOf` relation.
+    pub fn recursive_entity_types(&self) -> HashSet<Name> {
+        let references = self
+            .entity_types
+            .iter()
+            .map(|(name, ety)| {
+                let mut referenced = Vec::new();
+                for (_, attr_ty) in ety.attributes() {
+                    Self::collect_entity_types_in_type(&attr_ty.attr_type, &mut referenced);
+                }
+                (name, referenced)
+            })
+            .collect::<HashMap<_, _>>();
+        references
+            .iter()
+            .filter(|(start, referenced)| {
+                let mut visited = HashSet::new();
+                let mut stack = (*referenced).clone();
+                while let Some(name) = stack.pop() {
+                    if name == **start {
+                        return true;
+                    }
+                    if visited.insert(name) {
+                        stack.extend(references.get(name).into_iter().flatten());
+                    }
+                }
+                false
+            })
+            .map(|(name, _)| (*name).clone())
+            .collect()
+    }
+
+    // Push the names of all entity types appearing inside a type onto `names`.
+    fn collect_entity_types_in_type<'a>(ty: &'a Type, names: &mut Vec<&'a Name>) {
+        for ty in Self::type_nodes(ty) {
+            if let Type::EntityOrRecord(EntityRecordKind::Entity(lub)) = ty {
+                names.extend(lub.iter());
+            }
+        }
+    }
+
+    /// Get the validator entity equal to an EUID using the component for a head
+    /// var kind.
+    pub(crate) fn get_entity_eq<'a, H, K>(&self, var: H, euid: EntityUID) -> Option<K>
+    where
+        H: 'a + HeadVar<K>,
+        K: 'a,
+    {
+        var.get_euid_component(euid)
+    }
+
+    /// Get the validator entities that are in the descendants of an EUID using
+    /// the component for a head var kind.
+    pub(crate) fn get_entities_in<'a, H, K>(
+        &'a self,
+        var: H,
+        euid: EntityUID,
+    ) -> impl Iterator<Item = K> + 'a
+    where
+        H: 'a + HeadVar<K>,
+        K: 'a + Clone,
+    {
+        var.get_descendants_if_present(self, euid.clone())
+            .into_iter()
+            .flatten()
+            .map(Clone::clone)
+            .chain(var.get_euid_component_if_present(self, euid))
+    }
+
+    /// Get the validator entities that are in the descendants of any of the
+    /// entities in a set of EUID using the component for a head var kind.
+    pub(crate) fn get_entities_in_set<'a, H, K>(
+        &'a self,
+        var: H,
+        euids: impl IntoIterator<Item = EntityUID> + 'a,
+    ) -> impl Iterator<Item = K> + 'a
+    where
+        H: 'a + HeadVar<K>,
+        K: 'a + Clone,
+    {
+        euids
+            .into_iter()
+            .flat_map(move |e| self.get_entities_in(var, e))
+    }
+
+    /// Since different Actions have different schemas for `Context`, you must
+    /// specify the `Action` in order to get a `ContextSchema`.
+    ///
+    /// Returns `Ok(None)` if the action is not in the schema. Returns an error
+    /// if the action's context type cannot be represented as a Core
+    /// `SchemaType`. This is checked when the schema is constructed, so it
+    /// should not happen for a `ValidatorSchema` built from a schema file.
+    ///
+    /// To check context data against an action which may not be declared, use
+    /// `validate_context_json`, which reports an undeclared action as
+    /// `JsonDeserializationError::UndeclaredAction` rather than `None`.
+    pub fn get_context_schema(
+        &self,
+        action: &EntityUID,
+    ) -> Result<Option<impl cedar_policy_core::entities::ContextSchema>> {
+        self.get_action_id(action)
+            .map(|action_id| ContextSchema::new(&action_id.name, &action_id.context))
+            .transpose()
+    }
+
+    /// Check that `context` is a JSON representation of a context conforming
+    /// to the context type of `action`. Conformance errors are reported with
+    /// `JsonDeserializationErrorContext::Context`. Returns
+    /// `JsonDeserializationError::UndeclaredAction` if `action` is not
+    /// declared in the schema.
+    pub fn validate_context_json(
+        &self,
+        action: &EntityUID,
+        context: serde_json::Value,
+    ) -> std::result::Result<(), JsonDeserializationError> {
+        // Every action context is checked to be representable when the schema
+        // is constructed.
+        // PANIC SAFETY: see above
+        #[allow(clippy::expect_used)]
+        let context_schema = self
+            .get_context_schema(action)
+            .expect("action context should be representable")
+            .ok_or_else(|| JsonDeserializationError::UndeclaredAction {
+                action: Box::new(action.clone()),
+            })?;
+        let extensions = Extensions::all_available();
+        let context =
+            ContextJsonParser::new(Some(&context_schema), extensions).from_json_value(context)?;
+        // Schema-based parsing checks the record structure of the context and
+        // the attributes whose parsing depends on their type, but does not
+        // check the types of other attribute values, so we check them here.
+        let cedar_policy_core::entities::SchemaType::Record { attrs } =
+            cedar_policy_core::entities::ContextSchema::context_type(&context_schema)
+        else {
+            return Ok(());
+        };
+        for (attr, value) in context.iter() {
+            let Some(expected) = attrs.get(attr) else {
+                continue;
+            };
+            let actual = type_of_restricted_expr(value, extensions).map_err(|err| match err {
+                TypeOfRestrictedExprError::HeterogeneousSet(err) => {
+                    JsonDeserializationError::HeterogeneousSet {
+                        ctx: Box::new(JsonDeserializationErrorContext::Context),
+                        err,
+                    }
+                }
+                TypeOfRestrictedExprError::ExtensionFunctionLookup(err) => {
+                    JsonDeserializationError::ExtensionFunctionLookup {
+                        ctx: Box::new(JsonDeserializationErrorContext::Context),
+                        err,
+                    }
+                }
+            })?;
+            if !actual.is_consistent_with(expected.schema_type()) {
+                return Err(JsonDeserializationError::TypeMismatch {
+                    ctx: Box::new(JsonDeserializationErrorContext::Context),
+                    expected: Box::new(expected.schema_type().clone()),
+                    actual: Box::new(actual),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Invert the action hierarchy to get the ancestor relation expected for
+    /// the `Entity` datatype instead of descendants as stored by the schema.
+    fn action_entities_iter(&self) -> impl Iterator<Item = cedar_policy_core::ast::Entity> + '_ {
+        // We could store the un-inverted `memberOf` relation for each action,
+        // but I [john-h-kastner-aws] judge that the current implementation is
+        // actually less error prone, as it minimizes the threading of data
+        // structures through some complicated bits of schema construction code,
+        // and avoids computing the TC twice.
+        let mut action_ancestors: HashMap<&EntityUID, HashSet<EntityUID>> = HashMap::new();
+        for (action_euid, action_def) in self.action_ids.iter() {
+            for descendant in &action_def.descendants {
+                action_ancestors
+                    .entry(descendant)
+                    .or_default()
+                    .insert(action_euid.clone());
+            }
+        }
+        self.action_ids.iter().map(move |(action_id, action)| {
+            Entity::new(
+                action_id.clone(),
+                action.attributes.clone(),
+                action_ancestors.remove(action_id).unwrap_or_default(),
+            )
+        })
+    }
+
+    /// Check that the value of each action attribute conforms to the type
+    /// recorded for it in the action's attribute types. Attribute types are
+    /// inferred from the attribute values when the schema is constructed, but
+    /// the type of a set is inferred from its first element, so this catches
+    /// sets whose elements have different types. Returns
+    /// `InvalidActionAttribute` naming the action and attribute on the first
+    /// mismatch found. Actions only have attributes when the schema was built
+    /// with `ActionBehavior::PermitAttributes`.
+    pub fn validate_action_attributes(&self) -> Result<()> {
+        for (action_id, action) in self.action_ids.iter() {
+            if let Some(err) = Self::action_attribute_errors(action_id, action)
+                .into_iter()
+                .next()
+            {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the checks of `validate_action_attributes` on every attribute of
+    /// every action, as a pass separate from schema construction, so that a
+    /// schema built with `ActionBehavior::PermitAttributes` can still have
+    /// its attribute values checked in full. Returns the schema if every value
+    /// conforms to its type, and otherwise every `InvalidActionAttribute`
+    /// error found, grouped by action.
+    pub fn with_action_attributes_validated(
+        self,
+    ) -> std::result::Result<Self, HashMap<EntityUID, Vec<SchemaError>>> {
+        let errors = self
+            .action_ids
+            .iter()
+            .map(|(action_id, action)| {
+                (
+                    action_id.clone(),
+                    Self::action_attribute_errors(action_id, action),
+                )
+            })
+            .filter(|(_, errors)| !errors.is_empty())
+            .collect::<HashMap<_, _>>();
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Check each attribute of `action` against its recorded type, returning
+    /// an `InvalidActionAttribute` error for each which does not conform.
+    fn action_attribute_errors(
+        action_id: &EntityUID,
+        action: &ValidatorActionId,
+    ) -> Vec<SchemaError> {
+        let extensions = Extensions::all_available();
+        action
+            .attribute_types
+            .iter()
+            .filter_map(|(attr, attr_ty)| {
+                let invalid = |reason: String| {
+                    SchemaError::InvalidActionAttribute(action_id.clone(), attr.clone(), reason)
+                };
+                let Some(value) = action.attributes.get(attr) else {
+                    return attr_ty
+                        .is_required
+                        .then(|| invalid("the attribute has no value".to_string()));
+                };
+                let expected_ty: cedar_policy_core::entities::SchemaType =
+                    match attr_ty.attr_type.clone().try_into() {
+                        Ok(ty) => ty,
+                        Err(reason) => return Some(invalid(reason)),
+                    };
+                match type_of_restricted_expr(value.as_borrowed(), extensions) {
+                    Ok(actual_ty) if actual_ty.is_consistent_with(&expected_ty) => None,
+                    Ok(actual_ty) => Some(invalid(format!(
+                        "expected a value of type {expected_ty}, but found a value of type {actual_ty}"
+                    ))),
+                    Err(e) => Some(invalid(e.to_string())),
+                }
+            })
+            .collect()
+    }
+
+    /// Serialize this schema to JSON, for inspection or debugging.
+    ///
+    /// This is the validator's internal representation of the schema, keyed
+    /// by `entityTypes` and `actionIds`, with common types inlined and the
+    /// `memberOf` relation stored as transitively closed `descendants`. It is
+    /// not a schema file and cannot be loaded as a `SchemaFragment`.
+    pub fn to_json_value(&self) -> serde_json::Value {
+        // Maps keyed by anything other than strings are serialized as lists
+        // of pairs, so serializing to a `serde_json::Value` cannot fail.
+        // PANIC SAFETY: see above
+        #[allow(clippy::expect_used)]
+        serde_json::to_value(self).expect("schema should serialize to JSON")
+    }
+
+    /// Construct an `Entity` object for each action in the schema
+    pub fn action_entities(&self) -> cedar_policy_core::entities::Result<Entities> {
+        Entities::from_entities(
+            self.action_entities_iter(),
+            None::<&cedar_policy_core::entities::NoEntitiesSchema>, // we don't want to tell `Entities::from_entities()` to add the schema's action entities, that would infinitely recurse
+            TCComputation::AssumeAlreadyComputed,
+            Extensions::all_available(),
+        )
+    }
+
+    /// Construct the `Entity` for the single action `uid`, with its
+    /// transitively closed ancestors, as it would appear in
+    /// `action_entities`. Returns `None` if the action is not declared. The
+    /// action is matched according to the `LookupConfig` of the schema, and
+    /// the entity has the id it was declared with. This avoids building the
+    /// entity for every action in the schema.
+    pub fn action_entity(&self, uid: &EntityUID) -> Option<Entity> {
+        let uid = self.resolve_action_id(uid)?;
+        let action = self.action_ids.get(uid)?;
+        let ancestors = self
+            .action_ids
+            .iter()
+            .filter(|(_, ancestor)| ancestor.descendants.contains(uid))
+            .map(|(ancestor_euid, _)| ancestor_euid.clone())
+            .collect::<HashSet<_>>();
+        Some(Entity::new(
+            uid.clone(),
+            action.attributes.clone(),
+            ancestors,
+        ))
+    }
+}
+
+impl SchemaQuery for ValidatorSchema {
+    fn get_entity_type(&self, entity_type_id: &Name) -> Option<&ValidatorEntityType> {
+        self.entity_types.get(entity_type_id)
+    }
+
+    fn get_action_id(&self, action_id: &EntityUID) -> Option<&ValidatorActionId> {
+        ValidatorSchema::get_action_id(self, action_id)
+    }
+
+    fn entity_types<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = (&'a Name, &'a ValidatorEntityType)> + 'a> {
+        Box::new(self.entity_types.iter())
+    }
+
+    fn action_ids<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = (&'a EntityUID, &'a ValidatorActionId)> + 'a> {
+        Box::new(self.action_ids.iter())
+    }
+}
+
+/// Struct which carries enough information that it can (efficiently) impl Core's `Schema`
+pub struct CoreSchema<'a> {
+    /// Contains all the information
+    schema: &'a ValidatorSchema,
+    /// For easy lookup, this is a map from action name to `Entity` object
+    /// for each action in the schema. This information is contained in the
+    /// `ValidatorSchema`, but not efficient to extract -- getting the `Entity`
+    /// from the `ValidatorSchema` is O(N) as of this writing, but with this
+    /// cache it's O(1).
+    actions: HashMap<EntityUID, Arc<Entity>>,
+}
+
+impl<'a> CoreSchema<'a> {
+    pub fn new(schema: &'a ValidatorSchema) -> Self {
+        Self {
+            actions: schema
+                .action_entities_iter()
+                .map(|e| (e.uid(), Arc::new(e)))
+                .collect(),
+            schema,
+        }
+    }
+}
+
+impl<'a> cedar_policy_core::entities::Schema for CoreSchema<'a> {
+    type EntityTypeDescription = EntityTypeDescription<'a>;
+    type ActionEntityIterator = Vec<Arc<Entity>>;
+
+    fn entity_type(
+        &self,
+        entity_type: &cedar_policy_core::ast::EntityType,
+    ) -> Option<EntityTypeDescription<'a>> {
+        match entity_type {
+            cedar_policy_core::ast::EntityType::Unspecified => None, // Unspecified entities cannot be declared in the schema and should not appear in JSON data
+            cedar_policy_core::ast::EntityType::Concrete(name) => {
+                EntityTypeDescription::new(self.schema, name)
+            }
+        }
+    }
+
+    fn action(&self, action: &EntityUID) -> Option<Arc<cedar_policy_core::ast::Entity>> {
+        self.actions.get(action).map(Arc::clone)
+    }
+
+    fn entity_types_with_basename<'b>(
+        &'b self,
+        basename: &'b Id,
+    ) -> Box<dyn Iterator<Item = EntityType> + 'b> {
+        Box::new(self.schema.entity_types().filter_map(move |(name, _)| {
+            if name.basename() == basename {
+                Some(EntityType::Concrete(name.clone()))
+            } else {
+                None
+            }
+        }))
+    }
+
+    fn action_entities(&self) -> Self::ActionEntityIterator {
+        self.actions.values().map(Arc::clone).collect()
+    }
+}
+
+/// Struct which carries enough information that it can impl Core's `EntityTypeDescription`
+pub struct EntityTypeDescription<'a> {
+    /// Core `EntityType` this is describing
+    core_type: cedar_policy_core::ast::EntityType,
+    /// Contains most of the schema information for this entity type. This is
+    /// borrowed from the schema to avoid cloning the attribute record for
+    /// every entity parsed.
+    validator_type: &'a ValidatorEntityType,
+    /// Allowed parent types for this entity type. (As of this writing, this
+    /// information is not contained in the `validator_type` by itself.)
+    allowed_parent_types: Arc<HashSet<cedar_policy_core::ast::EntityType>>,
+}
+
+impl<'a> EntityTypeDescription<'a> {
+    /// Create a description of the given type in the given schema.
+    /// Returns `None` if the given type is not in the given schema.
+    pub fn new(schema: &'a ValidatorSchema, type_name: &Name) -> Option<Self> {
+        Some(Self {
+            core_type: cedar_policy_core::ast::EntityType::Concrete(type_name.clone()),
+            validator_type: schema.get_entity_type(type_name)?,
+            allowed_parent_types: {
+                let mut set = HashSet::new();
+                for (possible_parent_typename, possible_parent_et) in schema.entity_types.iter() {
+                    if possible_parent_et.descendants.contains(type_name) {
+                        set.insert(cedar_policy_core::ast::EntityType::Concrete(
+                            possible_parent_typename.clone(),
+                        ));
+                    }
+                }
+                Arc::new(set)
+            },
+        })
+    }
+}
+
+impl<'a> cedar_policy_core::entities::EntityTypeDescription for EntityTypeDescription<'a> {
+    fn entity_type(&self) -> cedar_policy_core::ast::EntityType {
+        self.core_type.clone()
+    }
+
+    fn attr_type(&self, attr: &str) -> Option<cedar_policy_core::entities::SchemaType> {
+        let attr_type: &crate::types::Type = self.validator_type.attr_type(attr)?;
+        // This converts a type from a schema into the representation of schema
+        // types used by core. `attr_type` is taken from a `ValidatorEntityType`
+        // which was constructed from a schema.
+        // PANIC SAFETY: see above
+        #[allow(clippy::expect_used)]
+        let core_schema_type: cedar_policy_core::entities::SchemaType = attr_type
+            .clone()
+            .try_into()
+            .expect("failed to convert validator type into Core SchemaType");
+        debug_assert!(attr_type.is_consistent_with(&core_schema_type));
+        Some(core_schema_type)
+    }
+
+    fn required_attrs<'s>(&'s self) -> Box<dyn Iterator<Item = SmolStr> + 's> {
+        Box::new(
+            self.validator_type
+                .attributes
+                .iter()
+                .filter(|(_, ty)| ty.is_required)
+                .map(|(attr, _)| attr.clone()),
+        )
+    }
+
+    fn allowed_parent_types(&self) -> Arc<HashSet<cedar_policy_core::ast::EntityType>> {
+        Arc::clone(&self.allowed_parent_types)
+    }
+
+    fn attr_defaults<'s>(&'s self) -> Box<dyn Iterator<Item = (SmolStr, RestrictedExpr)> + 's> {
+        Box::new(
+            self.validator_type
+                .attribute_defaults
+                .iter()
+                .map(|(attr, default)| (attr.clone(), default.clone())),
+        )
+    }
+
+    fn check_id_format(
+        &self,
+        eid: &cedar_policy_core::ast::Eid,
+    ) -> std::result::Result<(), SmolStr> {
+        match &self.validator_type.id_format {
+            Some(format) if !format.is_match(eid.as_ref()) => Err(format.pattern().into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn required_parents<'s>(&'s self) -> Box<dyn Iterator<Item = EntityUID> + 's> {
+        Box::new(self.validator_type.required_parents().cloned())
+    }
+}
+
+/// Struct which carries enough information that it can impl Core's
+/// `ContextSchema`. It can only be constructed through `ContextSchema::new`,
+/// which checks that the context type is representable as a Core `SchemaType`.
+struct ContextSchema(cedar_policy_core::entities::SchemaType);
+
+impl ContextSchema {
+    /// Build the `ContextSchema` for an action with the given context
+    /// attributes. The context is a closed record type, which is representable
+    /// as long as the attribute types are representable.
+    fn new(action: &EntityUID, context: &Attributes) -> Result<Self> {
+        crate::types::Type::record_with_attributes(
+            context.iter().map(|(k, v)| (k.clone(), v.clone())),
+            OpenTag::ClosedAttributes,
+        )
+        .try_into()
+        .map(Self)
+        .map_err(|err| SchemaError::UnrepresentableContext(action.clone(), err))
+    }
+}
+
+impl cedar_policy_core::entities::ContextSchema for ContextSchema {
+    fn context_type(&self) -> cedar_policy_core::entities::SchemaType {
+        self.0.clone()
+    }
+}
+
+/// This trait configures what sort of entity (principals, actions, or resources)
+/// are returned by the function `get_entities_satisfying_constraint`.
+pub(crate) trait HeadVar<K>: Copy {
+    /// For a validator, get the known entities for this sort of head variable.
+    /// This is all entity types (for principals and resources), or actions ids
+    /// (for actions) that appear in the service description.
+    fn get_known_vars<'a>(
+        &self,
+        schema: &'a ValidatorSchema,
+    ) -> Box<dyn Iterator<Item = &'a K> + 'a>;
+
+    /// Extract the relevant component of an entity uid. This is the entity type
+    /// for principals and resources, and the entity id for actions.
+    fn get_euid_component(&self, euid: EntityUID) -> Option<K>;
+
+    /// Extract the relevant component of an entity uid if the entity uid is in
+    /// the schema. Otherwise return None.
+    fn get_euid_component_if_present(&self, schema: &ValidatorSchema, euid: EntityUID)
+        -> Option<K>;
+
+    /// Get and iterator containing the valid descendants of an entity, if that
+    /// entity exists in the schema. Otherwise None.
+    fn get_descendants_if_present<'a>(
+        &self,
+        schema: &'a ValidatorSchema,
+        euid: EntityUID,
+    ) -> Option<Box<dyn Iterator<Item = &'a K> + 'a>>;
+}
+
+/// Used to have `get_entities_satisfying_constraint` return the
+/// `EntityTypeNames` for either principals or resources satisfying the head
+/// constraints.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PrincipalOrResourceHeadVar {
+    PrincipalOrResource,
+}
+
+impl HeadVar<Name> for PrincipalOrResourceHeadVar {
+    fn get_known_vars<'a>(
+        &self,
+        schema: &'a ValidatorSchema,
+    ) -> Box<dyn Iterator<Item = &'a Name> + 'a> {
+        Box::new(schema.known_entity_types())
+    }
+
+    fn get_euid_component(&self, euid: EntityUID) -> Option<Name> {
+        let (ty, _) = euid.components();
+        match ty {
+            EntityType::Unspecified => None,
+            EntityType::Concrete(name) => Some(name),
+        }
+    }
+
+    fn get_euid_component_if_present(
+        &self,
+        schema: &ValidatorSchema,
+        euid: EntityUID,
+    ) -> Option<Name> {
+        let euid_component = self.get_euid_component(euid)?;
+        if schema.is_known_entity_type(&euid_component) {
+            Some(euid_component)
+        } else {
+            None
+        }
+    }
+
+    fn get_descendants_if_present<'a>(
+        &self,
+        schema: &'a ValidatorSchema,
+        euid: EntityUID,
+    ) -> Option<Box<dyn Iterator<Item = &'a Name> + 'a>> {
+        let euid_component = self.get_euid_component(euid)?;
+        match schema.get_entity_type(&euid_component) {
+            Some(entity_type) => Some(Box::new(entity_type.descendants.iter())),
+            None => None,
+        }
+    }
+}
+
+/// Used to have `get_entities_satisfying_constraint` return the
+/// `ActionIdNames` for actions satisfying the head constraints
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ActionHeadVar {
+    Action,
+}
+
+impl HeadVar<EntityUID> for ActionHeadVar {
+    fn get_known_vars<'a>(
+        &self,
+        schema: &'a ValidatorSchema,
+    ) -> Box<dyn Iterator<Item = &'a EntityUID> + 'a> {
+        Box::new(schema.known_action_ids())
+    }
+
+    fn get_euid_component(&self, euid: EntityUID) -> Option<EntityUID> {
+        Some(euid)
+    }
+
+    fn get_euid_component_if_present(
+        &self,
+        schema: &ValidatorSchema,
+        euid: EntityUID,
+    ) -> Option<EntityUID> {
+        let euid_component = self.get_euid_component(euid)?;
+        if schema.is_known_action_id(&euid_component) {
+            Some(euid_component)
+        } else {
+            None
+        }
+    }
+
+    fn get_descendants_if_present<'a>(
+        &self,
+        schema: &'a ValidatorSchema,
+        euid: EntityUID,
+    ) -> Option<Box<dyn Iterator<Item = &'a EntityUID> + 'a>> {
+        let euid_component = self.get_euid_component(euid)?;
+        match schema.get_action_id_exact(&euid_component) {
+            Some(action_id) => Some(Box::new(action_id.descendants.iter())),
+            None => None,
+        }
+    }
+}
+
+/// Used to write a schema implicitly overriding the default handling of action
+/// groups.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct NamespaceDefinitionWithActionAttributes(pub(crate) NamespaceDefinition);
+
+impl TryInto<ValidatorSchema> for NamespaceDefinitionWithActionAttributes {
+    type Error = SchemaError;
+
+    fn try_into(self) -> Result<ValidatorSchema> {
+        ValidatorSchema::from_schema_fragments([ValidatorSchemaFragment::from_namespaces([
+            ValidatorNamespaceDef::from_namespace_definition(
+                None,
+                self.0,
+                crate::ActionBehavior::PermitAttributes,
+            )?,
+        ])])
+    }
+}
+
+// PANIC SAFETY unit tests
 #[allow(clippy::panic)]
 // PANIC SAFETY unit tests
 #[allow(clippy::indexing_slicing)]
@@ -883,1043 +3532,6419 @@ impl TryInto<ValidatorSchema> for NamespaceDefinitionWithActionAttributes {
 mod test {
     use std::{collections::BTreeMap, str::FromStr};
 
-    use crate::types::Type;
-    use crate::{SchemaType, SchemaTypeVariant};
+    use crate::types::Type;
+    use crate::{SchemaType, SchemaTypeVariant};
+
+    use cedar_policy_core::ast::{PrincipalOrResource, RestrictedExpr};
+    use cedar_policy_core::parser::err::{ParseError, ToASTError};
+    use serde_json::json;
+
+    use super::*;
+
+    // Well-formed schema
+    #[test]
+    fn test_from_schema_file() {
+        let src = json!(
+        {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": [ "Group" ]
+                },
+                "Group": {
+                    "memberOfTypes": []
+                },
+                "Photo": {
+                    "memberOfTypes": [ "Album" ]
+                },
+                "Album": {
+                    "memberOfTypes": []
+                }
+            },
+            "actions": {
+                "view_photo": {
+                    "appliesTo": {
+                        "principalTypes": ["User", "Group"],
+                        "resourceTypes": ["Photo"]
+                    }
+                }
+            }
+        });
+        let schema_file: NamespaceDefinition = serde_json::from_value(src).expect("Parse Error");
+        let schema: Result<ValidatorSchema> = schema_file.try_into();
+        assert!(schema.is_ok());
+    }
+
+    // Duplicate entity "Photo"
+    #[test]
+    fn test_from_schema_file_duplicate_entity() {
+        // Test written using `from_str` instead of `from_value` because the
+        // `json!` macro silently ignores duplicate map keys.
+        let src = r#"
+        {"": {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": [ "Group" ]
+                },
+                "Group": {
+                    "memberOfTypes": []
+                },
+                "Photo": {
+                    "memberOfTypes": [ "Album" ]
+                },
+                "Photo": {
+                    "memberOfTypes": []
+                }
+            },
+            "actions": {
+                "view_photo": {
+                    "memberOf": [],
+                    "appliesTo": {
+                        "principalTypes": ["User", "Group"],
+                        "resourceTypes": ["Photo"]
+                    }
+                }
+            }
+        }}"#;
+
+        match ValidatorSchema::from_str(src) {
+            Err(SchemaError::Serde(_)) => (),
+            _ => panic!("Expected serde error due to duplicate entity type."),
+        }
+    }
+
+    // Duplicate action "view_photo"
+    #[test]
+    fn test_from_schema_file_duplicate_action() {
+        // Test written using `from_str` instead of `from_value` because the
+        // `json!` macro silently ignores duplicate map keys.
+        let src = r#"
+        {"": {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": [ "Group" ]
+                },
+                "Group": {
+                    "memberOfTypes": []
+                },
+                "Photo": {
+                    "memberOfTypes": []
+                }
+            },
+            "actions": {
+                "view_photo": {
+                    "memberOf": [],
+                    "appliesTo": {
+                        "principalTypes": ["User", "Group"],
+                        "resourceTypes": ["Photo"]
+                    }
+                },
+                "view_photo": { }
+            }
+        }"#;
+        match ValidatorSchema::from_str(src) {
+            Err(SchemaError::Serde(_)) => (),
+            _ => panic!("Expected serde error due to duplicate action type."),
+        }
+    }
+
+    // Undefined entity types "Grop", "Usr", "Phoot"
+    #[test]
+    fn test_from_schema_file_undefined_entities() {
+        let src = json!(
+        {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": [ "Grop" ]
+                },
+                "Group": {
+                    "memberOfTypes": []
+                },
+                "Photo": {
+                    "memberOfTypes": []
+                }
+            },
+            "actions": {
+                "view_photo": {
+                    "appliesTo": {
+                        "principalTypes": ["Usr", "Group"],
+                        "resourceTypes": ["Phoot"]
+                    }
+                }
+            }
+        });
+        let schema_file: NamespaceDefinition = serde_json::from_value(src).expect("Parse Error");
+        let schema: Result<ValidatorSchema> = schema_file.try_into();
+        match schema {
+            Ok(_) => panic!("from_schema_file should have failed"),
+            Err(SchemaError::UndeclaredEntityTypes(v)) => {
+                assert_eq!(v.len(), 3)
+            }
+            _ => panic!("Unexpected error from from_schema_file"),
+        }
+    }
+
+    #[test]
+    fn undefined_entity_namespace_member_of() {
+        let src = json!(
+        {"Foo": {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": [ "Foo::Group", "Bar::Group" ]
+                },
+                "Group": { }
+            },
+            "actions": {}
+        }});
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: Result<ValidatorSchema> = schema_file.try_into();
+        match schema {
+            Ok(_) => panic!("try_into should have failed"),
+            Err(SchemaError::UndeclaredEntityTypes(v)) => {
+                assert_eq!(v, HashSet::from(["Bar::Group".to_string()]))
+            }
+            _ => panic!("Unexpected error from try_into"),
+        }
+    }
+
+    #[test]
+    fn undefined_entity_namespace_applies_to() {
+        let src = json!(
+        {"Foo": {
+            "entityTypes": { "User": { }, "Photo": { } },
+            "actions": {
+                "view_photo": {
+                    "appliesTo": {
+                        "principalTypes": ["Foo::User", "Bar::User"],
+                        "resourceTypes": ["Photo", "Bar::Photo"],
+                    }
+                }
+            }
+        }});
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: Result<ValidatorSchema> = schema_file.try_into();
+        match schema {
+            Ok(_) => panic!("try_into should have failed"),
+            Err(SchemaError::UndeclaredEntityTypes(v)) => {
+                assert_eq!(
+                    v,
+                    HashSet::from(["Bar::Photo".to_string(), "Bar::User".to_string()])
+                )
+            }
+            _ => panic!("Unexpected error from try_into"),
+        }
+    }
+
+    // Undefined action "photo_actions"
+    #[test]
+    fn test_from_schema_file_undefined_action() {
+        let src = json!(
+        {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": [ "Group" ]
+                },
+                "Group": {
+                    "memberOfTypes": []
+                },
+                "Photo": {
+                    "memberOfTypes": []
+                }
+            },
+            "actions": {
+                "view_photo": {
+                    "memberOf": [ {"id": "photo_action"} ],
+                    "appliesTo": {
+                        "principalTypes": ["User", "Group"],
+                        "resourceTypes": ["Photo"]
+                    }
+                }
+            }
+        });
+        let schema_file: NamespaceDefinition = serde_json::from_value(src).expect("Parse Error");
+        let schema: Result<ValidatorSchema> = schema_file.try_into();
+        match schema {
+            Ok(_) => panic!("from_schema_file should have failed"),
+            Err(SchemaError::UndeclaredActions(v)) => assert_eq!(v.len(), 1),
+            _ => panic!("Unexpected error from from_schema_file"),
+        }
+    }
+
+    // Trivial cycle in action hierarchy
+    // view_photo -> view_photo
+    #[test]
+    fn test_from_schema_file_action_cycle1() {
+        let src = json!(
+        {
+            "entityTypes": {},
+            "actions": {
+                "view_photo": {
+                    "memberOf": [ {"id": "view_photo"} ]
+                }
+            }
+        });
+        let schema_file: NamespaceDefinition = serde_json::from_value(src).expect("Parse Error");
+        let schema: Result<ValidatorSchema> = schema_file.try_into();
+        match schema {
+            Ok(_) => panic!("from_schema_file should have failed"),
+            Err(SchemaError::CycleInActionHierarchy) => (), // expected result
+            e => panic!("Unexpected error from from_schema_file: {:?}", e),
+        }
+    }
+
+    // Slightly more complex cycle in action hierarchy
+    // view_photo -> edit_photo -> delete_photo -> view_photo
+    #[test]
+    fn test_from_schema_file_action_cycle2() {
+        let src = json!(
+        {
+            "entityTypes": {},
+            "actions": {
+                "view_photo": {
+                    "memberOf": [ {"id": "edit_photo"} ]
+                },
+                "edit_photo": {
+                    "memberOf": [ {"id": "delete_photo"} ]
+                },
+                "delete_photo": {
+                    "memberOf": [ {"id": "view_photo"} ]
+                },
+                "other_action": {
+                    "memberOf": [ {"id": "edit_photo"} ]
+                }
+            }
+        });
+        let schema_file: NamespaceDefinition = serde_json::from_value(src).expect("Parse Error");
+        let schema: Result<ValidatorSchema> = schema_file.try_into();
+        match schema {
+            Ok(x) => {
+                println!("{:?}", x);
+                panic!("from_schema_file should have failed");
+            }
+            Err(SchemaError::CycleInActionHierarchy) => (), // expected result
+            e => panic!("Unexpected error from from_schema_file: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn validate_collecting() {
+        let fragment = |src: serde_json::Value| -> SchemaFragment {
+            serde_json::from_value(src).expect("Parse Error")
+        };
+        let first = fragment(json!({
+            "": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Group"] },
+                    "Photo": { "shape": { "type": "Long" } }
+                },
+                "actions": {
+                    "view": { "memberOf": [{ "id": "edit" }] },
+                    "edit": { "memberOf": [{ "id": "view" }, { "id": "delete" }] }
+                }
+            }
+        }));
+        let second = fragment(json!({
+            "": { "entityTypes": { "User": {} }, "actions": {} }
+        }));
+        let errors =
+            ValidatorSchema::validate_collecting([first, second]).expect_err("schema has errors");
+        assert!(
+            matches!(errors.first(), Some(SchemaError::DuplicateEntityType(name, _)) if name == "User"),
+            "{errors:?}"
+        );
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            SchemaError::ContextOrShapeNotRecord(ContextOrShape::EntityTypeShape(name))
+                if name.to_string() == "Photo"
+        )));
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, SchemaError::CycleInActionHierarchy)));
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            SchemaError::UndeclaredEntityTypes(types) if types == &HashSet::from(["Group".to_string()])
+        )));
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            SchemaError::UndeclaredActions(actions)
+                if actions == &HashSet::from([r#"Action::"delete""#.to_string()])
+        )));
+        assert_eq!(errors.len(), 5);
+
+        // Namespaces which fail to parse stop before the other checks.
+        let errors = ValidatorSchema::validate_collecting([fragment(json!({
+            "A": { "entityTypes": { "User": { "memberOfTypes": ["Undeclared"] } }, "actions": {} },
+            "B": { "entityTypes": { "Bad Type": {} }, "actions": {} },
+            "C": { "entityTypes": { "Also Bad": {} }, "actions": {} }
+        }))])
+        .expect_err("schema has errors");
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors
+            .iter()
+            .all(|e| matches!(e, SchemaError::ParseEntityType(..))));
+
+        let schema = ValidatorSchema::validate_collecting([fragment(json!({
+            "": { "entityTypes": { "User": {} }, "actions": { "view": {} } }
+        }))])
+        .expect("schema is valid");
+        assert!(schema.get_entity_type(&"User".parse().unwrap()).is_some());
+    }
+
+    #[test]
+    fn all_principal_and_resource_types() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {
+                    "User": {},
+                    "Admin": {},
+                    "Folder": {},
+                    "Photo": { "memberOfTypes": ["Folder"] }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypesWithDescendants": ["Folder"]
+                        }
+                    },
+                    "administer": {
+                        "appliesTo": {
+                            "principalTypes": ["Admin", "User"],
+                            "resourceTypes": ["User"]
+                        }
+                    },
+                    "unspecified": {}
+                }
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .expect("Expected valid schema");
+        let names = |types: HashSet<&Name>| {
+            let mut names = types.iter().map(ToString::to_string).collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        assert_eq!(names(schema.all_principal_types()), ["Admin", "User"]);
+        assert_eq!(
+            names(schema.all_resource_types()),
+            ["Folder", "Photo", "User"]
+        );
+    }
+
+    #[test]
+    fn orphan_actions() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {},
+                "actions": {
+                    "read": {},
+                    "view": { "memberOf": [{ "id": "read" }] },
+                    "list": { "memberOf": [{ "id": "view" }] },
+                    "delete": {},
+                    "ping": {}
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let mut orphans = schema
+            .orphan_actions()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        orphans.sort();
+        assert_eq!(orphans, [r#"Action::"delete""#, r#"Action::"ping""#]);
+        assert_eq!(ValidatorSchema::empty().orphan_actions().count(), 0);
+    }
+
+    #[cfg(feature = "preserve-order")]
+    #[test]
+    fn preserve_declaration_order() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "Zoo": {
+                "entityTypes": {
+                    "Zebra": {},
+                    "Aardvark": { "memberOfTypes": ["Keeper"] },
+                    "Keeper": {}
+                },
+                "actions": {
+                    "feed": {},
+                    "clean": {},
+                    "admire": { "memberOf": [{ "id": "feed" }] }
+                }
+            },
+            "App": {
+                "entityTypes": { "User": {} },
+                "actions": {}
+            }
+        }))
+        .expect("Schema should parse");
+        let schema: ValidatorSchema = fragment.try_into().expect("Schema should construct");
+        assert_eq!(
+            schema
+                .entity_types()
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>(),
+            ["Zoo::Zebra", "Zoo::Aardvark", "Zoo::Keeper", "App::User"]
+        );
+        assert_eq!(
+            schema
+                .known_action_ids()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                r#"Zoo::Action::"feed""#,
+                r#"Zoo::Action::"clean""#,
+                r#"Zoo::Action::"admire""#
+            ]
+        );
+    }
+
+    #[test]
+    fn check_attribute_name_conventions() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "jobLevel": { "type": "Long" },
+                                "_internal": { "type": "String" },
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "zip_code": { "type": "String" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "Reason": { "type": "String" },
+                                    "onBehalfOf": { "type": "Entity", "name": "User" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let camel_case = Regex::new("^[a-z][a-zA-Z0-9]*$").unwrap();
+        let violations = schema
+            .check_attribute_name_conventions(&camel_case)
+            .expect_err("Schema should violate the convention")
+            .into_iter()
+            .map(|(location, name)| (location.to_string(), name.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            violations,
+            [
+                ("Shape for entity type User", "_internal"),
+                ("Shape for entity type User", "zip_code"),
+                (r#"Context for action Action::"view""#, "Reason"),
+            ]
+            .map(|(location, name)| (location.to_string(), name.to_string()))
+        );
+
+        let any = Regex::new("^.+$").unwrap();
+        assert!(schema.check_attribute_name_conventions(&any).is_ok());
+    }
+
+    #[test]
+    fn apply_spec_groups() {
+        let schema_json = |principals_in: serde_json::Value| {
+            json!({
+                "App": {
+                    "entityTypes": {
+                        "User": { "memberOfTypes": ["Group"] },
+                        "Group": {},
+                        "Doc": { "memberOfTypes": ["Folder"] },
+                        "Folder": {}
+                    },
+                    "actions": {
+                        "administer": {
+                            "appliesTo": {
+                                "principalTypes": ["User"],
+                                "resourceTypes": ["Doc"],
+                                "principalsIn": principals_in,
+                                "resourcesIn": [{ "type": "App::Folder", "id": "shared" }]
+                            }
+                        },
+                        "view": {
+                            "appliesTo": {
+                                "principalTypes": ["User"],
+                                "resourceTypes": ["Doc"]
+                            }
+                        }
+                    }
+                }
+            })
+        };
+        let schema = ValidatorSchema::from_json_value(schema_json(
+            json!([{ "type": "Group", "id": "admins" }]),
+        ))
+        .expect("Schema should construct without error.");
+
+        let admins: EntityUID = r#"App::Group::"admins""#.parse().unwrap();
+        let shared: EntityUID = r#"App::Folder::"shared""#.parse().unwrap();
+        let administer = schema
+            .get_action_id(&r#"App::Action::"administer""#.parse().unwrap())
+            .unwrap();
+        let view = schema
+            .get_action_id(&r#"App::Action::"view""#.parse().unwrap())
+            .unwrap();
+        assert_eq!(administer.principal_groups().collect::<Vec<_>>(), [&admins]);
+        assert_eq!(administer.resource_groups().collect::<Vec<_>>(), [&shared]);
+        assert_eq!(view.principal_groups().count(), 0);
+
+        let alice = Entity::new(
+            r#"App::User::"alice""#.parse().unwrap(),
+            HashMap::new(),
+            HashSet::from([admins.clone()]),
+        );
+        let bob = Entity::new(
+            r#"App::User::"bob""#.parse().unwrap(),
+            HashMap::new(),
+            HashSet::new(),
+        );
+        let doc = Entity::new(
+            r#"App::Doc::"plan""#.parse().unwrap(),
+            HashMap::new(),
+            HashSet::from([shared.clone()]),
+        );
+        assert!(administer.applies_to_entities(&alice, &doc));
+        assert!(!administer.applies_to_entities(&bob, &doc));
+        assert!(!administer.applies_to_entities(&alice, &bob));
+        assert!(view.applies_to_entities(&bob, &doc));
+
+        match ValidatorSchema::from_json_value(schema_json(
+            json!([{ "type": "Team", "id": "admins" }]),
+        )) {
+            Err(SchemaError::UndeclaredEntityTypes(types)) => {
+                assert_eq!(types, HashSet::from(["App::Team".to_string()]))
+            }
+            r => panic!("Expected UndeclaredEntityTypes, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn minimal_entities_for() {
+        let schema_json = |user_id_format: serde_json::Value| {
+            json!({
+                "App": {
+                    "entityTypes": {
+                        "User": {
+                            "memberOfTypes": ["Group"],
+                            "memberOf": [{ "type": "Group", "id": "everyone" }],
+                            "idFormat": user_id_format,
+                            "shape": {
+                                "type": "Record",
+                                "attributes": {
+                                    "name": { "type": "String" },
+                                    "level": { "type": "Long" },
+                                    "status": {
+                                        "type": "String",
+                                        "allowedValues": ["suspended", "active"]
+                                    },
+                                    "tags": { "type": "Set", "element": { "type": "String" } },
+                                    "address": {
+                                        "type": "Record",
+                                        "attributes": {
+                                            "zip": { "type": "String" },
+                                            "note": { "type": "String", "required": false }
+                                        }
+                                    },
+                                    "dept": { "type": "Entity", "name": "Dept" },
+                                    "nickname": { "type": "String", "required": false }
+                                }
+                            }
+                        },
+                        "Group": {},
+                        "Dept": {
+                            "shape": {
+                                "type": "Record",
+                                "attributes": {
+                                    "network": { "type": "Extension", "name": "ipaddr" }
+                                }
+                            }
+                        },
+                        "Doc": { "memberOfTypes": ["Folder"] },
+                        "Folder": {}
+                    },
+                    "actions": {
+                        "edit": {
+                            "appliesTo": {
+                                "principalTypes": ["User"],
+                                "resourceTypes": ["Doc"],
+                                "resourcesIn": [{ "type": "Folder", "id": "shared" }]
+                            }
+                        }
+                    }
+                }
+            })
+        };
+        let schema = ValidatorSchema::from_json_value(schema_json(json!(null)))
+            .expect("Schema should construct without error.");
+        let edit: EntityUID = r#"App::Action::"edit""#.parse().unwrap();
+        let entities = schema
+            .minimal_entities_for(&edit)
+            .expect("Entities should be generated");
+        let uids = entities
+            .iter()
+            .map(|entity| entity.uid().to_string())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            uids,
+            BTreeSet::from(
+                [
+                    r#"App::Action::"edit""#,
+                    r#"App::Dept::"example""#,
+                    r#"App::Doc::"example""#,
+                    r#"App::Folder::"shared""#,
+                    r#"App::Group::"everyone""#,
+                    r#"App::User::"example""#,
+                ]
+                .map(ToString::to_string)
+            )
+        );
+
+        let entity = |uid: &str| {
+            let uid: EntityUID = uid.parse().unwrap();
+            entities.iter().find(|entity| entity.uid() == uid).unwrap()
+        };
+        let user = entity(r#"App::User::"example""#);
+        let doc = entity(r#"App::Doc::"example""#);
+        assert!(user.is_descendant_of(&r#"App::Group::"everyone""#.parse().unwrap()));
+        assert_eq!(
+            user.get("status").map(ToString::to_string).as_deref(),
+            Some(r#""active""#)
+        );
+        assert_eq!(
+            user.get("address").map(ToString::to_string).as_deref(),
+            Some(r#"{"zip": ""}"#)
+        );
+        assert!(user.get("nickname").is_none());
+        assert!(schema
+            .get_action_id(&edit)
+            .unwrap()
+            .applies_to_entities(user, doc));
+
+        match schema.minimal_entities_for(&r#"App::Action::"delete""#.parse().unwrap()) {
+            Err(SchemaError::UndeclaredActions(_)) => (),
+            r => panic!("Expected UndeclaredActions, got {:?}", r),
+        }
+
+        let schema = ValidatorSchema::from_json_value(schema_json(json!("[0-9]+")))
+            .expect("Schema should construct without error.");
+        match schema.minimal_entities_for(&edit) {
+            Err(SchemaError::EntityGeneration(action, _)) => assert_eq!(action, edit),
+            r => panic!("Expected EntityGeneration, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn is_compatible_extension_of() {
+        let base = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "role": { "type": "String", "allowedValues": ["admin"] },
+                                "age": { "type": "Long", "required": false },
+                                "email": { "type": "String" }
+                            }
+                        }
+                    },
+                    "Group": {},
+                    "Doc": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Doc", "Group"]
+                        }
+                    },
+                    "edit": {}
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let extension = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "role": {
+                                    "type": "String",
+                                    "allowedValues": ["admin", "viewer"]
+                                },
+                                "age": { "type": "Long", "required": false },
+                                "email": { "type": "String", "required": false },
+                                "team": { "type": "String", "required": false }
+                            }
+                        }
+                    },
+                    "Group": {},
+                    "Doc": {},
+                    "Folder": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User", "Group"],
+                            "resourceTypes": ["Doc", "Group", "Folder"]
+                        }
+                    },
+                    "edit": {},
+                    "share": {}
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        assert_eq!(extension.is_compatible_extension_of(&base), Ok(()));
+        assert_eq!(base.is_compatible_extension_of(&base), Ok(()));
+
+        let narrowed = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "Long" },
+                                "role": { "type": "String", "allowedValues": ["admin"] },
+                                "age": { "type": "Long" },
+                                "title": { "type": "String" }
+                            }
+                        }
+                    },
+                    "Group": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Group"],
+                            "resourcesIn": [{ "type": "Group", "id": "public" }]
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let user = || ContextOrShape::EntityTypeShape("User".parse().unwrap());
+        let view: EntityUID = r#"Action::"view""#.parse().unwrap();
+        assert_eq!(
+            narrowed.is_compatible_extension_of(&base),
+            Err(vec![
+                Incompatibility::RemovedEntityType("Doc".parse().unwrap()),
+                Incompatibility::RemovedMemberOfType {
+                    entity_type: "User".parse().unwrap(),
+                    parent: "Group".parse().unwrap(),
+                },
+                Incompatibility::NewlyRequiredAttribute {
+                    location: user(),
+                    attr: "age".into(),
+                },
+                Incompatibility::RemovedAttribute {
+                    location: user(),
+                    attr: "email".into(),
+                },
+                Incompatibility::NarrowedAttributeType {
+                    location: user(),
+                    attr: "name".into(),
+                },
+                Incompatibility::NewlyRequiredAttribute {
+                    location: user(),
+                    attr: "title".into(),
+                },
+                Incompatibility::RemovedAction(r#"Action::"edit""#.parse().unwrap()),
+                Incompatibility::NarrowedAppliesTo {
+                    action: view.clone(),
+                    target: PrincipalOrResource::Resource,
+                    removed_types: vec![EntityType::Concrete("Doc".parse().unwrap())],
+                },
+                Incompatibility::NarrowedGroups {
+                    action: view,
+                    target: PrincipalOrResource::Resource,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn modify_entity_type() {
+        let mut schema = ValidatorSchema::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "active": { "type": "Boolean", "required": false, "default": true }
+                            }
+                        }
+                    },
+                    "Group": {},
+                    "Org": {}
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let name = |name: &str| name.parse::<Name>().unwrap();
+
+        schema
+            .modify_entity_type(&name("App::User"), |user| {
+                user.attributes_mut().attrs.insert(
+                    "org".into(),
+                    AttributeType::required_attribute(Type::named_entity_reference(name(
+                        "App::Org",
+                    ))),
+                );
+            })
+            .expect("modification should succeed");
+        let user = schema.get_entity_type(&name("App::User")).unwrap();
+        assert_eq!(
+            user.attr_type("org"),
+            Some(&Type::named_entity_reference_from_str("App::Org"))
+        );
+        assert!(user.attr_default("active").is_some());
+
+        schema
+            .modify_entity_type(&name("App::Org"), |org| {
+                org.descendants.insert(name("App::Group"));
+            })
+            .expect("modification should succeed");
+        assert!(schema.is_entity_type_descendant_of(&name("App::User"), &name("App::Org")));
+
+        match schema.modify_entity_type(&name("App::User"), |user| {
+            user.attributes_mut().attrs.insert(
+                "team".into(),
+                AttributeType::required_attribute(Type::named_entity_reference(name("App::Team"))),
+            );
+        }) {
+            Err(SchemaError::UndeclaredEntityTypes(types)) => {
+                assert_eq!(types, HashSet::from(["App::Team".to_string()]));
+            }
+            r => panic!("Expected UndeclaredEntityTypes, got {:?}", r),
+        }
+        match schema.modify_entity_type(&name("App::User"), |user| {
+            user.attributes_mut().attrs.insert(
+                "active".into(),
+                AttributeType::required_attribute(Type::primitive_long()),
+            );
+        }) {
+            Err(SchemaError::InvalidAttributeDefault(_, attr, _)) => assert_eq!(attr, "active"),
+            r => panic!("Expected InvalidAttributeDefault, got {:?}", r),
+        }
+        assert!(schema
+            .get_entity_type(&name("App::User"))
+            .unwrap()
+            .attr_type("team")
+            .is_none());
+
+        schema
+            .modify_entity_type(&name("App::User"), |user| {
+                user.attributes_mut().attrs.remove("active");
+            })
+            .expect("modification should succeed");
+        assert!(schema
+            .get_entity_type(&name("App::User"))
+            .unwrap()
+            .attr_default("active")
+            .is_none());
+
+        match schema.modify_entity_type(&name("App::Team"), |_| ()) {
+            Err(SchemaError::UndeclaredEntityTypes(_)) => (),
+            r => panic!("Expected UndeclaredEntityTypes, got {:?}", r),
+        }
+        schema.freeze_namespace(&name("App"));
+        match schema.modify_entity_type(&name("App::User"), |_| ()) {
+            Err(SchemaError::NamespaceFrozen { ns }) => assert_eq!(ns, "App"),
+            r => panic!("Expected NamespaceFrozen, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn modify_entity_type_frozen_hierarchy() {
+        let mut schema = ValidatorSchema::from_json_value(json!({
+            "Core": {
+                "entityTypes": {
+                    "Group": {},
+                    "Admin": {}
+                },
+                "actions": {}
+            },
+            "App": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Team"] },
+                    "Team": { "memberOfTypes": ["Core::Group"] },
+                    "Guest": {}
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let name = |name: &str| name.parse::<Name>().unwrap();
+        schema.freeze_namespace(&name("Core"));
+
+        // `Core::Group` would gain `App::Guest` as a descendant
+        match schema.modify_entity_type(&name("App::Team"), |team| {
+            team.descendants.insert(name("App::Guest"));
+        }) {
+            Err(SchemaError::NamespaceFrozen { ns }) => assert_eq!(ns, "Core"),
+            r => panic!("Expected NamespaceFrozen, got {:?}", r),
+        }
+        // `Core::Admin` would become a member of `App::Team`
+        match schema.modify_entity_type(&name("App::Team"), |team| {
+            team.descendants.insert(name("Core::Admin"));
+        }) {
+            Err(SchemaError::NamespaceFrozen { ns }) => assert_eq!(ns, "Core"),
+            r => panic!("Expected NamespaceFrozen, got {:?}", r),
+        }
+        assert!(!schema.is_entity_type_descendant_of(&name("App::Guest"), &name("App::Team")));
+
+        schema
+            .modify_entity_type(&name("App::Guest"), |guest| {
+                guest.descendants.insert(name("App::User"));
+            })
+            .expect("modification should succeed");
+        assert!(schema.is_entity_type_descendant_of(&name("App::User"), &name("App::Guest")));
+    }
+
+    #[test]
+    fn describe_action() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {},
+                    "Group": {},
+                    "Doc": {}
+                },
+                "actions": {
+                    "all": {},
+                    "read": { "memberOf": [{ "id": "all" }] },
+                    "view": {
+                        "memberOf": [{ "id": "read" }],
+                        "appliesTo": {
+                            "principalTypes": ["User", "Group"],
+                            "resourceTypes": ["Doc"],
+                            "principalsIn": [{ "type": "Group", "id": "staff" }],
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "reason": { "type": "String", "required": false },
+                                    "ip": { "type": "Extension", "name": "ipaddr" }
+                                }
+                            }
+                        },
+                        "annotations": { "doc": "View a document" }
+                    }
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+
+        let description = schema
+            .describe_action(&r#"App::Action::"view""#.parse().unwrap())
+            .expect("action should be described");
+        assert_eq!(
+            serde_json::to_value(&description).unwrap(),
+            json!({
+                "uid": r#"App::Action::"view""#,
+                "ancestors": [r#"App::Action::"all""#, r#"App::Action::"read""#],
+                "descendants": [],
+                "principalTypes": ["App::Group", "App::User"],
+                "resourceTypes": ["App::Doc"],
+                "principalsIn": [r#"App::Group::"staff""#],
+                "resourcesIn": [],
+                "context": [
+                    {
+                        "name": "ip",
+                        "type": { "type": "Extension", "name": "ipaddr" },
+                        "required": true
+                    },
+                    { "name": "reason", "type": { "type": "String" }, "required": false }
+                ],
+                "annotations": { "doc": "View a document" }
+            })
+        );
+
+        let all = schema
+            .describe_action(&r#"App::Action::"all""#.parse().unwrap())
+            .expect("action should be described");
+        assert!(all.ancestors.is_empty());
+        assert_eq!(
+            all.descendants,
+            vec![r#"App::Action::"read""#, r#"App::Action::"view""#]
+        );
+        assert!(schema
+            .describe_action(&r#"App::Action::"edit""#.parse().unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn case_insensitive_action_lookup() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {},
+                "actions": {
+                    "view_photo": { "memberOf": [{ "id": "read" }] },
+                    "read": {},
+                    "edit": {},
+                    "Edit": {}
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let euid = |euid: &str| euid.parse::<EntityUID>().unwrap();
+        let view_photo = euid(r#"Action::"view_photo""#);
+        let mixed_case = euid(r#"Action::"View_Photo""#);
+
+        assert_eq!(schema.lookup_config(), LookupConfig::default());
+        assert!(schema.get_action_id(&mixed_case).is_none());
+        assert!(schema.require(&[], &[r#"Action::"View_Photo""#]).is_err());
+
+        let schema = schema.with_lookup_config(LookupConfig {
+            case_insensitive_actions: true,
+        });
+        assert_eq!(schema.resolve_action_id(&mixed_case), Some(&view_photo));
+        assert_eq!(
+            schema.get_action_id(&mixed_case).map(|action| &action.name),
+            Some(&view_photo)
+        );
+        assert_eq!(
+            schema.action_entity(&mixed_case).map(|entity| entity.uid()),
+            Some(view_photo)
+        );
+        schema
+            .require(&[], &[r#"Action::"VIEW_PHOTO""#])
+            .expect("action should be found ignoring case");
+        // Lookups continue with the declared id.
+        let description = schema
+            .describe_action(&mixed_case)
+            .expect("action should be found ignoring case");
+        assert_eq!(description.uid, r#"Action::"view_photo""#);
+        assert_eq!(description.ancestors, vec![r#"Action::"read""#]);
+        assert!(schema.is_action_descendant_of(&mixed_case, &euid(r#"Action::"READ""#)));
+        assert_eq!(
+            schema
+                .subschema_for_action(&mixed_case)
+                .expect("action should be found ignoring case")
+                .known_action_ids()
+                .count(),
+            2
+        );
+
+        // Exact matches are preferred, and ambiguous matches fail.
+        assert_eq!(
+            schema.resolve_action_id(&euid(r#"Action::"Edit""#)),
+            Some(&euid(r#"Action::"Edit""#))
+        );
+        assert!(schema.get_action_id(&euid(r#"Action::"EDIT""#)).is_none());
+        // The action entity type is still matched exactly.
+        assert!(schema
+            .get_action_id(&euid(r#"action::"view_photo""#))
+            .is_none());
+    }
+
+    #[test]
+    fn context_attr_entity_type() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": { "User": {} },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "onBehalfOf": { "type": "Entity", "name": "User" },
+                                    "reason": { "type": "String" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let view: EntityUID = r#"Action::"view""#.parse().unwrap();
+        assert_eq!(
+            schema.context_attr_entity_type(&view, "onBehalfOf"),
+            Some(&"User".parse().unwrap())
+        );
+        assert_eq!(schema.context_attr_entity_type(&view, "reason"), None);
+        assert_eq!(schema.context_attr_entity_type(&view, "missing"), None);
+        assert_eq!(
+            schema.context_attr_entity_type(&r#"Action::"edit""#.parse().unwrap(), "onBehalfOf"),
+            None
+        );
+    }
+
+    #[test]
+    fn actions_with_empty_context() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": { "User": {} },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "ip": { "type": "String" } }
+                            }
+                        }
+                    },
+                    "edit": { "appliesTo": { "principalTypes": ["User"] } },
+                    "ping": {}
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let mut empty = schema
+            .actions_with_empty_context()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        empty.sort();
+        assert_eq!(empty, [r#"Action::"edit""#, r#"Action::"ping""#]);
+        let with_context = schema
+            .actions_with_context()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(with_context, [r#"Action::"view""#]);
+    }
+
+    #[test]
+    fn require() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "App": {
+                "entityTypes": { "User": {}, "Group": {} },
+                "actions": { "login": {} }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        schema
+            .require(&["App::User", "App::Group"], &[r#"App::Action::"login""#])
+            .expect("everything required is declared");
+        schema.require(&[], &[]).expect("nothing is required");
+        match schema.require(
+            &["App::User", "User", "App::Admin", "not a name"],
+            &[r#"App::Action::"login""#, r#"App::Action::"logout""#],
+        ) {
+            Err(e @ SchemaError::MissingRequired { .. }) => {
+                assert_eq!(
+                    e.to_string(),
+                    r#"schema does not declare required entity types `User`, `App::Admin`, `not a name` and actions `App::Action::"logout"`"#
+                );
+            }
+            r => panic!("Expected MissingRequired, got {:?}", r),
+        }
+        match schema.require(&[], &[r#"Action::"login""#]) {
+            Err(SchemaError::MissingRequired {
+                entity_types,
+                actions,
+            }) => {
+                assert!(entity_types.is_empty());
+                assert_eq!(actions, [r#"Action::"login""#]);
+            }
+            r => panic!("Expected MissingRequired, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn actions_allowing_unspecified() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {},
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] }
+                    },
+                    "signUp": {
+                        "appliesTo": { "resourceTypes": ["Photo"] }
+                    },
+                    "listAll": {
+                        "appliesTo": { "principalTypes": ["User"] }
+                    },
+                    "ping": {}
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let names = |actions: Vec<&EntityUID>| {
+            actions
+                .into_iter()
+                .map(|action| action.eid().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(schema.actions_allowing_unspecified_principal()),
+            ["ping", "signUp"]
+        );
+        assert_eq!(
+            names(schema.actions_allowing_unspecified_resource()),
+            ["listAll", "ping"]
+        );
+    }
+
+    #[test]
+    fn schema_composer() {
+        let fragment = |json: serde_json::Value| -> ValidatorSchemaFragment {
+            serde_json::from_value::<SchemaFragment>(json)
+                .expect("Parse Error")
+                .try_into()
+                .expect("Expected valid fragment")
+        };
+        let mut composer = SchemaComposer::new();
+        composer
+            .add_fragment(fragment(json!({
+                "": { "entityTypes": { "User": {} }, "actions": {} }
+            })))
+            .expect("first fragment should be added");
+        composer
+            .add_fragment(fragment(json!({
+                "": {
+                    "entityTypes": {},
+                    "actions": {
+                        "view": {
+                            "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] }
+                        }
+                    }
+                }
+            })))
+            .expect("fragment referring to a later fragment should be added");
+        match composer.add_fragment(fragment(json!({
+            "": { "entityTypes": { "Photo": {}, "User": {} }, "actions": {} }
+        }))) {
+            Err(SchemaError::DuplicateEntityType(name, fragments)) => {
+                assert_eq!(name, "User");
+                assert_eq!(
+                    fragments,
+                    Some(FragmentIndices {
+                        first: 0,
+                        duplicate: 2
+                    })
+                );
+            }
+            r => panic!("Expected DuplicateEntityType, got {:?}", r),
+        }
+        match composer.add_fragment(fragment(json!({
+            "": {
+                "commonTypes": { "Tag": { "type": "String" } },
+                "entityTypes": {},
+                "actions": { "view": {} }
+            }
+        }))) {
+            Err(SchemaError::DuplicateAction(name, fragments)) => {
+                assert_eq!(name, r#"Action::"view""#);
+                assert_eq!(
+                    fragments,
+                    Some(FragmentIndices {
+                        first: 1,
+                        duplicate: 2
+                    })
+                );
+            }
+            r => panic!("Expected DuplicateAction, got {:?}", r),
+        }
+        composer
+            .add_fragment(fragment(json!({
+                "": {
+                    "commonTypes": { "Tag": { "type": "String" } },
+                    "entityTypes": {},
+                    "actions": {}
+                }
+            })))
+            .expect("fragment should be added");
+        match composer.add_fragment(fragment(json!({
+            "": { "commonTypes": { "Tag": { "type": "Long" } }, "entityTypes": {}, "actions": {} }
+        }))) {
+            Err(SchemaError::DuplicateCommonType(name, fragments)) => {
+                assert_eq!(name, "Tag");
+                assert_eq!(
+                    fragments,
+                    Some(FragmentIndices {
+                        first: 2,
+                        duplicate: 3
+                    })
+                );
+            }
+            r => panic!("Expected DuplicateCommonType, got {:?}", r),
+        }
+        composer
+            .add_fragment(fragment(json!({
+                "": { "entityTypes": { "Photo": {} }, "actions": {} }
+            })))
+            .expect("rejected fragment should not have been added");
+        let schema = composer.finalize().expect("Expected valid schema");
+        assert_eq!(schema.entity_types().count(), 2);
+        assert!(schema
+            .get_action_id(&EntityUID::with_eid_and_type("Action", "view").unwrap())
+            .is_some());
+
+        let mut composer = SchemaComposer::new();
+        composer
+            .add_fragment(fragment(json!({
+                "": { "entityTypes": { "User": { "memberOfTypes": ["Group"] } }, "actions": {} }
+            })))
+            .expect("fragment should be added");
+        match composer.finalize() {
+            Err(SchemaError::UndeclaredEntityTypes(types)) => {
+                assert_eq!(types, HashSet::from(["Group".to_string()]))
+            }
+            r => panic!("Expected UndeclaredEntityTypes, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn namespaced_schema() {
+        let src = r#"
+        { "N::S": {
+            "entityTypes": {
+                "User": {},
+                "Photo": {}
+            },
+            "actions": {
+                "view_photo": {
+                    "appliesTo": {
+                        "principalTypes": ["User"],
+                        "resourceTypes": ["Photo"]
+                    }
+                }
+            }
+        } }
+        "#;
+        let schema_file: SchemaFragment = serde_json::from_str(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Namespaced schema failed to convert.");
+        dbg!(&schema);
+        let user_entity_type: &Name = &"N::S::User"
+            .parse()
+            .expect("Namespaced entity type should have parsed");
+        let photo_entity_type: &Name = &"N::S::Photo"
+            .parse()
+            .expect("Namespaced entity type should have parsed");
+        assert!(
+            schema.entity_types.contains_key(user_entity_type),
+            "Expected and entity type User."
+        );
+        assert!(
+            schema.entity_types.contains_key(photo_entity_type),
+            "Expected an entity type Photo."
+        );
+        assert_eq!(
+            schema.entity_types.len(),
+            2,
+            "Expected exactly 2 entity types."
+        );
+        assert!(
+            schema.action_ids.contains_key(
+                &"N::S::Action::\"view_photo\""
+                    .parse::<EntityUID>()
+                    .expect("Namespaced action should have parsed")
+            ),
+            "Expected an action \"view_photo\"."
+        );
+        assert_eq!(schema.action_ids.len(), 1, "Expected exactly 1 action.");
+
+        let apply_spec = &schema
+            .action_ids
+            .values()
+            .next()
+            .expect("Expected Action")
+            .applies_to;
+        assert_eq!(
+            apply_spec.applicable_principal_types().collect::<Vec<_>>(),
+            vec![&EntityType::Concrete(user_entity_type.clone())]
+        );
+        assert_eq!(
+            apply_spec.applicable_resource_types().collect::<Vec<_>>(),
+            vec![&EntityType::Concrete(photo_entity_type.clone())]
+        );
+    }
+
+    #[test]
+    fn cant_use_namespace_in_entity_type() {
+        let src = r#"
+        {
+            "entityTypes": { "NS::User": {} },
+            "actions": {}
+        }
+        "#;
+        let schema_file: NamespaceDefinition = serde_json::from_str(src).expect("Parse Error");
+        assert!(
+            matches!(TryInto::<ValidatorSchema>::try_into(schema_file), Err(SchemaError::ParseEntityType(..))),
+            "Expected that namespace in the entity type NS::User would cause a EntityType parse error.");
+    }
+
+    #[test]
+    fn entity_attribute_entity_type_with_namespace() {
+        let schema_json: SchemaFragment = serde_json::from_str(
+            r#"
+            {"A::B": {
+                "entityTypes": {
+                    "Foo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "Entity", "name": "C::D::Foo" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+              }}
+            "#,
+        )
+        .expect("Expected valid schema");
+
+        let schema: Result<ValidatorSchema> = schema_json.try_into();
+        match schema {
+            Err(SchemaError::UndeclaredEntityTypes(tys)) => {
+                assert_eq!(tys, HashSet::from(["C::D::Foo".to_string()]))
+            }
+            _ => panic!("Schema construction should have failed due to undeclared entity type."),
+        }
+    }
+
+    #[test]
+    fn entity_attribute_entity_type_with_declared_namespace() {
+        let schema_json: SchemaFragment = serde_json::from_str(
+            r#"
+            {"A::B": {
+                "entityTypes": {
+                    "Foo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "Entity", "name": "A::B::Foo" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+              }}
+            "#,
+        )
+        .expect("Expected valid schema");
+
+        let schema: ValidatorSchema = schema_json
+            .try_into()
+            .expect("Expected schema to construct without error.");
+
+        let foo_name: Name = "A::B::Foo".parse().expect("Expected entity type name");
+        let foo_type = schema
+            .entity_types
+            .get(&foo_name)
+            .expect("Expected to find entity");
+        let name_type = foo_type.attr_type("name").expect("Expected attribute name");
+        let expected_name_type = Type::named_entity_reference(foo_name);
+        assert_eq!(name_type, &expected_name_type);
+    }
+
+    #[test]
+    fn cannot_declare_action_type_when_prohibited() {
+        let schema_json: NamespaceDefinition = serde_json::from_str(
+            r#"
+            {
+                "entityTypes": { "Action": {} },
+                "actions": {}
+              }
+            "#,
+        )
+        .expect("Expected valid schema");
+
+        let schema: Result<ValidatorSchema> = schema_json.try_into();
+        assert!(matches!(schema, Err(SchemaError::ActionEntityTypeDeclared)));
+    }
+
+    #[test]
+    fn can_declare_other_type_when_action_type_prohibited() {
+        let schema_json: NamespaceDefinition = serde_json::from_str(
+            r#"
+            {
+                "entityTypes": { "Foo": { } },
+                "actions": {}
+              }
+            "#,
+        )
+        .expect("Expected valid schema");
+
+        TryInto::<ValidatorSchema>::try_into(schema_json).expect("Did not expect any errors.");
+    }
+
+    #[test]
+    fn cannot_declare_action_in_group_when_prohibited() {
+        let schema_json: SchemaFragment = serde_json::from_str(
+            r#"
+            {"": {
+                "entityTypes": {},
+                "actions": {
+                    "universe": { },
+                    "view_photo": {
+                        "attributes": {"id": "universe"}
+                    },
+                    "edit_photo": {
+                        "attributes": {"id": "universe"}
+                    },
+                    "delete_photo": {
+                        "attributes": {"id": "universe"}
+                    }
+                }
+              }}
+            "#,
+        )
+        .expect("Expected valid schema");
+
+        let schema = ValidatorSchemaFragment::from_schema_fragment(
+            schema_json,
+            ActionBehavior::ProhibitAttributes,
+        );
+        match schema {
+            Err(SchemaError::UnsupportedFeature(UnsupportedFeature::ActionAttributes(actions))) => {
+                assert_eq!(
+                    actions.into_iter().collect::<HashSet<_>>(),
+                    HashSet::from([
+                        "view_photo".to_string(),
+                        "edit_photo".to_string(),
+                        "delete_photo".to_string(),
+                    ])
+                )
+            }
+            _ => panic!("Did not see expected error."),
+        }
+    }
+
+    #[test]
+    fn test_entity_type_no_namespace() {
+        let src = json!({"type": "Entity", "name": "Foo"});
+        let schema_ty: SchemaType = serde_json::from_value(src).expect("Parse Error");
+        assert_eq!(
+            schema_ty,
+            SchemaType::Type(SchemaTypeVariant::Entity { name: "Foo".into() })
+        );
+        let ty: Type = ValidatorNamespaceDef::try_schema_type_into_validator_type(
+            Some(&Name::parse_unqualified_name("NS").expect("Expected namespace.")),
+            schema_ty,
+        )
+        .expect("Error converting schema type to type.")
+        .resolve_type_defs(&HashMap::new())
+        .unwrap();
+        assert_eq!(ty, Type::named_entity_reference_from_str("NS::Foo"));
+    }
+
+    #[test]
+    fn test_entity_type_namespace() {
+        let src = json!({"type": "Entity", "name": "NS::Foo"});
+        let schema_ty: SchemaType = serde_json::from_value(src).expect("Parse Error");
+        assert_eq!(
+            schema_ty,
+            SchemaType::Type(SchemaTypeVariant::Entity {
+                name: "NS::Foo".into()
+            })
+        );
+        let ty: Type = ValidatorNamespaceDef::try_schema_type_into_validator_type(
+            Some(&Name::parse_unqualified_name("NS").expect("Expected namespace.")),
+            schema_ty,
+        )
+        .expect("Error converting schema type to type.")
+        .resolve_type_defs(&HashMap::new())
+        .unwrap();
+        assert_eq!(ty, Type::named_entity_reference_from_str("NS::Foo"));
+    }
+
+    #[test]
+    fn test_entity_type_namespace_parse_error() {
+        let src = json!({"type": "Entity", "name": "::Foo"});
+        let schema_ty: SchemaType = serde_json::from_value(src).expect("Parse Error");
+        assert_eq!(
+            schema_ty,
+            SchemaType::Type(SchemaTypeVariant::Entity {
+                name: "::Foo".into()
+            })
+        );
+        match ValidatorNamespaceDef::try_schema_type_into_validator_type(
+            Some(&Name::parse_unqualified_name("NS").expect("Expected namespace.")),
+            schema_ty,
+        ) {
+            Err(SchemaError::ParseEntityType(..)) => (),
+            _ => panic!("Did not see expected entity type parse error."),
+        }
+    }
+
+    #[test]
+    fn schema_type_record_is_validator_type_record() {
+        let src = json!({"type": "Record", "attributes": {}});
+        let schema_ty: SchemaType = serde_json::from_value(src).expect("Parse Error");
+        assert_eq!(
+            schema_ty,
+            SchemaType::Type(SchemaTypeVariant::Record {
+                attributes: BTreeMap::new(),
+                additional_attributes: false,
+            }),
+        );
+        let ty: Type = ValidatorNamespaceDef::try_schema_type_into_validator_type(None, schema_ty)
+            .expect("Error converting schema type to type.")
+            .resolve_type_defs(&HashMap::new())
+            .unwrap();
+        assert_eq!(ty, Type::closed_record_with_attributes(None));
+    }
+
+    #[test]
+    fn get_namespaces() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "Foo::Bar::Baz": {
+                "entityTypes": {},
+                "actions": {}
+            },
+            "Foo": {
+                "entityTypes": {},
+                "actions": {}
+            },
+            "Bar": {
+                "entityTypes": {},
+                "actions": {}
+            },
+        }))
+        .unwrap();
+
+        let schema_fragment: ValidatorSchemaFragment = fragment.try_into().unwrap();
+        assert_eq!(
+            schema_fragment
+                .0
+                .iter()
+                .map(|f| f.namespace())
+                .collect::<HashSet<_>>(),
+            HashSet::from([
+                &Some("Foo::Bar::Baz".parse().unwrap()),
+                &Some("Foo".parse().unwrap()),
+                &Some("Bar".parse().unwrap())
+            ])
+        );
+    }
+
+    #[test]
+    fn schema_no_fragments() {
+        let schema = ValidatorSchema::from_schema_fragments([]).unwrap();
+        assert!(schema.entity_types.is_empty());
+        assert!(schema.action_ids.is_empty());
+    }
+
+    #[test]
+    fn same_action_different_namespace() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "Foo::Bar": {
+                "entityTypes": {},
+                "actions": {
+                    "Baz": {}
+                }
+            },
+            "Bar::Foo": {
+                "entityTypes": {},
+                "actions": {
+                    "Baz": { }
+                }
+            },
+            "Biz": {
+                "entityTypes": {},
+                "actions": {
+                    "Baz": { }
+                }
+            }
+        }))
+        .unwrap();
+
+        let schema: ValidatorSchema = fragment.try_into().unwrap();
+        assert!(schema
+            .get_action_id(&"Foo::Bar::Action::\"Baz\"".parse().unwrap())
+            .is_some());
+        assert!(schema
+            .get_action_id(&"Bar::Foo::Action::\"Baz\"".parse().unwrap())
+            .is_some());
+        assert!(schema
+            .get_action_id(&"Biz::Action::\"Baz\"".parse().unwrap())
+            .is_some());
+    }
+
+    #[test]
+    fn same_type_different_namespace() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "Foo::Bar": {
+                "entityTypes": {"Baz" : {}},
+                "actions": { }
+            },
+            "Bar::Foo": {
+                "entityTypes": {"Baz" : {}},
+                "actions": { }
+            },
+            "Biz": {
+                "entityTypes": {"Baz" : {}},
+                "actions": { }
+            }
+        }))
+        .unwrap();
+        let schema: ValidatorSchema = fragment.try_into().unwrap();
+
+        assert!(schema
+            .get_entity_type(&"Foo::Bar::Baz".parse().unwrap())
+            .is_some());
+        assert!(schema
+            .get_entity_type(&"Bar::Foo::Baz".parse().unwrap())
+            .is_some());
+        assert!(schema
+            .get_entity_type(&"Biz::Baz".parse().unwrap())
+            .is_some());
+    }
+
+    #[test]
+    fn basename_map() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "Foo::Bar": {
+                "entityTypes": {"Baz" : {}},
+                "actions": { }
+            },
+            "Bar::Foo": {
+                "entityTypes": {"Baz" : {}},
+                "actions": { }
+            },
+            "Biz": {
+                "entityTypes": {"Baz" : {}, "Qux": {}},
+                "actions": { }
+            }
+        }))
+        .unwrap();
+        let map = schema.basename_map();
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            map.get(&"Baz".parse::<Id>().unwrap())
+                .map(|names| names.iter().map(ToString::to_string).collect::<Vec<_>>()),
+            Some(vec![
+                "Bar::Foo::Baz".to_string(),
+                "Biz::Baz".to_string(),
+                "Foo::Bar::Baz".to_string()
+            ])
+        );
+        assert_eq!(
+            map.get(&"Qux".parse::<Id>().unwrap()),
+            Some(&vec!["Biz::Qux".parse::<Name>().unwrap()])
+        );
+    }
+
+    #[test]
+    fn member_of_different_namespace() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "Bar": {
+                "entityTypes": {
+                    "Baz": {
+                        "memberOfTypes": ["Foo::Buz"]
+                    }
+                },
+                "actions": {}
+            },
+            "Foo": {
+                "entityTypes": { "Buz": {} },
+                "actions": { }
+            }
+        }))
+        .unwrap();
+        let schema: ValidatorSchema = fragment.try_into().unwrap();
+
+        let buz = schema
+            .get_entity_type(&"Foo::Buz".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            buz.descendants,
+            HashSet::from(["Bar::Baz".parse().unwrap()])
+        );
+    }
+
+    #[test]
+    fn attribute_different_namespace() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "Bar": {
+                "entityTypes": {
+                    "Baz": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "fiz": {
+                                    "type": "Entity",
+                                    "name": "Foo::Buz"
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            },
+            "Foo": {
+                "entityTypes": { "Buz": {} },
+                "actions": { }
+            }
+        }))
+        .unwrap();
+
+        let schema: ValidatorSchema = fragment.try_into().unwrap();
+        let baz = schema
+            .get_entity_type(&"Bar::Baz".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            baz.attr_type("fiz"),
+            Some(&Type::named_entity_reference_from_str("Foo::Buz")),
+        );
+    }
+
+    #[test]
+    fn applies_to_different_namespace() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "Foo::Bar": {
+                "entityTypes": { },
+                "actions": {
+                    "Baz": {
+                        "appliesTo": {
+                            "principalTypes": [ "Fiz::Buz" ],
+                            "resourceTypes": [ "Fiz::Baz" ],
+                        }
+                    }
+                }
+            },
+            "Fiz": {
+                "entityTypes": {
+                    "Buz": {},
+                    "Baz": {}
+                },
+                "actions": { }
+            }
+        }))
+        .unwrap();
+        let schema: ValidatorSchema = fragment.try_into().unwrap();
+
+        let baz = schema
+            .get_action_id(&"Foo::Bar::Action::\"Baz\"".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            baz.applies_to
+                .applicable_principal_types()
+                .collect::<HashSet<_>>(),
+            HashSet::from([&EntityType::Concrete("Fiz::Buz".parse().unwrap())])
+        );
+        assert_eq!(
+            baz.applies_to
+                .applicable_resource_types()
+                .collect::<HashSet<_>>(),
+            HashSet::from([&EntityType::Concrete("Fiz::Baz".parse().unwrap())])
+        );
+    }
+
+    #[test]
+    fn simple_defined_type() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "commonTypes": {
+                    "MyLong": {"type": "Long"}
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "a": {"type": "MyLong"}
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        let schema: ValidatorSchema = fragment.try_into().unwrap();
+        assert_eq!(
+            schema.entity_types.iter().next().unwrap().1.attributes,
+            Attributes::with_required_attributes([("a".into(), Type::primitive_long())])
+        );
+    }
+
+    #[test]
+    fn defined_record_as_attrs() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "commonTypes": {
+                    "MyRecord": {
+                        "type": "Record",
+                        "attributes":  {
+                            "a": {"type": "Long"}
+                        }
+                    }
+                },
+                "entityTypes": {
+                    "User": { "shape": { "type": "MyRecord", } }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        let schema: ValidatorSchema = fragment.try_into().unwrap();
+        assert_eq!(
+            schema.entity_types.iter().next().unwrap().1.attributes,
+            Attributes::with_required_attributes([("a".into(), Type::primitive_long())])
+        );
+    }
+
+    #[test]
+    fn cross_namespace_type() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "A": {
+                "commonTypes": {
+                    "MyLong": {"type": "Long"}
+                },
+                "entityTypes": { },
+                "actions": {}
+            },
+            "B": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "a": {"type": "A::MyLong"}
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        let schema: ValidatorSchema = fragment.try_into().unwrap();
+        assert_eq!(
+            schema.entity_types.iter().next().unwrap().1.attributes,
+            Attributes::with_required_attributes([("a".into(), Type::primitive_long())])
+        );
+    }
+
+    #[test]
+    fn relative_common_type_falls_back_to_root() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "commonTypes": { "MyLong": { "type": "Long" } },
+                "entityTypes": {},
+                "actions": {}
+            },
+            "A": {
+                "commonTypes": { "MyString": { "type": "String" } },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "a": { "type": "MyLong" },
+                                "b": { "type": "MyString" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Expected valid schema.");
+        assert_eq!(
+            schema
+                .get_entity_type(&"A::User".parse().unwrap())
+                .unwrap()
+                .attributes,
+            Attributes::with_required_attributes([
+                ("a".into(), Type::primitive_long()),
+                ("b".into(), Type::primitive_string())
+            ])
+        );
+    }
+
+    #[test]
+    fn ambiguous_common_type() {
+        let schema_with_attr_type = |attr_type: &str| {
+            ValidatorSchema::from_json_value(json!({
+                "": {
+                    "commonTypes": { "MyLong": { "type": "Long" } },
+                    "entityTypes": {},
+                    "actions": {}
+                },
+                "A": {
+                    "commonTypes": { "MyLong": { "type": "String" } },
+                    "entityTypes": {
+                        "User": {
+                            "shape": {
+                                "type": "Record",
+                                "attributes": { "a": { "type": attr_type } }
+                            }
+                        }
+                    },
+                    "actions": {}
+                }
+            }))
+        };
+        match schema_with_attr_type("MyLong") {
+            Err(SchemaError::AmbiguousCommonType { reference, local }) => {
+                assert_eq!(reference, "MyLong");
+                assert_eq!(local, "A::MyLong");
+            }
+            r => panic!("Expected AmbiguousCommonType, got {:?}", r),
+        }
+        let schema = schema_with_attr_type("A::MyLong").expect("Expected valid schema.");
+        assert_eq!(
+            schema
+                .get_entity_type(&"A::User".parse().unwrap())
+                .unwrap()
+                .attr_type("a"),
+            Some(&Type::primitive_string())
+        );
+
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "commonTypes": { "MyLong": { "type": "Long" } },
+                "entityTypes": {},
+                "actions": {}
+            },
+            "A": {
+                "commonTypes": { "MyLong": { "type": "String" } },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "a": { "type": "MyLong" } }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        assert!(matches!(
+            fragment.canonicalize(),
+            Err(SchemaError::AmbiguousCommonType { .. })
+        ));
+    }
+
+    #[test]
+    fn cross_fragment_type() {
+        let fragment1: ValidatorSchemaFragment = serde_json::from_value::<SchemaFragment>(json!({
+            "A": {
+                "commonTypes": {
+                    "MyLong": {"type": "Long"}
+                },
+                "entityTypes": { },
+                "actions": {}
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let fragment2: ValidatorSchemaFragment = serde_json::from_value::<SchemaFragment>(json!({
+            "A": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "a": {"type": "MyLong"}
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let schema = ValidatorSchema::from_schema_fragments([fragment1, fragment2]).unwrap();
+
+        assert_eq!(
+            schema.entity_types.iter().next().unwrap().1.attributes,
+            Attributes::with_required_attributes([("a".into(), Type::primitive_long())])
+        );
+    }
+
+    #[test]
+    fn from_files_cross_file_type() {
+        let file1 = r#"{ "A": { "commonTypes": { "MyLong": { "type": "Long" } }, "entityTypes": {}, "actions": {} } }"#;
+        let file2 = r#"{ "A": { "entityTypes": { "User": { "shape": { "type": "Record", "attributes": { "a": { "type": "MyLong" } } } } }, "actions": {} } }"#;
+        let schema = ValidatorSchema::from_files([file1.as_bytes(), file2.as_bytes()])
+            .expect("Schema should construct");
+        assert_eq!(
+            schema.entity_types.iter().next().unwrap().1.attributes,
+            Attributes::with_required_attributes([("a".into(), Type::primitive_long())])
+        );
+    }
+
+    #[test]
+    fn from_files_error_index() {
+        let good = r#"{ "A": { "entityTypes": {}, "actions": {} } }"#;
+        let bad_json = r#"{ "B": { "entityTypes": {}, "#;
+        match ValidatorSchema::from_files([good.as_bytes(), bad_json.as_bytes()]) {
+            Err(SchemaError::InInput(1, err)) => {
+                assert!(matches!(*err, SchemaError::Serde(_)))
+            }
+            s => panic!("Expected InInput error, got {:?}", s),
+        }
+
+        let bad_name = r#"{ "B": { "entityTypes": { "User // comment": {} }, "actions": {} } }"#;
+        match ValidatorSchema::from_files([bad_name.as_bytes(), good.as_bytes()]) {
+            Err(SchemaError::InInput(0, err)) => {
+                assert!(matches!(*err, SchemaError::ParseEntityType(..)))
+            }
+            s => panic!("Expected InInput error, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn from_file_streaming() {
+        let src = r#"{
+            "A": {
+                "commonTypes": { "MyLong": { "type": "Long" } },
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": { "type": "Record", "attributes": { "a": { "type": "MyLong" } } }
+                    },
+                    "Group": {}
+                },
+                "actions": { "view": { "appliesTo": { "principalTypes": ["User"] } } }
+            },
+            "B": { "entityTypes": { "Photo": {} }, "actions": {} }
+        }"#;
+        let streamed =
+            ValidatorSchema::from_file_streaming(src.as_bytes()).expect("Schema should construct");
+        let expected = ValidatorSchema::from_file(src.as_bytes()).expect("Schema should construct");
+        assert_eq!(
+            streamed.entity_types.keys().collect::<HashSet<_>>(),
+            expected.entity_types.keys().collect::<HashSet<_>>()
+        );
+        assert_eq!(
+            streamed.action_ids.keys().collect::<HashSet<_>>(),
+            expected.action_ids.keys().collect::<HashSet<_>>()
+        );
+        assert_eq!(
+            streamed
+                .get_entity_type(&"A::User".parse().unwrap())
+                .unwrap()
+                .attributes,
+            Attributes::with_required_attributes([("a".into(), Type::primitive_long())])
+        );
+
+        let with_bom = format!("\u{feff}{src}");
+        assert!(ValidatorSchema::from_file_streaming(with_bom.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn from_file_streaming_errors() {
+        let bad_name = r#"{ "B": { "entityTypes": { "User // comment": {} }, "actions": {} } }"#;
+        assert!(matches!(
+            ValidatorSchema::from_file_streaming(bad_name.as_bytes()),
+            Err(SchemaError::ParseEntityType(..))
+        ));
+
+        let duplicate_namespace = r#"{
+            "A": { "entityTypes": {}, "actions": {} },
+            "A": { "entityTypes": {}, "actions": {} }
+        }"#;
+        match ValidatorSchema::from_file_streaming(duplicate_namespace.as_bytes()) {
+            Err(SchemaError::Serde(err)) => {
+                assert!(err.to_string().contains("duplicate key"), "{err}")
+            }
+            s => panic!("Expected Serde error, got {:?}", s),
+        }
+
+        for src in [
+            r#"{ "A": { "entityTypes": {}, "#,
+            r#"{ "A": { "entityTypes": {}, "actions": {} } } trailing"#,
+            r#"[]"#,
+        ] {
+            assert!(
+                matches!(
+                    ValidatorSchema::from_file_streaming(src.as_bytes()),
+                    Err(SchemaError::Serde(_))
+                ),
+                "expected parse error for {src}"
+            );
+        }
+    }
+
+    #[test]
+    fn cross_fragment_duplicate_type() {
+        let fragment1: ValidatorSchemaFragment = serde_json::from_value::<SchemaFragment>(json!({
+            "A": {
+                "commonTypes": {
+                    "MyLong": {"type": "Long"}
+                },
+                "entityTypes": {},
+                "actions": {}
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let fragment2: ValidatorSchemaFragment = serde_json::from_value::<SchemaFragment>(json!({
+            "A": {
+                "commonTypes": {
+                    "MyLong": {"type": "Long"}
+                },
+                "entityTypes": {},
+                "actions": {}
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+
+        let schema = ValidatorSchema::from_schema_fragments([fragment1, fragment2]);
+
+        match schema {
+            Err(e @ SchemaError::DuplicateCommonType(..)) => {
+                assert_eq!(
+                    e.to_string(),
+                    "duplicate common type `A::MyLong`: declared in fragment 0 and fragment 1"
+                );
+                assert_eq!(e.source_fragment_index(), Some(1));
+            }
+            _ => panic!("should have errored because schema fragments have duplicate types"),
+        };
+    }
+
+    #[test]
+    fn undeclared_type_in_attr() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "commonTypes": { },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "a": {"type": "MyLong"}
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        match TryInto::<ValidatorSchema>::try_into(fragment) {
+            Err(e @ SchemaError::UndeclaredCommonTypes(_)) => {
+                assert_eq!(
+                    e.to_string(),
+                    "undeclared common type(s): {\"MyLong\"}; `MyLong` is used by attribute `a` in shape of entity type `User`"
+                );
+                let SchemaError::UndeclaredCommonTypes(names) = e else {
+                    unreachable!()
+                };
+                assert_eq!(*names, HashSet::from(["MyLong".to_string()]));
+                assert_eq!(
+                    names.references(),
+                    &BTreeMap::from([(
+                        "MyLong".to_string(),
+                        vec![CommonTypeReference {
+                            location: SchemaLocation {
+                                namespace: None,
+                                section: Some(SchemaSection::EntityTypeShape("User".into())),
+                            },
+                            attr_path: vec!["a".into()],
+                        }]
+                    )])
+                );
+            }
+            s => panic!(
+                "Expected Err(SchemaError::UndeclaredCommonType), got {:?}",
+                s
+            ),
+        }
+    }
+
+    #[test]
+    fn undeclared_type_in_nested_context_attr() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "NS": {
+                "entityTypes": {},
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "outer": {
+                                        "type": "Record",
+                                        "attributes": { "inner": { "type": "MyLong" } }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        match TryInto::<ValidatorSchema>::try_into(fragment) {
+            Err(e @ SchemaError::UndeclaredCommonTypes(_)) => assert_eq!(
+                e.to_string(),
+                "undeclared common type(s): {\"MyLong\"}; `MyLong` is used by attribute `outer.inner` in namespace `NS`, in `appliesTo` of action `view`"
+            ),
+            s => panic!(
+                "Expected Err(SchemaError::UndeclaredCommonType), got {:?}",
+                s
+            ),
+        }
+    }
+
+    #[test]
+    fn undeclared_type_in_type_def() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "commonTypes": {
+                    "a": { "type": "b" }
+                },
+                "entityTypes": { },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        match TryInto::<ValidatorSchema>::try_into(fragment) {
+            Err(e @ SchemaError::UndeclaredCommonTypes(_)) => assert_eq!(
+                e.to_string(),
+                "undeclared common type(s): {\"b\"}; `b` is used in common type `a`"
+            ),
+            s => panic!(
+                "Expected Err(SchemaError::UndeclaredCommonType), got {:?}",
+                s
+            ),
+        }
+    }
+
+    #[test]
+    fn shape_not_record() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "commonTypes": {
+                    "MyLong": { "type": "Long" }
+                },
+                "entityTypes": {
+                    "User": {
+                        "shape": { "type": "MyLong" }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        match TryInto::<ValidatorSchema>::try_into(fragment) {
+            Err(SchemaError::ContextOrShapeNotRecord(_)) => (),
+            s => panic!(
+                "Expected Err(SchemaError::ContextOrShapeNotRecord), got {:?}",
+                s
+            ),
+        }
+    }
+
+    #[test]
+    fn attribute_defaults() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "active": { "type": "Boolean", "required": false, "default": true },
+                                "name": { "type": "String" },
+                                "manager": {
+                                    "type": "Entity",
+                                    "name": "User",
+                                    "required": false,
+                                    "default": { "type": "User", "id": "root" }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .expect("Expected valid schema");
+        let user = schema
+            .get_entity_type(&"User".parse().unwrap())
+            .expect("User should be declared");
+        assert_eq!(
+            user.attr_default("active"),
+            Some(&RestrictedExpr::val(true))
+        );
+        assert_eq!(
+            user.attr_default("manager"),
+            Some(&RestrictedExpr::val(
+                EntityUID::from_str(r#"User::"root""#).unwrap()
+            ))
+        );
+        assert_eq!(user.attr_default("name"), None);
+
+        // absent optional attributes take their default when parsing entities
+        let core_schema = CoreSchema::new(&schema);
+        let eparser = cedar_policy_core::entities::EntityJsonParser::new(
+            Some(&core_schema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let entities = eparser
+            .from_json_value(json!([
+                { "uid": { "type": "User", "id": "alice" }, "attrs": { "name": "Alice" }, "parents": [] },
+                { "uid": { "type": "User", "id": "bob" }, "attrs": { "name": "Bob", "active": false }, "parents": [] }
+            ]))
+            .expect("Expected valid entities");
+        let alice = entities
+            .entity(&EntityUID::from_str(r#"User::"alice""#).unwrap())
+            .unwrap();
+        assert_eq!(
+            alice.get("active").map(ToString::to_string),
+            Some("true".to_string())
+        );
+        assert_eq!(
+            alice.get("manager").map(ToString::to_string),
+            Some(r#"User::"root""#.to_string())
+        );
+        let bob = entities
+            .entity(&EntityUID::from_str(r#"User::"bob""#).unwrap())
+            .unwrap();
+        assert_eq!(
+            bob.get("active").map(ToString::to_string),
+            Some("false".to_string())
+        );
+    }
+
+    #[test]
+    fn attribute_default_type_mismatch() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "active": { "type": "Boolean", "required": false, "default": "yes" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        match TryInto::<ValidatorSchema>::try_into(fragment) {
+            Err(SchemaError::InvalidAttributeDefault(ty, attr, _)) => {
+                assert_eq!(ty.to_string(), "User");
+                assert_eq!(attr, "active");
+            }
+            s => panic!(
+                "Expected Err(SchemaError::InvalidAttributeDefault), got {:?}",
+                s
+            ),
+        }
+    }
+
+    #[test]
+    fn attribute_default_on_required_attribute() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "active": { "type": "Boolean", "default": true }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        match TryInto::<ValidatorSchema>::try_into(fragment) {
+            Err(SchemaError::InvalidAttributeDefault(_, attr, _)) => assert_eq!(attr, "active"),
+            s => panic!(
+                "Expected Err(SchemaError::InvalidAttributeDefault), got {:?}",
+                s
+            ),
+        }
+    }
+
+    #[test]
+    fn attribute_default_in_context() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "entityTypes": {},
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "ip": { "type": "String", "required": false, "default": "" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        match TryInto::<ValidatorSchema>::try_into(fragment) {
+            Err(SchemaError::UnsupportedFeature(
+                UnsupportedFeature::NonEntityAttributeDefaults(attrs),
+            )) => assert_eq!(attrs, vec!["ip".to_string()]),
+            s => panic!("Expected Err(SchemaError::UnsupportedFeature), got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn actions_applicable_to_principal_and_resource() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Group"] },
+                    "Group": {},
+                    "Photo": { "memberOfTypes": ["Album"] },
+                    "Album": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["Group"],
+                            "resourceTypes": ["Photo"]
+                        }
+                    },
+                    "edit": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Album"]
+                        }
+                    },
+                    "unspecified": {}
+                }
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .expect("Expected valid schema");
+
+        let applicable_to_principal = |ty: &str| {
+            let ty: Name = ty.parse().unwrap();
+            schema
+                .actions_applicable_to_principal(&ty)
+                .map(ToString::to_string)
+                .collect::<HashSet<_>>()
+        };
+        let applicable_to_resource = |ty: &str| {
+            let ty: Name = ty.parse().unwrap();
+            schema
+                .actions_applicable_to_resource(&ty)
+                .map(ToString::to_string)
+                .collect::<HashSet<_>>()
+        };
+
+        assert_eq!(
+            applicable_to_principal("User"),
+            HashSet::from([
+                r#"Action::"view""#.to_string(),
+                r#"Action::"edit""#.to_string()
+            ])
+        );
+        assert_eq!(
+            applicable_to_principal("Group"),
+            HashSet::from([r#"Action::"view""#.to_string()])
+        );
+        assert_eq!(applicable_to_principal("Photo"), HashSet::new());
+        assert_eq!(
+            applicable_to_resource("Photo"),
+            HashSet::from([
+                r#"Action::"view""#.to_string(),
+                r#"Action::"edit""#.to_string()
+            ])
+        );
+        assert_eq!(
+            applicable_to_resource("Album"),
+            HashSet::from([r#"Action::"edit""#.to_string()])
+        );
+    }
+
+    #[test]
+    fn schema_query_descendants_of() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Team"] },
+                    "Team": { "memberOfTypes": ["Org"] },
+                    "Org": {}
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .expect("Expected valid schema");
+        // Query through the trait object to check that it is object safe
+        let query: &dyn SchemaQuery = &schema;
+        let descendants = query
+            .descendants_of(&"Org".parse().unwrap())
+            .expect("Org should be declared")
+            .map(ToString::to_string)
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            descendants,
+            HashSet::from(["User".to_string(), "Team".to_string()])
+        );
+        assert!(query.descendants_of(&"Photo".parse().unwrap()).is_none());
+        assert_eq!(query.entity_types().count(), 3);
+        assert_eq!(query.action_ids().count(), 0);
+    }
+
+    #[test]
+    fn entity_types_topo_sorted() {
+        let src = json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "manager": { "type": "Entity", "name": "User" },
+                                "address": { "type": "Entity", "name": "Address" },
+                                "team": { "type": "Entity", "name": "Team" }
+                            }
+                        }
+                    },
+                    "Team": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "lead": { "type": "Entity", "name": "User" }
+                            }
+                        }
+                    },
+                    "Group": {},
+                    "Address": {}
+                },
+                "actions": {}
+            }
+        });
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Schema should construct without error.");
+        // `User` and `Team` reference each other, so the cycle is broken at
+        // `Team`, the lesser name.
+        assert_eq!(
+            schema
+                .entity_types_topo_sorted()
+                .expect("memberOf relation is acyclic")
+                .into_iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["Address", "Group", "Team", "User"]
+        );
+    }
+
+    #[test]
+    fn entity_types_topo_sorted_member_of_cycle() {
+        let src = json!({
+            "": {
+                "entityTypes": {
+                    "A": { "memberOfTypes": ["B"] },
+                    "B": { "memberOfTypes": ["A"] }
+                },
+                "actions": {}
+            }
+        });
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Schema should construct without error.");
+        match schema.entity_types_topo_sorted() {
+            Err(SchemaError::CycleInEntityHierarchy) => (),
+            s => panic!("Expected CycleInEntityHierarchy, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn recursive_entity_types() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {
+                    "Comment": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "parent": { "type": "Entity", "name": "Comment", "required": false },
+                                "author": { "type": "Entity", "name": "User" }
+                            }
+                        }
+                    },
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "team": { "type": "Entity", "name": "Team" }
+                            }
+                        }
+                    },
+                    "Team": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "info": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "members": {
+                                            "type": "Set",
+                                            "element": { "type": "Entity", "name": "User" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "Photo": { "memberOfTypes": ["Album"] },
+                    "Album": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "cover": { "type": "Entity", "name": "Photo" }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .expect("Expected valid schema");
+        let mut recursive = schema
+            .recursive_entity_types()
+            .into_iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        recursive.sort();
+        // `Album` only refers to `Photo`; `memberOf` is not an attribute.
+        assert_eq!(recursive, ["Comment", "Team", "User"]);
+    }
+
+    #[test]
+    fn entity_type_union_attribute() {
+        let src = json!({
+            "": {
+                "entityTypes": {
+                    "User": {},
+                    "Group": {},
+                    "Photo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "owner": { "type": "Entity", "names": ["User", "Group"] }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        });
+        let fragment: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let serialized = serde_json::to_value(&fragment).expect("Serialize Error");
+        assert_eq!(
+            serialized[""]["entityTypes"]["Photo"]["shape"]["attributes"]["owner"],
+            json!({ "type": "Entity", "names": ["User", "Group"], "required": true })
+        );
+        let schema: ValidatorSchema = fragment.try_into().expect("Expected valid schema");
+        assert_eq!(
+            schema
+                .get_entity_type(&"Photo".parse().unwrap())
+                .and_then(|ety| ety.attr("owner"))
+                .map(|attr| attr.attr_type.clone()),
+            Type::entity_reference_union(["User".parse().unwrap(), "Group".parse().unwrap()])
+        );
+
+        let core_schema = CoreSchema::new(&schema);
+        let eparser = cedar_policy_core::entities::EntityJsonParser::new(
+            Some(&core_schema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let photo_owned_by = |ty: &str| {
+            json!([
+                { "uid": { "type": "Photo", "id": "p" }, "attrs": { "owner": { "type": ty, "id": "o" } }, "parents": [] }
+            ])
+        };
+        eparser
+            .from_json_value(photo_owned_by("User"))
+            .expect("a User may own a photo");
+        eparser
+            .from_json_value(photo_owned_by("Group"))
+            .expect("a Group may own a photo");
+        match eparser.from_json_value(photo_owned_by("Photo")) {
+            Err(cedar_policy_core::entities::EntitiesError::InvalidEntity(
+                cedar_policy_core::entities::EntitySchemaConformanceError::TypeMismatch {
+                    attr,
+                    ..
+                },
+            )) => assert_eq!(attr, "owner"),
+            r => panic!("Expected TypeMismatch, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn entity_type_union_errors() {
+        let schema_with_owner = |owner: serde_json::Value| {
+            serde_json::from_value::<SchemaFragment>(json!({
+                "": {
+                    "entityTypes": {
+                        "User": {},
+                        "Photo": {
+                            "shape": {
+                                "type": "Record",
+                                "attributes": { "owner": owner }
+                            }
+                        }
+                    },
+                    "actions": {}
+                }
+            }))
+        };
+        assert!(schema_with_owner(json!({ "type": "Entity", "names": [] })).is_err());
+        assert!(
+            schema_with_owner(json!({ "type": "Entity", "name": "User", "names": ["User"] }))
+                .is_err()
+        );
+        assert!(schema_with_owner(json!({ "type": "Set", "names": ["User"] })).is_err());
+        let fragment = schema_with_owner(json!({ "type": "Entity", "names": ["User", "Group"] }))
+            .expect("Parse Error");
+        match ValidatorSchema::try_from(fragment) {
+            Err(SchemaError::UndeclaredEntityTypes(types)) => {
+                assert_eq!(types, HashSet::from(["Group".to_string()]))
+            }
+            r => panic!("Expected UndeclaredEntityTypes, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn self_referential_attribute() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "Folder": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "parent": { "type": "Entity", "name": "Folder", "required": false },
+                                "links": {
+                                    "type": "Set",
+                                    "element": {
+                                        "type": "Record",
+                                        "attributes": {
+                                            "target": { "type": "Entity", "name": "Folder" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("self-referential attributes are legal");
+        let folder: Name = "Folder".parse().unwrap();
+        assert_eq!(
+            schema
+                .get_entity_type(&folder)
+                .and_then(|ety| ety.attr("parent"))
+                .map(|attr| attr.attr_type.clone()),
+            Some(Type::named_entity_reference(folder.clone()))
+        );
+        assert_eq!(schema.recursive_entity_types(), HashSet::from([folder]));
+        assert!(schema
+            .to_dot()
+            .contains(r#""Folder" -> "Folder" [style=dashed, label="parent"];"#));
+        assert!(!schema.validate().has_errors());
+
+        let core_schema = CoreSchema::new(&schema);
+        let eparser = cedar_policy_core::entities::EntityJsonParser::new(
+            Some(&core_schema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        eparser
+            .from_json_value(json!([
+                { "uid": { "type": "Folder", "id": "a" }, "attrs": { "parent": { "type": "Folder", "id": "b" }, "links": [] }, "parents": [] },
+                { "uid": { "type": "Folder", "id": "b" }, "attrs": { "parent": { "type": "Folder", "id": "a" }, "links": [{ "target": { "type": "Folder", "id": "b" } }] }, "parents": [] }
+            ]))
+            .expect("entities referring to each other through attributes conform");
+    }
+
+    #[test]
+    fn mutually_referential_attributes() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "NS": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "team": { "type": "Entity", "name": "Team" }
+                            }
+                        }
+                    },
+                    "Team": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "lead": { "type": "Entity", "name": "User" },
+                                "members": {
+                                    "type": "Set",
+                                    "element": { "type": "Entity", "name": "NS::User" }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("mutually referential attributes are legal");
+        let mut recursive = schema
+            .recursive_entity_types()
+            .into_iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        recursive.sort();
+        assert_eq!(recursive, ["NS::Team", "NS::User"]);
+        assert_eq!(
+            schema
+                .type_of_path(&"NS::Team".parse().unwrap(), &["lead"])
+                .ok(),
+            Some(&Type::named_entity_reference("NS::User".parse().unwrap()))
+        );
+        assert!(!schema.validate().has_errors());
+    }
+
+    fn resource_types_for(schema: &ValidatorSchema, action: &str) -> HashSet<String> {
+        schema
+            .get_action_id(&EntityUID::with_eid_and_type("Action", action).unwrap())
+            .expect("action should be declared")
+            .applies_to
+            .applicable_resource_types()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn validate_entities() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" }
+                            }
+                        }
+                    },
+                    "Group": {}
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let eparser = cedar_policy_core::entities::EntityJsonParser::new(
+            None::<&cedar_policy_core::entities::NoEntitiesSchema>,
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let entities = eparser
+            .from_json_value(json!([
+                {
+                    "uid": { "type": "User", "id": "alice" },
+                    "attrs": { "name": "Alice" },
+                    "parents": [{ "type": "Group", "id": "admins" }]
+                },
+                { "uid": { "type": "Group", "id": "admins" }, "attrs": {}, "parents": [] }
+            ]))
+            .expect("entities should parse");
+        assert!(schema.validate_entities(&entities).is_ok());
+
+        let entities = eparser
+            .from_json_value(json!([
+                { "uid": { "type": "User", "id": "bob" }, "attrs": {}, "parents": [] },
+                {
+                    "uid": { "type": "User", "id": "carol" },
+                    "attrs": { "name": 7 },
+                    "parents": []
+                },
+                {
+                    "uid": { "type": "Group", "id": "admins" },
+                    "attrs": {},
+                    "parents": [{ "type": "User", "id": "bob" }]
+                },
+                { "uid": { "type": "Photo", "id": "p" }, "attrs": {}, "parents": [] }
+            ]))
+            .expect("entities should parse");
+        let mut errs = schema
+            .validate_entities(&entities)
+            .expect_err("entities should not conform")
+            .into_iter()
+            .map(|err| match err {
+                EntitySchemaConformanceError::MissingRequiredEntityAttr { uid, .. } => {
+                    format!("missing {uid}")
+                }
+                EntitySchemaConformanceError::TypeMismatch { uid, .. } => {
+                    format!("mismatch {uid}")
+                }
+                EntitySchemaConformanceError::InvalidAncestorType { uid, .. } => {
+                    format!("ancestor {uid}")
+                }
+                EntitySchemaConformanceError::UnexpectedEntityType { uid, .. } => {
+                    format!("type {uid}")
+                }
+                err => panic!("Unexpected error {:?}", err),
+            })
+            .collect::<Vec<_>>();
+        errs.sort();
+        assert_eq!(
+            errs,
+            [
+                r#"ancestor Group::"admins""#,
+                r#"mismatch User::"carol""#,
+                r#"missing User::"bob""#,
+                r#"type Photo::"p""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn sensitive_attributes() {
+        let src = json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "ssn": { "type": "String", "sensitive": true },
+                                "passwordHash": { "type": "String", "required": false, "sensitive": true },
+                                "profile": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "phone": { "type": "String", "sensitive": true },
+                                        "city": { "type": "String" }
+                                    }
+                                },
+                                "contacts": {
+                                    "type": "Set",
+                                    "element": {
+                                        "type": "Record",
+                                        "attributes": {
+                                            "email": { "type": "String", "sensitive": true }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "Photo": {}
+                },
+                "actions": {}
+            }
+        });
+        let fragment: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let serialized = serde_json::to_value(&fragment).expect("Serialize Error");
+        let attributes = &serialized[""]["entityTypes"]["User"]["shape"]["attributes"];
+        assert_eq!(attributes["ssn"]["sensitive"], json!(true));
+        assert!(attributes["name"].get("sensitive").is_none());
+        let schema: ValidatorSchema = fragment.try_into().expect("Expected valid schema");
+        assert_eq!(
+            schema
+                .sensitive_attributes()
+                .into_iter()
+                .map(|(ty, path)| format!("{ty}.{}", path.join(".")))
+                .collect::<Vec<_>>(),
+            [
+                "User.contacts.email",
+                "User.passwordHash",
+                "User.profile.phone",
+                "User.ssn"
+            ]
+        );
+
+        let core_schema = CoreSchema::new(&schema);
+        let eparser = cedar_policy_core::entities::EntityJsonParser::new(
+            Some(&core_schema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let entities = eparser
+            .from_json_value(json!([
+                {
+                    "uid": { "type": "User", "id": "alice" },
+                    "attrs": {
+                        "name": "Alice",
+                        "ssn": "123-45-6789",
+                        "profile": { "phone": "555-0100", "city": "Springfield" },
+                        "contacts": [{ "email": "bob@example.com" }]
+                    },
+                    "parents": []
+                }
+            ]))
+            .expect("entities should conform");
+        let redacted = schema
+            .entities_to_json_redacted(&entities)
+            .expect("entities should serialize");
+        let attrs = &redacted[0]["attrs"];
+        assert_eq!(attrs["name"], json!("Alice"));
+        assert_eq!(
+            attrs["ssn"],
+            json!(cedar_policy_core::entities::REDACTED_ATTRIBUTE_VALUE)
+        );
+        assert!(attrs.get("passwordHash").is_none());
+        assert_eq!(
+            attrs["profile"]["phone"],
+            json!(cedar_policy_core::entities::REDACTED_ATTRIBUTE_VALUE)
+        );
+        assert_eq!(attrs["profile"]["city"], json!("Springfield"));
+        assert_eq!(
+            attrs["contacts"][0]["email"],
+            json!(cedar_policy_core::entities::REDACTED_ATTRIBUTE_VALUE)
+        );
+    }
+
+    #[test]
+    fn extension_typed_attributes() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "NS": {
+                "entityTypes": {
+                    "Host": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "ip": { "type": "Extension", "name": "ipaddr" },
+                                "name": { "type": "String" },
+                                "nested": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "ip": { "type": "Extension", "name": "ipaddr" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "Account": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "balance": { "type": "Extension", "name": "decimal" }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "connect": {
+                        "appliesTo": {
+                            "principalTypes": ["Host"],
+                            "resourceTypes": ["Host"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "source": { "type": "Extension", "name": "ipaddr" },
+                                    "port": { "type": "Long" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+
+        let ext = |s: &str| Name::parse_unqualified_name(s).unwrap();
+        assert_eq!(
+            schema.extension_typed_attributes(),
+            vec![
+                (
+                    "NS::Account".parse().unwrap(),
+                    "balance".into(),
+                    ext("decimal")
+                ),
+                ("NS::Host".parse().unwrap(), "ip".into(), ext("ipaddr")),
+            ]
+        );
+        assert_eq!(
+            schema.context_extension_typed_attributes(),
+            vec![(
+                r#"NS::Action::"connect""#.parse().unwrap(),
+                "source".into(),
+                ext("ipaddr")
+            )]
+        );
+    }
+
+    #[test]
+    fn attributes_referencing() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "manager": { "type": "Entity", "name": "User" },
+                                "name": { "type": "String" }
+                            }
+                        }
+                    },
+                    "Photo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "viewers": {
+                                    "type": "Set",
+                                    "element": { "type": "Entity", "name": "User" }
+                                },
+                                "meta": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "uploader": { "type": "Entity", "name": "User" }
+                                    }
+                                },
+                                "album": { "type": "Entity", "name": "Album" }
+                            }
+                        }
+                    },
+                    "Album": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "requester": { "type": "Entity", "name": "User" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let attrs = |ty: &str| {
+            schema
+                .attributes_referencing(&ty.parse().unwrap())
+                .into_iter()
+                .map(|(ty, attr)| format!("{ty}.{attr}"))
+                .collect::<Vec<_>>()
+        };
+        // The context attribute of `view` is not an entity type attribute.
+        assert_eq!(
+            attrs("User"),
+            ["Photo.meta", "Photo.viewers", "User.manager"]
+        );
+        assert_eq!(attrs("Album"), ["Photo.album"]);
+        assert!(attrs("Photo").is_empty());
+        assert!(attrs("Unknown").is_empty());
+    }
+
+    #[test]
+    fn references_to() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "manager": { "type": "Entity", "name": "User" },
+                                "friends": {
+                                    "type": "Set",
+                                    "element": {
+                                        "type": "Record",
+                                        "attributes": {
+                                            "who": { "type": "Entity", "name": "User" }
+                                        }
+                                    }
+                                },
+                                "name": { "type": "String" }
+                            }
+                        }
+                    },
+                    "Admin": { "memberOfTypes": ["User"] },
+                    "Team": {},
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User", "Admin"],
+                            "resourceTypes": ["Photo"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "delegate": { "type": "Entity", "name": "User" }
+                                }
+                            }
+                        }
+                    },
+                    "share": {
+                        "appliesTo": {
+                            "principalTypes": ["Admin"],
+                            "resourceTypes": ["User"]
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+
+        let user: Name = "User".parse().unwrap();
+        let view: EntityUID = r#"Action::"view""#.parse().unwrap();
+        let share: EntityUID = r#"Action::"share""#.parse().unwrap();
+        assert_eq!(
+            schema.references_to(&user),
+            vec![
+                SchemaReference::EntityAttribute {
+                    entity_type: user.clone(),
+                    attr: "friends".into()
+                },
+                SchemaReference::EntityAttribute {
+                    entity_type: user.clone(),
+                    attr: "manager".into()
+                },
+                SchemaReference::MemberOf {
+                    entity_type: "Admin".parse().unwrap()
+                },
+                SchemaReference::ContextAttribute {
+                    action: view.clone(),
+                    attr: "delegate".into()
+                },
+                SchemaReference::AppliesToPrincipal {
+                    action: view.clone()
+                },
+                SchemaReference::AppliesToResource { action: share },
+            ]
+        );
+        assert_eq!(
+            schema.references_to(&"Team".parse().unwrap()),
+            vec![
+                SchemaReference::MemberOf {
+                    entity_type: "Admin".parse().unwrap()
+                },
+                SchemaReference::MemberOf {
+                    entity_type: user.clone()
+                },
+            ]
+        );
+        assert_eq!(
+            schema.references_to(&"Photo".parse().unwrap()),
+            vec![SchemaReference::AppliesToResource { action: view }]
+        );
+        assert!(schema
+            .references_to(&"Undeclared".parse().unwrap())
+            .is_empty());
+    }
+
+    #[test]
+    fn canonicalize_equivalent_fragments() {
+        let compact = SchemaFragment::from_json_value(json!({
+            "NS": {
+                "commonTypes": {
+                    "Ctx": {
+                        "type": "Record",
+                        "attributes": { "ip": { "type": "Extension", "name": "ipaddr" } }
+                    }
+                },
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Team", "Group", "Team"] },
+                    "Group": {},
+                    "Team": {},
+                    "Photo": { "memberOfTypes": ["Album"] },
+                    "Album": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User", "Group"],
+                            "resourceTypes": ["Album", "Photo"],
+                            "resourceTypesWithDescendants": ["Album"],
+                            "context": { "type": "Ctx" }
+                        },
+                        "memberOf": [{ "id": "read" }, { "id": "all", "type": "Action" }]
+                    },
+                    "read": { "memberOf": [] },
+                    "all": { "appliesTo": {} }
+                }
+            }
+        }))
+        .unwrap();
+        let verbose = SchemaFragment::from_json_value(json!({
+            "NS": {
+                "entityTypes": {
+                    "Album": { "memberOfTypes": [] },
+                    "Photo": { "memberOfTypes": ["NS::Album"] },
+                    "Team": {},
+                    "Group": {},
+                    "User": { "memberOfTypes": ["NS::Group", "NS::Team"] }
+                },
+                "actions": {
+                    "all": {},
+                    "read": {},
+                    "view": {
+                        "memberOf": [
+                            { "id": "all", "type": "NS::Action" },
+                            { "id": "read", "type": "NS::Action" }
+                        ],
+                        "appliesTo": {
+                            "principalTypes": ["NS::Group", "NS::User"],
+                            "resourceTypes": ["NS::Photo"],
+                            "resourceTypesWithDescendants": ["NS::Album"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "ip": { "type": "Extension", "name": "ipaddr" } }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let compact = compact.canonicalize().expect("should canonicalize");
+        let verbose = verbose.canonicalize().expect("should canonicalize");
+        assert_eq!(
+            serde_json::to_string(&compact).unwrap(),
+            serde_json::to_string(&verbose).unwrap()
+        );
+
+        // The canonical form still describes the same schema.
+        let schema: ValidatorSchema = compact.try_into().expect("should be a valid schema");
+        assert!(schema.is_entity_type_descendant_of(
+            &"NS::Photo".parse().unwrap(),
+            &"NS::Album".parse().unwrap()
+        ));
+        assert!(schema.is_action_descendant_of(
+            &r#"NS::Action::"view""#.parse().unwrap(),
+            &r#"NS::Action::"all""#.parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn canonicalize_entity_union() {
+        let canonical = |names: serde_json::Value| {
+            let fragment = SchemaFragment::from_json_value(json!({
+                "NS": {
+                    "entityTypes": {
+                        "User": {},
+                        "Group": {},
+                        "Photo": {
+                            "shape": {
+                                "type": "Record",
+                                "attributes": {
+                                    "owner": { "type": "Entity", "names": names }
+                                }
+                            }
+                        }
+                    },
+                    "actions": {}
+                }
+            }))
+            .unwrap();
+            serde_json::to_string(&fragment.canonicalize().unwrap()).unwrap()
+        };
+        assert_eq!(
+            canonical(json!(["User", "Group", "NS::User"])),
+            canonical(json!(["NS::Group", "NS::User"]))
+        );
+    }
+
+    #[test]
+    fn canonicalize_distinguishes_unspecified_apply_spec() {
+        let canonical = |applies_to: serde_json::Value| {
+            let fragment = SchemaFragment::from_json_value(json!({
+                "": {
+                    "entityTypes": { "User": {} },
+                    "actions": { "view": { "appliesTo": applies_to } }
+                }
+            }))
+            .unwrap();
+            serde_json::to_string(&fragment.canonicalize().unwrap()).unwrap()
+        };
+        assert_ne!(
+            canonical(json!({ "principalTypes": ["User"] })),
+            canonical(json!({ "principalTypes": [] }))
+        );
+        assert_ne!(
+            canonical(json!({ "principalTypes": [] })),
+            canonical(json!({}))
+        );
+    }
+
+    #[test]
+    fn canonicalize_nested_common_types() {
+        let nested = SchemaFragment::from_json_value(json!({
+            "": {
+                "commonTypes": {
+                    "Address": {
+                        "type": "Record",
+                        "attributes": { "zip": { "type": "String" } }
+                    }
+                },
+                "entityTypes": {},
+                "actions": {}
+            },
+            "NS": {
+                "commonTypes": {
+                    "AddressList": { "type": "Set", "element": { "type": "Address" } },
+                    "Profile": {
+                        "type": "Record",
+                        "attributes": { "addresses": { "type": "AddressList" } }
+                    }
+                },
+                "entityTypes": { "User": { "shape": { "type": "Profile" } } },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        let expanded = SchemaFragment::from_json_value(json!({
+            "": { "entityTypes": {}, "actions": {} },
+            "NS": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "addresses": {
+                                    "type": "Set",
+                                    "element": {
+                                        "type": "Record",
+                                        "attributes": { "zip": { "type": "String" } }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        let nested = nested.canonicalize().expect("should canonicalize");
+        assert_eq!(
+            serde_json::to_string(&nested).unwrap(),
+            serde_json::to_string(&expanded.canonicalize().unwrap()).unwrap()
+        );
+        let _: ValidatorSchema = nested.try_into().expect("should be a valid schema");
+    }
+
+    #[test]
+    fn canonicalize_keeps_external_common_types() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "A": {
+                "entityTypes": {
+                    "User": { "shape": { "type": "Shared" } }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap()
+        .canonicalize()
+        .unwrap();
+        assert_eq!(
+            fragment
+                .0
+                .get("A")
+                .unwrap()
+                .entity_types
+                .get("User")
+                .unwrap()
+                .shape,
+            Some(crate::AttributesOrContext(SchemaType::TypeDef {
+                type_name: "A::Shared".into()
+            }))
+        );
 
-    use cedar_policy_core::ast::RestrictedExpr;
-    use cedar_policy_core::parser::err::{ParseError, ToASTError};
-    use serde_json::json;
+        let bad = SchemaFragment::from_json_value(json!({
+            "A": {
+                "entityTypes": { "User": { "memberOfTypes": ["Group // comment"] } },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        let err = bad
+            .canonicalize()
+            .expect_err("should fail to parse entity type");
+        assert_eq!(
+            err.location(),
+            Some(&SchemaLocation {
+                namespace: Some("A".into()),
+                section: Some(SchemaSection::EntityTypeMemberOf("User".into())),
+            })
+        );
+    }
 
-    use super::*;
+    #[test]
+    fn check_case_collisions() {
+        let schema = |src: serde_json::Value| -> ValidatorSchema {
+            serde_json::from_value::<SchemaFragment>(src)
+                .unwrap()
+                .try_into()
+                .unwrap()
+        };
+
+        let actions = schema(json!({
+            "NS": {
+                "entityTypes": {},
+                "actions": { "view_photo": {}, "View_Photo": {}, "edit": {} }
+            },
+            "Other": { "entityTypes": {}, "actions": { "VIEW_PHOTO": {} } }
+        }));
+        match actions.check_case_collisions() {
+            Err(SchemaError::CaseCollision { names }) => assert_eq!(
+                names,
+                vec![
+                    r#"NS::Action::"View_Photo""#.to_string(),
+                    r#"NS::Action::"view_photo""#.to_string()
+                ]
+            ),
+            s => panic!("Expected CaseCollision, got {:?}", s),
+        }
+
+        let entity_types = schema(json!({
+            "": {
+                "entityTypes": { "User": {}, "user": {}, "Photo": {} },
+                "actions": {}
+            }
+        }));
+        let err = entity_types
+            .check_case_collisions()
+            .expect_err("should report colliding entity types");
+        assert_eq!(err.to_string(), "names differ only by case: `User`, `user`");
+
+        // Names in different namespaces, or of different kinds, do not collide.
+        let distinct = schema(json!({
+            "A": { "entityTypes": { "Photo": {} }, "actions": { "photo": {} } },
+            "B": { "entityTypes": { "photo": {} }, "actions": {} }
+        }));
+        assert!(distinct.check_case_collisions().is_ok());
+    }
+
+    #[test]
+    fn from_json_str_with_source() {
+        let src =
+            "{\n  \"\": {\n    \"entityTypes\": {},\n    \"actions\": {}, \"unknown\": 1\n  }\n}";
+        let err = ValidatorSchema::from_json_str_with_source(src)
+            .expect_err("should reject unknown field");
+        assert!(matches!(err.error(), SchemaError::Serde(_)));
+        assert_eq!(err.source_text(), src);
+        let span = err.span().expect("serde errors should have a span");
+        // `serde_json` reports the position of the last character it read
+        assert_eq!(&src[span.range_start()..=span.range_start()], "\"");
+        assert!(src[..span.range_start()].ends_with("\"unknown"));
+
+        let src = r#"{ "": { "entityTypes": { "User": { "memberOfTypes": ["Group"] } }, "actions": {} } }"#;
+        let err = ValidatorSchema::from_json_str_with_source(src)
+            .expect_err("should reject undeclared entity type");
+        assert!(err.span().is_none());
+        assert_eq!(err.source_text(), src);
+        assert!(matches!(
+            err.into_error(),
+            SchemaError::UndeclaredEntityTypes(_)
+        ));
+
+        assert!(ValidatorSchema::from_json_str_with_source(
+            r#"{ "": { "entityTypes": {}, "actions": {} } }"#
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn shape_was_explicit() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "Implicit": {},
+                    "Explicit": { "shape": { "type": "Record", "attributes": {} } }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        // Whether the shape was written survives a round trip through JSON
+        let fragment =
+            SchemaFragment::from_json_value(serde_json::to_value(fragment).unwrap()).unwrap();
+        let schema: ValidatorSchema = fragment.try_into().unwrap();
+
+        let implicit = schema
+            .get_entity_type(&"Implicit".parse().unwrap())
+            .unwrap();
+        let explicit = schema
+            .get_entity_type(&"Explicit".parse().unwrap())
+            .unwrap();
+        assert!(!implicit.shape_was_explicit());
+        assert!(explicit.shape_was_explicit());
+        assert_eq!(implicit.attributes, explicit.attributes);
+    }
+
+    #[test]
+    fn iter_all_types() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "age": { "type": "Long" },
+                                "addresses": {
+                                    "type": "Set",
+                                    "element": {
+                                        "type": "Record",
+                                        "attributes": {
+                                            "zip": { "type": "Long" },
+                                            "ip": { "type": "Extension", "name": "ipaddr" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["User"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "amount": { "type": "Extension", "name": "decimal" },
+                                    "owner": { "type": "Entity", "name": "User" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+
+        // age, addresses, the address record, zip, ip, amount and owner
+        assert_eq!(schema.iter_all_types().count(), 7);
+        assert_eq!(
+            schema
+                .iter_all_types()
+                .filter(|ty| **ty == Type::primitive_long())
+                .count(),
+            2
+        );
+        let extensions = schema
+            .iter_all_types()
+            .filter_map(|ty| match ty {
+                Type::ExtensionType { name } => Some(name.to_string()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            extensions,
+            HashSet::from(["ipaddr".to_string(), "decimal".to_string()])
+        );
+        assert!(schema
+            .iter_all_types()
+            .any(|ty| *ty == Type::named_entity_reference("User".parse().unwrap())));
+    }
+
+    #[test]
+    fn compare_in_hierarchy() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Team"] },
+                    "Team": { "memberOfTypes": ["Org"] },
+                    "Org": {},
+                    "Photo": {},
+                    "A": { "memberOfTypes": ["B"] },
+                    "B": { "memberOfTypes": ["A"] }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let name = |s: &str| -> Name { s.parse().unwrap() };
+
+        assert_eq!(
+            schema.compare_in_hierarchy(&name("Org"), &name("User")),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            schema.compare_in_hierarchy(&name("User"), &name("Team")),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            schema.compare_in_hierarchy(&name("Team"), &name("Team")),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            schema.compare_in_hierarchy(&name("User"), &name("Photo")),
+            None
+        );
+        assert_eq!(schema.compare_in_hierarchy(&name("A"), &name("B")), None);
+        assert_eq!(
+            schema.compare_in_hierarchy(&name("Undeclared"), &name("Undeclared")),
+            None
+        );
+    }
+
+    #[test]
+    fn roots_and_dfs_from() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "Org": {},
+                    "Team": { "memberOfTypes": ["Org"] },
+                    "Role": { "memberOfTypes": ["Org"] },
+                    "User": { "memberOfTypes": ["Team", "Role"] },
+                    "Photo": {}
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let names = |names: Vec<&Name>| names.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let mut roots = schema.roots().collect::<Vec<_>>();
+        roots.sort();
+        assert_eq!(names(roots), ["Org", "Photo"]);
+        // `User` is a descendant of `Org` through both `Role` and `Team`, so it
+        // is not a direct child of `Org`, and it is only visited once.
+        assert_eq!(
+            names(schema.dfs_from(&"Org".parse().unwrap())),
+            ["Org", "Role", "User", "Team"]
+        );
+        assert_eq!(
+            names(schema.dfs_from(&"Team".parse().unwrap())),
+            ["Team", "User"]
+        );
+        assert!(schema.dfs_from(&"Album".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn record_nesting_limit() {
+        let fragment = || -> ValidatorSchemaFragment {
+            serde_json::from_value::<SchemaFragment>(json!({
+                "App": {
+                    "entityTypes": {
+                        "User": {
+                            "shape": {
+                                "type": "Record",
+                                "attributes": {
+                                    "address": {
+                                        "type": "Record",
+                                        "attributes": {
+                                            "geo": {
+                                                "type": "Record",
+                                                "attributes": { "lat": { "type": "Long" } }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "actions": {
+                        "view": {
+                            "appliesTo": {
+                                "context": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "tags": {
+                                            "type": "Set",
+                                            "element": {
+                                                "type": "Record",
+                                                "attributes": {
+                                                    "key": {
+                                                        "type": "Record",
+                                                        "attributes": {
+                                                            "ns": {
+                                                                "type": "Record",
+                                                                "attributes": {}
+                                                            }
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }))
+            .unwrap()
+            .try_into()
+            .unwrap()
+        };
+        ValidatorSchema::from_schema_fragments_with_limits([fragment()], SchemaLimits::default())
+            .expect("no limit by default");
+        ValidatorSchema::from_schema_fragments_with_limits(
+            [fragment()],
+            SchemaLimits {
+                max_record_nesting: 3,
+            },
+        )
+        .expect("records are nested at most 3 deep");
+        match ValidatorSchema::from_schema_fragments_with_limits(
+            [fragment()],
+            SchemaLimits {
+                max_record_nesting: 2,
+            },
+        ) {
+            Err(e @ SchemaError::RecordTooDeep { .. }) => assert_eq!(
+                e.to_string(),
+                "records nested 3 deep exceed the limit of 2 (in namespace `App`, in `appliesTo` of action `view`)"
+            ),
+            r => panic!("Expected RecordTooDeep, got {:?}", r),
+        }
+        match ValidatorSchema::from_schema_fragments_with_limits(
+            [fragment()],
+            SchemaLimits {
+                max_record_nesting: 1,
+            },
+        ) {
+            Err(SchemaError::RecordTooDeep {
+                location,
+                depth,
+                limit,
+            }) => {
+                assert_eq!(
+                    location.section,
+                    Some(SchemaSection::EntityTypeShape("User".into()))
+                );
+                assert_eq!((depth, limit), (2, 1));
+            }
+            r => panic!("Expected RecordTooDeep, got {:?}", r),
+        }
+    }
+
+    /// Record nesting is checked as shapes are converted, so it is reported
+    /// ahead of problems found once the whole schema is assembled
+    #[test]
+    fn record_nesting_limit_during_construction() {
+        let fragment = serde_json::from_value::<SchemaFragment>(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": { "zip": { "type": "String" } }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        match ValidatorSchema::from_schema_fragments_with_limits(
+            [fragment.try_into().unwrap()],
+            SchemaLimits {
+                max_record_nesting: 0,
+            },
+        ) {
+            Err(SchemaError::RecordTooDeep {
+                location,
+                depth,
+                limit,
+            }) => {
+                assert_eq!(location.namespace, Some("App".into()));
+                assert_eq!(
+                    location.section,
+                    Some(SchemaSection::EntityTypeShape("User".into()))
+                );
+                assert_eq!((depth, limit), (1, 0));
+            }
+            r => panic!("Expected RecordTooDeep, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn attribute_stats() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "street": { "type": "String" }
+                                    }
+                                },
+                                "age": { "type": "Long" }
+                            }
+                        }
+                    },
+                    "Photo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "owner": { "type": "Entity", "name": "User" }
+                            }
+                        }
+                    },
+                    "Album": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "history": {
+                                        "type": "Set",
+                                        "element": {
+                                            "type": "Record",
+                                            "attributes": {
+                                                "when": {
+                                                    "type": "Record",
+                                                    "attributes": {}
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        assert_eq!(
+            schema.attribute_stats(),
+            AttributeStats {
+                total: 4,
+                max_per_type: 3,
+                max_nesting_depth: 2,
+            }
+        );
+        assert_eq!(
+            ValidatorSchema::empty().attribute_stats(),
+            AttributeStats::default()
+        );
+    }
+
+    #[test]
+    fn sorted_entity_types_and_actions() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "NS": {
+                "entityTypes": { "User": {}, "Album": {}, "Photo": {} },
+                "actions": { "view": {}, "delete": {}, "edit": {} }
+            },
+            "": {
+                "entityTypes": { "Zone": {} },
+                "actions": { "list": {} }
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        assert_eq!(
+            schema
+                .entity_types_sorted()
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>(),
+            vec!["NS::Album", "NS::Photo", "NS::User", "Zone"]
+        );
+        assert_eq!(
+            schema
+                .actions_sorted()
+                .into_iter()
+                .map(|(euid, _)| euid.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                r#"Action::"list""#,
+                r#"NS::Action::"delete""#,
+                r#"NS::Action::"edit""#,
+                r#"NS::Action::"view""#
+            ]
+        );
+    }
 
-    // Well-formed schema
     #[test]
-    fn test_from_schema_file() {
-        let src = json!(
-        {
-            "entityTypes": {
-                "User": {
-                    "memberOfTypes": [ "Group" ]
-                },
-                "Group": {
-                    "memberOfTypes": []
-                },
-                "Photo": {
-                    "memberOfTypes": [ "Album" ]
-                },
-                "Album": {
-                    "memberOfTypes": []
-                }
-            },
-            "actions": {
-                "view_photo": {
-                    "appliesTo": {
-                        "principalTypes": ["User", "Group"],
-                        "resourceTypes": ["Photo"]
+    fn type_of_path() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "manager": { "type": "Entity", "name": "User" },
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "zip": { "type": "Long" },
+                                        "geo": {
+                                            "type": "Record",
+                                            "attributes": { "lat": { "type": "String" } },
+                                            "required": false
+                                        }
+                                    }
+                                }
+                            }
+                        }
                     }
-                }
+                },
+                "actions": {}
             }
-        });
-        let schema_file: NamespaceDefinition = serde_json::from_value(src).expect("Parse Error");
-        let schema: Result<ValidatorSchema> = schema_file.try_into();
-        assert!(schema.is_ok());
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let user = "User".parse().unwrap();
+        assert_eq!(
+            schema.type_of_path(&user, &["address", "geo", "lat"]),
+            Ok(&Type::primitive_string())
+        );
+        assert_eq!(
+            schema.type_of_path(&user, &["address", "zip"]),
+            Ok(&Type::primitive_long())
+        );
+        assert_eq!(
+            schema.type_of_path(&user, &["manager"]),
+            Ok(&Type::named_entity_reference_from_str("User"))
+        );
+        assert_eq!(
+            schema.type_of_path(&user, &["address", "street"]),
+            Err(PathError::MissingAttribute {
+                index: 1,
+                attr: "street".into()
+            })
+        );
+        assert_eq!(
+            schema.type_of_path(&user, &["name", "first"]),
+            Err(PathError::NotARecord {
+                index: 1,
+                attr: "first".into()
+            })
+        );
+        assert_eq!(
+            schema.type_of_path(&user, &["manager", "name"]),
+            Err(PathError::NotARecord {
+                index: 1,
+                attr: "name".into()
+            })
+        );
+        assert_eq!(schema.type_of_path(&user, &[]), Err(PathError::EmptyPath));
+        assert_eq!(
+            schema.type_of_path(&"Group".parse().unwrap(), &["name"]),
+            Err(PathError::UndeclaredEntityType("Group".parse().unwrap()))
+        );
     }
 
-    // Duplicate entity "Photo"
     #[test]
-    fn test_from_schema_file_duplicate_entity() {
-        // Test written using `from_str` instead of `from_value` because the
-        // `json!` macro silently ignores duplicate map keys.
-        let src = r#"
-        {"": {
-            "entityTypes": {
-                "User": {
-                    "memberOfTypes": [ "Group" ]
-                },
-                "Group": {
-                    "memberOfTypes": []
-                },
-                "Photo": {
-                    "memberOfTypes": [ "Album" ]
+    fn flatten_namespace() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "App": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": [ "Group" ],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "manager": { "type": "Entity", "name": "User" },
+                                "team": { "type": "Entity", "name": "Other::Team" }
+                            }
+                        }
+                    },
+                    "Group": {}
                 },
-                "Photo": {
-                    "memberOfTypes": []
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": [ "User" ], "resourceTypes": [ "Group" ] },
+                        "memberOf": [ { "id": "read" } ]
+                    },
+                    "read": {}
                 }
             },
-            "actions": {
-                "view_photo": {
-                    "memberOf": [],
-                    "appliesTo": {
-                        "principalTypes": ["User", "Group"],
-                        "resourceTypes": ["Photo"]
-                    }
-                }
-            }
-        }}"#;
+            "Other": { "entityTypes": { "Team": {} }, "actions": {} }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let flat = schema
+            .flatten_namespace(&"App".parse().unwrap())
+            .expect("flattening should succeed");
+        assert_eq!(
+            flat.entity_types_sorted()
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>(),
+            vec!["Group", "Other::Team", "User"]
+        );
+        let user = flat.get_entity_type(&"User".parse().unwrap()).unwrap();
+        assert_eq!(
+            user.attr_type("manager"),
+            Some(&Type::named_entity_reference_from_str("User"))
+        );
+        assert_eq!(
+            user.attr_type("team"),
+            Some(&Type::named_entity_reference_from_str("Other::Team"))
+        );
+        assert!(
+            flat.is_entity_type_descendant_of(&"User".parse().unwrap(), &"Group".parse().unwrap())
+        );
+        let view: EntityUID = r#"Action::"view""#.parse().unwrap();
+        assert!(flat.is_action_descendant_of(&view, &r#"Action::"read""#.parse().unwrap()));
+        let view = flat.get_action_id(&view).unwrap();
+        assert_eq!(
+            view.applies_to
+                .applicable_principal_types()
+                .collect::<Vec<_>>(),
+            vec![&EntityType::Concrete("User".parse().unwrap())]
+        );
+        assert!(flat
+            .get_action_id(&r#"App::Action::"view""#.parse().unwrap())
+            .is_none());
+    }
 
-        match ValidatorSchema::from_str(src) {
-            Err(SchemaError::Serde(_)) => (),
-            _ => panic!("Expected serde error due to duplicate entity type."),
+    #[test]
+    fn flatten_namespace_collision() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "App": {
+                "entityTypes": { "User": {}, "Group": {} },
+                "actions": { "view": {}, "edit": {} }
+            },
+            "": {
+                "entityTypes": { "User": {} },
+                "actions": { "view": {} }
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        match schema.flatten_namespace(&"App".parse().unwrap()) {
+            Err(SchemaError::FlattenCollision { collisions }) => assert_eq!(
+                collisions,
+                vec![
+                    (
+                        r#"App::Action::"view""#.to_string(),
+                        r#"Action::"view""#.to_string()
+                    ),
+                    ("App::User".to_string(), "User".to_string()),
+                ]
+            ),
+            r => panic!("Expected FlattenCollision, got {:?}", r),
         }
     }
 
-    // Duplicate action "view_photo"
     #[test]
-    fn test_from_schema_file_duplicate_action() {
-        // Test written using `from_str` instead of `from_value` because the
-        // `json!` macro silently ignores duplicate map keys.
-        let src = r#"
-        {"": {
-            "entityTypes": {
-                "User": {
-                    "memberOfTypes": [ "Group" ]
-                },
-                "Group": {
-                    "memberOfTypes": []
+    fn clone_namespace() {
+        let mut schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "Prod": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": [ "Group", "Other::Org" ],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "manager": { "type": "Entity", "name": "User" },
+                                "team": { "type": "Entity", "name": "Other::Team" }
+                            }
+                        }
+                    },
+                    "Group": {}
                 },
-                "Photo": {
-                    "memberOfTypes": []
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": [ "User" ], "resourceTypes": [ "Group" ] },
+                        "memberOf": [ { "id": "read" }, { "type": "Other::Action", "id": "all" } ]
+                    },
+                    "read": {}
                 }
             },
-            "actions": {
-                "view_photo": {
-                    "memberOf": [],
-                    "appliesTo": {
-                        "principalTypes": ["User", "Group"],
-                        "resourceTypes": ["Photo"]
-                    }
+            "Other": {
+                "entityTypes": {
+                    "Team": { "memberOfTypes": [ "Prod::Group" ] },
+                    "Org": {}
                 },
-                "view_photo": { }
+                "actions": { "all": {} }
             }
-        }"#;
-        match ValidatorSchema::from_str(src) {
-            Err(SchemaError::Serde(_)) => (),
-            _ => panic!("Expected serde error due to duplicate action type."),
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        schema
+            .clone_namespace(&"Prod".parse().unwrap(), &"Staging".parse().unwrap())
+            .expect("cloning should succeed");
+        assert_eq!(
+            schema
+                .entity_types_sorted()
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "Other::Org",
+                "Other::Team",
+                "Prod::Group",
+                "Prod::User",
+                "Staging::Group",
+                "Staging::User"
+            ]
+        );
+        let user = schema
+            .get_entity_type(&"Staging::User".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            user.attr_type("manager"),
+            Some(&Type::named_entity_reference_from_str("Staging::User"))
+        );
+        assert_eq!(
+            user.attr_type("team"),
+            Some(&Type::named_entity_reference_from_str("Other::Team"))
+        );
+        let is_member = |child: &str, parent: &str| {
+            schema.is_entity_type_descendant_of(&child.parse().unwrap(), &parent.parse().unwrap())
+        };
+        assert!(is_member("Staging::User", "Staging::Group"));
+        assert!(is_member("Staging::User", "Other::Org"));
+        assert!(!is_member("Staging::User", "Prod::Group"));
+        assert!(!is_member("Other::Team", "Staging::Group"));
+        assert!(is_member("Other::Team", "Prod::Group"));
+
+        let view: EntityUID = r#"Staging::Action::"view""#.parse().unwrap();
+        let is_action_member =
+            |parent: &str| schema.is_action_descendant_of(&view, &parent.parse().unwrap());
+        assert!(is_action_member(r#"Staging::Action::"read""#));
+        assert!(is_action_member(r#"Other::Action::"all""#));
+        assert!(!is_action_member(r#"Prod::Action::"read""#));
+        let view = schema.get_action_id(&view).unwrap();
+        assert_eq!(
+            view.applies_to
+                .applicable_principal_types()
+                .collect::<Vec<_>>(),
+            vec![&EntityType::Concrete("Staging::User".parse().unwrap())]
+        );
+
+        match schema.clone_namespace(&"Prod".parse().unwrap(), &"Other".parse().unwrap()) {
+            Err(SchemaError::NamespaceAlreadyDeclared(namespace)) => {
+                assert_eq!(namespace, "Other");
+            }
+            r => panic!("Expected NamespaceAlreadyDeclared, got {:?}", r),
         }
+        assert!(schema
+            .get_entity_type(&"Other::User".parse().unwrap())
+            .is_none());
     }
 
-    // Undefined entity types "Grop", "Usr", "Phoot"
     #[test]
-    fn test_from_schema_file_undefined_entities() {
-        let src = json!(
-        {
-            "entityTypes": {
-                "User": {
-                    "memberOfTypes": [ "Grop" ]
-                },
-                "Group": {
-                    "memberOfTypes": []
+    fn freeze_namespace() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "Tenant": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": [ "Platform::Org" ] }
                 },
-                "Photo": {
-                    "memberOfTypes": []
+                "actions": {
+                    "view": { "memberOf": [ { "type": "Platform::Action", "id": "all" } ] }
                 }
             },
-            "actions": {
-                "view_photo": {
-                    "appliesTo": {
-                        "principalTypes": ["Usr", "Group"],
-                        "resourceTypes": ["Phoot"]
-                    }
-                }
-            }
-        });
-        let schema_file: NamespaceDefinition = serde_json::from_value(src).expect("Parse Error");
-        let schema: Result<ValidatorSchema> = schema_file.try_into();
-        match schema {
-            Ok(_) => panic!("from_schema_file should have failed"),
-            Err(SchemaError::UndeclaredEntityTypes(v)) => {
-                assert_eq!(v.len(), 3)
+            "Platform": {
+                "entityTypes": { "Org": {} },
+                "actions": { "all": {} }
             }
-            _ => panic!("Unexpected error from from_schema_file"),
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let tenant: Name = "Tenant".parse().unwrap();
+        let platform: Name = "Platform".parse().unwrap();
+        let staging: Name = "Staging".parse().unwrap();
+
+        let mut frozen = schema.clone();
+        frozen.freeze_namespace(&staging);
+        assert!(frozen.is_namespace_frozen(&staging));
+        assert!(!schema.is_namespace_frozen(&staging));
+        match frozen.clone_namespace(&tenant, &staging) {
+            Err(SchemaError::NamespaceFrozen { ns }) => assert_eq!(ns, "Staging"),
+            r => panic!("Expected NamespaceFrozen, got {:?}", r),
         }
+
+        // Copies of `Tenant::User` would be members of `Platform::Org`.
+        let mut frozen = schema.clone();
+        frozen.freeze_namespace(&platform);
+        match frozen.clone_namespace(&tenant, &staging) {
+            Err(SchemaError::NamespaceFrozen { ns }) => assert_eq!(ns, "Platform"),
+            r => panic!("Expected NamespaceFrozen, got {:?}", r),
+        }
+        assert!(frozen
+            .get_entity_type(&"Staging::User".parse().unwrap())
+            .is_none());
+
+        // Cloning a frozen namespace leaves it unchanged.
+        let mut frozen = schema;
+        frozen.freeze_namespace(&tenant);
+        frozen
+            .clone_namespace(&tenant, &staging)
+            .expect("cloning should succeed");
+        assert!(frozen
+            .get_entity_type(&"Staging::User".parse().unwrap())
+            .is_some());
     }
 
     #[test]
-    fn undefined_entity_namespace_member_of() {
-        let src = json!(
-        {"Foo": {
-            "entityTypes": {
-                "User": {
-                    "memberOfTypes": [ "Foo::Group", "Bar::Group" ]
+    fn clone_shares_internals() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": { "User": {} },
+                "actions": { "view": {} }
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let cloned = schema.clone();
+        assert!(Arc::ptr_eq(&schema.entity_types, &cloned.entity_types));
+        assert!(Arc::ptr_eq(&schema.action_ids, &cloned.action_ids));
+        assert_eq!(
+            serde_json::to_value(&schema).unwrap(),
+            serde_json::to_value(&cloned).unwrap()
+        );
+    }
+
+    #[test]
+    fn resource_types_with_descendants() {
+        let src = json!({
+            "": {
+                "entityTypes": {
+                    "User": {},
+                    "Album": {},
+                    "Photo": { "memberOfTypes": ["Album"] },
+                    "Thumbnail": { "memberOfTypes": ["Photo"] }
                 },
-                "Group": { }
-            },
-            "actions": {}
-        }});
-        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
-        let schema: Result<ValidatorSchema> = schema_file.try_into();
-        match schema {
-            Ok(_) => panic!("try_into should have failed"),
-            Err(SchemaError::UndeclaredEntityTypes(v)) => {
-                assert_eq!(v, HashSet::from(["Bar::Group".to_string()]))
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypesWithDescendants": ["Album"]
+                        }
+                    },
+                    "share": {
+                        "appliesTo": {
+                            "resourceTypes": ["User"],
+                            "resourceTypesWithDescendants": ["Photo"]
+                        }
+                    },
+                    "edit": {
+                        "appliesTo": { "resourceTypes": ["Album"] }
+                    }
+                }
             }
-            _ => panic!("Unexpected error from try_into"),
-        }
+        });
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Schema should construct without error.");
+        assert_eq!(
+            resource_types_for(&schema, "view"),
+            HashSet::from([
+                "Album".to_string(),
+                "Photo".to_string(),
+                "Thumbnail".to_string()
+            ])
+        );
+        assert_eq!(
+            resource_types_for(&schema, "share"),
+            HashSet::from([
+                "User".to_string(),
+                "Photo".to_string(),
+                "Thumbnail".to_string()
+            ])
+        );
+        assert_eq!(
+            resource_types_for(&schema, "edit"),
+            HashSet::from(["Album".to_string()])
+        );
     }
 
     #[test]
-    fn undefined_entity_namespace_applies_to() {
-        let src = json!(
-        {"Foo": {
-            "entityTypes": { "User": { }, "Photo": { } },
-            "actions": {
-                "view_photo": {
-                    "appliesTo": {
-                        "principalTypes": ["Foo::User", "Bar::User"],
-                        "resourceTypes": ["Photo", "Bar::Photo"],
+    fn resource_types_with_descendants_undeclared() {
+        let src = json!({
+            "": {
+                "entityTypes": { "Photo": {} },
+                "actions": {
+                    "view": {
+                        "appliesTo": { "resourceTypesWithDescendants": ["Album"] }
                     }
                 }
             }
-        }});
+        });
         let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
         let schema: Result<ValidatorSchema> = schema_file.try_into();
         match schema {
-            Ok(_) => panic!("try_into should have failed"),
-            Err(SchemaError::UndeclaredEntityTypes(v)) => {
-                assert_eq!(
-                    v,
-                    HashSet::from(["Bar::Photo".to_string(), "Bar::User".to_string()])
-                )
+            Err(SchemaError::UndeclaredEntityTypes(undeclared)) => {
+                assert_eq!(undeclared, HashSet::from(["Album".to_string()]))
             }
-            _ => panic!("Unexpected error from try_into"),
+            s => panic!("Expected UndeclaredEntityTypes, got {:?}", s),
         }
     }
 
-    // Undefined action "photo_actions"
     #[test]
-    fn test_from_schema_file_undefined_action() {
-        let src = json!(
-        {
-            "entityTypes": {
-                "User": {
-                    "memberOfTypes": [ "Group" ]
-                },
-                "Group": {
-                    "memberOfTypes": []
-                },
-                "Photo": {
-                    "memberOfTypes": []
-                }
-            },
-            "actions": {
-                "view_photo": {
-                    "memberOf": [ {"id": "photo_action"} ],
-                    "appliesTo": {
-                        "principalTypes": ["User", "Group"],
-                        "resourceTypes": ["Photo"]
-                    }
-                }
+    fn validate_policy_scope() {
+        let src = json!({
+            "": {
+                "entityTypes": { "User": {}, "Album": {} },
+                "actions": { "view": {} }
             }
         });
-        let schema_file: NamespaceDefinition = serde_json::from_value(src).expect("Parse Error");
-        let schema: Result<ValidatorSchema> = schema_file.try_into();
-        match schema {
-            Ok(_) => panic!("from_schema_file should have failed"),
-            Err(SchemaError::UndeclaredActions(v)) => assert_eq!(v.len(), 1),
-            _ => panic!("Unexpected error from from_schema_file"),
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Schema should construct without error.");
+        let user: Name = "User".parse().unwrap();
+        let album: Name = "Album".parse().unwrap();
+        let photo: Name = "Photo".parse().unwrap();
+        let view = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        let edit = EntityUID::with_eid_and_type("Action", "edit").unwrap();
+
+        schema
+            .validate_policy_scope(Some(&user), Some(&view), Some(&album))
+            .expect("scope should be valid");
+        schema
+            .validate_policy_scope(None, None, None)
+            .expect("unconstrained scope should be valid");
+
+        match schema.validate_policy_scope(Some(&user), Some(&edit), Some(&photo)) {
+            Err(SchemaError::UndeclaredEntityTypes(undeclared)) => {
+                assert_eq!(undeclared, HashSet::from(["Photo".to_string()]))
+            }
+            s => panic!("Expected UndeclaredEntityTypes, got {:?}", s),
+        }
+        match schema.validate_policy_scope(Some(&user), Some(&edit), Some(&album)) {
+            Err(SchemaError::UndeclaredActions(undeclared)) => {
+                assert_eq!(undeclared, HashSet::from([r#"Action::"edit""#.to_string()]))
+            }
+            s => panic!("Expected UndeclaredActions, got {:?}", s),
         }
     }
 
-    // Trivial cycle in action hierarchy
-    // view_photo -> view_photo
     #[test]
-    fn test_from_schema_file_action_cycle1() {
-        let src = json!(
-        {
-            "entityTypes": {},
-            "actions": {
-                "view_photo": {
-                    "memberOf": [ {"id": "view_photo"} ]
+    fn descendant_queries() {
+        let src = json!({
+            "": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Group"] },
+                    "Group": { "memberOfTypes": ["Org"] },
+                    "Org": {}
+                },
+                "actions": {
+                    "read": {},
+                    "view": { "memberOf": [{ "id": "read" }] },
+                    "view_photo": { "memberOf": [{ "id": "view" }] },
+                    "edit": {}
                 }
             }
         });
-        let schema_file: NamespaceDefinition = serde_json::from_value(src).expect("Parse Error");
-        let schema: Result<ValidatorSchema> = schema_file.try_into();
-        match schema {
-            Ok(_) => panic!("from_schema_file should have failed"),
-            Err(SchemaError::CycleInActionHierarchy) => (), // expected result
-            e => panic!("Unexpected error from from_schema_file: {:?}", e),
-        }
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Schema should construct without error.");
+
+        let action = |id| EntityUID::with_eid_and_type("Action", id).unwrap();
+        assert!(schema.is_action_descendant_of(&action("view"), &action("read")));
+        assert!(schema.is_action_descendant_of(&action("view_photo"), &action("read")));
+        assert!(!schema.is_action_descendant_of(&action("read"), &action("view")));
+        assert!(!schema.is_action_descendant_of(&action("read"), &action("read")));
+        assert!(!schema.is_action_descendant_of(&action("edit"), &action("read")));
+        assert!(!schema.is_action_descendant_of(&action("view"), &action("undeclared")));
+
+        let name = |n: &str| -> Name { n.parse().unwrap() };
+        assert!(schema.is_entity_type_descendant_of(&name("User"), &name("Group")));
+        assert!(schema.is_entity_type_descendant_of(&name("User"), &name("Org")));
+        assert!(!schema.is_entity_type_descendant_of(&name("Org"), &name("User")));
+        assert!(!schema.is_entity_type_descendant_of(&name("User"), &name("Undeclared")));
     }
 
-    // Slightly more complex cycle in action hierarchy
-    // view_photo -> edit_photo -> delete_photo -> view_photo
     #[test]
-    fn test_from_schema_file_action_cycle2() {
-        let src = json!(
-        {
-            "entityTypes": {},
-            "actions": {
-                "view_photo": {
-                    "memberOf": [ {"id": "edit_photo"} ]
-                },
-                "edit_photo": {
-                    "memberOf": [ {"id": "delete_photo"} ]
-                },
-                "delete_photo": {
-                    "memberOf": [ {"id": "view_photo"} ]
+    fn subschema_for_action() {
+        let src = json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "manager": { "type": "Entity", "name": "Manager" }
+                            }
+                        }
+                    },
+                    "Manager": {
+                        "memberOfTypes": ["User"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "depts": {
+                                    "type": "Set",
+                                    "element": { "type": "Entity", "name": "Dept" }
+                                }
+                            }
+                        }
+                    },
+                    "Dept": {},
+                    "Group": {},
+                    "Photo": {},
+                    "Device": {},
+                    "Other": {}
                 },
-                "other_action": {
-                    "memberOf": [ {"id": "edit_photo"} ]
+                "actions": {
+                    "read": {
+                        "appliesTo": { "resourceTypes": ["Photo"] }
+                    },
+                    "view": {
+                        "memberOf": [{ "id": "read" }],
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Photo"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "device": { "type": "Entity", "name": "Device" }
+                                }
+                            }
+                        }
+                    },
+                    "thumbnail": {
+                        "memberOf": [{ "id": "view" }],
+                        "appliesTo": { "resourceTypes": ["Photo"] }
+                    },
+                    "edit": {
+                        "memberOf": [{ "id": "read" }],
+                        "appliesTo": { "principalTypes": ["Other"] }
+                    }
                 }
             }
         });
-        let schema_file: NamespaceDefinition = serde_json::from_value(src).expect("Parse Error");
-        let schema: Result<ValidatorSchema> = schema_file.try_into();
-        match schema {
-            Ok(x) => {
-                println!("{:?}", x);
-                panic!("from_schema_file should have failed");
-            }
-            Err(SchemaError::CycleInActionHierarchy) => (), // expected result
-            e => panic!("Unexpected error from from_schema_file: {:?}", e),
-        }
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Schema should construct without error.");
+
+        let view = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        let sub = schema
+            .subschema_for_action(&view)
+            .expect("view should be declared");
+        assert_eq!(
+            sub.action_ids
+                .keys()
+                .map(ToString::to_string)
+                .collect::<HashSet<_>>(),
+            HashSet::from([
+                r#"Action::"read""#.to_string(),
+                r#"Action::"view""#.to_string(),
+                r#"Action::"thumbnail""#.to_string(),
+            ])
+        );
+        assert_eq!(
+            sub.entity_types
+                .keys()
+                .map(ToString::to_string)
+                .collect::<HashSet<_>>(),
+            HashSet::from([
+                "User".to_string(),
+                "Manager".to_string(),
+                "Dept".to_string(),
+                "Photo".to_string(),
+                "Device".to_string(),
+            ])
+        );
+        let read = EntityUID::with_eid_and_type("Action", "read").unwrap();
+        assert_eq!(sub.get_action_id(&read).unwrap().descendants.len(), 2);
+        assert!(sub
+            .get_entity_type(&"User".parse().unwrap())
+            .unwrap()
+            .descendants
+            .contains(&"Manager".parse().unwrap()));
+        let mut errors = Vec::new();
+        ValidatorSchema::check_for_undeclared(
+            &sub.entity_types,
+            None,
+            &sub.action_ids,
+            None,
+            &mut errors,
+        );
+        assert!(errors.is_empty(), "subschema should be valid: {errors:?}");
+
+        let undeclared = EntityUID::with_eid_and_type("Action", "undeclared").unwrap();
+        assert!(schema.subschema_for_action(&undeclared).is_none());
     }
 
     #[test]
-    fn namespaced_schema() {
-        let src = r#"
-        { "N::S": {
-            "entityTypes": {
-                "User": {},
-                "Photo": {}
+    fn project_namespaces() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "Shared": {
+                "entityTypes": {
+                    "Org": {},
+                    "Team": { "memberOfTypes": ["Org"] },
+                    "Unused": {}
+                },
+                "actions": {
+                    "read": {}
+                }
             },
-            "actions": {
-                "view_photo": {
-                    "appliesTo": {
-                        "principalTypes": ["User"],
-                        "resourceTypes": ["Photo"]
+            "TenantA": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Shared::Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "manager": { "type": "Entity", "name": "TenantA::User" }
+                            }
+                        }
+                    },
+                    "Doc": {}
+                },
+                "actions": {
+                    "view": {
+                        "memberOf": [{ "type": "Shared::Action", "id": "read" }],
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Doc"] }
+                    }
+                }
+            },
+            "TenantB": {
+                "entityTypes": { "User": { "memberOfTypes": ["Shared::Team"] } },
+                "actions": {
+                    "view": {
+                        "memberOf": [{ "type": "Shared::Action", "id": "read" }],
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["User"] }
                     }
                 }
             }
-        } }
-        "#;
-        let schema_file: SchemaFragment = serde_json::from_str(src).expect("Parse Error");
-        let schema: ValidatorSchema = schema_file
-            .try_into()
-            .expect("Namespaced schema failed to convert.");
-        dbg!(&schema);
-        let user_entity_type = &"N::S::User"
-            .parse()
-            .expect("Namespaced entity type should have parsed");
-        let photo_entity_type = &"N::S::Photo"
-            .parse()
-            .expect("Namespaced entity type should have parsed");
-        assert!(
-            schema.entity_types.contains_key(user_entity_type),
-            "Expected and entity type User."
-        );
-        assert!(
-            schema.entity_types.contains_key(photo_entity_type),
-            "Expected an entity type Photo."
+        }))
+        .unwrap()
+        .try_into()
+        .expect("Expected valid schema");
+
+        let projected = schema
+            .project_namespaces(&["TenantA".parse().unwrap()])
+            .expect("projection should be valid");
+        let mut types = projected
+            .entity_types
+            .keys()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        types.sort();
+        assert_eq!(
+            types,
+            [
+                "Shared::Org",
+                "Shared::Team",
+                "TenantA::Doc",
+                "TenantA::User"
+            ]
         );
+        let mut actions = projected
+            .action_ids
+            .keys()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        actions.sort();
         assert_eq!(
-            schema.entity_types.len(),
-            2,
-            "Expected exactly 2 entity types."
+            actions,
+            [r#"Shared::Action::"read""#, r#"TenantA::Action::"view""#]
         );
-        assert!(
-            schema.action_ids.contains_key(
-                &"N::S::Action::\"view_photo\""
-                    .parse()
-                    .expect("Namespaced action should have parsed")
-            ),
-            "Expected an action \"view_photo\"."
+        // Descendants from namespaces which were not kept are dropped.
+        let team = projected
+            .get_entity_type(&"Shared::Team".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            team.descendants,
+            HashSet::from(["TenantA::User".parse().unwrap()])
         );
-        assert_eq!(schema.action_ids.len(), 1, "Expected exactly 1 action.");
+        let read: EntityUID = r#"Shared::Action::"read""#.parse().unwrap();
+        assert_eq!(projected.get_action_id(&read).unwrap().descendants.len(), 1);
+
+        let empty = schema
+            .project_namespaces(&["Missing".parse().unwrap()])
+            .expect("projection should be valid");
+        assert_eq!(empty.entity_types.len(), 0);
+        assert_eq!(empty.action_ids.len(), 0);
+    }
 
-        let apply_spec = &schema
-            .action_ids
-            .values()
-            .next()
-            .expect("Expected Action")
-            .applies_to;
+    #[test]
+    fn deprecated_items() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {
+                    "User": {},
+                    "LegacyUser": { "deprecated": true, "deprecationMessage": "use User" },
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User", "LegacyUser"],
+                            "resourceTypes": ["Photo"]
+                        }
+                    },
+                    "viewOld": {
+                        "deprecated": true,
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] }
+                    },
+                    "share": {
+                        "deprecationMessage": "ignored without `deprecated`",
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .expect("Expected valid schema");
+        let view_old = EntityUID::with_eid_and_type("Action", "viewOld").unwrap();
         assert_eq!(
-            apply_spec.applicable_principal_types().collect::<Vec<_>>(),
-            vec![&EntityType::Concrete(user_entity_type.clone())]
+            schema.deprecated_items(),
+            [
+                (
+                    DeprecatedItem::EntityType("LegacyUser".parse().unwrap()),
+                    Some("use User")
+                ),
+                (DeprecatedItem::Action(view_old.clone()), None),
+            ]
         );
+        let share = EntityUID::with_eid_and_type("Action", "share").unwrap();
+        let share = schema.get_action_id(&share).unwrap();
+        assert!(!share.is_deprecated());
+        assert_eq!(share.deprecation_message(), None);
+
+        let mut policies = cedar_policy_core::ast::PolicySet::new();
+        for (id, src) in [
+            (
+                "ok",
+                r#"permit(principal == User::"alice", action == Action::"view", resource);"#,
+            ),
+            (
+                "old",
+                r#"permit(principal in LegacyUser::"bob", action in [Action::"view", Action::"viewOld"], resource);"#,
+            ),
+        ] {
+            policies
+                .add_static(cedar_policy_core::parser::parse_policy(Some(id.into()), src).unwrap())
+                .unwrap();
+        }
+        policies
+            .add_template(
+                cedar_policy_core::parser::parse_policy_template(
+                    Some("template".into()),
+                    r#"permit(principal == ?principal, action == Action::"view", resource);"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        policies
+            .link(
+                cedar_policy_core::ast::PolicyID::from_string("template"),
+                cedar_policy_core::ast::PolicyID::from_string("linked"),
+                HashMap::from([(
+                    cedar_policy_core::ast::SlotId::principal(),
+                    r#"LegacyUser::"carol""#.parse().unwrap(),
+                )]),
+            )
+            .unwrap();
+
+        let usages = schema
+            .check_no_deprecated_usage(&policies)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
         assert_eq!(
-            apply_spec.applicable_resource_types().collect::<Vec<_>>(),
-            vec![&EntityType::Concrete(photo_entity_type.clone())]
+            usages,
+            [
+                "policy `linked` refers to deprecated entity type `LegacyUser`: use User",
+                "policy `old` refers to deprecated entity type `LegacyUser`: use User",
+                r#"policy `old` refers to deprecated action `Action::"viewOld"`"#,
+            ]
         );
     }
 
     #[test]
-    fn cant_use_namespace_in_entity_type() {
-        let src = r#"
-        {
-            "entityTypes": { "NS::User": {} },
-            "actions": {}
-        }
-        "#;
-        let schema_file: NamespaceDefinition = serde_json::from_str(src).expect("Parse Error");
-        assert!(
-            matches!(TryInto::<ValidatorSchema>::try_into(schema_file), Err(SchemaError::ParseEntityType(_))),
-            "Expected that namespace in the entity type NS::User would cause a EntityType parse error.");
-    }
-
-    #[test]
-    fn entity_attribute_entity_type_with_namespace() {
-        let schema_json: SchemaFragment = serde_json::from_str(
-            r#"
-            {"A::B": {
-                "entityTypes": {
-                    "Foo": {
-                        "shape": {
-                            "type": "Record",
-                            "attributes": {
-                                "name": { "type": "Entity", "name": "C::D::Foo" }
+    fn entity_type_and_action_hash_eq() {
+        let schema = |member_of: serde_json::Value, principals: serde_json::Value| {
+            ValidatorSchema::from_json_value(json!({
+                "": {
+                    "entityTypes": {
+                        "A": {},
+                        "B": {},
+                        "User": {
+                            "memberOfTypes": member_of,
+                            "shape": {
+                                "type": "Record",
+                                "attributes": { "name": { "type": "String" } }
                             }
+                        },
+                        "Admin": { "memberOfTypes": ["A", "B"] }
+                    },
+                    "actions": {
+                        "view": {
+                            "appliesTo": { "principalTypes": principals, "resourceTypes": ["A"] }
                         }
                     }
-                },
-                "actions": {}
-              }}
-            "#,
-        )
-        .expect("Expected valid schema");
+                }
+            }))
+            .expect("Expected valid schema")
+        };
+        let first = schema(json!(["A", "B"]), json!(["User", "Admin"]));
+        let second = schema(json!(["B", "A"]), json!(["Admin", "User"]));
+        let user: Name = "User".parse().unwrap();
+        let view = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        let hash = |value: &dyn Fn(&mut std::collections::hash_map::DefaultHasher)| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            value(&mut hasher);
+            std::hash::Hasher::finish(&hasher)
+        };
 
-        let schema: Result<ValidatorSchema> = schema_json.try_into();
-        match schema {
-            Err(SchemaError::UndeclaredEntityTypes(tys)) => {
-                assert_eq!(tys, HashSet::from(["C::D::Foo".to_string()]))
-            }
-            _ => panic!("Schema construction should have failed due to undeclared entity type."),
-        }
+        let (user1, user2) = (
+            first.get_entity_type(&user).unwrap(),
+            second.get_entity_type(&user).unwrap(),
+        );
+        assert_eq!(user1, user2);
+        assert_eq!(
+            hash(&|h| std::hash::Hash::hash(user1, h)),
+            hash(&|h| std::hash::Hash::hash(user2, h))
+        );
+        let (view1, view2) = (
+            first.get_action_id(&view).unwrap(),
+            second.get_action_id(&view).unwrap(),
+        );
+        assert_eq!(view1, view2);
+        assert_eq!(
+            hash(&|h| std::hash::Hash::hash(view1, h)),
+            hash(&|h| std::hash::Hash::hash(view2, h))
+        );
+
+        // Entity types are deduplicated structurally.
+        let entity_types = first
+            .entity_types()
+            .chain(second.entity_types())
+            .map(|(_, ety)| ety)
+            .collect::<HashSet<_>>();
+        assert_eq!(entity_types.len(), 4);
+        let admin: Name = "Admin".parse().unwrap();
+        assert_ne!(user1, first.get_entity_type(&admin).unwrap());
     }
 
     #[test]
-    fn entity_attribute_entity_type_with_declared_namespace() {
-        let schema_json: SchemaFragment = serde_json::from_str(
-            r#"
-            {"A::B": {
+    fn common_type_dependencies() {
+        let mut schema = ValidatorSchema::from_json_value(json!({
+            "A": {
+                "commonTypes": {
+                    "Address": {
+                        "type": "Record",
+                        "attributes": { "city": { "type": "String" } }
+                    },
+                    "AddressList": { "type": "Set", "element": { "type": "Address" } },
+                    "Contact": {
+                        "type": "Record",
+                        "attributes": {
+                            "home": { "type": "Address" },
+                            "count": { "type": "Count" }
+                        }
+                    }
+                },
                 "entityTypes": {
-                    "Foo": {
+                    "User": {
                         "shape": {
                             "type": "Record",
-                            "attributes": {
-                                "name": { "type": "Entity", "name": "A::B::Foo" }
-                            }
+                            "attributes": { "addresses": { "type": "AddressList" } }
                         }
                     }
                 },
                 "actions": {}
-              }}
-            "#,
-        )
-        .expect("Expected valid schema");
+            },
+            "": {
+                "commonTypes": { "Count": { "type": "Long" } },
+                "entityTypes": {},
+                "actions": {}
+            }
+        }))
+        .expect("Expected valid schema.");
+        let names = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.parse().unwrap())
+                .collect::<HashSet<Name>>()
+        };
+        let expected = HashMap::from([
+            ("A::Address".parse().unwrap(), names(&[])),
+            ("A::AddressList".parse().unwrap(), names(&["A::Address"])),
+            (
+                "A::Contact".parse().unwrap(),
+                names(&["A::Address", "Count"]),
+            ),
+            ("Count".parse().unwrap(), names(&[])),
+        ]);
+        assert_eq!(schema.common_type_dependencies(), expected);
+
+        // The reference is inlined, so the entity type sees the record type.
+        let user = schema
+            .get_entity_type(&"A::User".parse().unwrap())
+            .expect("User is declared");
+        assert_eq!(
+            user.attr("addresses").map(|attr| &attr.attr_type),
+            Some(&Type::set(Type::record_with_required_attributes(
+                [("city".into(), Type::primitive_string())],
+                OpenTag::ClosedAttributes,
+            )))
+        );
 
-        let schema: ValidatorSchema = schema_json
-            .try_into()
-            .expect("Expected schema to construct without error.");
+        let flattened = schema
+            .flatten_namespace(&"A".parse().unwrap())
+            .expect("Flattening should succeed");
+        assert_eq!(
+            flattened.common_type_dependencies(),
+            HashMap::from([
+                ("Address".parse().unwrap(), names(&[])),
+                ("AddressList".parse().unwrap(), names(&["Address"])),
+                ("Contact".parse().unwrap(), names(&["Address", "Count"])),
+                ("Count".parse().unwrap(), names(&[])),
+            ])
+        );
 
-        let foo_name: Name = "A::B::Foo".parse().expect("Expected entity type name");
-        let foo_type = schema
-            .entity_types
-            .get(&foo_name)
-            .expect("Expected to find entity");
-        let name_type = foo_type
-            .attr("name")
-            .expect("Expected attribute name")
-            .attr_type
-            .clone();
-        let expected_name_type = Type::named_entity_reference(foo_name);
-        assert_eq!(name_type, expected_name_type);
+        schema
+            .clone_namespace(&"A".parse().unwrap(), &"B".parse().unwrap())
+            .expect("Cloning should succeed");
+        let dependencies = schema.common_type_dependencies();
+        assert_eq!(
+            dependencies.get(&"B::AddressList".parse().unwrap()),
+            Some(&names(&["B::Address"]))
+        );
+        assert_eq!(
+            dependencies.get(&"A::AddressList".parse().unwrap()),
+            Some(&names(&["A::Address"]))
+        );
+        assert!(matches!(
+            schema.clone_namespace(&"B".parse().unwrap(), &"A".parse().unwrap()),
+            Err(SchemaError::NamespaceAlreadyDeclared(_))
+        ));
     }
 
     #[test]
-    fn cannot_declare_action_type_when_prohibited() {
-        let schema_json: NamespaceDefinition = serde_json::from_str(
-            r#"
-            {
-                "entityTypes": { "Action": {} },
+    fn cyclic_common_types() {
+        let err = ValidatorSchema::from_json_value(json!({
+            "": {
+                "commonTypes": {
+                    "Tree": {
+                        "type": "Record",
+                        "attributes": { "children": { "type": "Forest" } }
+                    },
+                    "Forest": { "type": "Set", "element": { "type": "Tree" } },
+                    "Grove": { "type": "Set", "element": { "type": "Forest" } },
+                    "Leaf": { "type": "String" }
+                },
+                "entityTypes": {},
                 "actions": {}
-              }
-            "#,
-        )
-        .expect("Expected valid schema");
-
-        let schema: Result<ValidatorSchema> = schema_json.try_into();
-        assert!(matches!(schema, Err(SchemaError::ActionEntityTypeDeclared)));
+            }
+        }))
+        .expect_err("Cyclic common types should be rejected");
+        match err {
+            SchemaError::CycleInCommonTypes(names) => {
+                assert_eq!(names, ["Forest", "Grove", "Tree"])
+            }
+            err => panic!("Expected CycleInCommonTypes, got {err:?}"),
+        }
     }
 
     #[test]
-    fn can_declare_other_type_when_action_type_prohibited() {
-        let schema_json: NamespaceDefinition = serde_json::from_str(
-            r#"
-            {
-                "entityTypes": { "Foo": { } },
-                "actions": {}
-              }
-            "#,
-        )
-        .expect("Expected valid schema");
+    fn invalid_attribute_name() {
+        let schema_with = |shape: serde_json::Value, context: serde_json::Value| {
+            ValidatorSchema::from_json_value(json!({
+                "": {
+                    "entityTypes": {
+                        "User": { "shape": { "type": "Record", "attributes": shape } }
+                    },
+                    "actions": {
+                        "view": {
+                            "appliesTo": {
+                                "context": { "type": "Record", "attributes": context }
+                            }
+                        }
+                    }
+                }
+            }))
+        };
 
-        TryInto::<ValidatorSchema>::try_into(schema_json).expect("Did not expect any errors.");
+        match schema_with(json!({ "": { "type": "Long" } }), json!({})) {
+            Err(SchemaError::InvalidAttributeName {
+                name,
+                location: ContextOrShape::EntityTypeShape(ty),
+            }) => {
+                assert_eq!(name, "");
+                assert_eq!(ty, "User".parse().unwrap());
+            }
+            r => panic!("Expected InvalidAttributeName, got {:?}", r),
+        }
+        match schema_with(json!({}), json!({ "  ": { "type": "Long" } })) {
+            Err(SchemaError::InvalidAttributeName {
+                name,
+                location: ContextOrShape::ActionContext(action),
+            }) => {
+                assert_eq!(name, "  ");
+                assert_eq!(
+                    action,
+                    EntityUID::with_eid_and_type("Action", "view").unwrap()
+                );
+            }
+            r => panic!("Expected InvalidAttributeName, got {:?}", r),
+        }
+        match schema_with(
+            json!({
+                "address": {
+                    "type": "Record",
+                    "attributes": { "\t": { "type": "String" } }
+                }
+            }),
+            json!({}),
+        ) {
+            Err(SchemaError::InvalidAttributeName { name, .. }) => assert_eq!(name, "\t"),
+            r => panic!("Expected InvalidAttributeName, got {:?}", r),
+        }
+        schema_with(
+            json!({ " padded ": { "type": "Long" } }),
+            json!({ "ok": { "type": "Long" } }),
+        )
+        .expect("names with non-whitespace characters are valid");
     }
 
     #[test]
-    fn cannot_declare_action_in_group_when_prohibited() {
-        let schema_json: SchemaFragment = serde_json::from_str(
-            r#"
-            {"": {
+    fn action_entity() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
                 "entityTypes": {},
                 "actions": {
-                    "universe": { },
-                    "view_photo": {
-                        "attributes": {"id": "universe"}
-                    },
-                    "edit_photo": {
-                        "attributes": {"id": "universe"}
-                    },
-                    "delete_photo": {
-                        "attributes": {"id": "universe"}
-                    }
+                    "all": {},
+                    "read": { "memberOf": [{ "id": "all" }] },
+                    "view": { "memberOf": [{ "id": "read" }] }
                 }
-              }}
-            "#,
-        )
-        .expect("Expected valid schema");
+            }
+        }))
+        .expect("Expected valid schema.");
+        let entities = schema.action_entities().expect("action entities");
+        for action in ["all", "read", "view"] {
+            let uid = EntityUID::with_eid_and_type("Action", action).unwrap();
+            let entity = schema.action_entity(&uid).expect("action should exist");
+            assert_eq!(
+                entity.ancestors().collect::<HashSet<_>>(),
+                entities
+                    .entity(&uid)
+                    .unwrap()
+                    .ancestors()
+                    .collect::<HashSet<_>>()
+            );
+        }
+        let view = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        assert_eq!(
+            schema
+                .action_entity(&view)
+                .unwrap()
+                .ancestors()
+                .map(ToString::to_string)
+                .collect::<BTreeSet<_>>(),
+            BTreeSet::from([
+                r#"Action::"all""#.to_string(),
+                r#"Action::"read""#.to_string()
+            ])
+        );
+        let undeclared = EntityUID::with_eid_and_type("Action", "undeclared").unwrap();
+        assert!(schema.action_entity(&undeclared).is_none());
+    }
 
-        let schema = ValidatorSchemaFragment::from_schema_fragment(
-            schema_json,
-            ActionBehavior::ProhibitAttributes,
+    #[test]
+    fn to_json_value() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": { "memberOfTypes": ["Group"] },
+                    "Group": {}
+                },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Group"] } }
+                }
+            }
+        }))
+        .expect("Schema should construct without error.");
+        let json = schema.to_json_value();
+        let entity_types = json["entityTypes"]
+            .as_array()
+            .expect("entity types are serialized as a list of pairs");
+        assert_eq!(entity_types.len(), 2);
+        let group = entity_types
+            .iter()
+            .find(|pair| pair[0]["id"] == json!("Group"))
+            .expect("Group should be serialized");
+        assert_eq!(
+            group[1]["descendants"],
+            json!([{ "id": "User", "path": [] }])
         );
-        match schema {
-            Err(SchemaError::UnsupportedFeature(UnsupportedFeature::ActionAttributes(actions))) => {
-                assert_eq!(
-                    actions.into_iter().collect::<HashSet<_>>(),
-                    HashSet::from([
-                        "view_photo".to_string(),
-                        "edit_photo".to_string(),
-                        "delete_photo".to_string(),
-                    ])
-                )
+        assert_eq!(json["actionIds"].as_array().map(Vec::len), Some(1));
+        assert!(serde_json::from_value::<SchemaFragment>(json).is_err());
+    }
+
+    #[test]
+    fn to_dot() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "manager": { "type": "Entity", "name": "User" }
+                            }
+                        }
+                    },
+                    "Team": { "memberOfTypes": ["Org"] },
+                    "Org": {}
+                },
+                "actions": {
+                    "read": {},
+                    "view": { "memberOf": [{ "id": "read" }] }
+                }
             }
-            _ => panic!("Did not see expected error."),
-        }
+        }))
+        .expect("Expected valid schema.");
+        assert_eq!(
+            schema.to_dot(),
+            r#"digraph schema {
+  subgraph cluster_entity_types {
+    label="Entity types";
+    node [shape=box, style=filled, fillcolor=lightblue];
+    "Org";
+    "Team";
+    "User";
+    "Org" -> "Team";
+    "Team" -> "User";
+  }
+  subgraph cluster_actions {
+    label="Actions";
+    node [shape=ellipse, style=filled, fillcolor=lightyellow];
+    "Action::\"read\"";
+    "Action::\"view\"";
+    "Action::\"read\"" -> "Action::\"view\"";
+  }
+  "User" -> "User" [style=dashed, label="manager"];
+}
+"#
+        );
     }
 
     #[test]
-    fn test_entity_type_no_namespace() {
-        let src = json!({"type": "Entity", "name": "Foo"});
-        let schema_ty: SchemaType = serde_json::from_value(src).expect("Parse Error");
+    fn resolve_undeclared_names() {
+        let fragment = |json: serde_json::Value| -> SchemaFragment {
+            serde_json::from_value(json).expect("Parse Error")
+        };
+        let base = fragment(json!({
+            "App": {
+                "commonTypes": {},
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": { "type": "Record", "attributes": { "info": { "type": "Info" } } }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["User"] },
+                        "memberOf": [{ "id": "read" }]
+                    }
+                }
+            }
+        }));
+        let store = |name: &UndeclaredName| match name {
+            UndeclaredName::EntityType(name) if name.to_string() == "App::Group" => {
+                Some(fragment(json!({
+                    "App": { "entityTypes": { "Group": { "memberOfTypes": ["Org"] } }, "actions": {} }
+                })))
+            }
+            UndeclaredName::EntityType(name) if name.to_string() == "App::Org" => {
+                Some(fragment(json!({
+                    "App": { "entityTypes": { "Org": {} }, "actions": {} }
+                })))
+            }
+            UndeclaredName::Action(action) if action.to_string() == r#"App::Action::"read""# => {
+                Some(fragment(json!({
+                    "App": { "entityTypes": {}, "actions": { "read": {} } }
+                })))
+            }
+            UndeclaredName::CommonType(name) if name == "Info" => Some(fragment(json!({
+                "App": {
+                    "commonTypes": { "Info": { "type": "Record", "attributes": {} } },
+                    "entityTypes": {},
+                    "actions": {}
+                }
+            }))),
+            _ => None,
+        };
+
+        let mut requested = Vec::new();
+        let schema = ValidatorSchema::from_schema_fragments_with_resolver([base.clone()], |name| {
+            requested.push(name.clone());
+            store(name)
+        })
+        .expect("every undeclared name should be resolved");
+        assert!(schema.is_entity_type_descendant_of(
+            &"App::User".parse().unwrap(),
+            &"App::Org".parse().unwrap()
+        ));
+        assert!(schema.is_action_descendant_of(
+            &r#"App::Action::"view""#.parse().unwrap(),
+            &r#"App::Action::"read""#.parse().unwrap()
+        ));
+        // `App::Org` is only referenced once `App::Group` has been resolved.
+        assert_eq!(requested.len(), 4);
         assert_eq!(
-            schema_ty,
-            SchemaType::Type(SchemaTypeVariant::Entity { name: "Foo".into() })
+            requested.last(),
+            Some(&UndeclaredName::EntityType("App::Org".parse().unwrap()))
         );
-        let ty: Type = ValidatorNamespaceDef::try_schema_type_into_validator_type(
-            Some(&Name::parse_unqualified_name("NS").expect("Expected namespace.")),
-            schema_ty,
-        )
-        .expect("Error converting schema type to type.")
-        .resolve_type_defs(&HashMap::new())
-        .unwrap();
-        assert_eq!(ty, Type::named_entity_reference_from_str("NS::Foo"));
+
+        match ValidatorSchema::from_schema_fragments_with_resolver([base], |name| match name {
+            UndeclaredName::EntityType(_) => None,
+            _ => store(name),
+        }) {
+            Err(SchemaError::UndeclaredEntityTypes(types)) => {
+                assert_eq!(types, HashSet::from(["App::Group".to_string()]))
+            }
+            r => panic!("Expected UndeclaredEntityTypes, got {:?}", r),
+        }
     }
 
     #[test]
-    fn test_entity_type_namespace() {
-        let src = json!({"type": "Entity", "name": "NS::Foo"});
-        let schema_ty: SchemaType = serde_json::from_value(src).expect("Parse Error");
+    fn autodeclare_undeclared_entity_types() {
+        let fragment = || -> ValidatorSchemaFragment {
+            serde_json::from_value::<SchemaFragment>(json!({
+                "": {
+                    "entityTypes": {
+                        "User": {
+                            "memberOfTypes": ["Group"],
+                            "shape": {
+                                "type": "Record",
+                                "attributes": {
+                                    "manager": { "type": "Entity", "name": "Manager" }
+                                }
+                            }
+                        },
+                        "Admin": { "memberOfTypes": ["User"] }
+                    },
+                    "actions": {
+                        "view": {
+                            "appliesTo": {
+                                "principalTypes": ["User"],
+                                "resourceTypes": ["Photo"],
+                                "context": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "device": { "type": "Entity", "name": "Device" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }))
+            .unwrap()
+            .try_into()
+            .expect("Expected valid schema fragment.")
+        };
+
+        match ValidatorSchema::from_schema_fragments([fragment()]) {
+            Err(SchemaError::UndeclaredEntityTypes(undeclared)) => assert_eq!(
+                undeclared,
+                HashSet::from(["Group", "Manager", "Photo", "Device"].map(String::from))
+            ),
+            r => panic!("Expected UndeclaredEntityTypes, got {:?}", r),
+        }
+
+        let (schema, autodeclared) =
+            ValidatorSchema::from_schema_fragments_autodeclare([fragment()])
+                .expect("Expected valid schema.");
         assert_eq!(
-            schema_ty,
-            SchemaType::Type(SchemaTypeVariant::Entity {
-                name: "NS::Foo".into()
-            })
+            autodeclared,
+            ["Device", "Group", "Manager", "Photo"].map(|n| n.parse::<Name>().unwrap())
         );
-        let ty: Type = ValidatorNamespaceDef::try_schema_type_into_validator_type(
-            Some(&Name::parse_unqualified_name("NS").expect("Expected namespace.")),
-            schema_ty,
-        )
-        .expect("Error converting schema type to type.")
-        .resolve_type_defs(&HashMap::new())
-        .unwrap();
-        assert_eq!(ty, Type::named_entity_reference_from_str("NS::Foo"));
+        let group = schema
+            .get_entity_type(&"Group".parse().unwrap())
+            .expect("Group should be declared");
+        assert_eq!(group.attributes().count(), 0);
+        assert_eq!(
+            group.descendants,
+            HashSet::from(["User", "Admin"].map(|n| n.parse::<Name>().unwrap()))
+        );
+        let photo = schema
+            .get_entity_type(&"Photo".parse().unwrap())
+            .expect("Photo should be declared");
+        assert!(photo.descendants.is_empty());
+        assert!(!photo.shape_was_explicit);
     }
 
     #[test]
-    fn test_entity_type_namespace_parse_error() {
-        let src = json!({"type": "Entity", "name": "::Foo"});
-        let schema_ty: SchemaType = serde_json::from_value(src).expect("Parse Error");
+    fn annotations() {
+        let src = json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "annotations": {
+                            "description": "A user of the console",
+                            "docUrl": "https://example.com/user"
+                        }
+                    },
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "annotations": { "deprecated": "true" }
+                    }
+                }
+            }
+        });
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let serialized = serde_json::to_value(&schema_file).expect("Serialize Error");
         assert_eq!(
-            schema_ty,
-            SchemaType::Type(SchemaTypeVariant::Entity {
-                name: "::Foo".into()
-            })
+            serialized[""]["entityTypes"]["User"]["annotations"]["docUrl"],
+            json!("https://example.com/user")
         );
-        match ValidatorNamespaceDef::try_schema_type_into_validator_type(
-            Some(&Name::parse_unqualified_name("NS").expect("Expected namespace.")),
-            schema_ty,
-        ) {
-            Err(SchemaError::ParseEntityType(_)) => (),
-            _ => panic!("Did not see expected entity type parse error."),
-        }
-    }
+        assert!(serialized[""]["entityTypes"]["Photo"]
+            .get("annotations")
+            .is_none());
+        let round_tripped: SchemaFragment =
+            serde_json::from_value(serialized).expect("Parse Error");
+        assert_eq!(round_tripped.0, schema_file.0);
 
-    #[test]
-    fn schema_type_record_is_validator_type_record() {
-        let src = json!({"type": "Record", "attributes": {}});
-        let schema_ty: SchemaType = serde_json::from_value(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Schema should construct without error.");
+        let user = schema
+            .get_entity_type(&"User".parse().unwrap())
+            .expect("User should be declared");
         assert_eq!(
-            schema_ty,
-            SchemaType::Type(SchemaTypeVariant::Record {
-                attributes: BTreeMap::new(),
-                additional_attributes: false,
-            }),
+            user.annotation("description"),
+            Some("A user of the console")
         );
-        let ty: Type = ValidatorNamespaceDef::try_schema_type_into_validator_type(None, schema_ty)
-            .expect("Error converting schema type to type.")
-            .resolve_type_defs(&HashMap::new())
-            .unwrap();
-        assert_eq!(ty, Type::closed_record_with_attributes(None));
+        assert_eq!(
+            user.annotations()
+                .map(|(k, _)| k.as_str())
+                .collect::<Vec<_>>(),
+            vec!["description", "docUrl"]
+        );
+        let photo = schema
+            .get_entity_type(&"Photo".parse().unwrap())
+            .expect("Photo should be declared");
+        assert_eq!(photo.annotations().count(), 0);
+        let view = schema
+            .get_action_id(&EntityUID::with_eid_and_type("Action", "view").unwrap())
+            .expect("view should be declared");
+        assert_eq!(view.annotation("deprecated"), Some("true"));
+        assert_eq!(view.annotation("description"), None);
     }
 
     #[test]
-    fn get_namespaces() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
-            "Foo::Bar::Baz": {
-                "entityTypes": {},
-                "actions": {}
-            },
-            "Foo": {
-                "entityTypes": {},
-                "actions": {}
-            },
-            "Bar": {
-                "entityTypes": {},
-                "actions": {}
-            },
+    fn display_names() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "XYZCorp": {
+                "entityTypes": {
+                    "User": { "annotations": { "title": "Console user" } },
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": { "annotations": { "title": "View a photo" } },
+                    "edit": {}
+                }
+            }
         }))
-        .unwrap();
-
-        let schema_fragment: ValidatorSchemaFragment = fragment.try_into().unwrap();
+        .expect("Schema should construct without error.");
         assert_eq!(
-            schema_fragment
-                .0
-                .iter()
-                .map(|f| f.namespace())
-                .collect::<HashSet<_>>(),
-            HashSet::from([
-                &Some("Foo::Bar::Baz".parse().unwrap()),
-                &Some("Foo".parse().unwrap()),
-                &Some("Bar".parse().unwrap())
-            ])
+            schema.entity_type_display_name(&"XYZCorp::User".parse().unwrap()),
+            "Console user"
+        );
+        assert_eq!(
+            schema.entity_type_display_name(&"XYZCorp::Photo".parse().unwrap()),
+            "Photo"
+        );
+        assert_eq!(
+            schema.entity_type_display_name(&"Other::Album".parse().unwrap()),
+            "Album"
+        );
+        assert_eq!(
+            schema.action_display_name(&r#"XYZCorp::Action::"view""#.parse().unwrap()),
+            "View a photo"
+        );
+        assert_eq!(
+            schema.action_display_name(&r#"XYZCorp::Action::"edit""#.parse().unwrap()),
+            "edit"
         );
     }
 
     #[test]
-    fn schema_no_fragments() {
-        let schema = ValidatorSchema::from_schema_fragments([]).unwrap();
-        assert!(schema.entity_types.is_empty());
-        assert!(schema.action_ids.is_empty());
+    fn context_schema_unrepresentable() {
+        let action = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        let context = Attributes::with_required_attributes([("bad".into(), Type::Never)]);
+        match ContextSchema::new(&action, &context) {
+            Err(SchemaError::UnrepresentableContext(euid, _)) => assert_eq!(euid, action),
+            Err(e) => panic!("Unexpected error: {e}"),
+            Ok(_) => panic!("Expected error for unrepresentable context"),
+        }
     }
 
     #[test]
-    fn same_action_different_namespace() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
-            "Foo::Bar": {
-                "entityTypes": {},
-                "actions": {
-                    "Baz": {}
-                }
-            },
-            "Bar::Foo": {
-                "entityTypes": {},
-                "actions": {
-                    "Baz": { }
-                }
-            },
-            "Biz": {
+    fn get_context_schema_for_declared_action() {
+        let src = json!({
+            "": {
                 "entityTypes": {},
                 "actions": {
-                    "Baz": { }
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "flag": { "type": "Boolean" } }
+                            }
+                        }
+                    }
                 }
             }
-        }))
-        .unwrap();
-
-        let schema: ValidatorSchema = fragment.try_into().unwrap();
-        assert!(schema
-            .get_action_id(&"Foo::Bar::Action::\"Baz\"".parse().unwrap())
-            .is_some());
-        assert!(schema
-            .get_action_id(&"Bar::Foo::Action::\"Baz\"".parse().unwrap())
-            .is_some());
-        assert!(schema
-            .get_action_id(&"Biz::Action::\"Baz\"".parse().unwrap())
-            .is_some());
-    }
-
-    #[test]
-    fn same_type_different_namespace() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
-            "Foo::Bar": {
-                "entityTypes": {"Baz" : {}},
-                "actions": { }
-            },
-            "Bar::Foo": {
-                "entityTypes": {"Baz" : {}},
-                "actions": { }
-            },
-            "Biz": {
-                "entityTypes": {"Baz" : {}},
-                "actions": { }
-            }
-        }))
-        .unwrap();
-        let schema: ValidatorSchema = fragment.try_into().unwrap();
-
-        assert!(schema
-            .get_entity_type(&"Foo::Bar::Baz".parse().unwrap())
-            .is_some());
-        assert!(schema
-            .get_entity_type(&"Bar::Foo::Baz".parse().unwrap())
-            .is_some());
-        assert!(schema
-            .get_entity_type(&"Biz::Baz".parse().unwrap())
-            .is_some());
+        });
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Schema should construct without error.");
+        let view = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        let context_schema = schema
+            .get_context_schema(&view)
+            .expect("context should be representable")
+            .expect("action should be declared");
+        assert!(matches!(
+            cedar_policy_core::entities::ContextSchema::context_type(&context_schema),
+            cedar_policy_core::entities::SchemaType::Record { attrs } if attrs.len() == 1
+        ));
+        let undeclared = EntityUID::with_eid_and_type("Action", "undeclared").unwrap();
+        assert!(matches!(schema.get_context_schema(&undeclared), Ok(None)));
     }
 
     #[test]
-    fn member_of_different_namespace() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
-            "Bar": {
-                "entityTypes": {
-                    "Baz": {
-                        "memberOfTypes": ["Foo::Buz"]
+    fn validate_context_json() {
+        let src = json!({
+            "": {
+                "entityTypes": {},
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "flag": { "type": "Boolean" } }
+                            }
+                        }
                     }
-                },
-                "actions": {}
-            },
-            "Foo": {
-                "entityTypes": { "Buz": {} },
-                "actions": { }
+                }
             }
-        }))
-        .unwrap();
-        let schema: ValidatorSchema = fragment.try_into().unwrap();
-
-        let buz = schema
-            .get_entity_type(&"Foo::Buz".parse().unwrap())
-            .unwrap();
-        assert_eq!(
-            buz.descendants,
-            HashSet::from(["Bar::Baz".parse().unwrap()])
-        );
+        });
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Schema should construct without error.");
+        let view = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        schema
+            .validate_context_json(&view, json!({ "flag": true }))
+            .expect("context should conform");
+        match schema.validate_context_json(&view, json!({ "flag": "yes" })) {
+            Err(JsonDeserializationError::TypeMismatch { ctx, .. }) => {
+                assert!(matches!(*ctx, JsonDeserializationErrorContext::Context))
+            }
+            r => panic!("Expected type mismatch, got {:?}", r),
+        }
+        assert!(matches!(
+            schema.validate_context_json(&view, json!({})),
+            Err(JsonDeserializationError::MissingRequiredRecordAttr { .. })
+        ));
+        let undeclared = EntityUID::with_eid_and_type("Action", "undeclared").unwrap();
+        match schema.validate_context_json(&undeclared, json!({})) {
+            Err(JsonDeserializationError::UndeclaredAction { action }) => {
+                assert_eq!(*action, undeclared)
+            }
+            r => panic!("Expected undeclared action, got {:?}", r),
+        }
     }
 
     #[test]
-    fn attribute_different_namespace() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
-            "Bar": {
+    fn optional_attributes_may_be_omitted() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
                 "entityTypes": {
-                    "Baz": {
+                    "User": {
                         "shape": {
                             "type": "Record",
                             "attributes": {
-                                "fiz": {
-                                    "type": "Entity",
-                                    "name": "Foo::Buz"
+                                "name": { "type": "String", "required": true },
+                                "nickname": { "type": "String", "required": false },
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "city": { "type": "String" },
+                                        "zip": { "type": "String", "required": false }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "reason": { "type": "String" },
+                                    "ticket": { "type": "Long", "required": false }
                                 }
                             }
                         }
                     }
-                },
-                "actions": {}
-            },
-            "Foo": {
-                "entityTypes": { "Buz": {} },
-                "actions": { }
+                }
             }
         }))
-        .unwrap();
+        .expect("Expected valid schema");
+        let user = schema
+            .get_entity_type(&"User".parse().unwrap())
+            .expect("User should be declared");
+        assert!(user.attr("name").unwrap().is_required);
+        assert!(!user.attr("nickname").unwrap().is_required);
+
+        let view = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        schema
+            .validate_context_json(&view, json!({ "reason": "audit" }))
+            .expect("optional context attribute may be omitted");
+        match schema.validate_context_json(&view, json!({ "ticket": 7 })) {
+            Err(JsonDeserializationError::MissingRequiredRecordAttr { record_attr, .. }) => {
+                assert_eq!(record_attr, "reason")
+            }
+            r => panic!("Expected missing required attribute, got {:?}", r),
+        }
 
-        let schema: ValidatorSchema = fragment.try_into().unwrap();
-        let baz = schema
-            .get_entity_type(&"Bar::Baz".parse().unwrap())
-            .unwrap();
-        assert_eq!(
-            baz.attr("fiz").unwrap().attr_type,
-            Type::named_entity_reference_from_str("Foo::Buz"),
+        let core_schema = CoreSchema::new(&schema);
+        let eparser = cedar_policy_core::entities::EntityJsonParser::new(
+            Some(&core_schema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
         );
+        eparser
+            .from_json_value(json!([
+                { "uid": { "type": "User", "id": "alice" }, "attrs": { "name": "Alice", "address": { "city": "Seattle" } }, "parents": [] }
+            ]))
+            .expect("optional attributes may be omitted");
+        match eparser.from_json_value(json!([
+            { "uid": { "type": "User", "id": "bob" }, "attrs": { "name": "Bob", "address": { "zip": "98101" } }, "parents": [] }
+        ])) {
+            Err(cedar_policy_core::entities::EntitiesError::Deserialization(
+                JsonDeserializationError::MissingRequiredRecordAttr { record_attr, .. },
+            )) => assert_eq!(record_attr, "city"),
+            r => panic!("Expected missing required attribute, got {:?}", r),
+        }
     }
 
     #[test]
-    fn applies_to_different_namespace() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
-            "Foo::Bar": {
-                "entityTypes": { },
+    fn from_json_value_checked() {
+        let schema = ValidatorSchema::from_json_value_checked(json!({
+            "": { "entityTypes": { "User": {} }, "actions": {} },
+            "NS::Inner": { "entityTypes": { "Photo": {} }, "actions": {} }
+        }))
+        .expect("Expected valid schema");
+        assert!(schema
+            .get_entity_type(&"NS::Inner::Photo".parse().unwrap())
+            .is_some());
+
+        // Malformed keys are reported before the bad entity type is parsed.
+        match ValidatorSchema::from_json_value_checked(json!({
+            "NS": { "entityTypes": { "Bad Type": {} }, "actions": {} },
+            "has space": { "entityTypes": {}, "actions": {} },
+            "A::": { "entityTypes": {}, "actions": {} }
+        })) {
+            Err(SchemaError::MalformedNamespaces(namespaces)) => {
+                assert_eq!(
+                    namespaces
+                        .iter()
+                        .map(|(key, _)| key.as_str())
+                        .collect::<Vec<_>>(),
+                    ["A::", "has space"]
+                );
+            }
+            r => panic!("Expected malformed namespaces, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn action_context_diff() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "": {
+                "entityTypes": {},
                 "actions": {
-                    "Baz": {
+                    "view": {
                         "appliesTo": {
-                            "principalTypes": [ "Fiz::Buz" ],
-                            "resourceTypes": [ "Fiz::Baz" ],
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "ip": { "type": "Extension", "name": "ipaddr" },
+                                    "mfa": { "type": "Boolean" },
+                                    "reason": { "type": "String" }
+                                }
+                            }
+                        }
+                    },
+                    "edit": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "ip": { "type": "Extension", "name": "ipaddr" },
+                                    "mfa": { "type": "Boolean", "required": false },
+                                    "ticket": { "type": "Long" }
+                                }
+                            }
                         }
                     }
                 }
-            },
-            "Fiz": {
-                "entityTypes": {
-                    "Buz": {},
-                    "Baz": {}
-                },
-                "actions": { }
             }
         }))
-        .unwrap();
-        let schema: ValidatorSchema = fragment.try_into().unwrap();
+        .unwrap()
+        .try_into()
+        .expect("Expected valid schema");
+        let view = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        let edit = EntityUID::with_eid_and_type("Action", "edit").unwrap();
 
-        let baz = schema
-            .get_action_id(&"Foo::Bar::Action::\"Baz\"".parse().unwrap())
-            .unwrap();
+        let diff = schema
+            .action_context_diff(&view, &edit)
+            .expect("both actions are declared");
         assert_eq!(
-            baz.applies_to
-                .applicable_principal_types()
-                .collect::<HashSet<_>>(),
-            HashSet::from([&EntityType::Concrete("Fiz::Buz".parse().unwrap())])
+            diff.only_in_a,
+            BTreeMap::from([(
+                "reason".into(),
+                AttributeType::required_attribute(Type::primitive_string())
+            )])
         );
         assert_eq!(
-            baz.applies_to
-                .applicable_resource_types()
-                .collect::<HashSet<_>>(),
-            HashSet::from([&EntityType::Concrete("Fiz::Baz".parse().unwrap())])
+            diff.only_in_b,
+            BTreeMap::from([(
+                "ticket".into(),
+                AttributeType::required_attribute(Type::primitive_long())
+            )])
+        );
+        assert_eq!(
+            diff.differing,
+            BTreeMap::from([(
+                "mfa".into(),
+                (
+                    AttributeType::required_attribute(Type::primitive_boolean()),
+                    AttributeType::new(Type::primitive_boolean(), false)
+                )
+            )])
         );
+        assert!(schema
+            .action_context_diff(&view, &view)
+            .expect("action is declared")
+            .is_empty());
+
+        let unknown = EntityUID::with_eid_and_type("Action", "delete").unwrap();
+        assert!(schema.action_context_diff(&view, &unknown).is_none());
+        assert!(schema.action_context_diff(&unknown, &edit).is_none());
     }
 
     #[test]
-    fn simple_defined_type() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
+    fn string_enum_attributes() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
             "": {
-                "commonTypes": {
-                    "MyLong": {"type": "Long"}
-                },
                 "entityTypes": {
                     "User": {
                         "shape": {
                             "type": "Record",
                             "attributes": {
-                                "a": {"type": "MyLong"}
+                                "status": {
+                                    "type": "String",
+                                    "allowedValues": ["active", "suspended", "deleted"]
+                                }
                             }
                         }
                     }
                 },
-                "actions": {}
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["User"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "reasons": {
+                                        "type": "Set",
+                                        "element": { "type": "String", "allowedValues": ["audit"] }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
             }
         }))
-        .unwrap();
-        let schema: ValidatorSchema = fragment.try_into().unwrap();
+        .unwrap()
+        .try_into()
+        .expect("Expected valid schema");
+        let user = schema
+            .get_entity_type(&"User".parse().unwrap())
+            .expect("User should be declared");
         assert_eq!(
-            schema.entity_types.iter().next().unwrap().1.attributes,
-            Attributes::with_required_attributes([("a".into(), Type::primitive_long())])
+            user.attr_type("status"),
+            Some(&Type::string_enum(BTreeSet::from([
+                "active".into(),
+                "deleted".into(),
+                "suspended".into()
+            ])))
         );
+
+        let core_schema = CoreSchema::new(&schema);
+        let eparser = cedar_policy_core::entities::EntityJsonParser::new(
+            Some(&core_schema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        eparser
+            .from_json_value(json!([
+                { "uid": { "type": "User", "id": "alice" }, "attrs": { "status": "active" }, "parents": [] }
+            ]))
+            .expect("Expected valid entities");
+        match eparser.from_json_value(json!([
+            { "uid": { "type": "User", "id": "bob" }, "attrs": { "status": "retired" }, "parents": [] }
+        ])) {
+            Err(cedar_policy_core::entities::EntitiesError::Deserialization(
+                JsonDeserializationError::EntitySchemaConformance(
+                    cedar_policy_core::entities::EntitySchemaConformanceError::DisallowedValue {
+                        err,
+                        ..
+                    },
+                ),
+            )) => {
+                assert_eq!(err.value(), "retired");
+                assert_eq!(err.allowed_values(), ["active", "deleted", "suspended"]);
+            }
+            r => panic!("Expected disallowed value, got {:?}", r),
+        }
+
+        let view = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        schema
+            .validate_context_json(&view, json!({ "reasons": ["audit"] }))
+            .expect("context should conform");
+        match schema.validate_context_json(&view, json!({ "reasons": ["audit", "curiosity"] })) {
+            Err(JsonDeserializationError::DisallowedValue { ctx, err, .. }) => {
+                assert!(matches!(*ctx, JsonDeserializationErrorContext::Context));
+                assert_eq!(err.value(), "curiosity");
+            }
+            r => panic!("Expected disallowed value, got {:?}", r),
+        }
+
+        // Enumerated strings can be used wherever a string is expected.
+        let policy = cedar_policy_core::parser::parse_policy(
+            None,
+            r#"permit(principal, action, resource) when { principal.status == "active" && resource.status like "sus*" };"#,
+        )
+        .unwrap();
+        let mut policies = cedar_policy_core::ast::PolicySet::new();
+        policies.add_static(policy).unwrap();
+        let validator = crate::Validator::new(schema);
+        assert!(validator
+            .validate(&policies, crate::ValidationMode::Strict)
+            .validation_passed());
     }
 
     #[test]
-    fn defined_record_as_attrs() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
-            "": {
-                "commonTypes": {
-                    "MyRecord": {
-                        "type": "Record",
-                        "attributes":  {
-                            "a": {"type": "Long"}
+    fn string_enum_allowed_values_must_be_nonempty_strings() {
+        let schema_with_status = |status: serde_json::Value| {
+            serde_json::from_value::<SchemaFragment>(json!({
+                "": {
+                    "entityTypes": {
+                        "User": {
+                            "shape": {
+                                "type": "Record",
+                                "attributes": { "status": status }
+                            }
                         }
-                    }
-                },
-                "entityTypes": {
-                    "User": { "shape": { "type": "MyRecord", } }
-                },
-                "actions": {}
-            }
-        }))
-        .unwrap();
-        let schema: ValidatorSchema = fragment.try_into().unwrap();
-        assert_eq!(
-            schema.entity_types.iter().next().unwrap().1.attributes,
-            Attributes::with_required_attributes([("a".into(), Type::primitive_long())])
+                    },
+                    "actions": {}
+                }
+            }))
+        };
+        let empty = schema_with_status(json!({ "type": "String", "allowedValues": [] }))
+            .expect("Expected schema to parse");
+        assert!(matches!(
+            ValidatorSchema::try_from(empty),
+            Err(SchemaError::EmptyAllowedValues)
+        ));
+        assert!(
+            schema_with_status(json!({ "type": "String", "allowedValues": ["active", 1] }))
+                .is_err()
+        );
+        assert!(
+            schema_with_status(json!({ "type": "Long", "allowedValues": ["active"] })).is_err()
         );
     }
 
     #[test]
-    fn cross_namespace_type() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
-            "A": {
-                "commonTypes": {
-                    "MyLong": {"type": "Long"}
-                },
-                "entityTypes": { },
-                "actions": {}
-            },
-            "B": {
+    fn entity_type_member_of_instances() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "NS": {
                 "entityTypes": {
                     "User": {
-                        "shape": {
-                            "type": "Record",
-                            "attributes": {
-                                "a": {"type": "A::MyLong"}
-                            }
-                        }
-                    }
+                        "memberOfTypes": ["Group"],
+                        "memberOf": [{ "type": "Group", "id": "everyone" }]
+                    },
+                    "Group": {}
                 },
                 "actions": {}
             }
         }))
-        .unwrap();
-        let schema: ValidatorSchema = fragment.try_into().unwrap();
-        assert_eq!(
-            schema.entity_types.iter().next().unwrap().1.attributes,
-            Attributes::with_required_attributes([("a".into(), Type::primitive_long())])
+        .unwrap()
+        .try_into()
+        .expect("Expected valid schema");
+        let everyone: EntityUID = r#"NS::Group::"everyone""#.parse().unwrap();
+        let user = schema
+            .get_entity_type(&"NS::User".parse().unwrap())
+            .expect("User should be declared");
+        assert_eq!(user.required_parents().collect::<Vec<_>>(), [&everyone]);
+
+        let core_schema = CoreSchema::new(&schema);
+        let eparser = cedar_policy_core::entities::EntityJsonParser::new(
+            Some(&core_schema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
         );
-    }
+        eparser
+            .from_json_value(json!([
+                {
+                    "uid": { "type": "NS::User", "id": "alice" },
+                    "attrs": {},
+                    "parents": [{ "type": "NS::Group", "id": "everyone" }]
+                }
+            ]))
+            .expect("Expected valid entities");
+        match eparser.from_json_value(json!([
+            {
+                "uid": { "type": "NS::User", "id": "bob" },
+                "attrs": {},
+                "parents": [{ "type": "NS::Group", "id": "admins" }]
+            }
+        ])) {
+            Err(cedar_policy_core::entities::EntitiesError::InvalidEntity(
+                cedar_policy_core::entities::EntitySchemaConformanceError::MissingRequiredParent {
+                    uid,
+                    parent,
+                },
+            )) => {
+                assert_eq!(uid.to_string(), r#"NS::User::"bob""#);
+                assert_eq!(*parent, everyone);
+            }
+            r => panic!("Expected missing required parent, got {:?}", r),
+        }
 
-    #[test]
-    fn cross_fragment_type() {
-        let fragment1: ValidatorSchemaFragment = serde_json::from_value::<SchemaFragment>(json!({
-            "A": {
-                "commonTypes": {
-                    "MyLong": {"type": "Long"}
+        let undeclared: Result<ValidatorSchema> = serde_json::from_value::<SchemaFragment>(json!({
+            "NS": {
+                "entityTypes": {
+                    "User": { "memberOf": [{ "type": "Team", "id": "core" }] }
                 },
-                "entityTypes": { },
                 "actions": {}
             }
         }))
         .unwrap()
-        .try_into()
-        .unwrap();
-        let fragment2: ValidatorSchemaFragment = serde_json::from_value::<SchemaFragment>(json!({
-            "A": {
+        .try_into();
+        match undeclared {
+            Err(SchemaError::UndeclaredEntityTypes(types)) => {
+                assert_eq!(types, HashSet::from(["NS::Team".to_string()]));
+            }
+            r => panic!("Expected undeclared entity type, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn entity_type_member_of_transitive() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "NS": {
                 "entityTypes": {
                     "User": {
-                        "shape": {
-                            "type": "Record",
-                            "attributes": {
-                                "a": {"type": "MyLong"}
-                            }
-                        }
-                    }
+                        "memberOfTypes": ["Team"],
+                        "memberOf": [{ "type": "Group", "id": "everyone" }]
+                    },
+                    "Team": { "memberOfTypes": ["Group"] },
+                    "Group": {}
                 },
                 "actions": {}
             }
         }))
         .unwrap()
         .try_into()
-        .unwrap();
-        let schema = ValidatorSchema::from_schema_fragments([fragment1, fragment2]).unwrap();
+        .expect("Expected valid schema");
+        let entities = json!([
+            {
+                "uid": { "type": "NS::User", "id": "alice" },
+                "attrs": {},
+                "parents": [{ "type": "NS::Team", "id": "t" }]
+            },
+            {
+                "uid": { "type": "NS::Team", "id": "t" },
+                "attrs": {},
+                "parents": [{ "type": "NS::Group", "id": "everyone" }]
+            }
+        ]);
 
-        assert_eq!(
-            schema.entity_types.iter().next().unwrap().1.attributes,
-            Attributes::with_required_attributes([("a".into(), Type::primitive_long())])
+        // `alice` is only a member of `everyone` through `t`.
+        let core_schema = CoreSchema::new(&schema);
+        cedar_policy_core::entities::EntityJsonParser::new(
+            Some(&core_schema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        )
+        .from_json_value(entities.clone())
+        .expect("Expected valid entities");
+        let unchecked = cedar_policy_core::entities::EntityJsonParser::new(
+            None::<&cedar_policy_core::entities::NoEntitiesSchema>,
+            Extensions::all_available(),
+            TCComputation::AssumeAlreadyComputed,
         );
-    }
+        let entities = unchecked
+            .from_json_value(entities)
+            .expect("entities should parse");
+        assert!(schema.validate_entities(&entities).is_ok());
+
+        let entities = unchecked
+            .from_json_value(json!([
+                {
+                    "uid": { "type": "NS::User", "id": "bob" },
+                    "attrs": {},
+                    "parents": [{ "type": "NS::Team", "id": "t" }]
+                },
+                { "uid": { "type": "NS::Team", "id": "t" }, "attrs": {}, "parents": [] }
+            ]))
+            .expect("entities should parse");
+        match schema
+            .validate_entities(&entities)
+            .as_ref()
+            .map_err(Vec::as_slice)
+        {
+            Err([EntitySchemaConformanceError::MissingRequiredParent { uid, .. }]) => {
+                assert_eq!(uid.to_string(), r#"NS::User::"bob""#);
+            }
+            r => panic!("Expected missing required parent, got {:?}", r),
+        }
 
-    #[test]
-    fn cross_fragment_duplicate_type() {
-        let fragment1: ValidatorSchemaFragment = serde_json::from_value::<SchemaFragment>(json!({
-            "A": {
-                "commonTypes": {
-                    "MyLong": {"type": "Long"}
+        // An entity missing a required parent is reported on its own, and the
+        // other entities still load.
+        let (parsed, results) = cedar_policy_core::entities::EntityJsonParser::new(
+            Some(&core_schema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        )
+        .from_json_value_per_entity_results(json!([
+            {
+                "uid": { "type": "NS::User", "id": "alice" },
+                "attrs": {},
+                "parents": [{ "type": "NS::Group", "id": "everyone" }]
+            },
+            {
+                "uid": { "type": "NS::User", "id": "bob" },
+                "attrs": {},
+                "parents": [{ "type": "NS::Team", "id": "t" }]
+            },
+            { "uid": { "type": "NS::Team", "id": "t" }, "attrs": {}, "parents": [] }
+        ]))
+        .expect("the list of entities should parse");
+        let alice: EntityUID = r#"NS::User::"alice""#.parse().unwrap();
+        let bob: EntityUID = r#"NS::User::"bob""#.parse().unwrap();
+        let team: EntityUID = r#"NS::Team::"t""#.parse().unwrap();
+        assert!(matches!(results.get(&alice), Some(Ok(()))));
+        assert!(matches!(results.get(&team), Some(Ok(()))));
+        match results.get(&bob) {
+            Some(Err(JsonDeserializationError::EntitySchemaConformance(
+                EntitySchemaConformanceError::MissingRequiredParent { uid, .. },
+            ))) => assert_eq!(uid, &bob),
+            r => panic!("Expected missing required parent, got {:?}", r),
+        }
+        assert!(matches!(
+            parsed.entity(&alice),
+            cedar_policy_core::entities::Dereference::Data(_)
+        ));
+        assert!(matches!(
+            parsed.entity(&team),
+            cedar_policy_core::entities::Dereference::Data(_)
+        ));
+        assert!(matches!(
+            parsed.entity(&bob),
+            cedar_policy_core::entities::Dereference::NoSuchEntity
+        ));
+
+        // No `User` could be a member of a `Group`.
+        let disallowed: Result<ValidatorSchema> = serde_json::from_value::<SchemaFragment>(json!({
+            "NS": {
+                "entityTypes": {
+                    "User": { "memberOf": [{ "type": "Group", "id": "everyone" }] },
+                    "Group": {}
                 },
-                "entityTypes": {},
                 "actions": {}
             }
         }))
         .unwrap()
-        .try_into()
-        .unwrap();
-        let fragment2: ValidatorSchemaFragment = serde_json::from_value::<SchemaFragment>(json!({
-            "A": {
-                "commonTypes": {
-                    "MyLong": {"type": "Long"}
+        .try_into();
+        match disallowed {
+            Err(SchemaError::DisallowedRequiredParent {
+                entity_type,
+                parent,
+            }) => {
+                assert_eq!(entity_type, "NS::User");
+                assert_eq!(parent, r#"NS::Group::"everyone""#);
+            }
+            r => panic!("Expected disallowed required parent, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn entity_id_format() {
+        let schema: ValidatorSchema = serde_json::from_value::<SchemaFragment>(json!({
+            "NS": {
+                "entityTypes": {
+                    "User": { "idFormat": "[a-z]+" },
+                    "Group": {}
                 },
-                "entityTypes": {},
                 "actions": {}
             }
         }))
         .unwrap()
         .try_into()
-        .unwrap();
-
-        let schema = ValidatorSchema::from_schema_fragments([fragment1, fragment2]);
+        .expect("Expected valid schema");
+        let user = schema
+            .get_entity_type(&"NS::User".parse().unwrap())
+            .expect("User should be declared");
+        assert_eq!(user.id_format(), Some("[a-z]+"));
+        assert!(user.is_valid_id("alice"));
+        // The pattern must match the whole id
+        assert!(!user.is_valid_id("alice1"));
+
+        let core_schema = CoreSchema::new(&schema);
+        let eparser = cedar_policy_core::entities::EntityJsonParser::new(
+            Some(&core_schema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        eparser
+            .from_json_value(json!([
+                { "uid": { "type": "NS::User", "id": "alice" }, "attrs": {}, "parents": [] },
+                { "uid": { "type": "NS::Group", "id": "Any Id 1" }, "attrs": {}, "parents": [] }
+            ]))
+            .expect("Expected valid entities");
+        match eparser.from_json_value(json!([
+            { "uid": { "type": "NS::User", "id": "Bob" }, "attrs": {}, "parents": [] }
+        ])) {
+            Err(cedar_policy_core::entities::EntitiesError::InvalidEntity(
+                cedar_policy_core::entities::EntitySchemaConformanceError::InvalidIdFormat {
+                    uid,
+                    format,
+                },
+            )) => {
+                assert_eq!(uid.to_string(), r#"NS::User::"Bob""#);
+                assert_eq!(format, "[a-z]+");
+            }
+            r => panic!("Expected invalid id format, got {:?}", r),
+        }
 
-        match schema {
-            Err(SchemaError::DuplicateCommonType(s)) if s.contains("A::MyLong") => (),
-            _ => panic!("should have errored because schema fragments have duplicate types"),
-        };
+        let invalid: Result<ValidatorSchema> = serde_json::from_value::<SchemaFragment>(json!({
+            "NS": {
+                "entityTypes": { "User": { "idFormat": "[a-z" } },
+                "actions": {}
+            }
+        }))
+        .unwrap()
+        .try_into();
+        match invalid {
+            Err(SchemaError::InvalidIdFormat {
+                entity_type,
+                pattern,
+                ..
+            }) => {
+                assert_eq!(entity_type, "NS::User");
+                assert_eq!(pattern, "[a-z");
+            }
+            r => panic!("Expected invalid id format, got {:?}", r),
+        }
     }
 
     #[test]
-    fn undeclared_type_in_attr() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
+    fn common_principal_attributes() {
+        let src = json!({
             "": {
-                "commonTypes": { },
                 "entityTypes": {
                     "User": {
                         "shape": {
                             "type": "Record",
                             "attributes": {
-                                "a": {"type": "MyLong"}
+                                "name": { "type": "String" },
+                                "age": { "type": "Long" },
+                                "team": { "type": "Entity", "name": "Team" },
+                                "email": { "type": "String", "required": false }
                             }
                         }
-                    }
+                    },
+                    "Service": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String", "required": false },
+                                "age": { "type": "String" },
+                                "team": { "type": "Entity", "name": "Team" },
+                                "email": { "type": "String" },
+                                "owner": { "type": "Entity", "name": "User" }
+                            }
+                        }
+                    },
+                    "Team": {}
                 },
-                "actions": {}
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": ["User", "Service"] }
+                    },
+                    "edit": {
+                        "appliesTo": { "principalTypes": ["Team", "User"] }
+                    },
+                    "unspecified": {}
+                }
             }
-        }))
-        .unwrap();
-        match TryInto::<ValidatorSchema>::try_into(fragment) {
-            Err(SchemaError::UndeclaredCommonTypes(_)) => (),
-            s => panic!(
-                "Expected Err(SchemaError::UndeclaredCommonType), got {:?}",
-                s
-            ),
-        }
-    }
+        });
+        let schema_file: SchemaFragment = serde_json::from_value(src).expect("Parse Error");
+        let schema: ValidatorSchema = schema_file
+            .try_into()
+            .expect("Schema should construct without error.");
 
-    #[test]
-    fn undeclared_type_in_type_def() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
-            "": {
-                "commonTypes": {
-                    "a": { "type": "b" }
-                },
-                "entityTypes": { },
-                "actions": {}
-            }
-        }))
-        .unwrap();
-        match TryInto::<ValidatorSchema>::try_into(fragment) {
-            Err(SchemaError::UndeclaredCommonTypes(_)) => (),
-            s => panic!(
-                "Expected Err(SchemaError::UndeclaredCommonType), got {:?}",
-                s
-            ),
-        }
+        let view = EntityUID::with_eid_and_type("Action", "view").unwrap();
+        let common = schema.common_principal_attributes(&view);
+        assert_eq!(
+            common.keys().map(SmolStr::as_str).collect::<Vec<_>>(),
+            vec!["email", "name", "team"]
+        );
+        assert!(!common.get_attr("name").unwrap().is_required);
+        assert!(!common.get_attr("email").unwrap().is_required);
+        assert!(common.get_attr("team").unwrap().is_required);
+        assert_eq!(
+            common.get_attr("team").unwrap().attr_type,
+            Type::named_entity_reference("Team".parse().unwrap())
+        );
+
+        let edit = EntityUID::with_eid_and_type("Action", "edit").unwrap();
+        assert_eq!(schema.common_principal_attributes(&edit).keys().count(), 0);
+
+        let unspecified = EntityUID::with_eid_and_type("Action", "unspecified").unwrap();
+        assert_eq!(
+            schema
+                .common_principal_attributes(&unspecified)
+                .keys()
+                .count(),
+            0
+        );
+
+        let undeclared = EntityUID::with_eid_and_type("Action", "undeclared").unwrap();
+        assert_eq!(
+            schema
+                .common_principal_attributes(&undeclared)
+                .keys()
+                .count(),
+            0
+        );
     }
 
     #[test]
-    fn shape_not_record() {
-        let fragment: SchemaFragment = serde_json::from_value(json!({
-            "": {
-                "commonTypes": {
-                    "MyLong": { "type": "Long" }
-                },
+    fn parse_error_records_namespace_and_section() {
+        let fragment = SchemaFragment::from_json_value(json!({
+            "NS": {
                 "entityTypes": {
-                    "User": {
-                        "shape": { "type": "MyLong" }
-                    }
+                    "User": { "memberOfTypes": ["Group // comment"] },
+                    "Group": {}
                 },
                 "actions": {}
             }
         }))
-        .unwrap();
-        match TryInto::<ValidatorSchema>::try_into(fragment) {
-            Err(SchemaError::ContextOrShapeNotRecord(_)) => (),
-            s => panic!(
-                "Expected Err(SchemaError::ContextOrShapeNotRecord), got {:?}",
-                s
-            ),
-        }
+        .expect("should parse as a fragment");
+        let err =
+            ValidatorSchema::try_from(fragment).expect_err("should fail to parse entity type name");
+        assert!(matches!(err, SchemaError::ParseEntityType(..)));
+        assert_eq!(
+            err.location(),
+            Some(&SchemaLocation {
+                namespace: Some("NS".into()),
+                section: Some(SchemaSection::EntityTypeMemberOf("User".into())),
+            })
+        );
+        assert!(
+            err.to_string().contains("in namespace `NS`"),
+            "unexpected message: {err}"
+        );
     }
 
     /// This test checks for regressions on (adapted versions of) the examples
@@ -1958,7 +9983,7 @@ mod test {
         .into();
 
         match err {
-            SchemaError::ParseEntityType(parse_error) => assert_eq!(parse_error, expected_err),
+            SchemaError::ParseEntityType(parse_error, _) => assert_eq!(parse_error, expected_err),
             err => panic!("Incorrect error {err}"),
         }
 
@@ -2087,6 +10112,343 @@ mod test {
         );
     }
 
+    #[test]
+    fn validate_action_attributes() {
+        let schema = |attributes: serde_json::Value| -> ValidatorSchema {
+            let schema_file: NamespaceDefinitionWithActionAttributes =
+                serde_json::from_value(json!({
+                    "entityTypes": {},
+                    "actions": { "view_photo": { "attributes": attributes } }
+                }))
+                .expect("Parse Error");
+            schema_file.try_into().expect("Schema Error")
+        };
+
+        let valid = schema(json!({
+            "attr": "foo",
+            "tags": ["a", "b"],
+            "limits": { "max": 3, "names": ["x"] }
+        }));
+        assert!(valid.validate_action_attributes().is_ok());
+
+        let heterogeneous = schema(json!({ "attr": "foo", "tags": ["a", 1] }));
+        match heterogeneous.validate_action_attributes() {
+            Err(SchemaError::InvalidActionAttribute(action, attr, _)) => {
+                assert_eq!(action.to_string(), r#"Action::"view_photo""#);
+                assert_eq!(attr, "tags");
+            }
+            s => panic!("Expected InvalidActionAttribute, got {:?}", s),
+        }
+
+        let nested = schema(json!({ "limits": { "names": [["x"], "y"] } }));
+        assert!(matches!(
+            nested.validate_action_attributes(),
+            Err(SchemaError::InvalidActionAttribute(_, attr, _)) if attr == "limits"
+        ));
+    }
+
+    #[test]
+    fn validate_report() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["L1"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "Principal": { "type": "String" } }
+                        }
+                    },
+                    "user": {},
+                    "L1": { "memberOfTypes": ["L2"] },
+                    "L2": { "memberOfTypes": ["L3"] },
+                    "L3": { "memberOfTypes": ["L4"] },
+                    "L4": { "memberOfTypes": ["L5"] },
+                    "L5": { "memberOfTypes": ["L6"] },
+                    "L6": {},
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] }
+                    },
+                    "noop": {
+                        "appliesTo": { "principalTypes": [], "resourceTypes": ["Photo"] }
+                    }
+                }
+            }
+        }))
+        .expect("Expected valid schema.");
+        let report = schema.validate();
+        assert!(report.has_errors());
+        assert_eq!(
+            report
+                .diagnostics()
+                .map(|diagnostic| diagnostic.kind.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                SchemaDiagnosticKind::CaseCollision {
+                    names: vec!["User".to_string(), "user".to_string()]
+                },
+                SchemaDiagnosticKind::UnusedEntityType {
+                    entity_type: "user".to_string()
+                },
+                SchemaDiagnosticKind::EmptyAppliesTo {
+                    action: r#"Action::"noop""#.to_string()
+                },
+                SchemaDiagnosticKind::ReservedAdjacentName {
+                    name: "Principal".to_string(),
+                    reserved: "principal".to_string()
+                },
+                SchemaDiagnosticKind::DeepHierarchy {
+                    entity_type: "User".to_string(),
+                    depth: 6
+                },
+            ]
+        );
+        assert_eq!(report.errors().count(), 1);
+        assert_eq!(report.warnings().count(), 4);
+        assert_eq!(
+            serde_json::to_value(report.diagnostics().nth(1).unwrap()).unwrap(),
+            json!({
+                "severity": "Warning",
+                "kind": "UnusedEntityType",
+                "entity_type": "user",
+                "message": "entity type `user` is never used"
+            })
+        );
+
+        let clean = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": { "User": {}, "Photo": {} },
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] }
+                    }
+                }
+            }
+        }))
+        .expect("Expected valid schema.");
+        assert!(clean.validate().is_empty());
+    }
+
+    #[test]
+    fn validate_with_entity_type_cycle() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "A": { "memberOfTypes": ["B"] },
+                    "B": { "memberOfTypes": ["A"] }
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": { "principalTypes": ["A"], "resourceTypes": ["B"] }
+                    }
+                }
+            }
+        }))
+        .expect("Expected valid schema.");
+        assert!(schema.validate().is_empty());
+    }
+
+    #[test]
+    fn entity_type_cycle() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "A": {
+                        "memberOfTypes": ["B"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "b": { "type": "Entity", "name": "B" } }
+                        }
+                    },
+                    "B": {
+                        "memberOfTypes": ["A"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "a": { "type": "Entity", "name": "A" } }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Expected valid schema.");
+        let a: Name = "A".parse().unwrap();
+        let b: Name = "B".parse().unwrap();
+        assert!(!schema.validate().has_errors());
+        assert_eq!(schema.roots().count(), 0);
+        assert_eq!(schema.dfs_from(&a), vec![&a, &b]);
+        assert_eq!(schema.dfs_from(&b), vec![&b, &a]);
+        assert_eq!(
+            schema.recursive_entity_types(),
+            HashSet::from([a.clone(), b.clone()])
+        );
+        let dot = schema.to_dot();
+        assert!(dot.contains(r#""A" -> "B";"#));
+        assert!(dot.contains(r#""B" -> "A";"#));
+    }
+
+    #[test]
+    fn validate_with_action_group_lint() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": { "User": {}, "Photo": {} },
+                "actions": {
+                    "read": {},
+                    "write": {
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] }
+                    },
+                    "view": {
+                        "memberOf": [{ "id": "read" }, { "id": "write" }],
+                        "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] }
+                    }
+                }
+            }
+        }))
+        .expect("Expected valid schema.");
+        // The lint is opt-in.
+        assert!(schema.validate().is_empty());
+        // `read` is a group without `appliesTo`, so only `write` is reported.
+        let report = schema.validate_with_lints(&[SchemaLint::ActionGroupWithAppliesTo]);
+        assert_eq!(
+            report
+                .warnings()
+                .map(|diagnostic| diagnostic.kind.clone())
+                .collect::<Vec<_>>(),
+            vec![SchemaDiagnosticKind::ActionGroupWithAppliesTo {
+                action: r#"Action::"write""#.to_string()
+            }]
+        );
+        assert!(!report.has_errors());
+    }
+
+    #[test]
+    fn actions_matching() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "App": {
+                "entityTypes": {},
+                "actions": {
+                    "photo_view": {},
+                    "photo_edit": {},
+                    "album_view": {}
+                }
+            }
+        }))
+        .expect("Expected valid schema.");
+        let matching = |pattern: &str| {
+            schema
+                .actions_matching(pattern)
+                .map(|euid| euid.eid().to_string())
+                .collect::<BTreeSet<_>>()
+        };
+        assert_eq!(
+            matching(r#"App::Action::"photo_*""#),
+            BTreeSet::from(["photo_edit".to_string(), "photo_view".to_string()])
+        );
+        assert_eq!(
+            matching(r#"*_view""#),
+            BTreeSet::from(["album_view".to_string(), "photo_view".to_string()])
+        );
+        assert_eq!(matching("*").len(), 3);
+        assert!(matching("photo_*").is_empty());
+        assert!(matching(r#"Action::"photo_*""#).is_empty());
+    }
+
+    #[test]
+    fn entity_types_with_attribute() {
+        let schema = ValidatorSchema::from_json_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "email": { "type": "String" },
+                                "profile": {
+                                    "type": "Record",
+                                    "attributes": { "email": { "type": "String" } }
+                                }
+                            }
+                        }
+                    },
+                    "Team": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "email": { "type": "Set", "element": { "type": "String" }, "required": false }
+                            }
+                        }
+                    },
+                    "Photo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "emailAddress": { "type": "String" } }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .expect("Expected valid schema.");
+        let with_email = schema
+            .entity_types_with_attribute("email")
+            .map(|(name, ty)| (name.to_string(), ty.clone()))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            with_email,
+            BTreeMap::from([
+                ("Team".to_string(), Type::set(Type::primitive_string())),
+                ("User".to_string(), Type::primitive_string()),
+            ])
+        );
+        assert_eq!(schema.entity_types_with_attribute("missing").count(), 0);
+    }
+
+    #[test]
+    fn with_action_attributes_validated() {
+        let schema_file: NamespaceDefinitionWithActionAttributes = serde_json::from_value(json!({
+            "entityTypes": {},
+            "actions": {
+                "view": { "attributes": { "tags": ["a", 1], "ids": [1, "b"] } },
+                "edit": { "attributes": { "limits": { "names": [["x"], "y"] } } },
+                "list": { "attributes": { "attr": "foo" } }
+            }
+        }))
+        .expect("Parse Error");
+        let schema: ValidatorSchema = schema_file.try_into().expect("Schema Error");
+        let errors = schema
+            .with_action_attributes_validated()
+            .expect_err("attribute values should not conform");
+        let invalid_attrs = |action: &str| {
+            errors[&EntityUID::with_eid_and_type("Action", action).unwrap()]
+                .iter()
+                .map(|err| match err {
+                    SchemaError::InvalidActionAttribute(_, attr, _) => attr.to_string(),
+                    err => panic!("Expected InvalidActionAttribute, got {:?}", err),
+                })
+                .collect::<BTreeSet<_>>()
+        };
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            invalid_attrs("view"),
+            BTreeSet::from(["ids".to_string(), "tags".to_string()])
+        );
+        assert_eq!(
+            invalid_attrs("edit"),
+            BTreeSet::from(["limits".to_string()])
+        );
+
+        let schema_file: NamespaceDefinitionWithActionAttributes = serde_json::from_value(json!({
+            "entityTypes": {},
+            "actions": { "list": { "attributes": { "attr": "foo", "n": [1, 2] } } }
+        }))
+        .expect("Parse Error");
+        let schema: ValidatorSchema = schema_file.try_into().expect("Schema Error");
+        assert!(schema.with_action_attributes_validated().is_ok());
+    }
+
     #[test]
     fn test_action_namespace_inference_multi_success() {
         let src = json!({
@@ -2152,6 +10514,68 @@ mod test {
         schema.expect_err("Schema should fail to construct as the normalization rules treat any qualification as starting from the root");
     }
 
+    fn relative_resolution_parent_type(src: serde_json::Value) -> String {
+        let schema_fragment =
+            serde_json::from_value::<SchemaFragment>(src).expect("Failed to parse schema");
+        let schema = ValidatorSchema::from_schema_fragments_with_action_resolution(
+            [schema_fragment
+                .try_into()
+                .expect("Fragment should construct")],
+            ActionReferenceResolution::RelativeThenRoot,
+        )
+        .expect("Schema should construct");
+        let view_photo = schema
+            .action_entities_iter()
+            .find(|e| e.uid() == r#"ExampleCo::Personnel::Action::"viewPhoto""#.parse().unwrap())
+            .unwrap();
+        let ancestors = view_photo.ancestors().collect::<Vec<_>>();
+        assert_eq!(ancestors.len(), 1);
+        assert_eq!(ancestors[0].eid().to_string(), "read");
+        ancestors[0].entity_type().to_string()
+    }
+
+    #[test]
+    fn test_action_namespace_relative_resolution() {
+        let view_photo = json!({
+            "entityTypes" : {},
+            "actions" : {
+                "viewPhoto" : {
+                    "memberOf" : [{ "id" : "read", "type" : "Foo::Action" }]
+                }
+            }
+        });
+        let read = json!({
+            "entityTypes" : {},
+            "actions" : { "read" : {} }
+        });
+
+        // Only the relative action exists.
+        assert_eq!(
+            relative_resolution_parent_type(json!({
+                "ExampleCo::Personnel::Foo" : read.clone(),
+                "ExampleCo::Personnel" : view_photo.clone(),
+            })),
+            "ExampleCo::Personnel::Foo::Action"
+        );
+        // Only the root action exists, so resolution falls back to it.
+        assert_eq!(
+            relative_resolution_parent_type(json!({
+                "Foo" : read.clone(),
+                "ExampleCo::Personnel" : view_photo.clone(),
+            })),
+            "Foo::Action"
+        );
+        // Both exist, and the relative action is preferred.
+        assert_eq!(
+            relative_resolution_parent_type(json!({
+                "Foo" : read.clone(),
+                "ExampleCo::Personnel::Foo" : read,
+                "ExampleCo::Personnel" : view_photo,
+            })),
+            "ExampleCo::Personnel::Foo::Action"
+        );
+    }
+
     #[test]
     fn test_action_namespace_inference() {
         let src = json!({
@@ -1,19 +1,23 @@
 //! This module contains the definition of `ValidatorActionId` and the types it relies on
 
 use cedar_policy_core::{
-    ast::{EntityType, EntityUID, RestrictedExpr},
+    ast::{Entity, EntityType, EntityUID, RestrictedExpr},
     transitive_closure::TCNode,
 };
 use serde::Serialize;
 use smol_str::SmolStr;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 
 use crate::types::{AttributeType, Attributes};
 
 /// Contains information about actions used by the validator.  The contents of
 /// the struct are the same as the schema entity type structure, but the
 /// `member_of` relation is reversed to instead be `descendants`.
-#[derive(Clone, Debug, Serialize)]
+///
+/// Two actions are equal if all of their contents are equal. Only the name,
+/// applies-to specification, descendants and context are hashed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct ValidatorActionId {
     /// The name of the action.
     pub(crate) name: EntityUID,
@@ -39,6 +43,20 @@ pub struct ValidatorActionId {
     /// `Entity` for this action. Could also be used for more precise
     /// typechecking by partial evaluation.
     pub(crate) attributes: HashMap<SmolStr, RestrictedExpr>,
+
+    /// Annotations declared for this action in the schema. These are not used
+    /// by validation.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) annotations: BTreeMap<SmolStr, String>,
+
+    /// Whether this action is deprecated in the schema. This is not used by
+    /// validation.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) deprecated: bool,
+
+    /// The message explaining why this action is deprecated, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) deprecation_message: Option<SmolStr>,
 }
 
 impl ValidatorActionId {
@@ -46,6 +64,85 @@ impl ValidatorActionId {
     pub fn context(&self) -> impl Iterator<Item = (&SmolStr, &AttributeType)> {
         self.context.iter()
     }
+
+    /// An iterator over the entities, e.g., groups, which a principal must be
+    /// in for this action to apply, from the `principalsIn` list in the
+    /// schema. Empty if the principal is only constrained by its type.
+    pub fn principal_groups(&self) -> impl Iterator<Item = &EntityUID> {
+        self.applies_to.principal_groups()
+    }
+
+    /// An iterator over the entities which a resource must be in for this
+    /// action to apply, from the `resourcesIn` list in the schema.
+    pub fn resource_groups(&self) -> impl Iterator<Item = &EntityUID> {
+        self.applies_to.resource_groups()
+    }
+
+    /// Returns `true` if the principal and resource of a request are each in
+    /// one of the groups this action requires, or the action requires none.
+    /// Only group membership is checked here, not entity types. The ancestors
+    /// of both entities must be transitively closed, as they are for entities
+    /// in an `Entities`.
+    pub fn applies_to_entities(&self, principal: &Entity, resource: &Entity) -> bool {
+        self.applies_to.applies_to_principal(principal)
+            && self.applies_to.applies_to_resource(resource)
+    }
+
+    /// Get the value of the annotation with the given key, if it exists
+    pub fn annotation(&self, key: &str) -> Option<&str> {
+        self.annotations.get(key).map(String::as_str)
+    }
+
+    /// An iterator over the annotations of this action, ordered by key
+    pub fn annotations(&self) -> impl Iterator<Item = (&SmolStr, &String)> {
+        self.annotations.iter()
+    }
+
+    /// Returns `true` if this action is deprecated in the schema
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated
+    }
+
+    /// Get the message explaining why this action is deprecated, if it is
+    /// deprecated and the schema gives one
+    pub fn deprecation_message(&self) -> Option<&str> {
+        match &self.deprecation_message {
+            Some(message) if self.deprecated => Some(message),
+            _ => None,
+        }
+    }
+}
+
+/// The differences between the context attributes of two actions, as computed
+/// by `ValidatorSchema::action_context_diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextDiff {
+    /// Context attributes of the first action which the second action does
+    /// not have.
+    pub only_in_a: BTreeMap<SmolStr, AttributeType>,
+    /// Context attributes of the second action which the first action does
+    /// not have.
+    pub only_in_b: BTreeMap<SmolStr, AttributeType>,
+    /// Context attributes which both actions have, but with different types
+    /// or where only one action requires the attribute. Each entry holds the
+    /// attribute type in the first action and then in the second.
+    pub differing: BTreeMap<SmolStr, (AttributeType, AttributeType)>,
+}
+
+impl ContextDiff {
+    /// Returns `true` if the two actions have identical context attributes.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.differing.is_empty()
+    }
+}
+
+impl Hash for ValidatorActionId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.applies_to.hash(state);
+        super::hash_unordered(&self.descendants, state);
+        self.context.hash(state);
+    }
 }
 
 impl TCNode<EntityUID> for ValidatorActionId {
@@ -67,7 +164,7 @@ impl TCNode<EntityUID> for ValidatorActionId {
 }
 
 /// The principals and resources that an action can be applied to.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub(crate) struct ValidatorApplySpec {
     /// The principal entity types the action can be applied to. This set may
     /// be a singleton set containing the unspecified entity type when the
@@ -82,6 +179,27 @@ pub(crate) struct ValidatorApplySpec {
     /// `principal_apply_spec` about the unspecified entity type.
     #[serde(rename = "resourceApplySpec")]
     resource_apply_spec: HashSet<EntityType>,
+
+    /// Entities, e.g., groups, which the principal must be in for the action
+    /// to apply. Empty if the principal is only constrained by its type.
+    #[serde(rename = "principalsIn")]
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    principal_groups: BTreeSet<EntityUID>,
+
+    /// Entities which the resource must be in for the action to apply. See
+    /// `principal_groups`.
+    #[serde(rename = "resourcesIn")]
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    resource_groups: BTreeSet<EntityUID>,
+}
+
+impl Hash for ValidatorApplySpec {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        super::hash_unordered(&self.principal_apply_spec, state);
+        super::hash_unordered(&self.resource_apply_spec, state);
+        self.principal_groups.hash(state);
+        self.resource_groups.hash(state);
+    }
 }
 
 impl ValidatorApplySpec {
@@ -94,6 +212,23 @@ impl ValidatorApplySpec {
         Self {
             principal_apply_spec,
             resource_apply_spec,
+            principal_groups: BTreeSet::new(),
+            resource_groups: BTreeSet::new(),
+        }
+    }
+
+    /// Require the principal to be in one of `principal_groups` and the
+    /// resource to be in one of `resource_groups`. An empty set leaves the
+    /// principal or resource unconstrained.
+    pub(crate) fn with_groups(
+        self,
+        principal_groups: BTreeSet<EntityUID>,
+        resource_groups: BTreeSet<EntityUID>,
+    ) -> Self {
+        Self {
+            principal_groups,
+            resource_groups,
+            ..self
         }
     }
 
@@ -106,4 +241,41 @@ impl ValidatorApplySpec {
     pub(crate) fn applicable_resource_types(&self) -> impl Iterator<Item = &EntityType> {
         self.resource_apply_spec.iter()
     }
+
+    /// Get the entities which the principal must be in for this spec.
+    pub(crate) fn principal_groups(&self) -> impl Iterator<Item = &EntityUID> {
+        self.principal_groups.iter()
+    }
+
+    /// Get the entities which the resource must be in for this spec.
+    pub(crate) fn resource_groups(&self) -> impl Iterator<Item = &EntityUID> {
+        self.resource_groups.iter()
+    }
+
+    /// Whether `principal` is in one of the principal groups of this spec, or
+    /// the spec has none. `Entity::is_descendant_of` must be transitively
+    /// closed for this to be accurate, as it is in a validated `Entities`.
+    pub(crate) fn applies_to_principal(&self, principal: &Entity) -> bool {
+        Self::is_in_any(principal, &self.principal_groups)
+    }
+
+    /// Whether `resource` is in one of the resource groups of this spec, or
+    /// the spec has none. See `applies_to_principal`.
+    pub(crate) fn applies_to_resource(&self, resource: &Entity) -> bool {
+        Self::is_in_any(resource, &self.resource_groups)
+    }
+
+    /// Whether `entity` is `in` one of `groups`, or `groups` is empty. As with
+    /// the `in` operator, an entity is in itself.
+    fn is_in_any(entity: &Entity, groups: &BTreeSet<EntityUID>) -> bool {
+        groups.is_empty()
+            || groups
+                .iter()
+                .any(|group| entity.uid() == *group || entity.is_descendant_of(group))
+    }
+
+    /// Add more applicable resource types to this spec.
+    pub(crate) fn add_resource_types(&mut self, types: impl IntoIterator<Item = EntityType>) {
+        self.resource_apply_spec.extend(types);
+    }
 }
@@ -0,0 +1,377 @@
+//! This module contains `SchemaFragment::canonicalize`, which rewrites a schema
+//! fragment into a canonical form so that semantically identical fragments
+//! serialize to identical JSON.
+
+use std::collections::HashMap;
+
+use cedar_policy_core::{ast::Name, parser::err::ParseErrors, FromNormalizedStr};
+use smol_str::SmolStr;
+
+use super::ValidatorNamespaceDef;
+use crate::{
+    err::*, ActionEntityUID, ActionType, ApplySpec, AttributesOrContext, EntityParentUID,
+    NamespaceDefinition, SchemaFragment, SchemaMap, SchemaType, SchemaTypeVariant, TypeOfAttribute,
+};
+
+impl SchemaFragment {
+    /// Rewrite this fragment into a canonical form. Two fragments which are
+    /// semantically identical have canonical forms which serialize to
+    /// identical JSON. In canonical form:
+    ///
+    /// - Every entity type, extension type, common type and action type name
+    ///   is fully qualified.
+    /// - Common types declared in this fragment are expanded wherever they are
+    ///   used and removed from `commonTypes`. References to common types which
+    ///   are not declared in this fragment are kept (fully qualified), so they
+    ///   can still be resolved against other fragments, but other fragments
+    ///   can no longer use the common types declared in this one.
+    /// - `memberOfTypes`, entity type and action `memberOf` lists, the
+    ///   `names` of entity types with several possible types, the `appliesTo`
+    ///   lists and `allowedValues` lists are sorted with duplicates removed, and types
+    ///   listed in `resourceTypesWithDescendants` are not repeated in
+    ///   `resourceTypes`.
+    /// - Empty action `memberOf` lists and `attributes` maps, and `appliesTo`
+    ///   declarations equivalent to omitting `appliesTo`, are omitted.
+    /// - A fragment declaring an older `"$version"` of the schema format is
+    ///   migrated to the current version, as by `migrate`.
+    ///
+    /// Qualified action types in `memberOf` lists are resolved from the root
+    /// namespace, as with `ActionReferenceResolution::Root`.
+    pub fn canonicalize(self) -> Result<SchemaFragment> {
+        let fragment = self.migrate()?;
+        let version = fragment.1;
+        let namespaces = fragment
+            .0
+            .into_iter()
+            .map(|(ns_str, ns_def)| {
+                let namespace = match ns_str.as_str() {
+                    "" => None,
+                    ns => Some(Name::from_normalized_str(ns).map_err(SchemaError::ParseNamespace)?),
+                };
+                Ok((ns_str, namespace, ns_def))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Common types may refer to other common types, so their definitions
+        // are canonicalized repeatedly, each time expanding the definitions
+        // from the previous round, until they no longer change. Unless the
+        // references are cyclic, this takes at most one round more than there
+        // are common types; the references in a cycle are kept, and reported
+        // when the schema is constructed.
+        let definitions = namespaces
+            .iter()
+            .flat_map(|(ns_str, namespace, ns_def)| {
+                ns_def
+                    .common_types
+                    .iter()
+                    .map(move |(name_str, ty)| (ns_str, namespace, name_str, ty))
+            })
+            .collect::<Vec<_>>();
+        let mut common_types = HashMap::new();
+        for _ in 0..=definitions.len() {
+            let mut expanded = HashMap::new();
+            for (ns_str, namespace, name_str, ty) in definitions.iter() {
+                let canonicalizer = Canonicalizer {
+                    namespace: namespace.as_ref(),
+                    common_types: &common_types,
+                };
+                let in_context = |e: SchemaError| {
+                    e.in_section(SchemaSection::CommonType((*name_str).clone()))
+                        .in_namespace(Some(ns_str))
+                };
+                let name = ValidatorNamespaceDef::parse_unqualified_name_with_namespace(
+                    name_str,
+                    (*namespace).clone(),
+                )
+                .map_err(SchemaError::parse_common_type)
+                .map_err(in_context)?;
+                let ty = canonicalizer
+                    .schema_type((*ty).clone())
+                    .map_err(in_context)?;
+                expanded.insert(name, ty);
+            }
+            if expanded == common_types {
+                break;
+            }
+            common_types = expanded;
+        }
+
+        namespaces
+            .into_iter()
+            .map(|(ns_str, namespace, ns_def)| {
+                let canonicalizer = Canonicalizer {
+                    namespace: namespace.as_ref(),
+                    common_types: &common_types,
+                };
+                let ns_def = canonicalizer
+                    .namespace_def(ns_def)
+                    .map_err(|e| e.in_namespace(Some(&ns_str)))?;
+                Ok((ns_str, ns_def))
+            })
+            .collect::<Result<SchemaMap<_, _>>>()
+            .map(|namespaces| SchemaFragment(namespaces, version))
+    }
+}
+
+/// Canonicalizes the declarations of a single namespace.
+struct Canonicalizer<'a> {
+    /// The namespace containing the declarations, used to qualify names.
+    namespace: Option<&'a Name>,
+    /// Canonical definitions of the common types to expand, keyed by their
+    /// fully qualified names.
+    common_types: &'a HashMap<Name, SchemaType>,
+}
+
+impl Canonicalizer<'_> {
+    fn namespace_def(&self, ns_def: NamespaceDefinition) -> Result<NamespaceDefinition> {
+        let entity_types = ns_def
+            .entity_types
+            .into_iter()
+            .map(|(name, entity_type)| {
+                let member_of_types = self
+                    .entity_type_list(entity_type.member_of_types)
+                    .map_err(|e| e.in_section(SchemaSection::EntityTypeMemberOf(name.clone())))?;
+                let member_of = self
+                    .entity_parent_list(entity_type.member_of)
+                    .map_err(|e| e.in_section(SchemaSection::EntityTypeMemberOf(name.clone())))?;
+                let shape = entity_type
+                    .shape
+                    .map(|shape| self.schema_type(shape.into_inner()))
+                    .transpose()
+                    .map_err(|e| e.in_section(SchemaSection::EntityTypeShape(name.clone())))?;
+                Ok((
+                    name,
+                    crate::EntityType {
+                        member_of_types,
+                        member_of,
+                        shape: shape.map(AttributesOrContext),
+                        annotations: entity_type.annotations,
+                        id_format: entity_type.id_format,
+                        deprecated: entity_type.deprecated,
+                        deprecation_message: entity_type.deprecation_message,
+                    },
+                ))
+            })
+            .collect::<Result<SchemaMap<_, _>>>()?;
+        let actions = ns_def
+            .actions
+            .into_iter()
+            .map(|(name, action)| {
+                let action = self.action_type(&name, action)?;
+                Ok((name, action))
+            })
+            .collect::<Result<SchemaMap<_, _>>>()?;
+        Ok(NamespaceDefinition {
+            common_types: SchemaMap::new(),
+            entity_types,
+            actions,
+        })
+    }
+
+    fn action_type(&self, name: &SmolStr, action: ActionType) -> Result<ActionType> {
+        let applies_to = action
+            .applies_to
+            .map(|spec| self.apply_spec(spec))
+            .transpose()
+            .map_err(|e| e.in_section(SchemaSection::ActionAppliesTo(name.clone())))?
+            .filter(|spec| !Self::is_unspecified(spec));
+        let member_of = action
+            .member_of
+            .map(|parents| self.action_list(parents))
+            .transpose()
+            .map_err(|e| e.in_section(SchemaSection::ActionMemberOf(name.clone())))?
+            .filter(|parents| !parents.is_empty());
+        Ok(ActionType {
+            attributes: action.attributes.filter(|attrs| !attrs.is_empty()),
+            applies_to,
+            member_of,
+            annotations: action.annotations,
+            deprecated: action.deprecated,
+            deprecation_message: action.deprecation_message,
+        })
+    }
+
+    /// An apply spec without principal or resource types or entities and with
+    /// an empty context is equivalent to omitting `appliesTo`.
+    fn is_unspecified(spec: &ApplySpec) -> bool {
+        spec.principal_types.is_none()
+            && spec.resource_types.is_none()
+            && spec.resource_types_with_descendants.is_none()
+            && spec.principals_in.is_empty()
+            && spec.resources_in.is_empty()
+            && spec.context == AttributesOrContext::default()
+    }
+
+    fn apply_spec(&self, spec: ApplySpec) -> Result<ApplySpec> {
+        let resource_types_with_descendants = spec
+            .resource_types_with_descendants
+            .map(|types| self.entity_type_list(types))
+            .transpose()?;
+        let mut resource_types = spec
+            .resource_types
+            .map(|types| self.entity_type_list(types))
+            .transpose()?;
+        // Types in `resourceTypesWithDescendants` are applicable resource
+        // types themselves, and listing them makes the resource apply spec
+        // defined even if `resourceTypes` is absent.
+        if let Some(roots) = &resource_types_with_descendants {
+            resource_types
+                .get_or_insert_with(Vec::new)
+                .retain(|ty| !roots.contains(ty));
+        }
+        Ok(ApplySpec {
+            resource_types,
+            resource_types_with_descendants,
+            principal_types: spec
+                .principal_types
+                .map(|types| self.entity_type_list(types))
+                .transpose()?,
+            principals_in: self.entity_parent_list(spec.principals_in)?,
+            resources_in: self.entity_parent_list(spec.resources_in)?,
+            context: AttributesOrContext(self.schema_type(spec.context.into_inner())?),
+        })
+    }
+
+    fn entity_type_list(&self, types: Vec<SmolStr>) -> Result<Vec<SmolStr>> {
+        let mut types = types
+            .iter()
+            .map(|ty| self.qualify(ty).map_err(SchemaError::parse_entity_type))
+            .collect::<Result<Vec<_>>>()?;
+        types.sort();
+        types.dedup();
+        Ok(types)
+    }
+
+    fn entity_parent_list(&self, parents: Vec<EntityParentUID>) -> Result<Vec<EntityParentUID>> {
+        let mut parents = parents
+            .into_iter()
+            .map(|parent| {
+                Ok(EntityParentUID {
+                    ty: self
+                        .qualify(&parent.ty)
+                        .map_err(SchemaError::parse_entity_type)?,
+                    id: parent.id,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        parents.sort();
+        parents.dedup();
+        Ok(parents)
+    }
+
+    fn action_list(&self, actions: Vec<ActionEntityUID>) -> Result<Vec<ActionEntityUID>> {
+        let mut actions = actions
+            .iter()
+            .map(|action| {
+                let euid =
+                    ValidatorNamespaceDef::parse_action_id_with_namespace(action, self.namespace)?;
+                Ok((euid.entity_type().to_string(), action.id.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        actions.sort();
+        actions.dedup();
+        Ok(actions
+            .into_iter()
+            .map(|(ty, id)| ActionEntityUID {
+                id,
+                ty: Some(ty.into()),
+            })
+            .collect())
+    }
+
+    fn schema_type(&self, ty: SchemaType) -> Result<SchemaType> {
+        Ok(match ty {
+            SchemaType::Type(SchemaTypeVariant::Set { element }) => SchemaTypeVariant::Set {
+                element: Box::new(self.schema_type(*element)?),
+            }
+            .into(),
+            SchemaType::Type(SchemaTypeVariant::Record {
+                attributes,
+                additional_attributes,
+            }) => SchemaTypeVariant::Record {
+                attributes: attributes
+                    .into_iter()
+                    .map(|(attr, attr_ty)| {
+                        Ok((
+                            attr,
+                            TypeOfAttribute {
+                                ty: self.schema_type(attr_ty.ty)?,
+                                ..attr_ty
+                            },
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+                additional_attributes,
+            }
+            .into(),
+            SchemaType::Type(SchemaTypeVariant::Entity { name }) => SchemaTypeVariant::Entity {
+                name: self
+                    .qualify(&name)
+                    .map_err(SchemaError::parse_entity_type)?,
+            }
+            .into(),
+            SchemaType::Type(SchemaTypeVariant::EntityUnion { names }) => {
+                SchemaTypeVariant::EntityUnion {
+                    names: self.entity_type_list(names)?,
+                }
+                .into()
+            }
+            SchemaType::Type(SchemaTypeVariant::Extension { name }) => {
+                SchemaTypeVariant::Extension {
+                    name: Name::from_normalized_str(&name)
+                        .map_err(SchemaError::parse_extension_type)?
+                        .to_string()
+                        .into(),
+                }
+                .into()
+            }
+            SchemaType::TypeDef { type_name } => {
+                let name =
+                    ValidatorNamespaceDef::parse_possibly_qualified_name_with_default_namespace(
+                        &type_name,
+                        self.namespace,
+                    )
+                    .map_err(SchemaError::parse_common_type)?;
+                // Resolve unqualified references as in schema construction:
+                // first in this namespace, then in the root namespace.
+                let root_ty = Name::parse_unqualified_name(&type_name)
+                    .ok()
+                    .filter(|root_name| *root_name != name)
+                    .and_then(|root_name| self.common_types.get(&root_name));
+                match (self.common_types.get(&name), root_ty) {
+                    (Some(_), Some(_)) => {
+                        return Err(SchemaError::AmbiguousCommonType {
+                            reference: type_name.to_string(),
+                            local: name.to_string(),
+                        })
+                    }
+                    (Some(ty), None) | (None, Some(ty)) => ty.clone(),
+                    (None, None) => SchemaType::TypeDef {
+                        type_name: name.to_string().into(),
+                    },
+                }
+            }
+            SchemaType::Type(SchemaTypeVariant::String { allowed_values }) => {
+                SchemaTypeVariant::String {
+                    allowed_values: allowed_values.map(|mut values| {
+                        values.sort();
+                        values.dedup();
+                        values
+                    }),
+                }
+                .into()
+            }
+            ty @ SchemaType::Type(SchemaTypeVariant::Long | SchemaTypeVariant::Boolean) => ty,
+        })
+    }
+
+    /// Qualify an entity type name with the namespace of the declarations if
+    /// it is not already qualified.
+    fn qualify(&self, name: &SmolStr) -> std::result::Result<SmolStr, ParseErrors> {
+        ValidatorNamespaceDef::parse_possibly_qualified_name_with_default_namespace(
+            name,
+            self.namespace,
+        )
+        .map(|name| name.to_string().into())
+    }
+}
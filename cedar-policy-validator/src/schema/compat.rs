@@ -0,0 +1,280 @@
+//! This module contains `ValidatorSchema::is_compatible_extension_of`, which
+//! checks that a new version of a schema does not break anything which
+//! conformed to an earlier version.
+
+use std::fmt::Display;
+
+use cedar_policy_core::ast::{EntityType, EntityUID, Name, PrincipalOrResource};
+use smol_str::SmolStr;
+
+use super::{ValidatorApplySpec, ValidatorSchema};
+use crate::{
+    types::{Attributes, Type},
+    ContextOrShape, ValidationMode,
+};
+
+/// A difference between two schemas which makes the newer one an
+/// incompatible extension of the older one, as reported by
+/// `ValidatorSchema::is_compatible_extension_of`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incompatibility {
+    /// An entity type of the base schema is not declared
+    RemovedEntityType(Name),
+    /// An action of the base schema is not declared
+    RemovedAction(EntityUID),
+    /// An entity type is no longer a member of an entity type it was a member
+    /// of, directly or transitively, in the base schema
+    RemovedMemberOfType {
+        /// The member entity type
+        entity_type: Name,
+        /// The entity type it is no longer a member of
+        parent: Name,
+    },
+    /// An attribute of an entity type shape or action context in the base
+    /// schema is not declared
+    RemovedAttribute {
+        /// The shape or context which declared the attribute
+        location: ContextOrShape,
+        /// The attribute
+        attr: SmolStr,
+    },
+    /// An attribute is required which was optional or not declared in the
+    /// base schema
+    NewlyRequiredAttribute {
+        /// The shape or context declaring the attribute
+        location: ContextOrShape,
+        /// The attribute
+        attr: SmolStr,
+    },
+    /// The type of an attribute does not admit every value its type in the
+    /// base schema did
+    NarrowedAttributeType {
+        /// The shape or context declaring the attribute
+        location: ContextOrShape,
+        /// The attribute
+        attr: SmolStr,
+    },
+    /// An action no longer applies to some principal or resource types it
+    /// applied to in the base schema
+    NarrowedAppliesTo {
+        /// The action
+        action: EntityUID,
+        /// Whether `removed_types` are principal or resource types
+        target: PrincipalOrResource,
+        /// The entity types the action no longer applies to, sorted
+        removed_types: Vec<EntityType>,
+    },
+    /// The `principalsIn` or `resourcesIn` list of an action is no longer
+    /// empty, or omits an entity which the list in the base schema has
+    NarrowedGroups {
+        /// The action
+        action: EntityUID,
+        /// Whether the list is `principalsIn` or `resourcesIn`
+        target: PrincipalOrResource,
+    },
+}
+
+impl Display for Incompatibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RemovedEntityType(name) => write!(f, "entity type `{name}` was removed"),
+            Self::RemovedAction(euid) => write!(f, "action `{euid}` was removed"),
+            Self::RemovedMemberOfType {
+                entity_type,
+                parent,
+            } => write!(
+                f,
+                "entity type `{entity_type}` is no longer a member of `{parent}`"
+            ),
+            Self::RemovedAttribute { location, attr } => {
+                write!(f, "{location}: attribute `{attr}` was removed")
+            }
+            Self::NewlyRequiredAttribute { location, attr } => {
+                write!(f, "{location}: attribute `{attr}` is newly required")
+            }
+            Self::NarrowedAttributeType { location, attr } => {
+                write!(f, "{location}: type of attribute `{attr}` was narrowed")
+            }
+            Self::NarrowedAppliesTo {
+                action,
+                target,
+                removed_types,
+            } => write!(
+                f,
+                "action `{action}` no longer applies to {target} types {}",
+                removed_types
+                    .iter()
+                    .map(|ty| format!("`{ty}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::NarrowedGroups { action, target } => write!(
+                f,
+                "action `{action}` no longer applies to some {target} entities it applied to"
+            ),
+        }
+    }
+}
+
+impl ValidatorSchema {
+    /// Check that this schema is a compatible extension of `base`, e.g., a
+    /// newer version of it, so that every entity and request which conforms
+    /// to `base` also conforms to this schema. Every entity type and action
+    /// of `base` must be declared, entity types must still be members of the
+    /// entity types they were members of, and attribute types and the
+    /// `appliesTo` of actions may be widened but not narrowed. New
+    /// attributes must be optional.
+    ///
+    /// Returns every breaking difference otherwise. Differences for entity
+    /// types come first, then those for actions, each sorted by name. The
+    /// type of an attribute is narrowed unless its type in `base` is a
+    /// subtype of its type here, so adding an attribute to a record nested in
+    /// an attribute is reported as narrowing, even if it is optional.
+    pub fn is_compatible_extension_of(
+        &self,
+        base: &ValidatorSchema,
+    ) -> std::result::Result<(), Vec<Incompatibility>> {
+        let mut incompatibilities = Vec::new();
+        for (name, base_type) in base.entity_types_sorted() {
+            let Some(entity_type) = self.get_entity_type(name) else {
+                incompatibilities.push(Incompatibility::RemovedEntityType(name.clone()));
+                continue;
+            };
+            self.attribute_incompatibilities(
+                ContextOrShape::EntityTypeShape(name.clone()),
+                &base_type.attributes,
+                &entity_type.attributes,
+                &mut incompatibilities,
+            );
+            let mut removed_members = base_type
+                .descendants
+                .iter()
+                .filter(|member| {
+                    !entity_type.descendants.contains(*member)
+                        && self.get_entity_type(member).is_some()
+                })
+                .collect::<Vec<_>>();
+            removed_members.sort_by_cached_key(|member| member.to_string());
+            incompatibilities.extend(removed_members.into_iter().map(|member| {
+                Incompatibility::RemovedMemberOfType {
+                    entity_type: member.clone(),
+                    parent: name.clone(),
+                }
+            }));
+        }
+
+        for (euid, base_action) in base.actions_sorted() {
+            let Some(action) = self.get_action_id(euid) else {
+                incompatibilities.push(Incompatibility::RemovedAction(euid.clone()));
+                continue;
+            };
+            self.attribute_incompatibilities(
+                ContextOrShape::ActionContext(euid.clone()),
+                &base_action.context,
+                &action.context,
+                &mut incompatibilities,
+            );
+            for target in [
+                PrincipalOrResource::Principal,
+                PrincipalOrResource::Resource,
+            ] {
+                let (base_types, base_groups) = applies_to(&base_action.applies_to, target);
+                let (types, groups) = applies_to(&action.applies_to, target);
+                let mut removed_types = base_types
+                    .into_iter()
+                    .filter(|ty| !types.contains(ty))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !removed_types.is_empty() {
+                    removed_types.sort();
+                    incompatibilities.push(Incompatibility::NarrowedAppliesTo {
+                        action: euid.clone(),
+                        target,
+                        removed_types,
+                    });
+                }
+                if groups_narrowed(&base_groups, &groups) {
+                    incompatibilities.push(Incompatibility::NarrowedGroups {
+                        action: euid.clone(),
+                        target,
+                    });
+                }
+            }
+        }
+
+        if incompatibilities.is_empty() {
+            Ok(())
+        } else {
+            Err(incompatibilities)
+        }
+    }
+
+    /// Push the incompatibilities between the attributes `base_attrs` of a
+    /// shape or context in the base schema and `attrs` in this schema onto
+    /// `incompatibilities`, sorted by attribute.
+    fn attribute_incompatibilities(
+        &self,
+        location: ContextOrShape,
+        base_attrs: &Attributes,
+        attrs: &Attributes,
+        incompatibilities: &mut Vec<Incompatibility>,
+    ) {
+        let mut names = base_attrs.keys().chain(attrs.keys()).collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        for attr in names {
+            let location = location.clone();
+            let attr = attr.clone();
+            match (base_attrs.get_attr(&attr), attrs.get_attr(&attr)) {
+                (Some(_), None) => {
+                    incompatibilities.push(Incompatibility::RemovedAttribute { location, attr })
+                }
+                (Some(base_ty), Some(ty))
+                    if !Type::is_subtype(
+                        self,
+                        &base_ty.attr_type,
+                        &ty.attr_type,
+                        ValidationMode::Permissive,
+                    ) =>
+                {
+                    incompatibilities
+                        .push(Incompatibility::NarrowedAttributeType { location, attr })
+                }
+                (base_ty, Some(ty))
+                    if ty.is_required && !base_ty.map(|ty| ty.is_required).unwrap_or(false) =>
+                {
+                    incompatibilities
+                        .push(Incompatibility::NewlyRequiredAttribute { location, attr })
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// The principal or resource types of `apply_spec`, depending on `target`,
+/// and the entities listed in its `principalsIn` or `resourcesIn`.
+fn applies_to(
+    apply_spec: &ValidatorApplySpec,
+    target: PrincipalOrResource,
+) -> (Vec<&EntityType>, Vec<&EntityUID>) {
+    match target {
+        PrincipalOrResource::Principal => (
+            apply_spec.applicable_principal_types().collect(),
+            apply_spec.principal_groups().collect(),
+        ),
+        PrincipalOrResource::Resource => (
+            apply_spec.applicable_resource_types().collect(),
+            apply_spec.resource_groups().collect(),
+        ),
+    }
+}
+
+/// Does the `principalsIn` or `resourcesIn` list `groups` narrow the list
+/// `base_groups`? An empty list allows every entity, so a nonempty list
+/// narrows it, and a nonempty list is narrowed by a list which omits any of
+/// its entities. The entity hierarchy is not considered.
+fn groups_narrowed(base_groups: &[&EntityUID], groups: &[&EntityUID]) -> bool {
+    !groups.is_empty()
+        && (base_groups.is_empty() || base_groups.iter().any(|group| !groups.contains(group)))
+}
@@ -0,0 +1,97 @@
+//! This module contains `SchemaComposer`, which accumulates schema fragments
+//! as they become available and builds a `ValidatorSchema` from them once.
+
+use std::collections::HashMap;
+
+use cedar_policy_core::ast::{EntityUID, Name};
+
+use super::{ValidatorNamespaceDef, ValidatorSchema, ValidatorSchemaFragment};
+use crate::{FragmentIndices, Result, SchemaError};
+
+/// Builds a `ValidatorSchema` from fragments which are added one at a time,
+/// e.g., as plugins register their schemas. Adding a fragment only checks
+/// that it does not redeclare a common type, entity type or action declared
+/// by an earlier fragment. Everything else (resolving common types, computing
+/// the transitive closure of the hierarchies and checking for undeclared
+/// names) happens once, in `finalize`, exactly as in
+/// `ValidatorSchema::from_schema_fragments`.
+#[derive(Debug, Default)]
+pub struct SchemaComposer {
+    namespaces: Vec<ValidatorNamespaceDef>,
+    /// The number of fragments added so far
+    fragments: usize,
+    /// The index of the fragment declaring each common type, entity type and
+    /// action added so far
+    common_types: HashMap<Name, usize>,
+    entity_types: HashMap<Name, usize>,
+    actions: HashMap<EntityUID, usize>,
+}
+
+impl SchemaComposer {
+    /// Create a composer with no fragments.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a fragment. Returns a `DuplicateCommonType`, `DuplicateEntityType`
+    /// or `DuplicateAction` error if the fragment declares something which was
+    /// already declared, either by an earlier fragment or twice in this one.
+    /// When an earlier fragment declared it, the error carries the indices of
+    /// both fragments, counting the fragments in the order they were added.
+    /// The fragment is not added if an error is returned, so the composer can
+    /// still be used.
+    pub fn add_fragment(&mut self, fragment: ValidatorSchemaFragment) -> Result<()> {
+        let index = self.fragments;
+        let fragment_indices = |first: Option<&usize>| {
+            first.map(|&first| FragmentIndices {
+                first,
+                duplicate: index,
+            })
+        };
+        let mut common_types = HashMap::new();
+        let mut entity_types = HashMap::new();
+        let mut actions = HashMap::new();
+        for ns_def in fragment.0.iter() {
+            for name in ns_def.type_defs.type_defs.keys() {
+                let first = self.common_types.get(name);
+                if first.is_some() || common_types.insert(name.clone(), index).is_some() {
+                    return Err(SchemaError::DuplicateCommonType(
+                        name.to_string(),
+                        fragment_indices(first),
+                    ));
+                }
+            }
+            for name in ns_def.entity_types.entity_types.keys() {
+                let first = self.entity_types.get(name);
+                if first.is_some() || entity_types.insert(name.clone(), index).is_some() {
+                    return Err(SchemaError::DuplicateEntityType(
+                        name.to_string(),
+                        fragment_indices(first),
+                    ));
+                }
+            }
+            for action in ns_def.actions.actions.keys() {
+                let first = self.actions.get(action);
+                if first.is_some() || actions.insert(action.clone(), index).is_some() {
+                    return Err(SchemaError::DuplicateAction(
+                        action.to_string(),
+                        fragment_indices(first),
+                    ));
+                }
+            }
+        }
+        self.common_types.extend(common_types);
+        self.entity_types.extend(entity_types);
+        self.actions.extend(actions);
+        self.namespaces.extend(fragment.0);
+        self.fragments += 1;
+        Ok(())
+    }
+
+    /// Build the schema from every fragment added so far. This returns the
+    /// same result as calling `ValidatorSchema::from_schema_fragments` with
+    /// those fragments.
+    pub fn finalize(self) -> Result<ValidatorSchema> {
+        ValidatorSchema::from_schema_fragments([ValidatorSchemaFragment(self.namespaces)])
+    }
+}
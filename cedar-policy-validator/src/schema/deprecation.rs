@@ -0,0 +1,150 @@
+//! This module contains the queries on entity types and actions which the
+//! schema marks as `deprecated`, supporting their staged removal.
+
+use std::fmt::Display;
+
+use cedar_policy_core::ast::{EntityType, EntityUID, Name, PolicyID, PolicySet};
+use smol_str::SmolStr;
+
+use super::ValidatorSchema;
+
+/// An entity type or action which the schema marks as deprecated.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DeprecatedItem {
+    /// A deprecated entity type
+    EntityType(Name),
+    /// A deprecated action
+    Action(EntityUID),
+}
+
+impl Display for DeprecatedItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EntityType(name) => write!(f, "entity type `{name}`"),
+            Self::Action(euid) => write!(f, "action `{euid}`"),
+        }
+    }
+}
+
+/// A policy which refers to a deprecated entity type or action in its scope,
+/// as reported by `ValidatorSchema::check_no_deprecated_usage`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeprecatedUsage {
+    /// The policy, template or template-linked policy referring to `item`
+    pub policy_id: PolicyID,
+    /// The deprecated entity type or action
+    pub item: DeprecatedItem,
+    /// The message the schema gives for the deprecation, if any
+    pub message: Option<SmolStr>,
+}
+
+impl Display for DeprecatedUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "policy `{}` refers to deprecated {}",
+            self.policy_id, self.item
+        )?;
+        match &self.message {
+            Some(message) => write!(f, ": {message}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ValidatorSchema {
+    /// Get every entity type and action marked as deprecated in the schema,
+    /// with its deprecation message if there is one. Entity types come first,
+    /// then actions, each sorted by name.
+    pub fn deprecated_items(&self) -> Vec<(DeprecatedItem, Option<&str>)> {
+        let mut items = self
+            .entity_types
+            .iter()
+            .filter(|(_, entity_type)| entity_type.is_deprecated())
+            .map(|(name, entity_type)| {
+                (
+                    DeprecatedItem::EntityType(name.clone()),
+                    entity_type.deprecation_message(),
+                )
+            })
+            .chain(
+                self.action_ids
+                    .iter()
+                    .filter(|(_, action)| action.is_deprecated())
+                    .map(|(euid, action)| {
+                        (
+                            DeprecatedItem::Action(euid.clone()),
+                            action.deprecation_message(),
+                        )
+                    }),
+            )
+            .collect::<Vec<_>>();
+        items.sort();
+        items
+    }
+
+    /// Find the policies whose scope refers to a deprecated entity type or
+    /// action, either directly or through an entity of a deprecated type. The
+    /// scopes of static policies and templates are checked, as are the values
+    /// linked into the slots of template-linked policies. Only the scope is
+    /// checked, not the conditions of a policy.
+    ///
+    /// The result is sorted by policy id and has one entry per policy and
+    /// deprecated item. An empty result means no policy targets a deprecated
+    /// item, so a staged removal can fail validation of new policies when the
+    /// result is not empty.
+    pub fn check_no_deprecated_usage(&self, policies: &PolicySet) -> Vec<DeprecatedUsage> {
+        let mut usages = Vec::new();
+        for template in policies.all_templates() {
+            let euids = template
+                .principal_constraint()
+                .as_inner()
+                .iter_euids()
+                .chain(template.action_constraint().iter_euids())
+                .chain(template.resource_constraint().as_inner().iter_euids());
+            for euid in euids {
+                self.push_deprecated_usages(template.id(), euid, &mut usages);
+            }
+        }
+        for policy in policies.policies().filter(|policy| !policy.is_static()) {
+            for euid in policy.env().values() {
+                self.push_deprecated_usages(policy.id(), euid, &mut usages);
+            }
+        }
+        usages.sort_by(|a, b| {
+            (a.policy_id.to_string(), &a.item).cmp(&(b.policy_id.to_string(), &b.item))
+        });
+        usages.dedup();
+        usages
+    }
+
+    /// Push a `DeprecatedUsage` onto `usages` if `euid` is a deprecated action
+    /// or an entity of a deprecated entity type.
+    fn push_deprecated_usages(
+        &self,
+        policy_id: &PolicyID,
+        euid: &EntityUID,
+        usages: &mut Vec<DeprecatedUsage>,
+    ) {
+        if let Some(action) = self.get_action_id(euid) {
+            if action.is_deprecated() {
+                usages.push(DeprecatedUsage {
+                    policy_id: policy_id.clone(),
+                    item: DeprecatedItem::Action(action.name.clone()),
+                    message: action.deprecation_message().map(Into::into),
+                });
+            }
+        }
+        if let EntityType::Concrete(name) = euid.entity_type() {
+            if let Some(entity_type) = self.get_entity_type(name) {
+                if entity_type.is_deprecated() {
+                    usages.push(DeprecatedUsage {
+                        policy_id: policy_id.clone(),
+                        item: DeprecatedItem::EntityType(name.clone()),
+                        message: entity_type.deprecation_message().map(Into::into),
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! This module contains `ValidatorSchema::describe_action`, which collects
+//! everything the schema says about an action into one serializable summary,
+//! e.g., for generating API documentation.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use cedar_policy_core::ast::{EntityType, EntityUID, Name};
+use serde::Serialize;
+use smol_str::SmolStr;
+
+use super::ValidatorSchema;
+
+/// Everything the schema declares about an action, as returned by
+/// `ValidatorSchema::describe_action`. Names are fully qualified and rendered
+/// as strings, and every list is sorted, so that the description can be
+/// emitted as JSON with a stable shape.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionDescription {
+    /// The action
+    pub uid: String,
+    /// The action groups the action is a member of, directly or transitively
+    pub ancestors: Vec<String>,
+    /// The actions which are members of the action, directly or transitively
+    pub descendants: Vec<String>,
+    /// The entity types the action applies to as its principal. The
+    /// unspecified entity type is not included.
+    pub principal_types: Vec<String>,
+    /// The entity types the action applies to as its resource. The
+    /// unspecified entity type is not included.
+    pub resource_types: Vec<String>,
+    /// The entities listed in `principalsIn`, one of which the principal must
+    /// be in, if any
+    pub principals_in: Vec<String>,
+    /// The entities listed in `resourcesIn`, one of which the resource must be
+    /// in, if any
+    pub resources_in: Vec<String>,
+    /// The attributes of the context of the action, sorted by name
+    pub context: Vec<ContextAttributeDescription>,
+    /// The annotations declared for the action
+    pub annotations: BTreeMap<SmolStr, String>,
+}
+
+/// An attribute of the context of an action, as part of an
+/// `ActionDescription`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContextAttributeDescription {
+    /// The name of the attribute
+    pub name: SmolStr,
+    /// The type of the attribute, in the JSON schema format
+    #[serde(rename = "type")]
+    pub ty: serde_json::Value,
+    /// Whether the attribute is required
+    pub required: bool,
+}
+
+impl ValidatorSchema {
+    /// Describe the action `uid`: its place in the action hierarchy, what it
+    /// applies to, its context attributes and its annotations. Returns `None`
+    /// if the action is not in the schema. The action is matched according to
+    /// the `LookupConfig` of the schema, and described with the id it was
+    /// declared with.
+    pub fn describe_action(&self, uid: &EntityUID) -> Option<ActionDescription> {
+        let action = self.get_action_id(uid)?;
+        let uid = &action.name;
+        Some(ActionDescription {
+            uid: uid.to_string(),
+            ancestors: sorted_strings(
+                self.action_ids
+                    .iter()
+                    .filter(|(_, ancestor)| ancestor.descendants.contains(uid))
+                    .map(|(ancestor, _)| ancestor),
+            ),
+            descendants: sorted_strings(action.descendants.iter()),
+            principal_types: sorted_strings(concrete(
+                action.applies_to.applicable_principal_types(),
+            )),
+            resource_types: sorted_strings(concrete(action.applies_to.applicable_resource_types())),
+            principals_in: sorted_strings(action.principal_groups()),
+            resources_in: sorted_strings(action.resource_groups()),
+            context: action
+                .context()
+                .map(|(name, attr_ty)| ContextAttributeDescription {
+                    name: name.clone(),
+                    ty: serde_json::Value::Object(attr_ty.attr_type.to_type_json()),
+                    required: attr_ty.is_required,
+                })
+                .collect(),
+            annotations: action
+                .annotations()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        })
+    }
+}
+
+/// The concrete entity types among `types`.
+fn concrete<'a>(types: impl Iterator<Item = &'a EntityType>) -> impl Iterator<Item = &'a Name> {
+    types.filter_map(|ty| match ty {
+        EntityType::Concrete(name) => Some(name),
+        EntityType::Unspecified => None,
+    })
+}
+
+/// The string forms of `items`, sorted.
+fn sorted_strings(items: impl Iterator<Item = impl Display>) -> Vec<String> {
+    let mut strings = items.map(|item| item.to_string()).collect::<Vec<_>>();
+    strings.sort();
+    strings
+}
@@ -0,0 +1,114 @@
+//! This module contains the export of the entity type and action hierarchies
+//! of a schema as a GraphViz DOT graph.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::hash::Hash;
+
+use super::ValidatorSchema;
+
+impl ValidatorSchema {
+    /// Render the entity type and action hierarchies of this schema as a
+    /// GraphViz DOT graph. Entity types and actions are nodes in two separate
+    /// clusters, with an edge from each parent to its direct children (the
+    /// transitive reduction of the `descendants` relation). Each entity
+    /// reference in the type of an entity attribute is drawn as a dashed edge
+    /// labeled with the attribute name. The output is sorted, so it is stable
+    /// for a given schema.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph schema {\n");
+
+        out.push_str("  subgraph cluster_entity_types {\n");
+        out.push_str("    label=\"Entity types\";\n");
+        out.push_str("    node [shape=box, style=filled, fillcolor=lightblue];\n");
+        let entity_types = self
+            .entity_types
+            .keys()
+            .map(ToString::to_string)
+            .collect::<BTreeSet<_>>();
+        for name in &entity_types {
+            let _ = writeln!(out, "    {};", quote(name));
+        }
+        for (parent, child) in hierarchy_edges(
+            self.entity_types
+                .iter()
+                .map(|(name, ety)| (name, &ety.descendants))
+                .collect(),
+        ) {
+            let _ = writeln!(out, "    {} -> {};", quote(&parent), quote(&child));
+        }
+        out.push_str("  }\n");
+
+        out.push_str("  subgraph cluster_actions {\n");
+        out.push_str("    label=\"Actions\";\n");
+        out.push_str("    node [shape=ellipse, style=filled, fillcolor=lightyellow];\n");
+        let actions = self
+            .action_ids
+            .keys()
+            .map(ToString::to_string)
+            .collect::<BTreeSet<_>>();
+        for name in &actions {
+            let _ = writeln!(out, "    {};", quote(name));
+        }
+        for (parent, child) in hierarchy_edges(
+            self.action_ids
+                .iter()
+                .map(|(euid, action)| (euid, &action.descendants))
+                .collect(),
+        ) {
+            let _ = writeln!(out, "    {} -> {};", quote(&parent), quote(&child));
+        }
+        out.push_str("  }\n");
+
+        let mut references = BTreeSet::new();
+        for (name, ety) in self.entity_types.iter() {
+            for (attr, attr_ty) in ety.attributes() {
+                let mut referenced = Vec::new();
+                Self::collect_entity_types_in_type(&attr_ty.attr_type, &mut referenced);
+                for target in referenced {
+                    references.insert((name.to_string(), target.to_string(), attr.to_string()));
+                }
+            }
+        }
+        for (source, target, attr) in references {
+            let _ = writeln!(
+                out,
+                "  {} -> {} [style=dashed, label={}];",
+                quote(&source),
+                quote(&target),
+                quote(&attr)
+            );
+        }
+
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Compute the edges from each node to its direct children, given each node
+/// with its transitively closed descendants. A descendant is a direct child
+/// unless it is also a descendant of another descendant of the node. Edges
+/// are returned sorted by their rendered names.
+fn hierarchy_edges<K: Eq + Hash + ToString>(
+    nodes: HashMap<&K, &HashSet<K>>,
+) -> BTreeSet<(String, String)> {
+    let mut edges = BTreeSet::new();
+    for (parent, descendants) in nodes.iter() {
+        for child in descendants.iter() {
+            let indirect = descendants.iter().any(|other| {
+                other != child
+                    && other != *parent
+                    && nodes.get(other).map(|d| d.contains(child)).unwrap_or(false)
+            });
+            if !indirect && child != *parent {
+                edges.insert((parent.to_string(), child.to_string()));
+            }
+        }
+    }
+    edges
+}
+
+/// Quote a string as a DOT identifier.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
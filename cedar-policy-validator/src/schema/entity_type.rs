@@ -1,20 +1,25 @@
 //! This module contains the definition of `ValidatorEntityType`
 
-use serde::Serialize;
+use regex::Regex;
+use serde::{Serialize, Serializer};
 use smol_str::SmolStr;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 
 use cedar_policy_core::{
-    ast::{EntityType, Name},
+    ast::{EntityType, EntityUID, Name, RestrictedExpr},
     transitive_closure::TCNode,
 };
 
-use crate::types::{AttributeType, Attributes};
+use crate::types::{AttributeType, Attributes, Type};
 
 /// Contains entity type information for use by the validator. The contents of
 /// the struct are the same as the schema entity type structure, but the
 /// `member_of` relation is reversed to instead be `descendants`.
-#[derive(Clone, Debug, Serialize)]
+///
+/// Two entity types are equal if all of their contents are equal. Only the
+/// name, descendants and attributes are hashed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct ValidatorEntityType {
     /// The name of the entity type.
     pub(crate) name: Name,
@@ -25,9 +30,43 @@ pub struct ValidatorEntityType {
     /// descendants before it is used in any validation.
     pub descendants: HashSet<Name>,
 
+    /// Specific entities which every entity of this type must be a member of,
+    /// declared with `memberOf` in the schema.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) required_parents: BTreeSet<EntityUID>,
+
     /// The attributes associated with this entity. Keys are the attribute
     /// identifiers while the values are the type of the attribute.
     pub(crate) attributes: Attributes,
+
+    /// Default values for optional attributes of this entity, used when
+    /// parsing entity data in which the attribute is absent. Each value has
+    /// been checked against the type of its attribute.
+    pub(crate) attribute_defaults: HashMap<SmolStr, RestrictedExpr>,
+
+    /// Whether the schema declared a `shape` for this entity type, as opposed
+    /// to omitting it. Either way, an entity type without a shape has no
+    /// attributes.
+    pub(crate) shape_was_explicit: bool,
+
+    /// Annotations declared for this entity type in the schema. These are not
+    /// used by validation.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) annotations: BTreeMap<SmolStr, String>,
+
+    /// The format which the ids of entities of this type must match, if the
+    /// schema declared one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) id_format: Option<IdFormat>,
+
+    /// Whether this entity type is deprecated in the schema. This is not used by
+    /// validation.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) deprecated: bool,
+
+    /// The message explaining why this entity type is deprecated, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) deprecation_message: Option<SmolStr>,
 }
 
 impl ValidatorEntityType {
@@ -36,11 +75,83 @@ impl ValidatorEntityType {
         self.attributes.get_attr(attr)
     }
 
+    /// Get the type of the attribute with the given name, if it exists,
+    /// ignoring whether the attribute is required
+    pub fn attr_type(&self, attr: &str) -> Option<&Type> {
+        Some(&self.attr(attr)?.attr_type)
+    }
+
+    /// Returns `true` if the schema declared a `shape` for this entity type,
+    /// even if it is an empty record, and `false` if the shape was omitted.
+    /// This distinguishes an entity type intentionally declared without
+    /// attributes from one whose attributes have not been specified, but has
+    /// no effect on validation.
+    pub fn shape_was_explicit(&self) -> bool {
+        self.shape_was_explicit
+    }
+
+    /// Get the default value declared for the attribute with the given name,
+    /// if it exists and has a default
+    pub fn attr_default(&self, attr: &str) -> Option<&RestrictedExpr> {
+        self.attribute_defaults.get(attr)
+    }
+
     /// An iterator over the attributes of this entity
     pub fn attributes(&self) -> impl Iterator<Item = (&SmolStr, &AttributeType)> {
         self.attributes.iter()
     }
 
+    /// The attributes of this entity, for editing them with
+    /// `ValidatorSchema::modify_entity_type`
+    pub fn attributes_mut(&mut self) -> &mut Attributes {
+        &mut self.attributes
+    }
+
+    /// An iterator over the specific entities which every entity of this
+    /// type must be a member of, as declared with `memberOf` in the schema,
+    /// in sorted order
+    pub fn required_parents(&self) -> impl Iterator<Item = &EntityUID> {
+        self.required_parents.iter()
+    }
+
+    /// Get the value of the annotation with the given key, if it exists
+    pub fn annotation(&self, key: &str) -> Option<&str> {
+        self.annotations.get(key).map(String::as_str)
+    }
+
+    /// An iterator over the annotations of this entity type, ordered by key
+    pub fn annotations(&self) -> impl Iterator<Item = (&SmolStr, &String)> {
+        self.annotations.iter()
+    }
+
+    /// Get the `idFormat` pattern declared for this entity type, if any
+    pub fn id_format(&self) -> Option<&str> {
+        self.id_format.as_ref().map(IdFormat::pattern)
+    }
+
+    /// Returns `true` if `eid` is a valid id for entities of this type, i.e.,
+    /// this entity type has no `idFormat` or `eid` matches it in full.
+    pub fn is_valid_id(&self, eid: &str) -> bool {
+        match &self.id_format {
+            Some(format) => format.is_match(eid),
+            None => true,
+        }
+    }
+
+    /// Returns `true` if this entity type is deprecated in the schema
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated
+    }
+
+    /// Get the message explaining why this entity type is deprecated, if it is
+    /// deprecated and the schema gives one
+    pub fn deprecation_message(&self) -> Option<&str> {
+        match &self.deprecation_message {
+            Some(message) if self.deprecated => Some(message),
+            _ => None,
+        }
+    }
+
     /// Return `true` if this entity type has an `EntityType` declared as a
     /// possible descendant in the schema. This takes an `EntityType` rather
     /// than a `Name`, It's not possible to declare the unspecified entity type
@@ -54,6 +165,54 @@ impl ValidatorEntityType {
     }
 }
 
+/// An `idFormat` pattern declared for an entity type, compiled when the schema
+/// is constructed. The pattern must match an entity id in full.
+#[derive(Clone, Debug)]
+pub(crate) struct IdFormat {
+    /// The pattern as written in the schema
+    pattern: SmolStr,
+    /// The pattern compiled with anchors on both ends
+    regex: Regex,
+}
+
+impl IdFormat {
+    /// Compile `pattern`, failing if it is not a valid regular expression.
+    pub(crate) fn new(pattern: SmolStr) -> Result<Self, regex::Error> {
+        let regex = Regex::new(&format!("^(?:{pattern})$"))?;
+        Ok(Self { pattern, regex })
+    }
+
+    pub(crate) fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub(crate) fn is_match(&self, eid: &str) -> bool {
+        self.regex.is_match(eid)
+    }
+}
+
+impl PartialEq for IdFormat {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for IdFormat {}
+
+impl Serialize for IdFormat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.pattern)
+    }
+}
+
+impl Hash for ValidatorEntityType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        super::hash_unordered(&self.descendants, state);
+        self.attributes.hash(state);
+    }
+}
+
 impl TCNode<Name> for ValidatorEntityType {
     fn get_key(&self) -> Name {
         self.name.clone()
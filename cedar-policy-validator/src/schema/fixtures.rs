@@ -0,0 +1,184 @@
+//! This module contains `ValidatorSchema::minimal_entities_for`, which
+//! generates placeholder entities conforming to the schema, e.g., to bootstrap
+//! authorization test cases.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use cedar_policy_core::{
+    ast::{Eid, Entity, EntityType, EntityUID, Name, RestrictedExpr},
+    entities::{Entities, TCComputation},
+    extensions::Extensions,
+};
+
+use super::{CoreSchema, ValidatorSchema};
+use crate::{
+    err::*,
+    types::{Attributes, EntityRecordKind, Primitive, Type},
+};
+
+/// The id of the entity generated for an entity type, unless the entity is a
+/// specific parent or group named in the schema.
+const PLACEHOLDER_EID: &str = "example";
+
+impl ValidatorSchema {
+    /// Generate a minimal set of entities conforming to the schema for
+    /// requests for `action`: an entity `T::"example"` for each principal and
+    /// resource type `T` the action applies to, along with every entity these
+    /// require. Required attributes are populated with placeholder values,
+    /// e.g., `0`, `""`, the first allowed value of a string enumeration or an
+    /// empty set, and optional attributes are omitted. An attribute referring
+    /// to an entity of type `T` refers to `T::"example"`, which is generated
+    /// too. Entities are also generated for the entities listed in `memberOf`
+    /// of their entity types, and for the groups in `principalsIn` and
+    /// `resourcesIn` of the action, with principals and resources in the first
+    /// of these groups. The action entities of the schema are included.
+    ///
+    /// Returns `UndeclaredActions` if `action` is not declared, and
+    /// `EntityGeneration` if an attribute has a type with no placeholder value,
+    /// such as an unknown extension type, or if the generated entities do not
+    /// conform to the schema, e.g., because `"example"` does not match the
+    /// `idFormat` of its entity type.
+    pub fn minimal_entities_for(&self, action: &EntityUID) -> Result<Entities> {
+        let generation_error =
+            |message: String| SchemaError::EntityGeneration(action.clone(), message);
+        let action_id = self
+            .get_action_id(action)
+            .ok_or_else(|| SchemaError::UndeclaredActions(HashSet::from([action.to_string()])))?;
+        let applies_to = &action_id.applies_to;
+
+        // Groups which the placeholder principals and resources must be in for
+        // the action to apply to them.
+        let mut groups: HashMap<EntityUID, HashSet<EntityUID>> = HashMap::new();
+        let mut worklist = Vec::new();
+        for (types, group) in [
+            (
+                applies_to.applicable_principal_types().collect::<Vec<_>>(),
+                applies_to.principal_groups().next(),
+            ),
+            (
+                applies_to.applicable_resource_types().collect::<Vec<_>>(),
+                applies_to.resource_groups().next(),
+            ),
+        ] {
+            for ty in types {
+                if let EntityType::Concrete(name) = ty {
+                    let uid = Self::placeholder_uid(name);
+                    groups
+                        .entry(uid.clone())
+                        .or_default()
+                        .extend(group.cloned());
+                    worklist.push(uid);
+                }
+            }
+            worklist.extend(group.cloned());
+        }
+
+        let mut entities = BTreeMap::new();
+        while let Some(uid) = worklist.pop() {
+            if entities.contains_key(&uid) {
+                continue;
+            }
+            let EntityType::Concrete(name) = uid.entity_type() else {
+                continue;
+            };
+            let entity_type = self.get_entity_type(name).ok_or_else(|| {
+                SchemaError::UndeclaredEntityTypes(HashSet::from([name.to_string()]))
+            })?;
+            let attrs = Self::placeholder_attrs(&entity_type.attributes, &mut worklist)
+                .map_err(generation_error)?;
+            let mut parents = entity_type
+                .required_parents()
+                .cloned()
+                .collect::<HashSet<_>>();
+            parents.extend(groups.remove(&uid).unwrap_or_default());
+            worklist.extend(parents.iter().cloned());
+            entities.insert(uid.clone(), Entity::new(uid, attrs, parents));
+        }
+
+        Entities::from_entities(
+            entities.into_values(),
+            Some(&CoreSchema::new(self)),
+            TCComputation::ComputeNow,
+            Extensions::all_available(),
+        )
+        .map_err(|err| generation_error(err.to_string()))
+    }
+
+    /// The entity generated by `minimal_entities_for` for the entity type `ty`
+    fn placeholder_uid(ty: &Name) -> EntityUID {
+        EntityUID::from_components(ty.clone(), Eid::new(PLACEHOLDER_EID))
+    }
+
+    /// Placeholder values for the required attributes in `attributes`. The
+    /// entities they refer to are pushed onto `worklist`.
+    fn placeholder_attrs(
+        attributes: &Attributes,
+        worklist: &mut Vec<EntityUID>,
+    ) -> std::result::Result<HashMap<smol_str::SmolStr, RestrictedExpr>, String> {
+        attributes
+            .iter()
+            .filter(|(_, attr_ty)| attr_ty.is_required)
+            .map(|(attr, attr_ty)| {
+                let value = Self::placeholder_value(&attr_ty.attr_type, worklist)
+                    .map_err(|message| format!("attribute `{attr}`: {message}"))?;
+                Ok((attr.clone(), value))
+            })
+            .collect()
+    }
+
+    /// A placeholder value of type `ty`. The entities it refers to are pushed
+    /// onto `worklist`.
+    fn placeholder_value(
+        ty: &Type,
+        worklist: &mut Vec<EntityUID>,
+    ) -> std::result::Result<RestrictedExpr, String> {
+        match ty {
+            Type::True => Ok(RestrictedExpr::val(true)),
+            Type::False
+            | Type::Primitive {
+                primitive_type: Primitive::Bool,
+            } => Ok(RestrictedExpr::val(false)),
+            Type::Primitive {
+                primitive_type: Primitive::Long,
+            } => Ok(RestrictedExpr::val(0)),
+            Type::Primitive {
+                primitive_type: Primitive::String,
+            } => Ok(RestrictedExpr::val("")),
+            Type::StringEnum { allowed_values } => match allowed_values.iter().next() {
+                Some(value) => Ok(RestrictedExpr::val(value.clone())),
+                None => Err("string type has no allowed values".to_string()),
+            },
+            Type::Set { .. } => Ok(RestrictedExpr::set([])),
+            Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+                RestrictedExpr::record(Self::placeholder_attrs(attrs, worklist)?)
+                    .map_err(|err| err.to_string())
+            }
+            Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => match lub.iter().next() {
+                Some(name) => {
+                    let uid = Self::placeholder_uid(name);
+                    worklist.push(uid.clone());
+                    Ok(RestrictedExpr::val(uid))
+                }
+                None => Err("entity type has no entity types".to_string()),
+            },
+            Type::ExtensionType { name } => {
+                let (constructor, arg) = match name.to_string().as_str() {
+                    "ipaddr" => ("ip", "0.0.0.0"),
+                    "decimal" => ("decimal", "0.0"),
+                    _ => return Err(format!("no placeholder for extension type `{name}`")),
+                };
+                let constructor =
+                    Name::parse_unqualified_name(constructor).map_err(|err| err.to_string())?;
+                Ok(RestrictedExpr::call_extension_fn(
+                    constructor,
+                    vec![RestrictedExpr::val(arg)],
+                ))
+            }
+            Type::Never
+            | Type::EntityOrRecord(EntityRecordKind::AnyEntity)
+            | Type::EntityOrRecord(EntityRecordKind::ActionEntity { .. }) => {
+                Err(format!("no placeholder for type {ty}"))
+            }
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! This module contains `ValidatorSchema::flatten_namespace`, which moves the
+//! entity types and actions of one namespace into the root namespace, for
+//! exporting a schema to systems without namespaces,
+//! `ValidatorSchema::clone_namespace`, which copies them into another
+//! namespace, and `ValidatorSchema::freeze_namespace`, which protects a
+//! namespace from being altered by such mutations.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use cedar_policy_core::ast::{
+    BorrowedRestrictedExpr, EntityType, EntityUID, ExprKind, Literal, Name, RestrictedExpr,
+};
+
+use super::{
+    ValidatorActionId, ValidatorApplySpec, ValidatorEntityType, ValidatorMap, ValidatorSchema,
+};
+use crate::err::*;
+
+impl ValidatorSchema {
+    /// Move the entity types and actions declared in the namespace `ns` into
+    /// the root namespace, so that `ns::User` becomes `User` and
+    /// `ns::Action::"view"` becomes `Action::"view"`. Every reference to those
+    /// names elsewhere in the schema is rewritten as well, as are the names in
+    /// `common_type_dependencies`. Entity types and
+    /// actions in other namespaces, including namespaces nested inside `ns`,
+    /// are unchanged.
+    ///
+    /// Returns `FlattenCollision` listing every name which would be merged
+    /// with a distinct entity type or action already in the root namespace.
+    pub fn flatten_namespace(&self, ns: &Name) -> Result<ValidatorSchema> {
+        let flattener = Renamer {
+            namespace: ns,
+            target: None,
+        };
+        let mut collisions = self
+            .entity_types
+            .keys()
+            .filter(|name| flattener.is_renamed(name))
+            .map(|name| (name.to_string(), flattener.name(name)))
+            .filter(|(_, flattened)| self.entity_types.contains_key(flattened))
+            .map(|(name, flattened)| (name, flattened.to_string()))
+            .chain(
+                self.action_ids
+                    .keys()
+                    .map(|euid| (euid, flattener.euid(euid)))
+                    .filter(|(euid, flattened)| {
+                        *euid != flattened && self.action_ids.contains_key(flattened)
+                    })
+                    .map(|(euid, flattened)| (euid.to_string(), flattened.to_string())),
+            )
+            .collect::<Vec<_>>();
+        if !collisions.is_empty() {
+            collisions.sort();
+            return Err(SchemaError::FlattenCollision { collisions });
+        }
+
+        let entity_types = self
+            .entity_types
+            .values()
+            .map(|entity_type| {
+                let entity_type = flattener.entity_type(entity_type);
+                (entity_type.name.clone(), entity_type)
+            })
+            .collect::<ValidatorMap<_, _>>();
+        let action_ids = self
+            .action_ids
+            .values()
+            .map(|action| {
+                let action = flattener.action(action);
+                (action.name.clone(), action)
+            })
+            .collect::<ValidatorMap<_, _>>();
+        let mut common_type_dependencies = HashMap::new();
+        for (name, dependencies) in self.common_type_dependencies.iter() {
+            common_type_dependencies
+                .entry(flattener.name(name))
+                .or_insert_with(HashSet::new)
+                .extend(
+                    dependencies
+                        .iter()
+                        .map(|dependency| flattener.name(dependency)),
+                );
+        }
+        Ok(ValidatorSchema {
+            entity_types: Arc::new(entity_types),
+            action_ids: Arc::new(action_ids),
+            common_type_dependencies: Arc::new(common_type_dependencies),
+            frozen_namespaces: Arc::clone(&self.frozen_namespaces),
+            lookup_config: self.lookup_config,
+        })
+    }
+
+    /// Copy the entity types and actions declared in the namespace `from`
+    /// into the namespace `to`, so that `from::User` is also declared as
+    /// `to::User` and `from::Action::"view"` as `to::Action::"view"`. In the
+    /// copies, references to names declared in `from` are rewritten to the
+    /// corresponding names in `to`, while references to other namespaces are
+    /// unchanged: a copy is a member of the same entity types and actions
+    /// outside `from` as the original. Entity types and actions outside `from`
+    /// which can be members of the originals do not become members of the
+    /// copies. Namespaces nested inside `from` are not copied.
+    ///
+    /// Returns `NamespaceFrozen` if `to` is frozen, or if the copies would be
+    /// members of entity types or actions declared in a frozen namespace,
+    /// since their descendants would change. Returns
+    /// `NamespaceAlreadyDeclared` if `to` already declares an entity type,
+    /// action or common type. The result is checked for references to
+    /// undeclared entity types and actions, as when constructing a schema, and
+    /// the schema is left unchanged if an error is returned.
+    pub fn clone_namespace(&mut self, from: &Name, to: &Name) -> Result<()> {
+        if self.frozen_namespaces.contains(to) {
+            return Err(SchemaError::NamespaceFrozen { ns: to.to_string() });
+        }
+        let target = Renamer {
+            namespace: to,
+            target: None,
+        };
+        if self
+            .entity_types
+            .keys()
+            .chain(self.common_type_dependencies.keys())
+            .chain(self.action_ids.keys().filter_map(action_type_name))
+            .any(|name| target.is_renamed(name))
+        {
+            return Err(SchemaError::NamespaceAlreadyDeclared(to.to_string()));
+        }
+
+        let renamer = Renamer {
+            namespace: from,
+            target: Some(to),
+        };
+        let mut entity_types = ValidatorMap::clone(&self.entity_types);
+        for entity_type in self.entity_types.values() {
+            if renamer.is_renamed(&entity_type.name) {
+                let mut copy = renamer.entity_type(entity_type);
+                copy.descendants.retain(|name| target.is_renamed(name));
+                entity_types.insert(copy.name.clone(), copy);
+            } else {
+                let copies = entity_type
+                    .descendants
+                    .iter()
+                    .filter(|name| renamer.is_renamed(name))
+                    .map(|name| renamer.name(name))
+                    .collect::<Vec<_>>();
+                if !copies.is_empty() {
+                    self.check_not_frozen(&entity_type.name)?;
+                }
+                if let Some(entity_type) = entity_types.get_mut(&entity_type.name) {
+                    entity_type.descendants.extend(copies);
+                }
+            }
+        }
+        let mut action_ids = ValidatorMap::clone(&self.action_ids);
+        for action in self.action_ids.values() {
+            if renamer.is_renamed_action(&action.name) {
+                let mut copy = renamer.action(action);
+                copy.descendants
+                    .retain(|euid| target.is_renamed_action(euid));
+                action_ids.insert(copy.name.clone(), copy);
+            } else {
+                let copies = action
+                    .descendants
+                    .iter()
+                    .filter(|euid| renamer.is_renamed_action(euid))
+                    .map(|euid| renamer.euid(euid))
+                    .collect::<Vec<_>>();
+                if let Some(name) = action_type_name(&action.name).filter(|_| !copies.is_empty()) {
+                    self.check_not_frozen(name)?;
+                }
+                if let Some(action) = action_ids.get_mut(&action.name) {
+                    action.descendants.extend(copies);
+                }
+            }
+        }
+        let mut common_type_dependencies = HashMap::clone(&self.common_type_dependencies);
+        for (name, dependencies) in self.common_type_dependencies.iter() {
+            if renamer.is_renamed(name) {
+                common_type_dependencies.insert(
+                    renamer.name(name),
+                    dependencies
+                        .iter()
+                        .map(|dependency| renamer.name(dependency))
+                        .collect::<HashSet<_>>(),
+                );
+            }
+        }
+
+        let mut errors = Vec::new();
+        Self::check_for_undeclared(&entity_types, None, &action_ids, None, &mut errors);
+        if let Some(err) = errors.into_iter().next() {
+            return Err(err);
+        }
+        self.entity_types = Arc::new(entity_types);
+        self.action_ids = Arc::new(action_ids);
+        self.common_type_dependencies = Arc::new(common_type_dependencies);
+        Ok(())
+    }
+
+    /// Freeze the namespace `ns`, so that mutating operations such as
+    /// `clone_namespace` return `NamespaceFrozen` instead of altering the
+    /// entity types, actions or common types declared directly in it. The
+    /// namespace need not declare anything yet, and namespaces nested inside
+    /// it are not frozen. A frozen namespace cannot be unfrozen.
+    pub fn freeze_namespace(&mut self, ns: &Name) {
+        Arc::make_mut(&mut self.frozen_namespaces).insert(ns.clone());
+    }
+
+    /// Is the namespace `ns` frozen? See `freeze_namespace`.
+    pub fn is_namespace_frozen(&self, ns: &Name) -> bool {
+        self.frozen_namespaces.contains(ns)
+    }
+
+    /// Returns `NamespaceFrozen` if `name` is declared directly in a frozen
+    /// namespace.
+    pub(super) fn check_not_frozen(&self, name: &Name) -> Result<()> {
+        match self.frozen_namespaces.iter().find(|ns| {
+            Renamer {
+                namespace: ns,
+                target: None,
+            }
+            .is_renamed(name)
+        }) {
+            Some(ns) => Err(SchemaError::NamespaceFrozen { ns: ns.to_string() }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The name of the type of the action `euid`, e.g., `ns::Action`.
+fn action_type_name(euid: &EntityUID) -> Option<&Name> {
+    match euid.entity_type() {
+        EntityType::Concrete(name) => Some(name),
+        EntityType::Unspecified => None,
+    }
+}
+
+/// Rewrites the names declared in a single namespace into another namespace.
+struct Renamer<'a> {
+    /// The namespace whose names are rewritten.
+    namespace: &'a Name,
+    /// The namespace they are rewritten into, or `None` for the root
+    /// namespace.
+    target: Option<&'a Name>,
+}
+
+impl Renamer<'_> {
+    /// Is `name` declared directly in the namespace being renamed?
+    fn is_renamed(&self, name: &Name) -> bool {
+        name.namespace_components().eq(self
+            .namespace
+            .namespace_components()
+            .chain(std::iter::once(self.namespace.basename())))
+    }
+
+    /// Is the action `euid` declared directly in the namespace being renamed?
+    fn is_renamed_action(&self, euid: &EntityUID) -> bool {
+        match action_type_name(euid) {
+            Some(name) => self.is_renamed(name),
+            None => false,
+        }
+    }
+
+    fn name(&self, name: &Name) -> Name {
+        if !self.is_renamed(name) {
+            return name.clone();
+        }
+        match self.target {
+            Some(target) => Name::type_in_namespace(name.basename().clone(), target.clone()),
+            None => Name::unqualified_name(name.basename().clone()),
+        }
+    }
+
+    fn entity_type_name(&self, ty: &EntityType) -> EntityType {
+        match ty {
+            EntityType::Concrete(name) => EntityType::Concrete(self.name(name)),
+            EntityType::Unspecified => EntityType::Unspecified,
+        }
+    }
+
+    fn euid(&self, euid: &EntityUID) -> EntityUID {
+        match euid.entity_type() {
+            EntityType::Concrete(name) => {
+                EntityUID::from_components(self.name(name), euid.eid().clone())
+            }
+            EntityType::Unspecified => euid.clone(),
+        }
+    }
+
+    fn entity_type(&self, entity_type: &ValidatorEntityType) -> ValidatorEntityType {
+        let rename = |name: &Name| self.name(name);
+        ValidatorEntityType {
+            name: self.name(&entity_type.name),
+            descendants: entity_type.descendants.iter().map(rename).collect(),
+            required_parents: entity_type
+                .required_parents
+                .iter()
+                .map(|euid| self.euid(euid))
+                .collect(),
+            attributes: entity_type.attributes.rename_entity_types(&rename),
+            attribute_defaults: entity_type
+                .attribute_defaults
+                .iter()
+                .map(|(attr, val)| (attr.clone(), self.value(val.as_borrowed())))
+                .collect(),
+            ..entity_type.clone()
+        }
+    }
+
+    fn action(&self, action: &ValidatorActionId) -> ValidatorActionId {
+        let rename = |name: &Name| self.name(name);
+        ValidatorActionId {
+            name: self.euid(&action.name),
+            applies_to: ValidatorApplySpec::new(
+                action
+                    .applies_to
+                    .applicable_principal_types()
+                    .map(|ty| self.entity_type_name(ty))
+                    .collect(),
+                action
+                    .applies_to
+                    .applicable_resource_types()
+                    .map(|ty| self.entity_type_name(ty))
+                    .collect(),
+            )
+            .with_groups(
+                action
+                    .applies_to
+                    .principal_groups()
+                    .map(|euid| self.euid(euid))
+                    .collect(),
+                action
+                    .applies_to
+                    .resource_groups()
+                    .map(|euid| self.euid(euid))
+                    .collect(),
+            ),
+            descendants: action
+                .descendants
+                .iter()
+                .map(|euid| self.euid(euid))
+                .collect(),
+            context: action.context.rename_entity_types(&rename),
+            attribute_types: action.attribute_types.rename_entity_types(&rename),
+            attributes: action
+                .attributes
+                .iter()
+                .map(|(attr, val)| (attr.clone(), self.value(val.as_borrowed())))
+                .collect(),
+            ..action.clone()
+        }
+    }
+
+    /// Rewrite the entity references in an attribute value.
+    fn value(&self, val: BorrowedRestrictedExpr<'_>) -> RestrictedExpr {
+        match val.expr_kind() {
+            ExprKind::Lit(Literal::EntityUID(euid)) => RestrictedExpr::val(self.euid(euid)),
+            ExprKind::Set(elements) => RestrictedExpr::set(
+                elements
+                    .iter()
+                    .map(|e| self.value(BorrowedRestrictedExpr::new_unchecked(e))),
+            ),
+            ExprKind::Record(attrs) => {
+                // PANIC SAFETY: the keys are those of an existing record, so they are distinct
+                #[allow(clippy::expect_used)]
+                RestrictedExpr::record(attrs.iter().map(|(k, v)| {
+                    (
+                        k.clone(),
+                        self.value(BorrowedRestrictedExpr::new_unchecked(v)),
+                    )
+                }))
+                .expect("record keys should be distinct")
+            }
+            ExprKind::ExtensionFunctionApp { fn_name, args } => RestrictedExpr::call_extension_fn(
+                fn_name.clone(),
+                args.iter()
+                    .map(|e| self.value(BorrowedRestrictedExpr::new_unchecked(e)))
+                    .collect(),
+            ),
+            _ => RestrictedExpr::new_unchecked(val.as_ref().clone()),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 //! This module contains the definition of `ValidatorNamespaceDef` and of types
 //! it relies on
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use cedar_policy_core::entities::JsonDeserializationErrorContext;
 use cedar_policy_core::{
@@ -12,14 +12,14 @@ use cedar_policy_core::{
 };
 use smol_str::SmolStr;
 
-use super::ValidatorApplySpec;
+use super::{IdFormat, ValidatorApplySpec, ValidatorMap};
 use crate::types::OpenTag;
 use crate::{
     err::*,
     schema_file_format,
     types::{AttributeType, Attributes, Type},
-    ActionBehavior, ActionEntityUID, ActionType, NamespaceDefinition, SchemaType,
-    SchemaTypeVariant, TypeOfAttribute, SCHEMA_TYPE_VARIANT_TAGS,
+    ActionBehavior, ActionEntityUID, ActionType, EntityParentUID, NamespaceDefinition, SchemaMap,
+    SchemaType, SchemaTypeVariant, TypeOfAttribute, SCHEMA_TYPE_VARIANT_TAGS,
 };
 
 /// The current schema format specification does not include multiple action entity
@@ -65,17 +65,20 @@ pub struct ValidatorNamespaceDef {
 }
 
 /// Holds a map from `Name`s of common type definitions to their corresponding
-/// `Type`.
+/// `Type`, along with the common types each definition may refer to. A
+/// definition may refer to other common types, so its `Type` is only known
+/// once those have been resolved.
 #[derive(Debug)]
 pub struct TypeDefs {
-    pub(super) type_defs: HashMap<Name, Type>,
+    pub(super) type_defs: HashMap<Name, WithUnresolvedTypeDefs<Type>>,
+    pub(super) dependencies: HashMap<Name, HashSet<Name>>,
 }
 
 /// Entity type declarations held in a `ValidatorNamespaceDef`. Entity type
 /// parents and attributes may reference undeclared entity types.
 #[derive(Debug)]
 pub struct EntityTypesDef {
-    pub(super) entity_types: HashMap<Name, EntityTypeFragment>,
+    pub(super) entity_types: ValidatorMap<Name, EntityTypeFragment>,
 }
 
 /// Defines an EntityType where we have not resolved typedefs occurring in the
@@ -93,13 +96,28 @@ pub struct EntityTypeFragment {
     /// namespace, so we will check if they are declared in any fragment when
     /// constructing a `ValidatorSchema`.
     pub(super) parents: HashSet<Name>,
+    /// The entities from the `memberOf` list, which every entity of this type
+    /// must be a member of. Their entity types might not be declared.
+    pub(super) required_parents: BTreeSet<EntityUID>,
+    /// Default values declared for the attributes of this entity type. These
+    /// have not yet been checked against the attribute types.
+    pub(super) attribute_defaults: HashMap<SmolStr, CedarValueJson>,
+    /// Whether the entity type declared a `shape`.
+    pub(super) shape_was_explicit: bool,
+    /// Annotations declared for this entity type.
+    pub(super) annotations: BTreeMap<SmolStr, String>,
+    /// The compiled `idFormat` declared for this entity type.
+    pub(super) id_format: Option<IdFormat>,
+    /// Whether this entity type is deprecated, and why.
+    pub(super) deprecated: bool,
+    pub(super) deprecation_message: Option<SmolStr>,
 }
 
 /// Action declarations held in a `ValidatorNamespaceDef`. Entity types
 /// referenced here do not need to be declared in the schema.
 #[derive(Debug)]
 pub struct ActionsDef {
-    pub(super) actions: HashMap<EntityUID, ActionFragment>,
+    pub(super) actions: ValidatorMap<EntityUID, ActionFragment>,
 }
 
 #[derive(Debug)]
@@ -110,14 +128,26 @@ pub struct ActionFragment {
     pub(super) context: WithUnresolvedTypeDefs<Type>,
     /// The principals and resources that an action can be applied to.
     pub(super) applies_to: ValidatorApplySpec,
+    /// Entity types from the `resourceTypesWithDescendants` list. These are
+    /// already included in `applies_to`, but their descendants are not.
+    pub(super) resource_descendant_roots: HashSet<Name>,
     /// The direct parent action entities for this action.
     pub(super) parents: HashSet<EntityUID>,
+    /// For each parent in `parents` that was written with a qualified action
+    /// type, the action it would refer to if the type were resolved relative
+    /// to the namespace containing this action rather than from the root.
+    pub(super) relative_parents: HashMap<EntityUID, EntityUID>,
     /// The types for the attributes defined for this actions entity.
     pub(super) attribute_types: Attributes,
     /// The values for the attributes defined for this actions entity, stored
     /// separately so that we can later extract use these values to construct
     /// the actual `Entity` objects defined by the schema.
     pub(super) attributes: HashMap<SmolStr, RestrictedExpr>,
+    /// Annotations declared for this action.
+    pub(super) annotations: BTreeMap<SmolStr, String>,
+    /// Whether this action is deprecated, and why.
+    pub(super) deprecated: bool,
+    pub(super) deprecation_message: Option<SmolStr>,
 }
 
 type ResolveFunc<T> = dyn FnOnce(&HashMap<Name, Type>) -> Result<T>;
@@ -191,14 +221,14 @@ impl ValidatorNamespaceDef {
         for name in namespace_def.entity_types.keys() {
             if !e_types_ids.insert(name.clone()) {
                 // insert returns false for duplicates
-                return Err(SchemaError::DuplicateEntityType(name.to_string()));
+                return Err(SchemaError::DuplicateEntityType(name.to_string(), None));
             }
         }
         let mut a_name_eids: HashSet<SmolStr> = HashSet::new();
         for name in namespace_def.actions.keys() {
             if !a_name_eids.insert(name.clone()) {
                 // insert returns false for duplicates
-                return Err(SchemaError::DuplicateAction(name.to_string()));
+                return Err(SchemaError::DuplicateAction(name.to_string(), None));
             }
         }
 
@@ -214,11 +244,15 @@ impl ValidatorNamespaceDef {
 
         // Convert the type defs, actions and entity types from the schema file
         // into the representation used by the validator.
+        let in_namespace = |e: SchemaError| e.in_namespace(namespace.as_ref());
         let type_defs =
-            Self::build_type_defs(namespace_def.common_types, schema_namespace.as_ref())?;
-        let actions = Self::build_action_ids(namespace_def.actions, schema_namespace.as_ref())?;
+            Self::build_type_defs(namespace_def.common_types, schema_namespace.as_ref())
+                .map_err(in_namespace)?;
+        let actions = Self::build_action_ids(namespace_def.actions, schema_namespace.as_ref())
+            .map_err(in_namespace)?;
         let entity_types =
-            Self::build_entity_types(namespace_def.entity_types, schema_namespace.as_ref())?;
+            Self::build_entity_types(namespace_def.entity_types, schema_namespace.as_ref())
+                .map_err(in_namespace)?;
 
         Ok(ValidatorNamespaceDef {
             namespace: schema_namespace,
@@ -235,33 +269,78 @@ impl ValidatorNamespaceDef {
     }
 
     fn build_type_defs(
-        schema_file_type_def: HashMap<SmolStr, SchemaType>,
+        schema_file_type_def: SchemaMap<SmolStr, SchemaType>,
         schema_namespace: Option<&Name>,
     ) -> Result<TypeDefs> {
+        let mut dependencies = HashMap::new();
         let type_defs = schema_file_type_def
             .into_iter()
             .map(|(name_str, schema_ty)| -> Result<_> {
                 if Self::is_builtin_type_name(&name_str) {
-                    return Err(SchemaError::DuplicateCommonType(name_str.to_string()));
+                    return Err(SchemaError::DuplicateCommonType(name_str.to_string(), None));
                 }
+                let in_section =
+                    |e: SchemaError| e.in_section(SchemaSection::CommonType(name_str.clone()));
                 let name = Self::parse_unqualified_name_with_namespace(
                     &name_str,
                     schema_namespace.cloned(),
                 )
-                .map_err(SchemaError::ParseCommonType)?;
-                let ty = Self::try_schema_type_into_validator_type(schema_namespace, schema_ty)?
-                    .resolve_type_defs(&HashMap::new())?;
+                .map_err(SchemaError::parse_common_type)
+                .map_err(in_section)?;
+                let mut references = HashSet::new();
+                Self::collect_common_type_references(&schema_ty, schema_namespace, &mut references);
+                dependencies.insert(name.clone(), references);
+                let ty = Self::try_schema_type_into_validator_type(schema_namespace, schema_ty)
+                    .map_err(in_section)?;
                 Ok((name, ty))
             })
             .collect::<Result<HashMap<_, _>>>()?;
-        Ok(TypeDefs { type_defs })
+        Ok(TypeDefs {
+            type_defs,
+            dependencies,
+        })
+    }
+
+    /// Collect the fully qualified names of the common types which `ty` may
+    /// refer to, including inside set element and record attribute types.
+    /// An unqualified reference inside a namespace may refer to the common
+    /// type in that namespace or to the one in the root namespace, so both
+    /// names are collected. References which are not valid names are
+    /// skipped; they are reported when `ty` is converted.
+    pub(crate) fn collect_common_type_references(
+        ty: &SchemaType,
+        default_namespace: Option<&Name>,
+        references: &mut HashSet<Name>,
+    ) {
+        match ty {
+            SchemaType::TypeDef { type_name } => {
+                if let Ok(name) = Self::parse_possibly_qualified_name_with_default_namespace(
+                    type_name,
+                    default_namespace,
+                ) {
+                    references.insert(name);
+                }
+                if let Ok(root_name) = Name::parse_unqualified_name(type_name) {
+                    references.insert(root_name);
+                }
+            }
+            SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+                Self::collect_common_type_references(element, default_namespace, references)
+            }
+            SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+                for attr in attributes.values() {
+                    Self::collect_common_type_references(&attr.ty, default_namespace, references)
+                }
+            }
+            SchemaType::Type(_) => (),
+        }
     }
 
     // Transform the schema data structures for entity types into the structures
     // used internally by the validator. This is mostly accomplished by directly
     // copying data between fields.
     fn build_entity_types(
-        schema_files_types: HashMap<SmolStr, schema_file_format::EntityType>,
+        schema_files_types: SchemaMap<SmolStr, schema_file_format::EntityType>,
         schema_namespace: Option<&Name>,
     ) -> Result<EntityTypesDef> {
         Ok(EntityTypesDef {
@@ -272,7 +351,10 @@ impl ValidatorNamespaceDef {
                         &name_str,
                         schema_namespace.cloned(),
                     )
-                    .map_err(SchemaError::ParseEntityType)?;
+                    .map_err(|e| {
+                        SchemaError::parse_entity_type(e)
+                            .in_section(SchemaSection::EntityType(name_str.clone()))
+                    })?;
 
                     let parents = entity_type
                         .member_of_types
@@ -282,27 +364,84 @@ impl ValidatorNamespaceDef {
                                 parent,
                                 schema_namespace,
                             )
-                            .map_err(SchemaError::ParseEntityType)
+                            .map_err(|e| {
+                                SchemaError::parse_entity_type(e)
+                                    .in_section(SchemaSection::EntityTypeMemberOf(name_str.clone()))
+                            })
                         })
                         .collect::<Result<HashSet<_>>>()?;
 
-                    let attributes = Self::try_schema_type_into_validator_type(
-                        schema_namespace,
-                        entity_type.shape.into_inner(),
-                    )?;
+                    let required_parents = entity_type
+                        .member_of
+                        .iter()
+                        .map(|parent| -> Result<_> {
+                            let ty = Self::parse_possibly_qualified_name_with_default_namespace(
+                                &parent.ty,
+                                schema_namespace,
+                            )
+                            .map_err(|e| {
+                                SchemaError::parse_entity_type(e)
+                                    .in_section(SchemaSection::EntityTypeMemberOf(name_str.clone()))
+                            })?;
+                            Ok(EntityUID::from_components(ty, Eid::new(parent.id.clone())))
+                        })
+                        .collect::<Result<BTreeSet<_>>>()?;
+
+                    let shape_was_explicit = entity_type.shape.is_some();
+                    let mut shape = entity_type.shape.unwrap_or_default().into_inner();
+                    let attribute_defaults = Self::take_attribute_defaults(&mut shape);
+                    let attributes =
+                        Self::try_schema_type_into_validator_type(schema_namespace, shape)
+                            .map_err(|e| {
+                                e.in_section(SchemaSection::EntityTypeShape(name_str.clone()))
+                            })?;
+
+                    let id_format = entity_type
+                        .id_format
+                        .map(|pattern| {
+                            IdFormat::new(pattern.clone()).map_err(|e| {
+                                SchemaError::InvalidIdFormat {
+                                    entity_type: name.to_string(),
+                                    pattern: pattern.to_string(),
+                                    message: e.to_string(),
+                                }
+                            })
+                        })
+                        .transpose()?;
 
                     Ok((
                         name,
                         EntityTypeFragment {
                             attributes,
                             parents,
+                            required_parents,
+                            attribute_defaults,
+                            shape_was_explicit,
+                            annotations: entity_type.annotations,
+                            id_format,
+                            deprecated: entity_type.deprecated,
+                            deprecation_message: entity_type.deprecation_message,
                         },
                     ))
                 })
-                .collect::<Result<HashMap<_, _>>>()?,
+                .collect::<Result<ValidatorMap<_, _>>>()?,
         })
     }
 
+    /// Remove the default values declared for the top-level attributes of an
+    /// entity type shape, returning them keyed by attribute name. Defaults are
+    /// not supported anywhere else, so any that remain in the shape will be
+    /// reported as an error when it is converted into a validator type.
+    fn take_attribute_defaults(shape: &mut SchemaType) -> HashMap<SmolStr, CedarValueJson> {
+        match shape {
+            SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => attributes
+                .iter_mut()
+                .filter_map(|(attr, ty)| Some((attr.clone(), ty.default.take()?)))
+                .collect(),
+            _ => HashMap::new(),
+        }
+    }
+
     // Helper to get types from `CedarValueJson`s. Currently doesn't support all
     // `CedarValueJson` types. Note: If this function is extended to cover move
     // `CedarValueJson`s, we must update `convert_attr_jsonval_map_to_attributes` to
@@ -401,50 +540,106 @@ impl ValidatorNamespaceDef {
     // internally by the validator. This is mostly accomplished by directly
     // copying data between fields.
     fn build_action_ids(
-        schema_file_actions: HashMap<SmolStr, ActionType>,
+        schema_file_actions: SchemaMap<SmolStr, ActionType>,
         schema_namespace: Option<&Name>,
     ) -> Result<ActionsDef> {
         Ok(ActionsDef {
             actions: schema_file_actions
                 .into_iter()
                 .map(|(action_id_str, action_type)| -> Result<_> {
+                    let in_applies_to = |e: SchemaError| {
+                        e.in_section(SchemaSection::ActionAppliesTo(action_id_str.clone()))
+                    };
+                    let in_member_of = |e: SchemaError| {
+                        e.in_section(SchemaSection::ActionMemberOf(action_id_str.clone()))
+                    };
                     let action_id = Self::parse_action_id_with_namespace(
-                        &ActionEntityUID::default_type(action_id_str),
+                        &ActionEntityUID::default_type(action_id_str.clone()),
                         schema_namespace,
                     )?;
 
-                    let (principal_types, resource_types, context) = action_type
+                    let (
+                        principal_types,
+                        mut resource_types,
+                        resource_types_with_descendants,
+                        principals_in,
+                        resources_in,
+                        context,
+                    ) = action_type
                         .applies_to
                         .map(|applies_to| {
                             (
                                 applies_to.principal_types,
                                 applies_to.resource_types,
+                                applies_to.resource_types_with_descendants,
+                                applies_to.principals_in,
+                                applies_to.resources_in,
                                 applies_to.context,
                             )
                         })
                         .unwrap_or_default();
 
+                    // Types listed in `resourceTypesWithDescendants` are
+                    // applicable resource types themselves. Their descendants
+                    // are added once the entity type hierarchy is known.
+                    let resource_descendant_roots = match resource_types_with_descendants {
+                        Some(types) => {
+                            let roots = types
+                                .iter()
+                                .map(|ty_str| {
+                                    Self::parse_possibly_qualified_name_with_default_namespace(
+                                        ty_str,
+                                        schema_namespace,
+                                    )
+                                    .map_err(SchemaError::parse_entity_type)
+                                })
+                                .collect::<Result<HashSet<_>>>()
+                                .map_err(in_applies_to)?;
+                            resource_types.get_or_insert_with(Vec::new).extend(types);
+                            roots
+                        }
+                        None => HashSet::new(),
+                    };
+
                     // Convert the entries in the `appliesTo` lists into sets of
                     // `EntityTypes`. If one of the lists is `None` (absent from the
                     // schema), then the specification is undefined.
                     let applies_to = ValidatorApplySpec::new(
-                        Self::parse_apply_spec_type_list(principal_types, schema_namespace)?,
-                        Self::parse_apply_spec_type_list(resource_types, schema_namespace)?,
+                        Self::parse_apply_spec_type_list(principal_types, schema_namespace)
+                            .map_err(in_applies_to)?,
+                        Self::parse_apply_spec_type_list(resource_types, schema_namespace)
+                            .map_err(in_applies_to)?,
+                    )
+                    .with_groups(
+                        Self::parse_apply_spec_groups(&principals_in, schema_namespace)
+                            .map_err(in_applies_to)?,
+                        Self::parse_apply_spec_groups(&resources_in, schema_namespace)
+                            .map_err(in_applies_to)?,
                     );
 
                     let context = Self::try_schema_type_into_validator_type(
                         schema_namespace,
                         context.into_inner(),
-                    )?;
+                    )
+                    .map_err(in_applies_to)?;
 
+                    let mut relative_parents = HashMap::new();
                     let parents = action_type
                         .member_of
                         .unwrap_or_default()
                         .iter()
                         .map(|parent| -> Result<_> {
-                            Self::parse_action_id_with_namespace(parent, schema_namespace)
+                            let parent_euid =
+                                Self::parse_action_id_with_namespace(parent, schema_namespace)?;
+                            if let Some(relative) =
+                                Self::parse_relative_action_id(parent, schema_namespace)?
+                            {
+                                relative_parents.insert(parent_euid.clone(), relative);
+                            }
+                            Ok(parent_euid)
                         })
-                        .collect::<Result<HashSet<_>>>()?;
+                        .collect::<Result<HashSet<_>>>()
+                        .map_err(in_member_of)?;
 
                     let (attribute_types, attributes) =
                         Self::convert_attr_jsonval_map_to_attributes(
@@ -457,13 +652,18 @@ impl ValidatorNamespaceDef {
                         ActionFragment {
                             context,
                             applies_to,
+                            resource_descendant_roots,
                             parents,
+                            relative_parents,
                             attribute_types,
                             attributes,
+                            annotations: action_type.annotations,
+                            deprecated: action_type.deprecated,
+                            deprecation_message: action_type.deprecation_message,
                         },
                     ))
                 })
-                .collect::<Result<HashMap<_, _>>>()?,
+                .collect::<Result<ValidatorMap<_, _>>>()?,
         })
     }
 
@@ -488,7 +688,7 @@ impl ValidatorNamespaceDef {
         }
         if action_behavior == ActionBehavior::ProhibitAttributes {
             let mut actions_with_attributes: Vec<String> = Vec::new();
-            for (name, a) in &schema_file.actions {
+            for (name, a) in schema_file.actions.iter() {
                 if a.attributes.is_some() {
                     actions_with_attributes.push(name.to_string());
                 }
@@ -511,6 +711,17 @@ impl ValidatorNamespaceDef {
         schema_namespace: Option<&Name>,
         attrs: impl IntoIterator<Item = (SmolStr, TypeOfAttribute)>,
     ) -> Result<WithUnresolvedTypeDefs<Attributes>> {
+        let attrs = attrs.into_iter().collect::<Vec<_>>();
+        let attrs_with_defaults = attrs
+            .iter()
+            .filter(|(_, ty)| ty.default.is_some())
+            .map(|(attr, _)| attr.to_string())
+            .collect::<Vec<_>>();
+        if !attrs_with_defaults.is_empty() {
+            return Err(SchemaError::UnsupportedFeature(
+                UnsupportedFeature::NonEntityAttributeDefaults(attrs_with_defaults),
+            ));
+        }
         let attrs_with_type_defs = attrs
             .into_iter()
             .map(|(attr, ty)| -> Result<_> {
@@ -519,6 +730,7 @@ impl ValidatorNamespaceDef {
                     (
                         Self::try_schema_type_into_validator_type(schema_namespace, ty.ty)?,
                         ty.required,
+                        ty.sensitive,
                     ),
                 ))
             })
@@ -526,10 +738,17 @@ impl ValidatorNamespaceDef {
         Ok(WithUnresolvedTypeDefs::new(|typ_defs| {
             attrs_with_type_defs
                 .into_iter()
-                .map(|(s, (attr_ty, is_req))| {
+                .map(|(s, (attr_ty, is_req, is_sensitive))| {
                     attr_ty
                         .resolve_type_defs(typ_defs)
-                        .map(|ty| (s, AttributeType::new(ty, is_req)))
+                        .map_err(|e| e.in_attribute(&s))
+                        .map(|ty| {
+                            let attr_ty = AttributeType {
+                                is_sensitive,
+                                ..AttributeType::new(ty, is_req)
+                            };
+                            (s, attr_ty)
+                        })
                 })
                 .collect::<Result<Vec<_>>>()
                 .map(Attributes::with_attributes)
@@ -556,7 +775,7 @@ impl ValidatorNamespaceDef {
                             Self::parse_possibly_qualified_name_with_default_namespace(
                                 ty_str, namespace,
                             )
-                            .map_err(SchemaError::ParseEntityType)?,
+                            .map_err(SchemaError::parse_entity_type)?,
                         ))
                     })
                     // Fail if any of the types failed.
@@ -565,6 +784,24 @@ impl ValidatorNamespaceDef {
             .unwrap_or_else(|| Ok(HashSet::from([EntityType::Unspecified])))
     }
 
+    // Parse the entities in a `principalsIn` or `resourcesIn` list. Their
+    // types may be unqualified, in which case they are in `namespace`.
+    fn parse_apply_spec_groups(
+        groups: &[EntityParentUID],
+        namespace: Option<&Name>,
+    ) -> Result<BTreeSet<EntityUID>> {
+        groups
+            .iter()
+            .map(|group| {
+                let ty = Self::parse_possibly_qualified_name_with_default_namespace(
+                    &group.ty, namespace,
+                )
+                .map_err(SchemaError::parse_entity_type)?;
+                Ok(EntityUID::from_components(ty, Eid::new(group.id.clone())))
+            })
+            .collect()
+    }
+
     // Parse a `Name` from a string (possibly including namespaces). If it is
     // not qualified with any namespace, then apply the  default namespace to
     // create a qualified name.  Do not modify any existing namespace on the
@@ -595,7 +832,7 @@ impl ValidatorNamespaceDef {
     /// Parse a name from a string into the `Id` (basename only).  Then
     /// initialize the namespace for this type with the provided namespace vec
     /// to create the qualified `Name`.
-    fn parse_unqualified_name_with_namespace(
+    pub(crate) fn parse_unqualified_name_with_namespace(
         type_name: impl AsRef<str>,
         namespace: Option<Name>,
     ) -> std::result::Result<Name, ParseErrors> {
@@ -611,13 +848,13 @@ impl ValidatorNamespaceDef {
     /// have the base type `Action`. The type will be qualified with any
     /// namespace provided in the `namespace` argument or with the namespace
     /// inside the ActionEntityUID if one is present.
-    fn parse_action_id_with_namespace(
+    pub(crate) fn parse_action_id_with_namespace(
         action_id: &ActionEntityUID,
         namespace: Option<&Name>,
     ) -> Result<EntityUID> {
         let namespaced_action_type = if let Some(action_ty) = &action_id.ty {
             Self::parse_possibly_qualified_name_with_default_namespace(action_ty, namespace)
-                .map_err(SchemaError::ParseEntityType)?
+                .map_err(SchemaError::parse_entity_type)?
         } else {
             // PANIC SAFETY: The constant ACTION_ENTITY_TYPE is valid entity type.
             #[allow(clippy::expect_used)]
@@ -635,6 +872,35 @@ impl ValidatorNamespaceDef {
         ))
     }
 
+    /// If the action identifier has a qualified action type and is declared
+    /// inside a namespace, construct the `EntityUID` it refers to when that
+    /// type is resolved relative to the namespace. For example, `Foo::Action`
+    /// inside `ExampleCo::Personnel` becomes `ExampleCo::Personnel::Foo::Action`.
+    /// Returns `None` when relative resolution does not apply.
+    fn parse_relative_action_id(
+        action_id: &ActionEntityUID,
+        namespace: Option<&Name>,
+    ) -> Result<Option<EntityUID>> {
+        let (Some(action_ty), Some(namespace)) = (&action_id.ty, namespace) else {
+            return Ok(None);
+        };
+        let action_ty =
+            Name::from_normalized_str(action_ty).map_err(SchemaError::parse_entity_type)?;
+        if action_ty.namespace_components().next().is_none() {
+            return Ok(None);
+        }
+        let path = namespace
+            .namespace_components()
+            .chain(std::iter::once(namespace.basename()))
+            .chain(action_ty.namespace_components())
+            .cloned()
+            .collect::<Vec<_>>();
+        Ok(Some(EntityUID::from_components(
+            Name::new(action_ty.basename().clone(), path),
+            Eid::new(action_id.id.clone()),
+        )))
+    }
+
     /// Implemented to convert a type as written in the schema json format into the
     /// `Type` type used by the validator. Conversion can fail if an entity or
     /// record attribute name is invalid. It will also fail for some types that can
@@ -645,7 +911,17 @@ impl ValidatorNamespaceDef {
         schema_ty: SchemaType,
     ) -> Result<WithUnresolvedTypeDefs<Type>> {
         match schema_ty {
-            SchemaType::Type(SchemaTypeVariant::String) => Ok(Type::primitive_string().into()),
+            SchemaType::Type(SchemaTypeVariant::String { allowed_values }) => {
+                match allowed_values {
+                    None => Ok(Type::primitive_string().into()),
+                    Some(allowed_values) if allowed_values.is_empty() => {
+                        Err(SchemaError::EmptyAllowedValues)
+                    }
+                    Some(allowed_values) => {
+                        Ok(Type::string_enum(allowed_values.into_iter().collect()).into())
+                    }
+                }
+            }
             SchemaType::Type(SchemaTypeVariant::Long) => Ok(Type::primitive_long().into()),
             SchemaType::Type(SchemaTypeVariant::Boolean) => Ok(Type::primitive_boolean().into()),
             SchemaType::Type(SchemaTypeVariant::Set { element }) => Ok(
@@ -673,12 +949,27 @@ impl ValidatorNamespaceDef {
                     &name,
                     default_namespace,
                 )
-                .map_err(SchemaError::ParseEntityType)?;
+                .map_err(SchemaError::parse_entity_type)?;
                 Ok(Type::named_entity_reference(entity_type_name).into())
             }
+            SchemaType::Type(SchemaTypeVariant::EntityUnion { names }) => {
+                let entity_type_names = names
+                    .iter()
+                    .map(|name| {
+                        Self::parse_possibly_qualified_name_with_default_namespace(
+                            name,
+                            default_namespace,
+                        )
+                        .map_err(SchemaError::parse_entity_type)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Type::entity_reference_union(entity_type_names)
+                    .map(Into::into)
+                    .ok_or(SchemaError::EmptyEntityTypeNames)
+            }
             SchemaType::Type(SchemaTypeVariant::Extension { name }) => {
                 let extension_type_name =
-                    Name::from_normalized_str(&name).map_err(SchemaError::ParseExtensionType)?;
+                    Name::from_normalized_str(&name).map_err(SchemaError::parse_extension_type)?;
                 Ok(Type::extension(extension_type_name).into())
             }
             SchemaType::TypeDef { type_name } => {
@@ -686,11 +977,27 @@ impl ValidatorNamespaceDef {
                     &type_name,
                     default_namespace,
                 )
-                .map_err(SchemaError::ParseCommonType)?;
+                .map_err(SchemaError::parse_common_type)?;
+                // An unqualified reference inside a namespace resolves to the
+                // common type declared in that namespace, or otherwise to the
+                // common type of the same name in the root namespace.
+                let root_type_name = Name::parse_unqualified_name(&type_name)
+                    .ok()
+                    .filter(|root_type_name| *root_type_name != defined_type_name);
                 Ok(WithUnresolvedTypeDefs::new(move |typ_defs| {
-                    typ_defs.get(&defined_type_name).cloned().ok_or(
-                        SchemaError::UndeclaredCommonTypes(HashSet::from([type_name.to_string()])),
-                    )
+                    let root_ty = root_type_name
+                        .as_ref()
+                        .and_then(|root_type_name| typ_defs.get(root_type_name));
+                    match (typ_defs.get(&defined_type_name), root_ty) {
+                        (Some(_), Some(_)) => Err(SchemaError::AmbiguousCommonType {
+                            reference: type_name.to_string(),
+                            local: defined_type_name.to_string(),
+                        }),
+                        (Some(ty), None) | (None, Some(ty)) => Ok(ty.clone()),
+                        (None, None) => {
+                            Err(SchemaError::undeclared_common_type(type_name.to_string()))
+                        }
+                    }
                 }))
             }
         }
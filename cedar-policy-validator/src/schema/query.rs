@@ -0,0 +1,112 @@
+//! This module contains the `SchemaQuery` trait, which describes the read-only
+//! lookups that can be performed on an already-built schema.
+//!
+//! Everything needed to construct a `ValidatorSchema` (parsing schema
+//! fragments, resolving common types, computing the transitive closure of the
+//! entity type and action hierarchies, and checking for undeclared names)
+//! stays on `ValidatorSchema` itself. The queries here only ever read
+//! `ValidatorEntityType`s and `ValidatorActionId`s that have already been
+//! built, never allocate beyond the returned iterators, and never fail, so
+//! consumers that only need to consult a schema can depend on this trait
+//! alone.
+
+use cedar_policy_core::ast::{EntityType, EntityUID, Name};
+
+use super::{ValidatorActionId, ValidatorEntityType};
+
+/// Read-only queries on a built schema. Implementors only need to provide
+/// lookups and iterators over the entity types and actions; the remaining
+/// queries are derived from those.
+///
+/// Implementors must ensure that the `descendants` of each entity type and
+/// action are transitively closed, as they are for a `ValidatorSchema`.
+pub trait SchemaQuery {
+    /// Lookup the `ValidatorEntityType` object with the given name.
+    fn get_entity_type(&self, entity_type_id: &Name) -> Option<&ValidatorEntityType>;
+
+    /// Lookup the `ValidatorActionId` object with the given name.
+    fn get_action_id(&self, action_id: &EntityUID) -> Option<&ValidatorActionId>;
+
+    /// An iterator over all entity types and their `ValidatorEntityType`s.
+    fn entity_types<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = (&'a Name, &'a ValidatorEntityType)> + 'a>;
+
+    /// An iterator over all actions and their `ValidatorActionId`s.
+    fn action_ids<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = (&'a EntityUID, &'a ValidatorActionId)> + 'a>;
+
+    /// An iterator over the entity types which can be (transitively) members
+    /// of the given entity type, or `None` if the entity type is not declared.
+    fn descendants_of<'a>(
+        &'a self,
+        entity_type: &Name,
+    ) -> Option<Box<dyn Iterator<Item = &'a Name> + 'a>> {
+        self.get_entity_type(entity_type)
+            .map(|et| Box::new(et.descendants.iter()) as Box<dyn Iterator<Item = &'a Name>>)
+    }
+
+    /// An iterator over the actions which can be applied to principals of the
+    /// given entity type. This includes actions which list `principal_ty` in
+    /// their `appliesTo` principal types, as well as actions which list an
+    /// entity type that has `principal_ty` as a descendant.
+    fn actions_applicable_to_principal<'a>(
+        &'a self,
+        principal_ty: &'a Name,
+    ) -> Box<dyn Iterator<Item = &'a EntityUID> + 'a> {
+        Box::new(
+            self.action_ids()
+                .filter(move |(_, action)| {
+                    is_applicable_type(
+                        self,
+                        action.applies_to.applicable_principal_types(),
+                        principal_ty,
+                    )
+                })
+                .map(|(action_id, _)| action_id),
+        )
+    }
+
+    /// An iterator over the actions which can be applied to resources of the
+    /// given entity type. This includes actions which list `resource_ty` in
+    /// their `appliesTo` resource types, as well as actions which list an
+    /// entity type that has `resource_ty` as a descendant.
+    fn actions_applicable_to_resource<'a>(
+        &'a self,
+        resource_ty: &'a Name,
+    ) -> Box<dyn Iterator<Item = &'a EntityUID> + 'a> {
+        Box::new(
+            self.action_ids()
+                .filter(move |(_, action)| {
+                    is_applicable_type(
+                        self,
+                        action.applies_to.applicable_resource_types(),
+                        resource_ty,
+                    )
+                })
+                .map(|(action_id, _)| action_id),
+        )
+    }
+}
+
+/// Return true when `ty` is one of the `applicable_types` or is a descendant
+/// of one of them.
+fn is_applicable_type<'a, S: SchemaQuery + ?Sized>(
+    schema: &S,
+    applicable_types: impl IntoIterator<Item = &'a EntityType>,
+    ty: &Name,
+) -> bool {
+    applicable_types
+        .into_iter()
+        .any(|applicable_ty| match applicable_ty {
+            EntityType::Concrete(applicable_ty) => {
+                applicable_ty == ty
+                    || matches!(
+                        schema.get_entity_type(applicable_ty),
+                        Some(et) if et.descendants.contains(ty)
+                    )
+            }
+            EntityType::Unspecified => false,
+        })
+}
@@ -0,0 +1,383 @@
+//! This module contains `ValidatorSchema::validate`, which runs the checks
+//! which are not performed when constructing a schema and reports their
+//! findings together.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use cedar_policy_core::ast::{EntityType, Name};
+use serde::Serialize;
+
+use super::ValidatorSchema;
+use crate::SchemaError;
+
+/// Entity type hierarchies deeper than this are reported by
+/// `ValidatorSchema::validate` as `DeepHierarchy` warnings.
+pub const MAX_RECOMMENDED_HIERARCHY_DEPTH: usize = 5;
+
+/// Identifiers which have a meaning in the policy language. A name equal to
+/// one of these ignoring case is reported as a `ReservedAdjacentName`.
+const RESERVED_IDENTIFIERS: [&str; 13] = [
+    "true",
+    "false",
+    "if",
+    "then",
+    "else",
+    "in",
+    "like",
+    "has",
+    "is",
+    "principal",
+    "action",
+    "resource",
+    "context",
+];
+
+/// Checks which `ValidatorSchema::validate_with_lints` performs in addition
+/// to those of `ValidatorSchema::validate`. They are opt-in because they flag
+/// patterns which some schemas use on purpose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SchemaLint {
+    /// Report actions which are action groups, i.e., some action is a member
+    /// of them, but which also apply to some principal or resource types. This
+    /// usually means a group and a concrete action were conflated.
+    ActionGroupWithAppliesTo,
+}
+
+/// How serious a `SchemaDiagnostic` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Severity {
+    /// The schema is valid, but likely not what was intended.
+    Warning,
+    /// The schema would be rejected by one of the checks which are not
+    /// performed during construction.
+    Error,
+}
+
+/// The kinds of findings reported by `ValidatorSchema::validate`. Names are
+/// fully qualified and rendered as strings so that the report can be emitted
+/// as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(tag = "kind")]
+pub enum SchemaDiagnosticKind {
+    /// Entity types or actions declared in the same namespace have names
+    /// which differ only by case, as reported by
+    /// `ValidatorSchema::check_case_collisions`.
+    CaseCollision {
+        /// The colliding names, sorted
+        names: Vec<String>,
+    },
+    /// The value of an action attribute does not conform to its type, as
+    /// reported by `ValidatorSchema::validate_action_attributes`.
+    InvalidActionAttribute {
+        /// The action declaring the attribute
+        action: String,
+        /// The attribute
+        attr: String,
+        /// Why the value does not conform
+        reason: String,
+    },
+    /// An entity type is not a member of any entity type, has no members, and
+    /// is not referenced by any action or attribute.
+    UnusedEntityType {
+        /// The unused entity type
+        entity_type: String,
+    },
+    /// An action has no principal types or no resource types, so no request
+    /// for it is valid.
+    EmptyAppliesTo {
+        /// The action
+        action: String,
+    },
+    /// An entity type or attribute name is equal to a reserved identifier of
+    /// the policy language ignoring case, or starts with `__cedar`.
+    ReservedAdjacentName {
+        /// The entity type or attribute name
+        name: String,
+        /// The reserved identifier it resembles
+        reserved: String,
+    },
+    /// An entity type has a chain of ancestors longer than
+    /// `MAX_RECOMMENDED_HIERARCHY_DEPTH`.
+    DeepHierarchy {
+        /// The entity type at the bottom of the chain
+        entity_type: String,
+        /// The length of the longest chain of ancestors
+        depth: usize,
+    },
+    /// Some action is a member of this action, but the action also applies
+    /// to some principal or resource types. Only reported when the
+    /// `SchemaLint::ActionGroupWithAppliesTo` lint is enabled.
+    ActionGroupWithAppliesTo {
+        /// The action group
+        action: String,
+    },
+}
+
+impl SchemaDiagnosticKind {
+    /// The severity of this kind of finding.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::CaseCollision { .. } | Self::InvalidActionAttribute { .. } => Severity::Error,
+            Self::UnusedEntityType { .. }
+            | Self::EmptyAppliesTo { .. }
+            | Self::ReservedAdjacentName { .. }
+            | Self::DeepHierarchy { .. }
+            | Self::ActionGroupWithAppliesTo { .. } => Severity::Warning,
+        }
+    }
+}
+
+impl Display for SchemaDiagnosticKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CaseCollision { names } => write!(
+                f,
+                "names differ only by case: {}",
+                names
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::InvalidActionAttribute {
+                action,
+                attr,
+                reason,
+            } => write!(
+                f,
+                "attribute `{attr}` of action `{action}` does not match its type: {reason}"
+            ),
+            Self::UnusedEntityType { entity_type } => {
+                write!(f, "entity type `{entity_type}` is never used")
+            }
+            Self::EmptyAppliesTo { action } => write!(
+                f,
+                "action `{action}` has no principal types or no resource types"
+            ),
+            Self::ReservedAdjacentName { name, reserved } => {
+                write!(f, "`{name}` resembles the reserved identifier `{reserved}`")
+            }
+            Self::DeepHierarchy { entity_type, depth } => write!(
+                f,
+                "entity type `{entity_type}` has a hierarchy of depth {depth}, more than {MAX_RECOMMENDED_HIERARCHY_DEPTH}"
+            ),
+            Self::ActionGroupWithAppliesTo { action } => write!(
+                f,
+                "action `{action}` is an action group but also declares principal or resource types"
+            ),
+        }
+    }
+}
+
+/// A single finding of `ValidatorSchema::validate`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaDiagnostic {
+    /// How serious the finding is
+    pub severity: Severity,
+    /// What was found
+    #[serde(flatten)]
+    pub kind: SchemaDiagnosticKind,
+    /// A human readable description of the finding
+    pub message: String,
+}
+
+impl From<SchemaDiagnosticKind> for SchemaDiagnostic {
+    fn from(kind: SchemaDiagnosticKind) -> Self {
+        Self {
+            severity: kind.severity(),
+            message: kind.to_string(),
+            kind,
+        }
+    }
+}
+
+/// The findings of `ValidatorSchema::validate`, errors first and otherwise
+/// sorted by kind.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    diagnostics: Vec<SchemaDiagnostic>,
+}
+
+impl ValidationReport {
+    /// All findings, errors first.
+    pub fn diagnostics(&self) -> impl Iterator<Item = &SchemaDiagnostic> {
+        self.diagnostics.iter()
+    }
+
+    /// The findings with `Severity::Error`.
+    pub fn errors(&self) -> impl Iterator<Item = &SchemaDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// The findings with `Severity::Warning`.
+    pub fn warnings(&self) -> impl Iterator<Item = &SchemaDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Warning)
+    }
+
+    /// Returns `true` if there is at least one finding with `Severity::Error`.
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// Returns `true` if there are no findings at all.
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+impl ValidatorSchema {
+    /// Run the checks which are not performed when constructing a schema and
+    /// report their findings together, each with a severity. Errors are the
+    /// findings of `check_case_collisions` and `validate_action_attributes`,
+    /// with every failure reported rather than only the first. Warnings are
+    /// reported for unused entity types, actions which apply to no principal
+    /// or no resource types, entity type and attribute names resembling
+    /// reserved identifiers, and entity type hierarchies deeper than
+    /// `MAX_RECOMMENDED_HIERARCHY_DEPTH`.
+    pub fn validate(&self) -> ValidationReport {
+        self.validate_with_lints(&[])
+    }
+
+    /// Like `validate`, but also report the findings of the opt-in `lints`.
+    pub fn validate_with_lints(&self, lints: &[SchemaLint]) -> ValidationReport {
+        let mut kinds = Vec::new();
+
+        kinds.extend(self.case_collision_groups().into_iter().map(|names| {
+            SchemaDiagnosticKind::CaseCollision {
+                names: names.into_iter().collect(),
+            }
+        }));
+
+        for (action_id, action) in self.action_ids.iter() {
+            kinds.extend(
+                Self::action_attribute_errors(action_id, action)
+                    .into_iter()
+                    .filter_map(|err| match err {
+                        SchemaError::InvalidActionAttribute(action, attr, reason) => {
+                            Some(SchemaDiagnosticKind::InvalidActionAttribute {
+                                action: action.to_string(),
+                                attr: attr.to_string(),
+                                reason,
+                            })
+                        }
+                        _ => None,
+                    }),
+            );
+            if action
+                .applies_to
+                .applicable_principal_types()
+                .next()
+                .is_none()
+                || action
+                    .applies_to
+                    .applicable_resource_types()
+                    .next()
+                    .is_none()
+            {
+                kinds.push(SchemaDiagnosticKind::EmptyAppliesTo {
+                    action: action_id.to_string(),
+                });
+            }
+            kinds.extend(
+                action
+                    .context
+                    .iter()
+                    .filter_map(|(attr, _)| reserved_adjacent(attr)),
+            );
+            if lints.contains(&SchemaLint::ActionGroupWithAppliesTo)
+                && !action.descendants.is_empty()
+                && action
+                    .applies_to
+                    .applicable_principal_types()
+                    .chain(action.applies_to.applicable_resource_types())
+                    .any(|ty| matches!(ty, EntityType::Concrete(_)))
+            {
+                kinds.push(SchemaDiagnosticKind::ActionGroupWithAppliesTo {
+                    action: action_id.to_string(),
+                });
+            }
+        }
+
+        let mut parents: HashMap<&Name, Vec<&Name>> = HashMap::new();
+        for (parent, entity_type) in self.entity_types.iter() {
+            for member in entity_type.descendants.iter() {
+                parents.entry(member).or_default().push(parent);
+            }
+        }
+        let mut depths = HashMap::new();
+        for (name, entity_type) in self.entity_types.iter() {
+            if !parents.contains_key(name) && self.references_to(name).is_empty() {
+                kinds.push(SchemaDiagnosticKind::UnusedEntityType {
+                    entity_type: name.to_string(),
+                });
+            }
+            kinds.extend(reserved_adjacent(name.basename().as_ref()));
+            kinds.extend(
+                entity_type
+                    .attributes()
+                    .filter_map(|(attr, _)| reserved_adjacent(attr)),
+            );
+            let depth = Self::hierarchy_depth(name, &parents, &mut HashSet::new(), &mut depths);
+            if depth > MAX_RECOMMENDED_HIERARCHY_DEPTH {
+                kinds.push(SchemaDiagnosticKind::DeepHierarchy {
+                    entity_type: name.to_string(),
+                    depth,
+                });
+            }
+        }
+
+        kinds.sort_by(|a, b| b.severity().cmp(&a.severity()).then_with(|| a.cmp(b)));
+        kinds.dedup();
+        ValidationReport {
+            diagnostics: kinds.into_iter().map(SchemaDiagnostic::from).collect(),
+        }
+    }
+
+    /// The length of the longest chain of ancestors of the entity type
+    /// `name`, memoized in `depths`. `parents` maps each entity type to the
+    /// entity types it is a member of. Entity type cycles are allowed, so
+    /// `in_progress` holds the entity types on the current chain, and a chain
+    /// ends where it would return to one of them.
+    fn hierarchy_depth<'a>(
+        name: &'a Name,
+        parents: &HashMap<&'a Name, Vec<&'a Name>>,
+        in_progress: &mut HashSet<&'a Name>,
+        depths: &mut HashMap<&'a Name, usize>,
+    ) -> usize {
+        if let Some(depth) = depths.get(name) {
+            return *depth;
+        }
+        in_progress.insert(name);
+        let mut depth = 0;
+        for parent in parents.get(name).into_iter().flatten() {
+            if !in_progress.contains(parent) {
+                depth = depth.max(Self::hierarchy_depth(parent, parents, in_progress, depths) + 1);
+            }
+        }
+        in_progress.remove(name);
+        depths.insert(name, depth);
+        depth
+    }
+}
+
+/// Get a `ReservedAdjacentName` finding for `name` if it resembles a reserved
+/// identifier.
+fn reserved_adjacent(name: &str) -> Option<SchemaDiagnosticKind> {
+    let folded = name.to_lowercase();
+    let reserved = if folded.starts_with("__cedar") {
+        "__cedar"
+    } else {
+        RESERVED_IDENTIFIERS
+            .iter()
+            .find(|reserved| **reserved == folded)?
+    };
+    Some(SchemaDiagnosticKind::ReservedAdjacentName {
+        name: name.to_string(),
+        reserved: reserved.to_string(),
+    })
+}
@@ -14,29 +14,377 @@
  * limitations under the License.
  */
 
-use cedar_policy_core::entities::CedarValueJson;
+use cedar_policy_core::{ast::RestrictedExpr, entities::CedarValueJson};
 use serde::{
     de::{MapAccess, Visitor},
     Deserialize, Serialize,
 };
 use serde_with::serde_as;
 use smol_str::SmolStr;
+use std::borrow::Borrow;
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::io::Read;
+use std::marker::PhantomData;
+
+use crate::{Result, SchemaError};
+
+/// Byte order mark which some editors write at the start of UTF-8 files
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// The top-level key of a schema fragment declaring the version of the schema
+/// format it is written in. It cannot be confused with a namespace because
+/// namespace names may not contain `$`.
+pub(crate) const VERSION_KEY: &str = "$version";
+
+/// The map from names to declarations used by schema fragments for their
+/// namespaces, common types, entity types and actions. With the
+/// `preserve-order` feature, iterating over it visits the entries in the
+/// order they were inserted, which for a deserialized fragment is the order
+/// they were declared in. Otherwise the order is arbitrary. The feature only
+/// changes the representation, not the type or its methods.
+#[derive(Debug, Clone)]
+pub struct SchemaMap<K, V>(SchemaMapInner<K, V>);
+
+/// The representation of a `SchemaMap`.
+#[cfg(feature = "preserve-order")]
+type SchemaMapInner<K, V> = indexmap::IndexMap<K, V>;
+/// The representation of a `SchemaMap`.
+#[cfg(not(feature = "preserve-order"))]
+type SchemaMapInner<K, V> = HashMap<K, V>;
+
+impl<K: Hash + Eq, V> SchemaMap<K, V> {
+    /// An empty map.
+    pub fn new() -> Self {
+        Self(SchemaMapInner::new())
+    }
+
+    /// The number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether the map has an entry for `key`.
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.0.contains_key(key)
+    }
+
+    /// The value for `key`, if any.
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.0.get(key)
+    }
+
+    /// The value for `key`, if any, mutably.
+    pub fn get_mut<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        self.0.get_mut(key)
+    }
+
+    /// Insert `value` for `key`, returning the value it replaces, if any. A
+    /// new key goes after every existing one; a replaced value keeps the
+    /// position of its key.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.0.insert(key, value)
+    }
+
+    /// Remove the entry for `key`, returning its value, if any. The other
+    /// entries keep their order.
+    #[cfg(feature = "preserve-order")]
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.0.shift_remove(key)
+    }
+
+    /// Remove the entry for `key`, returning its value, if any.
+    #[cfg(not(feature = "preserve-order"))]
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.0.remove(key)
+    }
+
+    /// An iterator over the keys of the map, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.0.keys()
+    }
+
+    /// An iterator over the values of the map, in order.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.0.values()
+    }
+
+    /// An iterator over the values of the map, in order, mutably.
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.0.values_mut()
+    }
+
+    /// An iterator over the entries of the map, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.0.iter()
+    }
+
+    /// An iterator over the entries of the map, in order, with mutable
+    /// values.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.0.iter_mut()
+    }
+}
 
-use crate::Result;
+/// Two maps are equal if they have the same entries, in any order.
+impl<K: Hash + Eq, V: PartialEq> PartialEq for SchemaMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<K: Hash + Eq, V: Eq> Eq for SchemaMap<K, V> {}
+
+impl<K: Hash + Eq, V> Default for SchemaMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for SchemaMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<K: Hash + Eq, V> Extend<(K, V)> for SchemaMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl<K: Hash + Eq, V> IntoIterator for SchemaMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = SchemaMapIntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SchemaMapIntoIter(self.0.into_iter())
+    }
+}
+
+/// An iterator over the entries of a `SchemaMap`, by value, in the order of
+/// the map.
+#[derive(Debug)]
+pub struct SchemaMapIntoIter<K, V>(<SchemaMapInner<K, V> as IntoIterator>::IntoIter);
+
+impl<K, V> Iterator for SchemaMapIntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K: Hash + Eq + Serialize, V: Serialize> Serialize for SchemaMap<K, V> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for SchemaMap<K, V>
+where
+    K: Hash + Eq + Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_map(SchemaMapVisitor(PhantomData))
+    }
+}
+
+/// Visitor for a `SchemaMap`, which reports duplicate keys as an error.
+struct SchemaMapVisitor<K, V>(PhantomData<(K, V)>);
+
+impl<'de, K, V> Visitor<'de> for SchemaMapVisitor<K, V>
+where
+    K: Hash + Eq + Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    type Value = SchemaMap<K, V>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<M>(self, mut map: M) -> std::result::Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut entries = SchemaMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            if entries.insert(key, value).is_some() {
+                return Err(serde::de::Error::custom(
+                    "invalid entry: found duplicate key",
+                ));
+            }
+        }
+        Ok(entries)
+    }
+}
 
 /// A SchemaFragment describe the types for a given instance of Cedar.
 /// SchemaFragments are composed of Entity Types and Action Types. The
 /// schema fragment is split into multiple namespace definitions, eac including
 /// a namespace name which is applied to all entity types (and the implicit
 /// `Action` entity type for all actions) in the schema.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(transparent)]
+#[derive(Debug, Clone)]
 pub struct SchemaFragment(
-    #[serde(with = "::serde_with::rust::maps_duplicate_key_is_error")]
-    pub  HashMap<SmolStr, NamespaceDefinition>,
+    pub SchemaMap<SmolStr, NamespaceDefinition>,
+    /// The version of the schema format declared with a top-level `"$version"`
+    /// key, if any. A fragment which does not declare a version is in the
+    /// current format.
+    pub Option<SchemaVersion>,
 );
 
+impl Serialize for SchemaFragment {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map =
+            serializer.serialize_map(Some(self.0.len() + usize::from(self.1.is_some())))?;
+        if let Some(version) = &self.1 {
+            map.serialize_entry(VERSION_KEY, version)?;
+        }
+        for (namespace, ns_def) in self.0.iter().collect::<BTreeMap<_, _>>() {
+            map.serialize_entry(namespace, ns_def)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemaFragment {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_map(SchemaFragmentVisitor)
+    }
+}
+
+/// Visitor for the top-level map of a schema fragment, which holds the
+/// namespace definitions and possibly the `"$version"` key. Duplicate
+/// namespaces are reported as an error.
+struct SchemaFragmentVisitor;
+
+impl<'de> Visitor<'de> for SchemaFragmentVisitor {
+    type Value = SchemaFragment;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("a map from namespace names to namespace definitions")
+    }
+
+    fn visit_map<M>(self, mut map: M) -> std::result::Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut namespaces = SchemaMap::new();
+        let mut version = None;
+        while let Some(key) = map.next_key::<SmolStr>()? {
+            if key == VERSION_KEY {
+                if version.is_some() {
+                    return Err(serde::de::Error::duplicate_field(VERSION_KEY));
+                }
+                version = Some(map.next_value()?);
+            } else {
+                if namespaces.contains_key(&key) {
+                    return Err(serde::de::Error::custom(
+                        "invalid entry: found duplicate key",
+                    ));
+                }
+                namespaces.insert(key, map.next_value()?);
+            }
+        }
+        Ok(SchemaFragment(namespaces, version))
+    }
+}
+
+/// The versions of the schema format. A schema fragment may declare the
+/// version it is written in with a top-level `"$version"` key, whose value is
+/// the number of the version. `SchemaFragment::migrate` upgrades a fragment
+/// written in an older version to the current one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchemaVersion {
+    /// The format in which the values of action attributes could use the
+    /// `__expr` escape to give a Cedar expression as a string.
+    V1,
+    /// The current format, in which the `__expr` escape is not supported.
+    V2,
+}
+
+impl SchemaVersion {
+    /// The version of the format read by this library.
+    pub const CURRENT: SchemaVersion = SchemaVersion::V2;
+
+    /// The number of this version, as written in `"$version"`.
+    pub fn number(self) -> u64 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    /// Get the version with the given number, if it is known.
+    pub fn from_number(number: u64) -> Option<Self> {
+        match number {
+            1 => Some(Self::V1),
+            2 => Some(Self::V2),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for SchemaVersion {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.number())
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemaVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let number = u64::deserialize(deserializer)?;
+        Self::from_number(number).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "unsupported schema version {number}: the newest supported version is {}",
+                Self::CURRENT.number()
+            ))
+        })
+    }
+}
+
 impl SchemaFragment {
     /// Create a `SchemaFragment` from a JSON value (which should be an object
     /// of the appropriate shape).
@@ -44,9 +392,209 @@ impl SchemaFragment {
         serde_json::from_value(json).map_err(Into::into)
     }
 
-    /// Create a `SchemaFragment` directly from a file.
-    pub fn from_file(file: impl std::io::Read) -> Result<Self> {
-        serde_json::from_reader(file).map_err(Into::into)
+    /// Create a `SchemaFragment` directly from a file. A leading UTF-8 byte
+    /// order mark is ignored.
+    pub fn from_file(mut file: impl std::io::Read) -> Result<Self> {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)
+            .map_err(|source| SchemaError::Io {
+                source,
+                bytes_read: bytes.len(),
+            })?;
+        let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(&bytes);
+        let src = std::str::from_utf8(bytes)
+            .map_err(|err| SchemaError::InvalidUtf8(err.valid_up_to()))?;
+        serde_json::from_str(src).map_err(Into::into)
+    }
+
+    /// Create a `SchemaFragment` from bytes containing JSON, e.g., embedded
+    /// with `include_bytes!`. A leading UTF-8 byte order mark is ignored.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+        serde_json::from_slice(bytes).map_err(Into::into)
+    }
+
+    /// Create a `SchemaFragment` from a string containing JSON with comments.
+    /// Line comments (`// ...`) and block comments (`/* ... */`) outside of
+    /// strings are ignored. Comments are replaced by whitespace rather than
+    /// removed, so the lines and columns reported in parse errors are those
+    /// of the original string.
+    #[cfg(feature = "jsonc")]
+    pub fn from_jsonc_str(src: &str) -> Result<Self> {
+        serde_json::from_str(&blank_json_comments(src)).map_err(Into::into)
+    }
+
+    /// Upgrade this fragment from the version of the schema format it
+    /// declares to the current version. A fragment which does not declare a
+    /// version, or declares the current one, is returned unchanged. Schemas
+    /// are migrated automatically when a `ValidatorSchema` is constructed from
+    /// them.
+    ///
+    /// Migrating from version 1 replaces each `__expr` escape in the values of
+    /// action attributes with the value of the expression it contains, which
+    /// must be a restricted expression. An expression which cannot be
+    /// converted is reported as `SchemaError::InvalidExprEscape`.
+    pub fn migrate(self) -> Result<SchemaFragment> {
+        match self.1 {
+            Some(version) if version < SchemaVersion::CURRENT => Ok(SchemaFragment(
+                self.0
+                    .into_iter()
+                    .map(|(namespace, ns_def)| Ok((namespace, ns_def.migrate(version)?)))
+                    .collect::<Result<_>>()?,
+                Some(SchemaVersion::CURRENT),
+            )),
+            _ => Ok(self),
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for SchemaFragment {
+    type Error = SchemaError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Self::from_slice(bytes)
+    }
+}
+
+/// Replace each comment in `src` which is outside of a JSON string with
+/// spaces, keeping line breaks, so that every other character stays at the
+/// same line, column and byte offset. An unterminated block comment is left
+/// as is, so that it is reported by the JSON parser.
+#[cfg(any(feature = "jsonc", test))]
+fn blank_json_comments(src: &str) -> String {
+    let blank = |comment: &str| {
+        comment
+            .chars()
+            .map(|c| {
+                if c == '\n' || c == '\r' {
+                    c.to_string()
+                } else {
+                    " ".repeat(c.len_utf8())
+                }
+            })
+            .collect::<String>()
+    };
+    let mut out = String::with_capacity(src.len());
+    let mut rest = src;
+    let mut in_string = false;
+    while let Some(c) = rest.chars().next() {
+        let comment_len = if in_string {
+            None
+        } else if rest.starts_with("//") {
+            Some(rest.find('\n').unwrap_or(rest.len()))
+        } else if rest.starts_with("/*") {
+            rest.get(2..)
+                .and_then(|body| body.find("*/"))
+                .map(|end| end + 4)
+        } else {
+            None
+        };
+        if let Some(len) = comment_len {
+            let (comment, tail) = rest.split_at(len);
+            out.push_str(&blank(comment));
+            rest = tail;
+            continue;
+        }
+        let mut len = c.len_utf8();
+        match c {
+            '"' => in_string = !in_string,
+            // Copy escaped characters, including `\"`, unchanged
+            '\\' if in_string => {
+                len += rest.chars().nth(1).map_or(0, char::len_utf8);
+            }
+            _ => (),
+        }
+        let (copied, tail) = rest.split_at(len);
+        out.push_str(copied);
+        rest = tail;
+    }
+    out
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+/// Wrap `reader` so that a leading UTF-8 byte order mark is skipped.
+pub(crate) fn skip_utf8_bom(mut reader: impl Read) -> Result<impl Read> {
+    let mut prefix = Vec::with_capacity(UTF8_BOM.len());
+    (&mut reader)
+        .take(UTF8_BOM.len() as u64)
+        .read_to_end(&mut prefix)
+        .map_err(|source| SchemaError::Io {
+            source,
+            bytes_read: prefix.len(),
+        })?;
+    if prefix == UTF8_BOM {
+        prefix.clear();
+    }
+    Ok(std::io::Cursor::new(prefix).chain(reader))
+}
+
+/// Wraps a reader, counting the bytes read from it so that an IO error can
+/// report how far into the input it occurred.
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    bytes_read: usize,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            bytes_read: 0,
+        }
+    }
+
+    /// Convert an error from deserializing the data read from this reader
+    /// into a `SchemaError`, distinguishing IO errors from parse errors.
+    pub(crate) fn schema_error(&self, err: serde_json::Error) -> SchemaError {
+        if err.is_io() {
+            SchemaError::Io {
+                source: err.into(),
+                bytes_read: self.bytes_read,
+            }
+        } else {
+            err.into()
+        }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes_read += n;
+        Ok(n)
+    }
+}
+
+/// Serialize a `SchemaMap` with its entries sorted by key, so that the output
+/// does not depend on the iteration order of the map.
+fn serialize_sorted_map<S, K, V>(
+    map: &SchemaMap<K, V>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    K: Hash + Ord + Serialize,
+    V: Serialize,
+{
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+/// As `serialize_sorted_map`, for an optional map.
+fn serialize_sorted_optional_map<S, K, V>(
+    map: &Option<HashMap<K, V>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    K: Ord + Serialize,
+    V: Serialize,
+{
+    match map {
+        Some(map) => serializer.serialize_some(&map.iter().collect::<BTreeMap<_, _>>()),
+        None => serializer.serialize_none(),
     }
 }
 
@@ -57,23 +605,68 @@ impl SchemaFragment {
 #[doc(hidden)]
 pub struct NamespaceDefinition {
     #[serde(default)]
-    #[serde(with = "::serde_with::rust::maps_duplicate_key_is_error")]
+    #[serde(serialize_with = "serialize_sorted_map")]
     #[serde(rename = "commonTypes")]
-    pub common_types: HashMap<SmolStr, SchemaType>,
+    pub common_types: SchemaMap<SmolStr, SchemaType>,
     #[serde(rename = "entityTypes")]
-    #[serde(with = "::serde_with::rust::maps_duplicate_key_is_error")]
-    pub entity_types: HashMap<SmolStr, EntityType>,
-    #[serde(with = "::serde_with::rust::maps_duplicate_key_is_error")]
-    pub actions: HashMap<SmolStr, ActionType>,
+    #[serde(serialize_with = "serialize_sorted_map")]
+    pub entity_types: SchemaMap<SmolStr, EntityType>,
+    #[serde(serialize_with = "serialize_sorted_map")]
+    pub actions: SchemaMap<SmolStr, ActionType>,
 }
 
 impl NamespaceDefinition {
+    /// Upgrade this namespace definition from the schema format `version` to
+    /// the current one. See `SchemaFragment::migrate`.
+    pub(crate) fn migrate(mut self, version: SchemaVersion) -> Result<Self> {
+        if version < SchemaVersion::V2 {
+            for (action, action_type) in self.actions.iter_mut() {
+                for (attr, value) in action_type.attributes.iter_mut().flatten() {
+                    *value = Self::replace_expr_escapes(value.clone()).map_err(|message| {
+                        SchemaError::InvalidExprEscape {
+                            action: action.to_string(),
+                            attr: attr.to_string(),
+                            message,
+                        }
+                    })?;
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Replace each `__expr` escape in `value` with the value of the
+    /// expression it contains.
+    fn replace_expr_escapes(value: CedarValueJson) -> std::result::Result<CedarValueJson, String> {
+        match value {
+            CedarValueJson::ExprEscape { __expr } => {
+                let expr = __expr
+                    .parse::<RestrictedExpr>()
+                    .map_err(|err| err.to_string())?;
+                CedarValueJson::from_expr(expr.as_borrowed()).map_err(|err| err.to_string())
+            }
+            CedarValueJson::Set(elements) => Ok(CedarValueJson::Set(
+                elements
+                    .into_iter()
+                    .map(Self::replace_expr_escapes)
+                    .collect::<std::result::Result<_, _>>()?,
+            )),
+            CedarValueJson::Record(record) => Ok(CedarValueJson::Record(
+                record
+                    .into_iter()
+                    .map(|(attr, value)| Ok((attr, Self::replace_expr_escapes(value)?)))
+                    .collect::<std::result::Result<_, String>>()?,
+            )),
+            value => Ok(value),
+        }
+    }
+
     pub fn new(
         entity_types: impl IntoIterator<Item = (SmolStr, EntityType)>,
         actions: impl IntoIterator<Item = (SmolStr, ActionType)>,
     ) -> Self {
         Self {
-            common_types: HashMap::new(),
+            common_types: SchemaMap::new(),
             entity_types: entity_types.into_iter().collect(),
             actions: actions.into_iter().collect(),
         }
@@ -89,8 +682,53 @@ pub struct EntityType {
     #[serde(default)]
     #[serde(rename = "memberOfTypes")]
     pub member_of_types: Vec<SmolStr>,
+    /// Specific entities which every entity of this type must be a member of,
+    /// e.g., a root group. Unlike `memberOfTypes`, this lists entities rather
+    /// than entity types. The type of each entity must be declared and allowed
+    /// as an ancestor by `memberOfTypes`. Entity data in which an entity of
+    /// this type is not a member of one of these entities, directly or
+    /// transitively, is rejected when it is parsed against the schema.
     #[serde(default)]
-    pub shape: AttributesOrContext,
+    #[serde(rename = "memberOf")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub member_of: Vec<EntityParentUID>,
+    /// The attributes of this entity type. An absent shape declares no
+    /// attributes, just like an empty record, but the validator records
+    /// whether a shape was written so that an entity type intentionally
+    /// declared without attributes can be told apart from one whose attributes
+    /// were not specified.
+    /// Attribute types may refer to this entity type itself, directly or
+    /// through other entity types (e.g., a `Folder` with a `parent` attribute
+    /// of type `Folder`). The `memberOfTypes` relation may also contain
+    /// cycles, e.g., two entity types which are each a member of the other.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shape: Option<AttributesOrContext>,
+    /// Free-form metadata about this entity type, such as a description or a
+    /// link to documentation. Annotations are preserved in the validator's
+    /// representation of the schema but are ignored by validation.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<SmolStr, String>,
+    /// A regular expression which the id of every entity of this type must
+    /// match in full. Entity data which does not match is rejected when it is
+    /// parsed against the schema.
+    #[serde(default)]
+    #[serde(rename = "idFormat")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_format: Option<SmolStr>,
+    /// Whether this entity type is deprecated. Deprecated entity types can still be
+    /// used, but `ValidatorSchema::check_no_deprecated_usage` reports policies
+    /// which refer to them.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub deprecated: bool,
+    /// Why this entity type is deprecated or what to use instead. Only meaningful
+    /// if `deprecated` is set.
+    #[serde(default)]
+    #[serde(rename = "deprecationMessage")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecation_message: Option<SmolStr>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -125,6 +763,7 @@ pub struct ActionType {
     /// `cedar_policy_core::entities::json::value::CedarValueJson` which is the
     /// canonical representation of a cedar value as JSON.
     #[serde(default)]
+    #[serde(serialize_with = "serialize_sorted_optional_map")]
     pub attributes: Option<HashMap<SmolStr, CedarValueJson>>,
     #[serde(default)]
     #[serde(rename = "appliesTo")]
@@ -132,6 +771,24 @@ pub struct ActionType {
     #[serde(default)]
     #[serde(rename = "memberOf")]
     pub member_of: Option<Vec<ActionEntityUID>>,
+    /// Free-form metadata about this action, such as a description or a
+    /// link to documentation. Annotations are preserved in the validator's
+    /// representation of the schema but are ignored by validation.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<SmolStr, String>,
+    /// Whether this action is deprecated. Deprecated actions can still be
+    /// used, but `ValidatorSchema::check_no_deprecated_usage` reports policies
+    /// which refer to them.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub deprecated: bool,
+    /// Why this action is deprecated or what to use instead. Only meaningful
+    /// if `deprecated` is set.
+    #[serde(default)]
+    #[serde(rename = "deprecationMessage")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecation_message: Option<SmolStr>,
 }
 
 /// The apply spec specifies what principals and resources an action can be used
@@ -142,6 +799,18 @@ pub struct ActionType {
 /// different than providing an empty list because the empty list is interpreted
 /// as specifying that there are no principals or resources that an action
 /// applies to.
+///
+/// Resource types may also be listed in `resourceTypesWithDescendants`, in which
+/// case the action applies to each listed type and to every entity type which
+/// can (transitively) be a member of it. The resource apply spec is defined if
+/// either list is present.
+///
+/// `principalsIn` and `resourcesIn` list specific entities, e.g., a group,
+/// which the principal or resource must be in for the action to apply. Unlike
+/// the type lists, these constrain the entities of a request rather than their
+/// types, and an empty list means there is no such constraint. The constraint
+/// is only checked by `ValidatorActionId::applies_to_entities`; validation and
+/// authorization ignore it.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct ApplySpec {
@@ -149,9 +818,21 @@ pub struct ApplySpec {
     #[serde(rename = "resourceTypes")]
     pub resource_types: Option<Vec<SmolStr>>,
     #[serde(default)]
+    #[serde(rename = "resourceTypesWithDescendants")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_types_with_descendants: Option<Vec<SmolStr>>,
+    #[serde(default)]
     #[serde(rename = "principalTypes")]
     pub principal_types: Option<Vec<SmolStr>>,
     #[serde(default)]
+    #[serde(rename = "principalsIn")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub principals_in: Vec<EntityParentUID>,
+    #[serde(default)]
+    #[serde(rename = "resourcesIn")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub resources_in: Vec<EntityParentUID>,
+    #[serde(default)]
     pub context: AttributesOrContext,
 }
 
@@ -171,6 +852,24 @@ impl ActionEntityUID {
     }
 }
 
+/// A reference to a specific entity in the `memberOf` list of an entity type.
+/// Unlike an `ActionEntityUID`, the entity type is required. It may be
+/// unqualified, in which case it refers to an entity type in the namespace of
+/// the entity type declaring the `memberOf` list.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntityParentUID {
+    #[serde(rename = "type")]
+    pub ty: SmolStr,
+    pub id: SmolStr,
+}
+
+impl std::fmt::Display for EntityParentUID {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}::\"{}\"", self.ty, self.id)
+    }
+}
+
 impl std::fmt::Display for ActionEntityUID {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Some(ty) = &self.ty {
@@ -216,6 +915,8 @@ enum TypeFields {
     Attributes,
     AdditionalAttributes,
     Name,
+    Names,
+    AllowedValues,
 }
 
 // This macro is used to avoid duplicating the fields names when calling
@@ -237,6 +938,12 @@ macro_rules! type_field_name {
     (Name) => {
         "name"
     };
+    (Names) => {
+        "names"
+    };
+    (AllowedValues) => {
+        "allowedValues"
+    };
 }
 
 impl TypeFields {
@@ -247,6 +954,8 @@ impl TypeFields {
             TypeFields::Attributes => type_field_name!(Attributes),
             TypeFields::AdditionalAttributes => type_field_name!(AdditionalAttributes),
             TypeFields::Name => type_field_name!(Name),
+            TypeFields::Names => type_field_name!(Names),
+            TypeFields::AllowedValues => type_field_name!(AllowedValues),
         }
     }
 }
@@ -286,6 +995,8 @@ impl<'de> Visitor<'de> for SchemaTypeVisitor {
         let mut attributes: Option<std::result::Result<AttributesTypeMap, M::Error>> = None;
         let mut additional_attributes: Option<std::result::Result<bool, M::Error>> = None;
         let mut name: Option<std::result::Result<SmolStr, M::Error>> = None;
+        let mut names: Option<std::result::Result<Vec<SmolStr>, M::Error>> = None;
+        let mut allowed_values: Option<std::result::Result<Vec<SmolStr>, M::Error>> = None;
 
         // Gather all the fields in the object. Any fields that are not one of
         // the possible fields for some schema type will have been reported by
@@ -324,10 +1035,30 @@ impl<'de> Visitor<'de> for SchemaTypeVisitor {
                     }
                     name = Some(map.next_value());
                 }
+                Names => {
+                    if names.is_some() {
+                        return Err(serde::de::Error::duplicate_field(Names.as_str()));
+                    }
+                    names = Some(map.next_value());
+                }
+                AllowedValues => {
+                    if allowed_values.is_some() {
+                        return Err(serde::de::Error::duplicate_field(AllowedValues.as_str()));
+                    }
+                    allowed_values = Some(map.next_value());
+                }
             }
         }
 
-        Self::build_schema_type::<M>(type_name, element, attributes, additional_attributes, name)
+        Self::build_schema_type::<M>(
+            type_name,
+            element,
+            attributes,
+            additional_attributes,
+            name,
+            names,
+            allowed_values,
+        )
     }
 }
 
@@ -342,6 +1073,8 @@ impl SchemaTypeVisitor {
         attributes: Option<std::result::Result<AttributesTypeMap, M::Error>>,
         additional_attributes: Option<std::result::Result<bool, M::Error>>,
         name: Option<std::result::Result<SmolStr, M::Error>>,
+        names: Option<std::result::Result<Vec<SmolStr>, M::Error>>,
+        allowed_values: Option<std::result::Result<Vec<SmolStr>, M::Error>>,
     ) -> std::result::Result<SchemaType, M::Error>
     where
         M: MapAccess<'de>,
@@ -353,6 +1086,8 @@ impl SchemaTypeVisitor {
             (Attributes, attributes.is_some()),
             (AdditionalAttributes, additional_attributes.is_some()),
             (Name, name.is_some()),
+            (Names, names.is_some()),
+            (AllowedValues, allowed_values.is_some()),
         ]
         .into_iter()
         .filter(|(_, present)| *present)
@@ -371,13 +1106,29 @@ impl SchemaTypeVisitor {
             Ok(())
         };
         let error_if_any_fields = || -> std::result::Result<(), M::Error> {
-            error_if_fields(&[Element, Attributes, AdditionalAttributes, Name], &[])
+            error_if_fields(
+                &[
+                    Element,
+                    Attributes,
+                    AdditionalAttributes,
+                    Name,
+                    Names,
+                    AllowedValues,
+                ],
+                &[],
+            )
         };
 
         match type_name.transpose()?.as_ref().map(|s| s.as_str()) {
             Some("String") => {
-                error_if_any_fields()?;
-                Ok(SchemaType::Type(SchemaTypeVariant::String))
+                error_if_fields(
+                    &[Element, Attributes, AdditionalAttributes, Name, Names],
+                    &[type_field_name!(AllowedValues)],
+                )?;
+
+                Ok(SchemaType::Type(SchemaTypeVariant::String {
+                    allowed_values: allowed_values.transpose()?,
+                }))
             }
             Some("Long") => {
                 error_if_any_fields()?;
@@ -389,7 +1140,7 @@ impl SchemaTypeVisitor {
             }
             Some("Set") => {
                 error_if_fields(
-                    &[Attributes, AdditionalAttributes, Name],
+                    &[Attributes, AdditionalAttributes, Name, Names, AllowedValues],
                     &[type_field_name!(Element)],
                 )?;
 
@@ -403,7 +1154,7 @@ impl SchemaTypeVisitor {
             }
             Some("Record") => {
                 error_if_fields(
-                    &[Element, Name],
+                    &[Element, Name, Names, AllowedValues],
                     &[
                         type_field_name!(Attributes),
                         type_field_name!(AdditionalAttributes),
@@ -423,19 +1174,40 @@ impl SchemaTypeVisitor {
             }
             Some("Entity") => {
                 error_if_fields(
-                    &[Element, Attributes, AdditionalAttributes],
-                    &[type_field_name!(Name)],
+                    &[Element, Attributes, AdditionalAttributes, AllowedValues],
+                    &[type_field_name!(Name), type_field_name!(Names)],
                 )?;
 
-                if let Some(name) = name {
-                    Ok(SchemaType::Type(SchemaTypeVariant::Entity { name: name? }))
-                } else {
-                    Err(serde::de::Error::missing_field(Name.as_str()))
+                match (name, names) {
+                    (Some(name), None) => {
+                        Ok(SchemaType::Type(SchemaTypeVariant::Entity { name: name? }))
+                    }
+                    (None, Some(names)) => {
+                        let names = names?;
+                        if names.is_empty() {
+                            Err(serde::de::Error::invalid_length(
+                                0,
+                                &"at least one entity type name",
+                            ))
+                        } else {
+                            Ok(SchemaType::Type(SchemaTypeVariant::EntityUnion { names }))
+                        }
+                    }
+                    (Some(_), Some(_)) => Err(serde::de::Error::custom(
+                        "an entity type may have `name` or `names`, but not both",
+                    )),
+                    (None, None) => Err(serde::de::Error::missing_field(Name.as_str())),
                 }
             }
             Some("Extension") => {
                 error_if_fields(
-                    &[Element, Attributes, AdditionalAttributes],
+                    &[
+                        Element,
+                        Attributes,
+                        AdditionalAttributes,
+                        Names,
+                        AllowedValues,
+                    ],
                     &[type_field_name!(Name)],
                 )?;
 
@@ -467,7 +1239,13 @@ impl From<SchemaTypeVariant> for SchemaType {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 #[serde(tag = "type")]
 pub enum SchemaTypeVariant {
-    String,
+    String {
+        /// If present, the values which strings of this type may take. This
+        /// must be non-empty.
+        #[serde(rename = "allowedValues")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        allowed_values: Option<Vec<SmolStr>>,
+    },
     Long,
     Boolean,
     Set {
@@ -481,6 +1259,13 @@ pub enum SchemaTypeVariant {
     Entity {
         name: SmolStr,
     },
+    /// A reference to an entity of any one of the entity types named in
+    /// `names`, written as an `Entity` type with a `names` list instead of a
+    /// single `name`.
+    #[serde(rename = "Entity")]
+    EntityUnion {
+        names: Vec<SmolStr>,
+    },
     Extension {
         name: SmolStr,
     },
@@ -534,7 +1319,9 @@ impl<'a> arbitrary::Arbitrary<'a> for SchemaType {
         use std::collections::BTreeSet;
 
         Ok(SchemaType::Type(match u.int_in_range::<u8>(1..=8)? {
-            1 => SchemaTypeVariant::String,
+            1 => SchemaTypeVariant::String {
+                allowed_values: None,
+            },
             2 => SchemaTypeVariant::Long,
             3 => SchemaTypeVariant::Boolean,
             4 => SchemaTypeVariant::Set {
@@ -578,6 +1365,10 @@ impl<'a> arbitrary::Arbitrary<'a> for SchemaType {
 /// flattened for serialization, so, in JSON format, this appears as a regular
 /// type with one extra property `required`.
 ///
+/// Optional attributes of an entity type (but not attributes of nested records
+/// or of action contexts) may also declare a `default` value. This value is
+/// used when parsing entity data where the attribute is absent.
+///
 /// Note that we can't add #[serde(deny_unknown_fields)] here because we are
 /// using #[serde(tag = "type")] in ty:SchemaType which is flattened here.
 /// The way serde(flatten) is implemented means it may be possible to access
@@ -594,6 +1385,19 @@ pub struct TypeOfAttribute {
     pub ty: SchemaType,
     #[serde(default = "record_attribute_required_default")]
     pub required: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub default: Option<CedarValueJson>,
+    /// Whether the attribute holds sensitive data, such as a password hash.
+    /// Sensitive attributes are validated like any other attribute, but their
+    /// values can be redacted when entities are serialized with
+    /// `ValidatorSchema::entities_to_json_redacted`, including when they are
+    /// attributes of records nested inside entity attributes.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub sensitive: bool,
 }
 
 /// Defines the default value for `additionalAttributes` on records and
@@ -611,6 +1415,47 @@ fn record_attribute_required_default() -> bool {
 mod test {
     use super::*;
 
+    #[test]
+    fn blank_json_comments() {
+        let src = "{ // line comment\n  \"a\": \"// not /* a comment\\\" */\", /* block\n comment é */ \"b\": 1 }";
+        let blanked = super::blank_json_comments(src);
+        assert_eq!(blanked.len(), src.len());
+        assert_eq!(
+            blanked,
+            "{                \n  \"a\": \"// not /* a comment\\\" */\",         \n               \"b\": 1 }"
+        );
+        // An unterminated block comment is kept for the JSON parser to report.
+        assert_eq!(super::blank_json_comments("{} /* open"), "{} /* open");
+    }
+
+    #[cfg(feature = "jsonc")]
+    #[test]
+    fn from_jsonc_str() {
+        let fragment = SchemaFragment::from_jsonc_str(
+            r#"{
+                // The root namespace
+                "": {
+                    "entityTypes": { "User": {} /* no attributes */ },
+                    "actions": {}
+                }
+            }"#,
+        )
+        .expect("Expected valid schema");
+        assert!(fragment
+            .0
+            .get("")
+            .unwrap()
+            .entity_types
+            .contains_key("User"));
+
+        match SchemaFragment::from_jsonc_str("/* one\ntwo */ {\n  \"\": 1 }") {
+            Err(SchemaError::Serde(err)) => {
+                assert_eq!(err.line(), 3);
+            }
+            r => panic!("Expected a parse error, got {:?}", r),
+        }
+    }
+
     #[test]
     fn test_entity_type_parser1() {
         let user = r#"
@@ -621,7 +1466,7 @@ mod test {
         let et = serde_json::from_str::<EntityType>(user).expect("Parse Error");
         assert_eq!(et.member_of_types, vec!["UserGroup"]);
         assert_eq!(
-            et.shape.into_inner(),
+            et.shape.unwrap_or_default().into_inner(),
             SchemaType::Type(SchemaTypeVariant::Record {
                 attributes: BTreeMap::new(),
                 additional_attributes: false
@@ -637,7 +1482,7 @@ mod test {
         let et = serde_json::from_str::<EntityType>(src).expect("Parse Error");
         assert_eq!(et.member_of_types.len(), 0);
         assert_eq!(
-            et.shape.into_inner(),
+            et.shape.unwrap_or_default().into_inner(),
             SchemaType::Type(SchemaTypeVariant::Record {
                 attributes: BTreeMap::new(),
                 additional_attributes: false
@@ -659,7 +1504,10 @@ mod test {
         let at: ActionType = serde_json::from_str(src).expect("Parse Error");
         let spec = ApplySpec {
             resource_types: Some(vec!["Album".into()]),
+            resource_types_with_descendants: None,
             principal_types: Some(vec!["User".into()]),
+            principals_in: Vec::new(),
+            resources_in: Vec::new(),
             context: AttributesOrContext::default(),
         };
         assert_eq!(at.applies_to, Some(spec));
@@ -918,6 +1766,221 @@ mod test {
         let schema: NamespaceDefinition = serde_json::from_value(src).unwrap();
         println!("{:#?}", schema);
     }
+
+    #[test]
+    fn from_file_with_bom() {
+        let src = "\u{FEFF}{ \"NS\": { \"entityTypes\": { \"User\": {} }, \"actions\": {} } }";
+        assert!(src.as_bytes().starts_with(UTF8_BOM));
+        let fragment = SchemaFragment::from_file(src.as_bytes()).expect("Parse Error");
+        assert!(fragment
+            .0
+            .get("NS")
+            .unwrap()
+            .entity_types
+            .contains_key("User"));
+    }
+
+    #[cfg(feature = "preserve-order")]
+    #[test]
+    fn fragment_keeps_declaration_order() {
+        let fragment: SchemaFragment = serde_json::from_value(serde_json::json!({
+            "Zoo": {
+                "entityTypes": { "Zebra": {}, "Aardvark": {}, "Keeper": {} },
+                "actions": { "feed": {}, "clean": {} }
+            },
+            "App": { "entityTypes": {}, "actions": {} }
+        }))
+        .expect("Parse Error");
+        assert_eq!(fragment.0.keys().collect::<Vec<_>>(), ["Zoo", "App"]);
+        let zoo = fragment.0.get("Zoo").unwrap();
+        assert_eq!(
+            zoo.entity_types.keys().collect::<Vec<_>>(),
+            ["Zebra", "Aardvark", "Keeper"]
+        );
+        assert_eq!(zoo.actions.keys().collect::<Vec<_>>(), ["feed", "clean"]);
+    }
+
+    #[test]
+    fn from_slice() {
+        let src = br#"{ "NS": { "entityTypes": { "User": {} }, "actions": {} } }"#;
+        let fragment = SchemaFragment::from_slice(src).expect("Parse Error");
+        assert!(fragment
+            .0
+            .get("NS")
+            .unwrap()
+            .entity_types
+            .contains_key("User"));
+
+        let mut with_bom = UTF8_BOM.to_vec();
+        with_bom.extend_from_slice(src);
+        let fragment = SchemaFragment::try_from(with_bom.as_slice()).expect("Parse Error");
+        assert!(fragment
+            .0
+            .get("NS")
+            .unwrap()
+            .entity_types
+            .contains_key("User"));
+
+        match SchemaFragment::from_slice(b"{ \"NS\": \xFF }") {
+            Err(SchemaError::Serde(_)) => (),
+            s => panic!("Expected Serde error, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn migrate_v1_expr_escapes() {
+        let fragment = SchemaFragment::from_json_value(serde_json::json!({
+            "$version": 1,
+            "": {
+                "entityTypes": { "User": {} },
+                "actions": {
+                    "view": {
+                        "attributes": {
+                            "owner": { "__expr": "User::\"alice\"" },
+                            "limits": [{ "__expr": "decimal(\"1.5\")" }, 2],
+                            "plain": "text"
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("Parse Error");
+        assert_eq!(fragment.1, Some(SchemaVersion::V1));
+        let migrated = fragment.migrate().expect("Migration Error");
+        assert_eq!(migrated.1, Some(SchemaVersion::CURRENT));
+        let json = serde_json::to_value(&migrated).expect("Serialize Error");
+        assert_eq!(json["$version"], serde_json::json!(2));
+        assert_eq!(
+            json[""]["actions"]["view"]["attributes"],
+            serde_json::json!({
+                "owner": { "__entity": { "type": "User", "id": "alice" } },
+                "limits": [{ "__extn": { "fn": "decimal", "arg": "1.5" } }, 2],
+                "plain": "text"
+            })
+        );
+
+        // Constructing a schema migrates the fragment first.
+        let src = serde_json::json!({
+            "$version": 1,
+            "": {
+                "entityTypes": {},
+                "actions": {
+                    "view": { "attributes": { "limits": { "__expr": "{ max: [1, 2] }" } } }
+                }
+            }
+        });
+        let permit = crate::ActionBehavior::PermitAttributes;
+        crate::ValidatorSchemaFragment::from_schema_fragment(
+            SchemaFragment::from_json_value(src.clone()).unwrap(),
+            permit,
+        )
+        .expect("Expected valid schema");
+        crate::ValidatorSchemaFragment::from_file_streaming(src.to_string().as_bytes(), permit)
+            .expect("Expected valid schema");
+
+        // Without a version, the fragment is in the current format, where the
+        // `__expr` escape is not supported.
+        let mut unversioned = src;
+        unversioned.as_object_mut().unwrap().remove("$version");
+        let fragment = SchemaFragment::from_json_value(unversioned.clone()).expect("Parse Error");
+        assert_eq!(fragment.1, None);
+        let before = serde_json::to_value(&fragment).unwrap();
+        assert_eq!(
+            serde_json::to_value(fragment.migrate().expect("Migration Error")).unwrap(),
+            before
+        );
+        match crate::ValidatorSchemaFragment::from_schema_fragment(
+            SchemaFragment::from_json_value(unversioned).unwrap(),
+            permit,
+        ) {
+            Err(SchemaError::UnsupportedActionAttribute(_, kind)) => {
+                assert!(kind.contains("__expr"), "{kind}")
+            }
+            s => panic!("Expected UnsupportedActionAttribute error, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn schema_version_errors() {
+        match SchemaFragment::from_json_value(serde_json::json!({ "$version": 3 })) {
+            Err(SchemaError::Serde(err)) => assert!(
+                err.to_string()
+                    .contains("unsupported schema version 3: the newest supported version is 2"),
+                "{err}"
+            ),
+            s => panic!("Expected Serde error, got {:?}", s),
+        }
+
+        let fragment = SchemaFragment::from_json_value(serde_json::json!({
+            "$version": 1,
+            "": {
+                "entityTypes": {},
+                "actions": { "view": { "attributes": { "bad": { "__expr": "1 +" } } } }
+            }
+        }))
+        .expect("Parse Error");
+        match fragment.migrate() {
+            Err(SchemaError::InvalidExprEscape { action, attr, .. }) => {
+                assert_eq!(action, "view");
+                assert_eq!(attr, "bad");
+            }
+            s => panic!("Expected InvalidExprEscape error, got {:?}", s),
+        }
+
+        // The streaming parser needs to know the version before it reads the
+        // namespaces.
+        let src = r#"{ "": { "entityTypes": {}, "actions": {} }, "$version": 1 }"#;
+        match crate::ValidatorSchema::from_file_streaming(src.as_bytes()) {
+            Err(SchemaError::Serde(err)) => {
+                assert!(
+                    err.to_string().contains("`$version` must come before"),
+                    "{err}"
+                )
+            }
+            s => panic!("Expected Serde error, got {:?}", s),
+        }
+        SchemaFragment::from_file(src.as_bytes()).expect("Parse Error");
+    }
+
+    #[test]
+    fn from_file_invalid_utf8() {
+        let mut src = UTF8_BOM.to_vec();
+        src.extend_from_slice(b"{ \"NS\": \xFF }");
+        match SchemaFragment::from_file(src.as_slice()) {
+            Err(SchemaError::InvalidUtf8(offset)) => assert_eq!(offset, 8),
+            s => panic!("Expected InvalidUtf8 error, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn from_file_io_error() {
+        struct BrokenReader;
+        impl std::io::Read for BrokenReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::Other, "disk error"))
+            }
+        }
+        let src = || br#"{ "A": { "entityTypes": {}"#.chain(BrokenReader);
+
+        match SchemaFragment::from_file(src()) {
+            Err(SchemaError::Io { source, bytes_read }) => {
+                assert_eq!(source.to_string(), "disk error");
+                assert_eq!(bytes_read, 26);
+            }
+            s => panic!("Expected Io error, got {:?}", s),
+        }
+        match crate::ValidatorSchema::from_file_streaming(src()) {
+            Err(SchemaError::Io { source, bytes_read }) => {
+                assert_eq!(source.to_string(), "disk error");
+                assert_eq!(bytes_read, 26);
+            }
+            s => panic!("Expected Io error, got {:?}", s),
+        }
+        match SchemaFragment::from_file(BrokenReader) {
+            Err(SchemaError::Io { bytes_read, .. }) => assert_eq!(bytes_read, 0),
+            s => panic!("Expected Io error, got {:?}", s),
+        }
+    }
 }
 
 /// Tests in this module check the behavior of schema parsing given duplicate
@@ -396,7 +396,7 @@ impl<'a> Typechecker<'a> {
         let all_actions = self
             .schema
             .known_action_ids()
-            .filter_map(|a| self.schema.get_action_id(a));
+            .filter_map(|a| self.schema.get_action_id_exact(a));
 
         // For every action compute the cross product of the principal and
         // resource applies_to sets.
@@ -1006,6 +1006,7 @@ impl<'a> Typechecker<'a> {
                             Some(AttributeType {
                                 attr_type: _,
                                 is_required: true,
+                                ..
                             }) => {
                                 // Since an entity doesn't always have to exist
                                 // in the entity store, and `has` evaluates to
@@ -1040,6 +1041,7 @@ impl<'a> Typechecker<'a> {
                             Some(AttributeType {
                                 attr_type: _,
                                 is_required: false,
+                                ..
                             }) => TypecheckAnswer::success_with_effect(
                                 ExprBuilder::with_data(Some(
                                     // The optional attribute `HasAttr` can have
@@ -19,7 +19,7 @@
 #![cfg(test)]
 // GRCOV_STOP_COVERAGE
 
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr};
 
 use cedar_policy_core::ast::{BinaryOp, EntityUID, Expr, PatternElem, SlotId, Var};
 use serde_json::json;
@@ -57,7 +57,12 @@ fn slot_typechecks() {
 fn slot_in_typechecks() {
     let etype = EntityType {
         member_of_types: vec![],
-        shape: AttributesOrContext::default(),
+        member_of: vec![],
+        shape: None,
+        annotations: BTreeMap::new(),
+        deprecated: false,
+        deprecation_message: None,
+        id_format: None,
     };
     let schema = NamespaceDefinition::new([("typename".into(), etype)], []);
     assert_typechecks_for_mode(
@@ -86,7 +91,12 @@ fn slot_in_typechecks() {
 fn slot_equals_typechecks() {
     let etype = EntityType {
         member_of_types: vec![],
-        shape: AttributesOrContext::default(),
+        member_of: vec![],
+        shape: None,
+        annotations: BTreeMap::new(),
+        deprecated: false,
+        deprecation_message: None,
+        id_format: None,
     };
     // These don't typecheck in strict mode because the test_util expression
     // typechecker doesn't have access to a schema, so it can't instantiate
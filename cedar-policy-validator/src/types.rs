@@ -62,6 +62,13 @@ pub enum Type {
         primitive_type: Primitive,
     },
 
+    /// Strings restricted to a (non-empty) set of allowed values. This is a
+    /// subtype of the primitive string type.
+    StringEnum {
+        #[serde(rename = "allowedValues")]
+        allowed_values: BTreeSet<SmolStr>,
+    },
+
     /// The type of sets containing some type.
     Set {
         /// The type of the elements in the set, or None if it represents an
@@ -109,6 +116,44 @@ impl Type {
         }
     }
 
+    pub(crate) fn string_enum(allowed_values: BTreeSet<SmolStr>) -> Type {
+        Type::StringEnum { allowed_values }
+    }
+
+    /// Rename each entity type referenced by this type with `rename`.
+    pub(crate) fn rename_entity_types(&self, rename: &impl Fn(&Name) -> Name) -> Type {
+        match self {
+            Type::Set { element_type } => Type::Set {
+                element_type: element_type
+                    .as_ref()
+                    .map(|ty| Box::new(ty.rename_entity_types(rename))),
+            },
+            Type::EntityOrRecord(EntityRecordKind::Record {
+                attrs,
+                open_attributes,
+            }) => Type::EntityOrRecord(EntityRecordKind::Record {
+                attrs: attrs.rename_entity_types(rename),
+                open_attributes: *open_attributes,
+            }),
+            Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => {
+                Type::EntityOrRecord(EntityRecordKind::Entity(lub.rename_entity_types(rename)))
+            }
+            Type::EntityOrRecord(EntityRecordKind::ActionEntity { name, attrs }) => {
+                Type::EntityOrRecord(EntityRecordKind::ActionEntity {
+                    name: rename(name),
+                    attrs: attrs.rename_entity_types(rename),
+                })
+            }
+            Type::Never
+            | Type::True
+            | Type::False
+            | Type::Primitive { .. }
+            | Type::StringEnum { .. }
+            | Type::EntityOrRecord(EntityRecordKind::AnyEntity)
+            | Type::ExtensionType { .. } => self.clone(),
+        }
+    }
+
     /// Construct a type for a literal EUID. This type will be a named entity
     /// type for the type of the EntityUID.
     pub(crate) fn euid_literal(entity: EntityUID, schema: &ValidatorSchema) -> Option<Type> {
@@ -117,7 +162,7 @@ impl Type {
             EntityType::Concrete(name) => {
                 if is_action_entity_type(name) {
                     schema
-                        .get_action_id(&entity)
+                        .get_action_id_exact(&entity)
                         .and_then(Type::entity_reference_from_action_id)
                 } else {
                     schema
@@ -195,6 +240,19 @@ impl Type {
         Type::EntityOrRecord(EntityRecordKind::Entity(EntityLUB::single_entity(name)))
     }
 
+    /// Construct the type of a reference to an entity of any of the entity
+    /// types in `names`. Returns `None` if `names` is empty.
+    pub(crate) fn entity_reference_union(names: impl IntoIterator<Item = Name>) -> Option<Type> {
+        let lub_elements = names.into_iter().collect::<BTreeSet<_>>();
+        if lub_elements.is_empty() {
+            None
+        } else {
+            Some(Type::EntityOrRecord(EntityRecordKind::Entity(EntityLUB {
+                lub_elements,
+            })))
+        }
+    }
+
     pub(crate) fn any_entity_reference() -> Type {
         Type::EntityOrRecord(EntityRecordKind::AnyEntity)
     }
@@ -232,6 +290,23 @@ impl Type {
                 ty0 == ty1
             }
 
+            // A string enumeration is a subtype of the primitive string type,
+            // and of any string enumeration allowing at least its values.
+            (
+                Type::StringEnum { .. },
+                Type::Primitive {
+                    primitive_type: Primitive::String,
+                },
+            ) => true,
+            (
+                Type::StringEnum {
+                    allowed_values: values0,
+                },
+                Type::StringEnum {
+                    allowed_values: values1,
+                },
+            ) => values0.is_subset(values1),
+
             // A set type is a subtype other set type when its element type is a subtype.
             (
                 Type::Set {
@@ -274,6 +349,15 @@ impl Type {
 
             (Type::True | Type::False, Type::True | Type::False) => Some(Type::primitive_boolean()),
 
+            (
+                Type::StringEnum {
+                    allowed_values: values0,
+                },
+                Type::StringEnum {
+                    allowed_values: values1,
+                },
+            ) => Some(Type::string_enum(values0.union(values1).cloned().collect())),
+
             // `None` as an element type represents the top type for the set
             // element, so every other set is a subtype of set<None>, making a
             // least upper bound containing  set<None> and another set type
@@ -421,7 +505,7 @@ impl Type {
             .collect()
     }
 
-    fn to_type_json(&self) -> serde_json::value::Map<String, serde_json::value::Value> {
+    pub(crate) fn to_type_json(&self) -> serde_json::value::Map<String, serde_json::value::Value> {
         match self {
             Type::Never => Type::json_type("Never"),
             Type::True => Type::json_type("True"),
@@ -435,6 +519,17 @@ impl Type {
             Type::Primitive {
                 primitive_type: Primitive::String,
             } => Type::json_type("String"),
+            Type::StringEnum { allowed_values } => {
+                let mut enum_json = Type::json_type("String");
+                enum_json.insert(
+                    "allowedValues".into(),
+                    allowed_values
+                        .iter()
+                        .map(|v| serde_json::Value::from(v.as_str()))
+                        .collect(),
+                );
+                enum_json
+            }
             Type::Set { element_type } => {
                 let mut set_json = Type::json_type("Set");
                 match element_type {
@@ -464,6 +559,9 @@ impl Type {
                                     let mut attr_ty_json = attr_ty.attr_type.to_type_json();
                                     attr_ty_json
                                         .insert("required".to_string(), attr_ty.is_required.into());
+                                    if attr_ty.is_sensitive {
+                                        attr_ty_json.insert("sensitive".to_string(), true.into());
+                                    }
                                     attr_ty_json.into()
                                 })
                             })
@@ -484,6 +582,9 @@ impl Type {
                                     let mut attr_ty_json = attr_ty.attr_type.to_type_json();
                                     attr_ty_json
                                         .insert("required".to_string(), attr_ty.is_required.into());
+                                    if attr_ty.is_sensitive {
+                                        attr_ty_json.insert("sensitive".to_string(), true.into());
+                                    }
                                     attr_ty_json.into()
                                 })
                             })
@@ -535,8 +636,17 @@ impl Type {
                 self,
                 Type::Primitive {
                     primitive_type: Primitive::String
-                }
+                } | Type::StringEnum { .. }
             ),
+            CoreSchemaType::StringEnum { allowed_values } => match self {
+                Type::Primitive {
+                    primitive_type: Primitive::String,
+                } => true,
+                Type::StringEnum {
+                    allowed_values: self_values,
+                } => !self_values.is_disjoint(allowed_values),
+                _ => false,
+            },
             CoreSchemaType::Set { element_ty } => {
                 matches!(self, Type::Set { element_type: Some(element_type) } if element_type.is_consistent_with(element_ty))
             }
@@ -609,6 +719,9 @@ impl Type {
                 },
                 _ => false,
             },
+            CoreSchemaType::EntityUnion { tys } => tys
+                .iter()
+                .any(|ty| self.is_consistent_with(&CoreSchemaType::Entity { ty: ty.clone() })),
             CoreSchemaType::Extension { name } => {
                 matches!(self, Type::ExtensionType { name: n } if name == n)
             }
@@ -643,6 +756,9 @@ impl TryFrom<Type> for cedar_policy_core::entities::SchemaType {
             Type::Primitive {
                 primitive_type: Primitive::String,
             } => Ok(CoreSchemaType::String),
+            Type::StringEnum { allowed_values } => {
+                Ok(CoreSchemaType::StringEnum { allowed_values })
+            }
             Type::Set {
                 element_type: Some(element_type),
             } => Ok(CoreSchemaType::Set {
@@ -679,13 +795,17 @@ impl TryFrom<Type> for cedar_policy_core::entities::SchemaType {
                     },
                 })
             }
-            Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => match lub.into_single_entity() {
+            Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => match lub.get_single_entity() {
                 Some(name) => Ok(CoreSchemaType::Entity {
-                    ty: EntityType::Concrete(name),
+                    ty: EntityType::Concrete(name.clone()),
+                }),
+                None => Ok(CoreSchemaType::EntityUnion {
+                    tys: lub
+                        .lub_elements
+                        .into_iter()
+                        .map(EntityType::Concrete)
+                        .collect(),
                 }),
-                None => {
-                    Err("non-singleton LUB type is not representable in core::Type".to_string())
-                }
             },
             Type::ExtensionType { name } => Ok(CoreSchemaType::Extension { name }),
         }
@@ -809,6 +929,15 @@ impl EntityLUB {
         self.lub_elements.iter()
     }
 
+    /// Rename each entity type in this LUB with `rename`. Entity types which
+    /// are renamed to the same name are merged.
+    pub(crate) fn rename_entity_types(&self, rename: &impl Fn(&Name) -> Name) -> EntityLUB {
+        // Renaming can't make the set empty, so the invariant is maintained.
+        EntityLUB {
+            lub_elements: self.lub_elements.iter().map(rename).collect(),
+        }
+    }
+
     fn to_type_json(&self) -> serde_json::value::Map<String, serde_json::value::Value> {
         let mut ordered_lub_elems = self.lub_elements.iter().collect::<Vec<_>>();
         // We want the display order of elements of the set to be consistent.
@@ -874,6 +1003,21 @@ impl Attributes {
         self.attrs.iter()
     }
 
+    /// Rename each entity type referenced by the attribute types with
+    /// `rename`.
+    pub(crate) fn rename_entity_types(&self, rename: &impl Fn(&Name) -> Name) -> Attributes {
+        Self::with_attributes(self.iter().map(|(attr, attr_ty)| {
+            (
+                attr.clone(),
+                AttributeType {
+                    attr_type: attr_ty.attr_type.rename_entity_types(rename),
+                    is_required: attr_ty.is_required,
+                    is_sensitive: attr_ty.is_sensitive,
+                },
+            )
+        }))
+    }
+
     /// Iterate over the attribute names of this record
     pub fn keys(&self) -> impl Iterator<Item = &SmolStr> {
         self.attrs.keys()
@@ -985,6 +1129,20 @@ impl Attributes {
             Self::attributes_lub_iter(schema, attrs0, attrs1, ValidationMode::Permissive).flatten(),
         )
     }
+
+    /// Keep only the attributes present in both argument records whose types
+    /// have a least upper bound under strict validation. Attributes with
+    /// incompatible types are dropped rather than causing the whole
+    /// computation to fail.
+    pub(crate) fn strict_intersection(
+        schema: &ValidatorSchema,
+        attrs0: &Attributes,
+        attrs1: &Attributes,
+    ) -> Attributes {
+        Attributes::with_attributes(
+            Self::attributes_lub_iter(schema, attrs0, attrs1, ValidationMode::Strict).flatten(),
+        )
+    }
 }
 
 impl IntoIterator for Attributes {
@@ -1296,6 +1454,12 @@ pub struct AttributeType {
     /// may not be present in a record or entity.
     #[serde(rename = "isRequired")]
     pub is_required: bool,
+
+    /// True when the attribute was declared `sensitive` in the schema. This
+    /// does not affect typechecking.
+    #[serde(rename = "isSensitive")]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_sensitive: bool,
 }
 
 impl AttributeType {
@@ -1305,6 +1469,7 @@ impl AttributeType {
         Self {
             attr_type,
             is_required,
+            is_sensitive: false,
         }
     }
 
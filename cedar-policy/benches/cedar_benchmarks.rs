@@ -18,7 +18,7 @@ use std::str::FromStr;
 
 use cedar_policy::{
     Authorizer, Context, Entities, EntityId, EntityTypeName, EntityUid, Policy, PolicySet, Request,
-    RestrictedExpression,
+    RestrictedExpression, Schema,
 };
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
@@ -147,6 +147,46 @@ pub fn criterion_benchmark(c: &mut Criterion) {
             )
         })
     });
+
+    // Entity type with a large attribute record, parsed against a schema.
+    let num_attrs = 100;
+    let schema_attrs = (0..num_attrs)
+        .map(|i| (format!("attr{i}"), serde_json::json!({ "type": "Long" })))
+        .collect::<serde_json::Map<_, _>>();
+    let schema = Schema::from_json_value(serde_json::json!({
+        "": {
+            "entityTypes": {
+                "User": {
+                    "shape": { "type": "Record", "attributes": schema_attrs }
+                }
+            },
+            "actions": {}
+        }
+    }))
+    .unwrap();
+    let entity_attrs = (0..num_attrs)
+        .map(|i| (format!("attr{i}"), serde_json::json!(i)))
+        .collect::<serde_json::Map<_, _>>();
+    let schema_entities_json = serde_json::Value::Array(
+        (0..100)
+            .map(|i| {
+                serde_json::json!({
+                    "uid": { "type": "User", "id": format!("user{i}") },
+                    "attrs": entity_attrs,
+                    "parents": []
+                })
+            })
+            .collect(),
+    );
+
+    c.bench_function("entities_from_json_with_schema", |b| {
+        b.iter(|| {
+            Entities::from_json_value(
+                black_box(schema_entities_json.clone()),
+                Some(black_box(&schema)),
+            )
+        })
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
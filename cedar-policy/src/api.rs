@@ -39,7 +39,8 @@ pub use cedar_policy_core::parser::err::ParseErrors;
 use cedar_policy_core::parser::SourceInfo;
 use cedar_policy_core::FromNormalizedStr;
 pub use cedar_policy_validator::{
-    TypeErrorKind, UnsupportedFeature, ValidationErrorKind, ValidationWarningKind,
+    CommonTypeReference, FragmentIndices, SchemaLocation, SchemaSection, TypeErrorKind,
+    UndeclaredCommonTypeNames, UnsupportedFeature, ValidationErrorKind, ValidationWarningKind,
 };
 use itertools::Itertools;
 use ref_cast::RefCast;
@@ -198,13 +199,17 @@ impl std::fmt::Display for Entity {
     }
 }
 
+/// The result of parsing and checking each entity, as returned by
+/// [`Entities::from_json_value_per_entity_results`].
+pub type PerEntityResults = HashMap<EntityUid, Result<(), EntitiesError>>;
+
 /// Represents an entity hierarchy, and allows looking up `Entity` objects by
 /// Uid.
 #[repr(transparent)]
 #[derive(Debug, Clone, Default, PartialEq, Eq, RefCast)]
 pub struct Entities(pub(crate) entities::Entities);
 
-pub use entities::EntitiesError;
+pub use entities::{EntitiesError, OpaqueExtensionValue};
 
 impl Entities {
     /// Create a fresh `Entities` with no entities
@@ -515,6 +520,69 @@ impl Entities {
         eparser.from_json_value(json).map(Entities)
     }
 
+    /// Parse an entities JSON file (in `serde_json::Value` form) into an
+    /// `Entities` object, keeping extension values which cannot be
+    /// interpreted.
+    ///
+    /// This is like [`Entities::from_json_value`], except that values of
+    /// extension functions which are not available (for instance, from a newer
+    /// version of Cedar) do not cause an error. They are kept as opaque values,
+    /// and each attribute containing one is reported in the returned warnings.
+    /// Evaluating such an attribute fails, so authorization over these
+    /// entities fails closed.
+    pub fn from_json_value_lenient(
+        json: serde_json::Value,
+        schema: Option<&Schema>,
+    ) -> Result<(Self, Vec<OpaqueExtensionValue>), entities::EntitiesError> {
+        let schema = schema.map(|s| cedar_policy_validator::CoreSchema::new(&s.0));
+        let eparser = entities::EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            entities::TCComputation::ComputeNow,
+        );
+        eparser
+            .from_json_value_lenient(json)
+            .map(|(entities, warnings)| (Self(entities), warnings))
+    }
+
+    /// Parse an entities JSON file (in `serde_json::Value` form) into an
+    /// `Entities` object, reporting the result of parsing and checking each
+    /// entity.
+    ///
+    /// This is like [`Entities::from_json_value`], except that an entity which
+    /// fails to parse or to conform to the `schema` does not fail the whole
+    /// operation. It is mapped to its error and left out of the returned
+    /// `Entities`, and every other entity is mapped to `Ok(())`. An error is
+    /// still returned if the JSON is not a list of entities, if an entity's
+    /// `uid` is malformed, or if two entities have the same `uid`.
+    pub fn from_json_value_per_entity_results(
+        json: serde_json::Value,
+        schema: Option<&Schema>,
+    ) -> Result<(Self, PerEntityResults), entities::EntitiesError> {
+        let schema = schema.map(|s| cedar_policy_validator::CoreSchema::new(&s.0));
+        let eparser = entities::EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            entities::TCComputation::ComputeNow,
+        );
+        eparser
+            .from_json_value_per_entity_results(json)
+            .map(|(entities, results)| {
+                (
+                    Self(entities),
+                    results
+                        .into_iter()
+                        .map(|(uid, result)| {
+                            (
+                                EntityUid(uid),
+                                result.map_err(entities::EntitiesError::Deserialization),
+                            )
+                        })
+                        .collect(),
+                )
+            })
+    }
+
     /// Parse an entities JSON file (in `std::io::Read` form) into an `Entities`
     /// object
     ///
@@ -581,6 +649,19 @@ impl Entities {
     ) -> std::result::Result<(), entities::EntitiesError> {
         self.0.write_to_json(f)
     }
+
+    /// Convert an `Entities` object into a JSON value, replacing the value of
+    /// each attribute declared `sensitive` in `schema` with a placeholder
+    /// string.
+    ///
+    /// This is meant for logging. If any value was redacted, the result no
+    /// longer conforms to `schema`.
+    pub fn to_json_value_redacted(
+        &self,
+        schema: &Schema,
+    ) -> std::result::Result<serde_json::Value, entities::EntitiesError> {
+        schema.0.entities_to_json_redacted(&self.0)
+    }
 }
 
 /// Authorizer object, which provides responses to authorization queries
@@ -1045,6 +1126,24 @@ impl SchemaFragment {
             cedar_policy_validator::SchemaFragment::from_file(file)?.try_into()?,
         ))
     }
+
+    /// Create a `SchemaFragment` from bytes containing JSON, e.g., embedded
+    /// with `include_bytes!`. A leading UTF-8 byte order mark is ignored.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, SchemaError> {
+        Ok(Self(
+            cedar_policy_validator::SchemaFragment::from_slice(bytes)?.try_into()?,
+        ))
+    }
+
+    /// Create a `SchemaFragment` from a string containing JSON with `//` and
+    /// `/* */` comments. Parse errors report lines and columns in the
+    /// original string.
+    #[cfg(feature = "jsonc")]
+    pub fn from_jsonc_str(src: &str) -> Result<Self, SchemaError> {
+        Ok(Self(
+            cedar_policy_validator::SchemaFragment::from_jsonc_str(src)?.try_into()?,
+        ))
+    }
 }
 
 impl TryInto<Schema> for SchemaFragment {
@@ -1119,6 +1218,15 @@ impl Schema {
         ))
     }
 
+    /// Create a `Schema` from a JSON value, as `from_json_value`, but first
+    /// check that every top-level key is a well-formed namespace (or the empty
+    /// string), reporting every malformed key at once.
+    pub fn from_json_value_checked(json: serde_json::Value) -> Result<Self, SchemaError> {
+        Ok(Self(
+            cedar_policy_validator::ValidatorSchema::from_json_value_checked(json)?,
+        ))
+    }
+
     /// Create a `Schema` directly from a file.
     pub fn from_file(file: impl std::io::Read) -> Result<Self, SchemaError> {
         Ok(Self(cedar_policy_validator::ValidatorSchema::from_file(
@@ -1131,6 +1239,14 @@ impl Schema {
     pub fn action_entities(&self) -> Result<Entities, entities::EntitiesError> {
         Ok(Entities(self.0.action_entities()?))
     }
+
+    /// Check that no two entity types, and no two actions, declared in the
+    /// same namespace have names which differ only by case. This check is not
+    /// performed when constructing a `Schema`. Returns
+    /// `SchemaError::CaseCollision` listing the colliding names if it fails.
+    pub fn check_case_collisions(&self) -> Result<(), SchemaError> {
+        Ok(self.0.check_case_collisions()?)
+    }
 }
 
 /// Errors encountered during construction of a Validation Schema
@@ -1139,6 +1255,16 @@ pub enum SchemaError {
     /// Error thrown by the `serde_json` crate during deserialization
     #[error("failed to parse schema: {0}")]
     Serde(#[from] serde_json::Error),
+    /// Error reading a schema file, as opposed to parsing its contents.
+    /// `bytes_read` is the number of bytes successfully read from the file
+    /// before the error.
+    #[error("failed to read schema after {bytes_read} bytes: {source}")]
+    Io {
+        /// The underlying IO error
+        source: std::io::Error,
+        /// Bytes read from the file before the error
+        bytes_read: usize,
+    },
     /// Errors occurring while computing or enforcing transitive closure on
     /// action hierarchy.
     #[error("transitive closure computation/enforcement error on action hierarchy: {0}")]
@@ -1159,36 +1285,51 @@ pub enum SchemaError {
     /// Undeclared action(s) used in the `memberOf` field of an action.
     #[error("undeclared action(s): {0:?}")]
     UndeclaredActions(HashSet<String>),
-    /// Undeclared common type(s) used in entity or context attributes.
-    #[error("undeclared common type(s): {0:?}")]
-    UndeclaredCommonTypes(HashSet<String>),
-    /// Duplicate specifications for an entity type. Argument is the name of
-    /// the duplicate entity type.
-    #[error("duplicate entity type `{0}`")]
-    DuplicateEntityType(String),
-    /// Duplicate specifications for an action. Argument is the name of the
-    /// duplicate action.
-    #[error("duplicate action `{0}`")]
-    DuplicateAction(String),
-    /// Duplicate specification for a reusable type declaration.
-    #[error("duplicate common type `{0}`")]
-    DuplicateCommonType(String),
+    /// Undeclared common type(s) used in entity or context attributes, or in
+    /// the definitions of other common types.
+    #[error("undeclared common type(s): {0}")]
+    UndeclaredCommonTypes(UndeclaredCommonTypeNames),
+    /// Duplicate specifications for an entity type. Arguments are the name of
+    /// the duplicate entity type and, if the declarations came from different
+    /// fragments, the indices of those fragments.
+    #[error("duplicate entity type `{0}`{}", format_fragment_indices(.1.as_ref()))]
+    DuplicateEntityType(String, Option<FragmentIndices>),
+    /// Duplicate specifications for an action. Arguments are the name of the
+    /// duplicate action and, if the declarations came from different
+    /// fragments, the indices of those fragments.
+    #[error("duplicate action `{0}`{}", format_fragment_indices(.1.as_ref()))]
+    DuplicateAction(String, Option<FragmentIndices>),
+    /// Duplicate specification for a reusable type declaration. Arguments are
+    /// as for `DuplicateEntityType`.
+    #[error("duplicate common type `{0}`{}", format_fragment_indices(.1.as_ref()))]
+    DuplicateCommonType(String, Option<FragmentIndices>),
     /// Cycle in the schema's action hierarchy.
     #[error("cycle in action hierarchy")]
     CycleInActionHierarchy,
-    /// Parse errors occurring while parsing an entity type.
-    #[error("parse error in entity type: {0}")]
-    ParseEntityType(ParseErrors),
+    /// Cycle in the schema's entity type hierarchy. These are permitted when
+    /// constructing a schema, but prevent sorting its entity types.
+    #[error("cycle in entity type hierarchy")]
+    CycleInEntityHierarchy,
+    /// Cycle in the references between common type definitions. The argument
+    /// lists, sorted, the common types which are in or depend on the cycle.
+    #[error("cycle in common type definitions: {}", .0.join(", "))]
+    CycleInCommonTypes(Vec<String>),
+    /// Parse errors occurring while parsing an entity type. The second
+    /// argument records where the entity type name appeared in the schema.
+    #[error("parse error in entity type{}: {0}", format_schema_location(.1))]
+    ParseEntityType(ParseErrors, SchemaLocation),
     /// Parse errors occurring while parsing a namespace identifier.
     #[error("parse error in namespace identifier: {0}")]
     ParseNamespace(ParseErrors),
-    /// Parse errors occurring while parsing an extension type.
-    #[error("parse error in extension type: {0}")]
-    ParseExtensionType(ParseErrors),
+    /// Parse errors occurring while parsing an extension type. The second
+    /// argument records where the extension type name appeared in the schema.
+    #[error("parse error in extension type{}: {0}", format_schema_location(.1))]
+    ParseExtensionType(ParseErrors, SchemaLocation),
     /// Parse errors occurring while parsing the name of a reusable
-    /// declared type.
-    #[error("parse error in common type identifier: {0}")]
-    ParseCommonType(ParseErrors),
+    /// declared type. The second argument records where the name appeared in
+    /// the schema.
+    #[error("parse error in common type identifier{}: {0}", format_schema_location(.1))]
+    ParseCommonType(ParseErrors, SchemaLocation),
     /// The schema file included an entity type `Action` in the entity type
     /// list. The `Action` entity type is always implicitly declared, and it
     /// cannot currently have attributes or be in any groups, so there is no
@@ -1211,6 +1352,200 @@ pub enum SchemaError {
     /// Support for this escape form has been dropped.
     #[error("schema contained the non-supported `__expr` escape.")]
     ExprEscapeUsed,
+    /// The default value declared for an entity attribute is invalid, either
+    /// because it does not have the attribute's type or because the attribute
+    /// is required.
+    #[error("invalid default value for attribute `{1}` of entity type `{0}`: {2}")]
+    InvalidAttributeDefault(EntityTypeName, String, String),
+    /// The value of an action attribute does not conform to the type recorded
+    /// for it.
+    #[error("attribute `{1}` of action `{0}` does not match its type: {2}")]
+    InvalidActionAttribute(EntityUid, String, String),
+    /// The context type of an action cannot be used to parse context data.
+    /// This should not happen for contexts declared in a schema file.
+    #[error("context for action `{0}` cannot be used to parse context data: {1}")]
+    UnrepresentableContext(EntityUid, String),
+    /// The schema file is not valid UTF-8. The argument is the byte offset of
+    /// the first invalid byte, ignoring any byte order mark.
+    #[error("schema is not valid UTF-8: invalid byte at offset {0}")]
+    InvalidUtf8(usize),
+    /// Error parsing one of several schema inputs. The first argument is the
+    /// index of the input which failed to parse.
+    #[error("in schema input {0}: {1}")]
+    InInput(usize, Box<Self>),
+    /// Entity types or actions declared in the same namespace have names which
+    /// differ only by case. This is only reported by
+    /// `Schema::check_case_collisions`.
+    #[error("names differ only by case: {}", .names.iter().map(|name| format!("`{name}`")).join(", "))]
+    CaseCollision {
+        /// The colliding names, fully qualified and sorted
+        names: Vec<String>,
+    },
+    /// A `String` type declares an empty list of `allowedValues`, so no
+    /// string would have the type.
+    #[error("`allowedValues` for a `String` type must not be empty")]
+    EmptyAllowedValues,
+    /// An `Entity` type declares an empty list of `names`, so no entity would
+    /// have the type.
+    #[error("`names` for an `Entity` type must not be empty")]
+    EmptyEntityTypeNames,
+    /// Flattening a namespace into the root namespace would merge entity types
+    /// or actions with distinct ones already declared in the root namespace.
+    /// This is only reported by `ValidatorSchema::flatten_namespace`.
+    #[error("flattening namespace would merge distinct declarations: {}", .collisions.iter().map(|(name, flattened)| format!("`{name}` with `{flattened}`")).join(", "))]
+    FlattenCollision {
+        /// Each name in the flattened namespace, fully qualified, paired with
+        /// the root namespace name it would be merged with. Sorted.
+        collisions: Vec<(String, String)>,
+    },
+    /// An `idFormat` declared for an entity type is not a valid regular
+    /// expression.
+    #[error("invalid `idFormat` `{pattern}` for entity type `{entity_type}`: {message}")]
+    InvalidIdFormat {
+        /// The fully qualified name of the entity type
+        entity_type: String,
+        /// The pattern as written in the schema
+        pattern: String,
+        /// Why the pattern could not be compiled
+        message: String,
+    },
+    /// One or more top-level keys of a schema are not well-formed namespace
+    /// identifiers. Each malformed key is listed, in sorted order, with the
+    /// errors from parsing it. This is only reported by
+    /// `Schema::from_json_value_checked`.
+    #[error("malformed namespace identifiers: {}", .0.iter().map(|(key, errs)| format!("`{key}` ({errs})")).join("; "))]
+    MalformedNamespaces(Vec<(String, ParseErrors)>),
+    /// An unqualified common type reference inside a namespace matches both a
+    /// common type declared in that namespace and one declared in the root
+    /// namespace.
+    #[error("common type `{reference}` is ambiguous: it may refer to `{local}` or to `{reference}` in the root namespace")]
+    AmbiguousCommonType {
+        /// The reference as written in the schema
+        reference: String,
+        /// The fully qualified name of the common type in the namespace of
+        /// the reference
+        local: String,
+    },
+    /// An attribute of an entity type shape or action context, or of a
+    /// record nested inside one, has an empty name or a name consisting only
+    /// of whitespace.
+    #[error("{location} declares an attribute with invalid name `{name}`: attribute names must not be empty or only whitespace")]
+    InvalidAttributeName {
+        /// The invalid attribute name
+        name: String,
+        /// The shape or context declaring the attribute
+        location: ContextOrShape,
+    },
+    /// The `__expr` escape in the value of an action attribute of a schema
+    /// written in version 1 of the schema format could not be converted when
+    /// migrating the schema to the current version.
+    #[error(
+        "cannot migrate `__expr` escape in attribute `{attr}` of action `{action}`: {message}"
+    )]
+    InvalidExprEscape {
+        /// The action declaring the attribute, as written in the schema
+        action: String,
+        /// The attribute
+        attr: String,
+        /// Why the escape could not be converted
+        message: String,
+    },
+    /// The namespace into which another namespace is cloned already declares
+    /// entity types, actions or common types. This is only reported by
+    /// `ValidatorSchema::clone_namespace`.
+    #[error("cannot clone into namespace `{0}` because it already has declarations")]
+    NamespaceAlreadyDeclared(String),
+    /// An entity type shape or action context has an attribute whose record
+    /// types are nested more deeply than allowed. This is only reported by
+    /// `ValidatorSchema::from_schema_fragments_with_limits`.
+    #[error("records nested {depth} deep exceed the limit of {limit}{}", format_schema_location(.location))]
+    RecordTooDeep {
+        /// The entity type shape or action context containing the attribute
+        location: SchemaLocation,
+        /// How deeply records are nested in the attribute
+        depth: usize,
+        /// The deepest nesting allowed
+        limit: usize,
+    },
+    /// Entity types or actions required to be declared are not. This is only
+    /// reported by `ValidatorSchema::require`.
+    #[error("schema does not declare required {}", format_missing_required(.entity_types, .actions))]
+    MissingRequired {
+        /// The required entity types which are not declared, as given
+        entity_types: Vec<String>,
+        /// The required actions which are not declared, as given
+        actions: Vec<String>,
+    },
+    /// Entities conforming to the schema could not be generated for the
+    /// action. This is only reported when generating placeholder entities
+    /// for an action.
+    #[error("cannot generate entities for action `{0}`: {1}")]
+    EntityGeneration(EntityUid, String),
+    /// A mutation would alter the declarations of a frozen namespace. This is
+    /// only reported by mutating operations such as
+    /// `ValidatorSchema::clone_namespace`.
+    #[error("namespace `{ns}` is frozen")]
+    NamespaceFrozen {
+        /// The frozen namespace
+        ns: String,
+    },
+    /// An entity in the `memberOf` list of an entity type has a type which
+    /// the `memberOfTypes` of the entity type does not allow as an ancestor,
+    /// so no entity of the entity type could be a member of it.
+    #[error("entity type `{entity_type}` cannot be a member of `{parent}` because `memberOfTypes` does not allow its type")]
+    DisallowedRequiredParent {
+        /// The fully qualified name of the entity type
+        entity_type: String,
+        /// The entity from the `memberOf` list
+        parent: String,
+    },
+}
+
+impl SchemaError {
+    /// For a duplicate declaration found while combining several fragments,
+    /// the index of the fragment containing the second declaration.
+    pub fn source_fragment_index(&self) -> Option<usize> {
+        match self {
+            Self::DuplicateEntityType(_, fragments)
+            | Self::DuplicateAction(_, fragments)
+            | Self::DuplicateCommonType(_, fragments) => {
+                fragments.as_ref().map(|fragments| fragments.duplicate)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Format the undeclared names for inclusion in the message of
+/// `SchemaError::MissingRequired`
+fn format_missing_required(entity_types: &[String], actions: &[String]) -> String {
+    let names = |kind: &str, names: &[String]| {
+        (!names.is_empty()).then(|| {
+            format!(
+                "{kind} {}",
+                names.iter().map(|name| format!("`{name}`")).join(", ")
+            )
+        })
+    };
+    names("entity types", entity_types)
+        .into_iter()
+        .chain(names("actions", actions))
+        .join(" and ")
+}
+
+/// Format the fragments containing a duplicate declaration for inclusion in
+/// the message of the duplicate declaration errors
+fn format_fragment_indices(fragments: Option<&FragmentIndices>) -> String {
+    fragments.map_or_else(String::new, |fragments| format!(": {fragments}"))
+}
+
+/// Format the location of a schema parse error for inclusion in its message
+fn format_schema_location(location: &SchemaLocation) -> String {
+    if location.is_unknown() {
+        String::new()
+    } else {
+        format!(" ({location})")
+    }
 }
 
 /// Describes in what action context or entity type shape a schema parsing error
@@ -1251,9 +1586,13 @@ impl From<cedar_policy_validator::ContextOrShape> for ContextOrShape {
 
 #[doc(hidden)]
 impl From<cedar_policy_validator::SchemaError> for SchemaError {
+    #[allow(clippy::too_many_lines)]
     fn from(value: cedar_policy_validator::SchemaError) -> Self {
         match value {
             cedar_policy_validator::SchemaError::Serde(e) => Self::Serde(e),
+            cedar_policy_validator::SchemaError::Io { source, bytes_read } => {
+                Self::Io { source, bytes_read }
+            }
             cedar_policy_validator::SchemaError::ActionTransitiveClosure(e) => {
                 Self::ActionTransitiveClosure(e.to_string())
             }
@@ -1267,24 +1606,36 @@ impl From<cedar_policy_validator::SchemaError> for SchemaError {
                 Self::UndeclaredEntityTypes(e)
             }
             cedar_policy_validator::SchemaError::UndeclaredActions(e) => Self::UndeclaredActions(e),
-            cedar_policy_validator::SchemaError::UndeclaredCommonTypes(c) => {
-                Self::UndeclaredCommonTypes(c)
+            cedar_policy_validator::SchemaError::UndeclaredCommonTypes(names) => {
+                Self::UndeclaredCommonTypes(names)
+            }
+            cedar_policy_validator::SchemaError::DuplicateEntityType(e, fragments) => {
+                Self::DuplicateEntityType(e, fragments)
             }
-            cedar_policy_validator::SchemaError::DuplicateEntityType(e) => {
-                Self::DuplicateEntityType(e)
+            cedar_policy_validator::SchemaError::DuplicateAction(e, fragments) => {
+                Self::DuplicateAction(e, fragments)
             }
-            cedar_policy_validator::SchemaError::DuplicateAction(e) => Self::DuplicateAction(e),
-            cedar_policy_validator::SchemaError::DuplicateCommonType(c) => {
-                Self::DuplicateCommonType(c)
+            cedar_policy_validator::SchemaError::DuplicateCommonType(c, fragments) => {
+                Self::DuplicateCommonType(c, fragments)
             }
             cedar_policy_validator::SchemaError::CycleInActionHierarchy => {
                 Self::CycleInActionHierarchy
             }
-            cedar_policy_validator::SchemaError::ParseEntityType(e) => Self::ParseEntityType(e),
+            cedar_policy_validator::SchemaError::CycleInEntityHierarchy => {
+                Self::CycleInEntityHierarchy
+            }
+            cedar_policy_validator::SchemaError::CycleInCommonTypes(names) => {
+                Self::CycleInCommonTypes(names)
+            }
+            cedar_policy_validator::SchemaError::ParseEntityType(e, location) => {
+                Self::ParseEntityType(e, location)
+            }
             cedar_policy_validator::SchemaError::ParseNamespace(e) => Self::ParseNamespace(e),
-            cedar_policy_validator::SchemaError::ParseCommonType(e) => Self::ParseCommonType(e),
-            cedar_policy_validator::SchemaError::ParseExtensionType(e) => {
-                Self::ParseExtensionType(e)
+            cedar_policy_validator::SchemaError::ParseCommonType(e, location) => {
+                Self::ParseCommonType(e, location)
+            }
+            cedar_policy_validator::SchemaError::ParseExtensionType(e, location) => {
+                Self::ParseExtensionType(e, location)
             }
             cedar_policy_validator::SchemaError::ActionEntityTypeDeclared => {
                 Self::ActionEntityTypeDeclared
@@ -1299,6 +1650,89 @@ impl From<cedar_policy_validator::SchemaError> for SchemaError {
                 Self::UnsupportedActionAttribute(EntityUid(uid), escape_type)
             }
             cedar_policy_validator::SchemaError::ExprEscapeUsed => Self::ExprEscapeUsed,
+            cedar_policy_validator::SchemaError::InvalidAttributeDefault(ty, attr, reason) => {
+                Self::InvalidAttributeDefault(EntityTypeName(ty), attr.to_string(), reason)
+            }
+            cedar_policy_validator::SchemaError::InvalidActionAttribute(action, attr, reason) => {
+                Self::InvalidActionAttribute(EntityUid(action), attr.to_string(), reason)
+            }
+            cedar_policy_validator::SchemaError::UnrepresentableContext(action, reason) => {
+                Self::UnrepresentableContext(EntityUid(action), reason)
+            }
+            cedar_policy_validator::SchemaError::InvalidUtf8(offset) => Self::InvalidUtf8(offset),
+            cedar_policy_validator::SchemaError::InInput(index, err) => {
+                Self::InInput(index, Box::new((*err).into()))
+            }
+            cedar_policy_validator::SchemaError::CaseCollision { names } => {
+                Self::CaseCollision { names }
+            }
+            cedar_policy_validator::SchemaError::EmptyAllowedValues => Self::EmptyAllowedValues,
+            cedar_policy_validator::SchemaError::EmptyEntityTypeNames => Self::EmptyEntityTypeNames,
+            cedar_policy_validator::SchemaError::FlattenCollision { collisions } => {
+                Self::FlattenCollision { collisions }
+            }
+            cedar_policy_validator::SchemaError::InvalidIdFormat {
+                entity_type,
+                pattern,
+                message,
+            } => Self::InvalidIdFormat {
+                entity_type,
+                pattern,
+                message,
+            },
+            cedar_policy_validator::SchemaError::MalformedNamespaces(namespaces) => {
+                Self::MalformedNamespaces(namespaces)
+            }
+            cedar_policy_validator::SchemaError::AmbiguousCommonType { reference, local } => {
+                Self::AmbiguousCommonType { reference, local }
+            }
+            cedar_policy_validator::SchemaError::InvalidAttributeName { name, location } => {
+                Self::InvalidAttributeName {
+                    name: name.to_string(),
+                    location: location.into(),
+                }
+            }
+            cedar_policy_validator::SchemaError::InvalidExprEscape {
+                action,
+                attr,
+                message,
+            } => Self::InvalidExprEscape {
+                action,
+                attr,
+                message,
+            },
+            cedar_policy_validator::SchemaError::NamespaceAlreadyDeclared(namespace) => {
+                Self::NamespaceAlreadyDeclared(namespace)
+            }
+            cedar_policy_validator::SchemaError::RecordTooDeep {
+                location,
+                depth,
+                limit,
+            } => Self::RecordTooDeep {
+                location,
+                depth,
+                limit,
+            },
+            cedar_policy_validator::SchemaError::MissingRequired {
+                entity_types,
+                actions,
+            } => Self::MissingRequired {
+                entity_types,
+                actions,
+            },
+            cedar_policy_validator::SchemaError::EntityGeneration(action, message) => {
+                Self::EntityGeneration(EntityUid(action), message)
+            }
+            cedar_policy_validator::SchemaError::NamespaceFrozen { ns } => {
+                Self::NamespaceFrozen { ns }
+            }
+            cedar_policy_validator::SchemaError::DisallowedRequiredParent {
+                entity_type,
+                parent,
+            } => Self::DisallowedRequiredParent {
+                entity_type,
+                parent,
+            },
         }
     }
 }
@@ -3279,6 +3713,7 @@ impl Context {
         schema
             .0
             .get_context_schema(&action.0)
+            .map_err(|err| ContextJsonError::InvalidContextSchema(err.into()))?
             .ok_or_else(|| ContextJsonError::MissingAction {
                 action: action.clone(),
             })
@@ -3297,6 +3732,10 @@ pub enum ContextJsonError {
         /// UID of the action which doesn't exist
         action: EntityUid,
     },
+    /// The context type for the supplied action cannot be used to parse
+    /// context data
+    #[error(transparent)]
+    InvalidContextSchema(SchemaError),
 }
 
 impl std::fmt::Display for Request {
@@ -5219,7 +5658,7 @@ mod schema_based_parsing_tests {
                         "isFullTime": true,
                         "numDirectReports": 3,
                         "department": "Sales",
-                        "manager": "34FB87",
+                        "manager": 34,
                         "hr_contacts": [
                             { "type": "HR", "id": "aaaaa" },
                             { "type": "HR", "id": "bbbbb" }
@@ -5242,7 +5681,7 @@ mod schema_based_parsing_tests {
             .expect_err("should fail due to type mismatch on manager");
         assert!(
             err.to_string()
-                .contains(r#"in attribute `manager` on `Employee::"12UA45"`, expected a literal entity reference, but got `"34FB87"`"#),
+                .contains(r#"in attribute `manager` on `Employee::"12UA45"`, expected a literal entity reference, but got `34`"#),
             "actual error message was {err}"
         );
 
@@ -152,7 +152,8 @@ mod test {
 
     #[test]
     fn test_validate_empty_policy_directly() {
-        let schema = cedar_policy_validator::SchemaFragment(HashMap::new());
+        let schema =
+            cedar_policy_validator::SchemaFragment(cedar_policy_validator::SchemaMap::new(), None);
 
         let call = ValidateCall {
             validation_settings: ValidationSettings::default(),